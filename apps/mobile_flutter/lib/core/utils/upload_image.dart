@@ -0,0 +1,68 @@
+import 'dart:math' as math;
+import 'dart:typed_data';
+import 'dart:ui' as ui;
+
+import 'package:image_picker/image_picker.dart';
+
+/// 画像アップロード用のバイト列とファイル名。
+///
+/// サーバーの画像パイプライン（投稿・アバター・進捗写真）は JPEG / PNG / WebP のみ受け付け、
+/// HEIC は 400 になる。HEIC/HEIF（iPhone のカメラ、一部の Android 端末）は端末のデコーダーで
+/// 読み込み、長辺 [maxEdge] px 以内の PNG に変換してから送る。
+class UploadImage {
+  final Uint8List bytes;
+  final String filename;
+
+  const UploadImage(this.bytes, this.filename);
+
+  /// サーバー側の最大サイズ（投稿の full バリアント）に合わせる
+  static const int maxEdge = 2048;
+
+  static Future<UploadImage> fromXFile(
+    XFile file, {
+    String fallbackName = 'image.jpg',
+  }) async {
+    final bytes = await file.readAsBytes();
+    final filename = file.name.isNotEmpty ? file.name : fallbackName;
+    if (!isHeif(bytes)) {
+      return UploadImage(bytes, filename);
+    }
+
+    final png = await _heifToPng(bytes);
+    final dot = filename.lastIndexOf('.');
+    final stem = dot > 0 ? filename.substring(0, dot) : filename;
+    return UploadImage(png, '$stem.png');
+  }
+
+  /// ISO BMFF の ftyp ボックスで HEIC/HEIF を判定する（サーバーの判定と同じブランド）
+  static bool isHeif(Uint8List bytes) {
+    if (bytes.length < 12) return false;
+    if (String.fromCharCodes(bytes.sublist(4, 8)) != 'ftyp') return false;
+    const brands = {'heic', 'heix', 'mif1', 'hevc'};
+    return brands.contains(String.fromCharCodes(bytes.sublist(8, 12)));
+  }
+
+  static Future<Uint8List> _heifToPng(Uint8List bytes) async {
+    final buffer = await ui.ImmutableBuffer.fromUint8List(bytes);
+    final descriptor = await ui.ImageDescriptor.encoded(buffer);
+    final longest = math.max(descriptor.width, descriptor.height);
+    final scale = longest > maxEdge ? maxEdge / longest : 1.0;
+    final codec = await descriptor.instantiateCodec(
+      targetWidth: (descriptor.width * scale).round(),
+      targetHeight: (descriptor.height * scale).round(),
+    );
+    try {
+      final frame = await codec.getNextFrame();
+      final data = await frame.image.toByteData(format: ui.ImageByteFormat.png);
+      frame.image.dispose();
+      if (data == null) {
+        throw StateError('HEIC画像の変換に失敗しました');
+      }
+      return data.buffer.asUint8List();
+    } finally {
+      codec.dispose();
+      descriptor.dispose();
+      buffer.dispose();
+    }
+  }
+}
@@ -2,6 +2,7 @@ import 'package:dio/dio.dart';
 import 'package:image_picker/image_picker.dart';
 import '../../core/api/api_client.dart';
 import '../../core/auth/secure_token_storage.dart';
+import '../../core/utils/upload_image.dart';
 
 class AuthService {
   final ApiClient _apiClient;
@@ -227,13 +228,12 @@ class AuthService {
   // Upload avatar image
   Future<String> uploadAvatar(XFile file) async {
     try {
-      final bytes = await file.readAsBytes();
-      final filename = file.name.isNotEmpty ? file.name : 'avatar.jpg';
+      final image = await UploadImage.fromXFile(file, fallbackName: 'avatar.jpg');
 
       final form = FormData.fromMap({
         'file': MultipartFile.fromBytes(
-          bytes,
-          filename: filename,
+          image.bytes,
+          filename: image.filename,
         ),
       });
 
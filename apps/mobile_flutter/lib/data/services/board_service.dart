@@ -1,6 +1,7 @@
 import 'package:dio/dio.dart';
 import 'package:image_picker/image_picker.dart';
 import '../../core/api/api_client.dart';
+import '../../core/utils/upload_image.dart';
 import '../models/board_models.dart';
 import '../models/meal_models.dart';
 
@@ -50,14 +51,14 @@ class BoardService {
   /// 画像付きの投稿を作成
   Future<CreatePostResponse> createPostWithImage(String content, XFile imageFile) async {
     try {
-      final bytes = await imageFile.readAsBytes();
-      final filename = imageFile.name.isNotEmpty ? imageFile.name : 'image.jpg';
+      // HEIC は端末で PNG に変換してから送る（サーバーは JPEG / PNG / WebP のみ）
+      final image = await UploadImage.fromXFile(imageFile);
 
       final form = FormData.fromMap({
         'content': content,
         'image': MultipartFile.fromBytes(
-          bytes,
-          filename: filename,
+          image.bytes,
+          filename: image.filename,
         ),
       });
 
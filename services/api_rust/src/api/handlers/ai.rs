@@ -1,17 +1,26 @@
-use axum::{extract::State, Json};
+use axum::{extract::State, http::StatusCode, Json};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::{
     api::middleware::AuthUser,
     error::{AppError, AppResult},
-    infrastructure::supabase::AiMessage,
-    state::{check_safety_flags, get_system_instruction, sanitize_user_input, StateGenerator},
+    infrastructure::{gemini::GeminiResponse, supabase::AiMessage},
+    redaction::Redactor,
+    state::{
+        get_system_instruction,
+        persona::Language,
+        prompts::PromptSelection,
+        retrieval::{HistoryIndex, HistorySnippet, SourceType},
+        safety::{PolicyDecision, PolicyOutcome, Severity},
+        sanitize_user_input, StateGenerator, StateVersion, UserState,
+    },
     AppState,
 };
 
-async fn send_chat_push_best_effort(
+pub(crate) async fn send_chat_push_best_effort(
     state: &AppState,
     user_access_token: &str,
     title: &str,
@@ -44,14 +53,371 @@ async fn send_chat_push_best_effort(
 }
 
 // =============================================================================
-// POST /v1/ai/ask - Ask AI coach a question
+// PII redaction (prompt -> Gemini -> response)
+// =============================================================================
+
+/// Serialize `UserState` for the prompt with PII in free-form fields
+/// (`environment` / `constraints`) replaced by placeholders
+fn redacted_state_json(redactor: &mut Redactor, user_state: &UserState) -> AppResult<String> {
+    let mut value = serde_json::to_value(user_state)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize state: {}", e)))?;
+    redactor.redact_json(&mut value);
+    serde_json::to_string_pretty(&value)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize state: {}", e)))
+}
+
+/// Put redacted values back into the model output before it is stored / returned
+fn restore_gemini_response(redactor: &mut Redactor, response: &mut GeminiResponse) {
+    if redactor.is_empty() {
+        return;
+    }
+    response.answer_text = redactor.restore(&response.answer_text);
+    for warning in response.warnings.iter_mut() {
+        *warning = redactor.restore(warning);
+    }
+    for rec in response.recommendations.iter_mut() {
+        redactor.restore_json(&mut rec.payload);
+    }
+}
+
+// =============================================================================
+// Safety policy events
 // =============================================================================
 
+#[derive(Debug, Serialize)]
+struct CreateAiSafetyEvent {
+    user_id: String,
+    session_id: Option<String>,
+    intent: String,
+    flags: serde_json::Value,
+    action: String,
+    severity: Option<Severity>,
+    needs_review: bool,
+}
+
+/// Persist a policy decision that matched at least one rule (best-effort)
+async fn record_safety_event(
+    state: &AppState,
+    user: &AuthUser,
+    session_id: Option<&str>,
+    intent: &str,
+    decision: &PolicyDecision,
+) {
+    if decision.matches.is_empty() {
+        return;
+    }
+    let event = CreateAiSafetyEvent {
+        user_id: user.user_id.clone(),
+        session_id: session_id.map(String::from),
+        intent: intent.to_string(),
+        flags: serde_json::to_value(&decision.matches).unwrap_or_default(),
+        action: decision.action_name().to_string(),
+        severity: decision.severity(),
+        needs_review: decision.escalate,
+    };
+    if let Err(e) = state
+        .supabase
+        .insert_batch("ai_safety_events", &[event], &user.token)
+        .await
+    {
+        tracing::warn!(user_id = %user.user_id, "Failed to record safety event: {}", e);
+    }
+}
+
+/// Policy warnings first, then the model's (deduplicated)
+fn merge_warnings(policy: &[String], model: Vec<String>) -> Vec<String> {
+    let mut warnings = policy.to_vec();
+    for w in model {
+        if !warnings.contains(&w) {
+            warnings.push(w);
+        }
+    }
+    warnings
+}
+
+// =============================================================================
+// History retrieval
+// =============================================================================
+
+/// Records from the user's history injected into the ask prompt
+const HISTORY_SNIPPET_LIMIT: usize = 5;
+
+/// Index the newest records before searching, so the answer sees the current state (best-effort)
+async fn sync_history_index(state: &AppState, access_token: &str, user_id: &str) {
+    let index = HistoryIndex::new(&state.supabase, access_token, state.embedder.as_deref());
+    match index.sync(user_id).await {
+        Ok(0) => {}
+        Ok(n) => tracing::debug!(user_id = %user_id, indexed = n, "History index synced"),
+        Err(e) => tracing::warn!(user_id = %user_id, "History index sync failed: {}", e),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum HistoryJob {
+    /// Index rows `sync` did not get to (long history, large imports)
+    Backfill,
+    /// Re-index everything and prune docs of deleted rows
+    Rebuild,
+}
+
+/// Run a history index job on the AI job workers. Returns `Ok(false)` if one is
+/// already queued or running for this user.
+fn submit_history_job(state: &AppState, user: &AuthUser, job: HistoryJob) -> AppResult<bool> {
+    if !state
+        .history_jobs
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(user.user_id.clone())
+    {
+        return Ok(false);
+    }
+    let submitted = state.ai_jobs.try_submit(run_history_job(
+        state.clone(),
+        user.token.clone(),
+        user.user_id.clone(),
+        job,
+    ));
+    if !submitted {
+        state
+            .history_jobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&user.user_id);
+        return Err(AppError::Unavailable("AI job queue is full".to_string()));
+    }
+    Ok(true)
+}
+
+async fn run_history_job(state: AppState, access_token: String, user_id: String, job: HistoryJob) {
+    let index = HistoryIndex::new(&state.supabase, &access_token, state.embedder.as_deref());
+    let result = match job {
+        HistoryJob::Backfill => index.backfill(&user_id).await,
+        HistoryJob::Rebuild => index.rebuild(&user_id).await,
+    };
+    state
+        .history_jobs
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&user_id);
+    match result {
+        Ok(0) => {}
+        Ok(n) => tracing::info!(user_id = %user_id, job = ?job, indexed = n, "History index job finished"),
+        Err(e) => tracing::warn!(user_id = %user_id, job = ?job, "History index job failed: {}", e),
+    }
+}
+
+/// Refresh the docs of edited source rows (sync only picks up new rows)
+pub(crate) async fn reindex_history(
+    state: AppState,
+    access_token: String,
+    user_id: String,
+    source: SourceType,
+    filter: String,
+) {
+    let index = HistoryIndex::new(&state.supabase, &access_token, state.embedder.as_deref());
+    if let Err(e) = index.reindex(&user_id, source, &filter).await {
+        tracing::warn!(user_id = %user_id, source = source.as_str(), "History reindex failed: {}", e);
+    }
+}
+
+// =============================================================================
+// Session memory (rolling summaries)
+// =============================================================================
+
+/// Unsummarized messages sent verbatim; once a session has more, older ones are folded into the summary
+const SESSION_HISTORY_MAX_MESSAGES: usize = 12;
+/// Messages kept verbatim after folding (last 2 turns)
+const SESSION_SUMMARY_KEEP_RECENT: usize = 4;
+/// Upper bound of messages summarized in one fold round
+const SESSION_SUMMARY_FOLD_MAX_MESSAGES: usize = 40;
+/// Upper bound of fold rounds per refresh (one model call each)
+const SESSION_SUMMARY_MAX_FOLDS: usize = 5;
+const MAX_SESSION_SUMMARY_CHARS: usize = 2000;
+
+#[derive(Debug, Default, Deserialize)]
+struct AiSessionMemory {
+    summary: Option<String>,
+    summary_through: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
+struct SessionSummaryOutput {
+    summary: String,
+}
+
+async fn load_session_memory(
+    state: &AppState,
+    access_token: &str,
+    session_id: &str,
+) -> AppResult<AiSessionMemory> {
+    let memory: Option<AiSessionMemory> = state
+        .supabase
+        .select_single(
+            "ai_sessions",
+            &format!("id=eq.{}&select=summary,summary_through", session_id),
+            access_token,
+        )
+        .await?;
+    Ok(memory.unwrap_or_default())
+}
+
+/// Latest `limit` messages not yet covered by the summary (oldest -> newest)
+async fn fetch_unsummarized_messages(
+    state: &AppState,
+    access_token: &str,
+    session_id: &str,
+    summary_through: Option<&str>,
+    limit: usize,
+) -> AppResult<Vec<AiMessage>> {
+    let mut query = format!(
+        "session_id=eq.{}&select=*&order=created_at.desc&limit={}",
+        session_id, limit
+    );
+    if let Some(through) = summary_through {
+        query.push_str(&format!("&created_at=gt.{}", urlencoding::encode(through)));
+    }
+    let mut messages: Vec<AiMessage> = state
+        .supabase
+        .select("ai_messages", &query, access_token)
+        .await?;
+    messages.reverse(); // oldest -> newest
+    Ok(messages)
+}
+
+async fn refresh_session_summary(
+    state: AppState,
+    access_token: String,
+    session_id: String,
+    prompt_selection: PromptSelection,
+    lang: Language,
+) {
+    if let Err(e) =
+        fold_session_summary(&state, &access_token, &session_id, &prompt_selection, lang).await
+    {
+        tracing::warn!(session_id = %session_id, "Session summary update failed: {}", e);
+    }
+}
+
+/// Summarize everything except the last few turns once the unsummarized tail grows too long.
+/// A long backlog (old sessions, earlier failed folds) is folded oldest-first over several rounds.
+async fn fold_session_summary(
+    state: &AppState,
+    access_token: &str,
+    session_id: &str,
+    prompt_selection: &PromptSelection,
+    lang: Language,
+) -> AppResult<()> {
+    for _ in 0..SESSION_SUMMARY_MAX_FOLDS {
+        if !fold_oldest_messages(state, access_token, session_id, prompt_selection, lang).await? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// One fold round. Returns false when there is nothing (more) to fold.
+async fn fold_oldest_messages(
+    state: &AppState,
+    access_token: &str,
+    session_id: &str,
+    prompt_selection: &PromptSelection,
+    lang: Language,
+) -> AppResult<bool> {
+    let memory = load_session_memory(state, access_token, session_id).await?;
+    let mut filter = format!("session_id=eq.{}", session_id);
+    if let Some(through) = memory.summary_through.as_deref() {
+        filter.push_str(&format!("&created_at=gt.{}", urlencoding::encode(through)));
+    }
+    let pending = state.supabase.count("ai_messages", &filter, access_token).await? as usize;
+    if pending <= SESSION_HISTORY_MAX_MESSAGES {
+        return Ok(false);
+    }
+
+    // Oldest first: summary_through moves forward without skipping anything
+    let take = (pending - SESSION_SUMMARY_KEEP_RECENT).min(SESSION_SUMMARY_FOLD_MAX_MESSAGES);
+    let fold: Vec<AiMessage> = state
+        .supabase
+        .select(
+            "ai_messages",
+            &format!("{}&select=*&order=created_at.asc&limit={}", filter, take),
+            access_token,
+        )
+        .await?;
+    let Some(last) = fold.last() else {
+        return Ok(false);
+    };
+
+    let conversation = fold
+        .iter()
+        .map(|m| format!("- {}: {}", m.role, m.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prompt = state.prompts.render(
+        &lang.template("summarize_session"),
+        prompt_selection,
+        &HashMap::from([
+            (
+                "previous_summary",
+                memory.summary.clone().unwrap_or_else(|| lang.none().to_string()),
+            ),
+            ("conversation", conversation),
+        ]),
+    )?;
+
+    let mut redactor = Redactor::new();
+    let prompt = redactor.redact(&prompt);
+    let output: SessionSummaryOutput = state.gemini.generate_json(&prompt, None).await?;
+    let summary: String = redactor
+        .restore(&output.summary)
+        .trim()
+        .chars()
+        .take(MAX_SESSION_SUMMARY_CHARS)
+        .collect();
+    if summary.is_empty() {
+        return Ok(false);
+    }
+
+    // Optimistic guard: stop if a concurrent request already moved the summary forward
+    let guard = match memory.summary_through.as_deref() {
+        Some(through) => format!("summary_through=eq.{}", urlencoding::encode(through)),
+        None => "summary_through=is.null".to_string(),
+    };
+    let updated: Vec<serde_json::Value> = state
+        .supabase
+        .update_returning(
+            "ai_sessions",
+            &format!("id=eq.{}&{}&select=id", session_id, guard),
+            &serde_json::json!({
+                "summary": summary,
+                "summary_through": last.created_at,
+                "summary_updated_at": Utc::now().to_rfc3339(),
+            }),
+            access_token,
+        )
+        .await?;
+    if updated.is_empty() {
+        return Ok(false);
+    }
+
+    tracing::info!(
+        session_id = %session_id,
+        folded_messages = fold.len(),
+        "AI session summary updated"
+    );
+    Ok(true)
+}
+
+// =============================================================================
+// POST /v1/ai/ask - Ask AI coach a question
+// =============================================================================
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AskRequest {
     pub message: String,
     /// Existing session id (UUID). If invalid, the request is rejected.
     pub session_id: Option<Uuid>,
+    /// UserState layout ("v1" / "v2"), defaults to v2
+    pub state_version: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -60,6 +426,8 @@ pub struct AskResponse {
     pub answer_text: String,
     pub recommendations: Vec<RecommendationResponse>,
     pub warnings: Vec<String>,
+    /// Records from the user's history given to the model, cited as `[ref]` in answer_text
+    pub citations: Vec<HistorySnippet>,
 }
 
 #[derive(Debug, Serialize)]
@@ -74,9 +442,36 @@ pub struct RecommendationResponse {
 struct CreateAiSession {
     user_id: String,
     intent: String,
+    state_version: String,
     model: String,
     input_summary: Option<serde_json::Value>,
     safety_flags: serde_json::Value,
+    prompt_versions: serde_json::Value,
+    experiment_key: Option<String>,
+    experiment_variant: Option<String>,
+}
+
+impl CreateAiSession {
+    fn new(
+        user_id: &str,
+        intent: &str,
+        state_version: &str,
+        model: &str,
+        prompts: &PromptSelection,
+    ) -> Self {
+        let experiment = prompts.primary_experiment();
+        Self {
+            user_id: user_id.to_string(),
+            intent: intent.to_string(),
+            state_version: state_version.to_string(),
+            model: model.to_string(),
+            input_summary: None,
+            safety_flags: serde_json::json!([]),
+            prompt_versions: serde_json::to_value(&prompts.versions).unwrap_or_default(),
+            experiment_key: experiment.map(|(key, _)| key.to_string()),
+            experiment_variant: experiment.map(|(_, variant)| variant.to_string()),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -84,6 +479,11 @@ struct AiSessionResponse {
     id: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct AiSessionModel {
+    model: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 struct CreateAiMessage {
     session_id: String,
@@ -113,50 +513,70 @@ pub async fn ask_ai(
     user: AuthUser,
     Json(req): Json<AskRequest>,
 ) -> AppResult<Json<AskResponse>> {
-    // Sanitize user input to prevent prompt injection
-    let sanitized_message = sanitize_user_input(&req.message);
+    let response = run_ask(&state, &user, req).await?;
 
-    // Check safety flags on sanitized input
-    let safety_flags = check_safety_flags(&sanitized_message);
+    // Push notification (best-effort)
+    send_chat_push_best_effort(
+        &state,
+        &user.token,
+        "ガチトレAI",
+        &response.answer_text,
+        serde_json::json!({
+            "session_id": response.session_id,
+            "kind": "ai_reply"
+        }),
+    )
+    .await;
 
-    // Reject dangerous content
-    if safety_flags.iter().any(|f| f == "steroids" || f == "anabolics") {
-        return Err(AppError::SafetyGuard(
-            "This type of advice cannot be provided.".to_string(),
-        ));
-    }
+    Ok(Json(response))
+}
 
-    // Reject potential prompt injection attempts
-    if safety_flags.iter().any(|f| f == "prompt_injection") {
-        tracing::warn!(
-            user_id = %user.user_id,
-            "Prompt injection attempt detected"
-        );
-        return Err(AppError::SafetyGuard(
-            "Invalid input detected.".to_string(),
-        ));
-    }
+/// Ask flow shared by `POST /v1/ai/ask` and async AI jobs (no push notification)
+pub(crate) async fn run_ask(
+    state: &AppState,
+    user: &AuthUser,
+    req: AskRequest,
+) -> AppResult<AskResponse> {
+    // Sanitize user input to prevent prompt injection
+    let sanitized_message = sanitize_user_input(&req.message);
 
     // Generate user state using Supabase REST API
+    let state_version = StateVersion::from_request(req.state_version.as_deref())?;
     let today = Utc::now().date_naive();
     let state_gen = StateGenerator::new(&state.supabase, &user.token);
-    let user_state = state_gen.generate(&user.user_id.clone(), today).await?;
+    let user_state = state_gen
+        .generate_cached(&user.user_id, today, state_version)
+        .await?;
+    let lang = user_state.profile.language;
+
+    // Evaluate safety policy on sanitized input (canned answers in the user's language)
+    let decision = state.safety.evaluate(&sanitized_message, lang);
+    decision.log(&user.user_id, "ask");
+    if let PolicyOutcome::Block(message) = &decision.outcome {
+        record_safety_event(state, user, None, "ask", &decision).await;
+        return Err(AppError::SafetyGuard(message.clone()));
+    }
+    let safety_flags = decision.flags();
+
+    // Prompt template versions for this user (A/B experiment assignment)
+    let prompt_selection = state.prompts.select(&user.user_id);
 
     // Resolve (or create) session before calling Gemini so we can fetch history
-    let session_id = if let Some(sid) = req.session_id {
+    let continues_session = req.session_id.is_some();
+    let (session_id, session_model) = if let Some(sid) = req.session_id {
         let sid = sid.to_string();
         // SECURITY: Verify session belongs to current user (IDOR protection)
         // Query explicitly includes user_id check to prevent access to other users' sessions
-        let existing: Option<serde_json::Value> = state
+        let existing: Option<AiSessionModel> = state
             .supabase
             .select_single(
                 "ai_sessions",
-                &format!("id=eq.{}&user_id=eq.{}&select=id", sid, user.user_id),
+                &format!("id=eq.{}&user_id=eq.{}&select=model", sid, user.user_id),
                 &user.token,
             )
             .await?;
-        if existing.is_some() {
-            sid
+        if let Some(existing) = existing {
+            (sid, existing.model)
         } else {
             // SECURITY: Reject invalid session_id instead of silently creating new session
             // This prevents confusion and potential security issues
@@ -168,70 +588,148 @@ pub async fn ask_ai(
     } else {
         // Create new session
         let session_data = CreateAiSession {
-            user_id: user.user_id.clone(),
-            intent: "ask".to_string(),
-            model: state.config.gemini_model.clone(),
             input_summary: Some(serde_json::json!({
                 "state_version": user_state.version,
                 "goal": user_state.profile.goal,
                 "today_workout_count": user_state.today.workout_count,
             })),
             safety_flags: serde_json::to_value(&safety_flags).unwrap(),
+            ..CreateAiSession::new(
+                &user.user_id,
+                "ask",
+                &user_state.version,
+                &state.config.gemini_model,
+                &prompt_selection,
+            )
         };
         let session: AiSessionResponse = state
             .supabase
             .insert("ai_sessions", &session_data, &user.token)
             .await?;
-        session.id
+        (session.id, Some(state.config.gemini_model.clone()))
     };
 
-    // Fetch recent conversation history (up to last 2 turns = max 4 messages)
-    let history_query = format!(
-        "session_id=eq.{}&select=*&order=created_at.desc&limit=4",
-        session_id
-    );
-    let mut recent_messages: Vec<AiMessage> = state
-        .supabase
-        .select("ai_messages", &history_query, &user.token)
-        .await
-        .unwrap_or_default();
-    recent_messages.reverse(); // oldest -> newest
+    // Canned safe answer: skip the model, but keep the exchange in the session
+    if let PolicyOutcome::SafeAnswer(answer_text) = &decision.outcome {
+        for (role, content) in [("user", &sanitized_message), ("assistant", answer_text)] {
+            let message = CreateAiMessage {
+                session_id: session_id.clone(),
+                role: role.to_string(),
+                content: content.clone(),
+            };
+            let _: AiMessageResponse = state
+                .supabase
+                .insert("ai_messages", &message, &user.token)
+                .await?;
+        }
+        record_safety_event(state, user, Some(&session_id), "ask", &decision).await;
+        if continues_session {
+            tokio::spawn(reindex_history(
+                state.clone(),
+                user.token.clone(),
+                user.user_id.clone(),
+                SourceType::AiSession,
+                format!("id=eq.{}", session_id),
+            ));
+        }
 
-    // Build prompt with state context
-    let state_json = serde_json::to_string_pretty(&user_state)
-        .map_err(|e| AppError::Internal(format!("Failed to serialize state: {}", e)))?;
+        return Ok(AskResponse {
+            session_id,
+            answer_text: answer_text.clone(),
+            recommendations: Vec::new(),
+            warnings: decision.warnings.clone(),
+            citations: Vec::new(),
+        });
+    }
 
-    let prompt = format!(
-        r#"【ユーザーの現在の状態】
-```json
-{}
-```
+    // Session memory: rolling summary of older turns + unsummarized recent turns
+    let memory = load_session_memory(state, &user.token, &session_id)
+        .await
+        .unwrap_or_default();
+    let recent_messages = fetch_unsummarized_messages(
+        state,
+        &user.token,
+        &session_id,
+        memory.summary_through.as_deref(),
+        SESSION_HISTORY_MAX_MESSAGES,
+    )
+    .await
+    .unwrap_or_default();
+
+    // Relevant records from the user's own history (best-effort)
+    sync_history_index(state, &user.token, &user.user_id).await;
+    let history = HistoryIndex::new(&state.supabase, &user.token, state.embedder.as_deref());
+    let citations: Vec<HistorySnippet> = match history
+        .search(&user.user_id, &sanitized_message, HISTORY_SNIPPET_LIMIT + 1)
+        .await
+    {
+        Ok(snippets) => snippets
+            .into_iter()
+            // The current session is already in the prompt as history
+            .filter(|s| !(s.source_type == "ai_session" && s.source_id == session_id))
+            .take(HISTORY_SNIPPET_LIMIT)
+            .enumerate()
+            .map(|(i, s)| HistorySnippet { ref_no: i + 1, ..s })
+            .collect(),
+        Err(e) => {
+            tracing::warn!(user_id = %user.user_id, "History search failed: {}", e);
+            Vec::new()
+        }
+    };
 
-【ユーザーの質問】
-{}
+    // Build prompt with state context.
+    // PII is replaced with placeholders before anything is sent to Gemini.
+    let mut redactor = Redactor::new();
+    let state_json = redacted_state_json(&mut redactor, &user_state)?;
 
-上記の状態を踏まえて、適切なアドバイスをJSON形式で返してください。"#,
-        state_json, sanitized_message
-    );
+    let prompt = state.prompts.render(
+        &lang.template("ask"),
+        &prompt_selection,
+        &HashMap::from([
+            ("state_json", state_json.clone()),
+            ("message", sanitized_message.clone()),
+        ]),
+    )?;
 
     // Get system instruction
-    let mut system_instruction = get_system_instruction(&user_state);
+    let mut system_instruction =
+        get_system_instruction(&state.prompts, &prompt_selection, &user_state)?;
+    if let Some(summary) = memory.summary.as_deref().filter(|s| !s.trim().is_empty()) {
+        system_instruction.push_str("\n\n");
+        system_instruction.push_str(&state.prompts.render(
+            &lang.template("session_summary"),
+            &prompt_selection,
+            &HashMap::from([("summary", summary.to_string())]),
+        )?);
+    }
     if !recent_messages.is_empty() {
         let history_text = recent_messages
             .iter()
             .map(|m| format!("- {}: {}", m.role, m.content))
             .collect::<Vec<_>>()
             .join("\n");
-        system_instruction.push_str(&format!(
-            r#"
-
-【直近の会話（最大2往復）】
-{}
-
-上の会話の流れを踏まえて、会話が自然につながるように回答して。"#,
-            history_text
-        ));
+        system_instruction.push_str("\n\n");
+        system_instruction.push_str(&state.prompts.render(
+            &lang.template("ask_history"),
+            &prompt_selection,
+            &HashMap::from([("history", history_text)]),
+        )?);
     }
+    if !citations.is_empty() {
+        let snippets = citations
+            .iter()
+            .map(HistorySnippet::prompt_line)
+            .collect::<Vec<_>>()
+            .join("\n");
+        system_instruction.push_str("\n\n");
+        system_instruction.push_str(&state.prompts.render(
+            &lang.template("history_context"),
+            &prompt_selection,
+            &HashMap::from([("snippets", snippets)]),
+        )?);
+    }
+    let prompt = redactor.redact(&prompt);
+    let system_instruction = redactor.redact(&system_instruction);
 
     // Debug: Log prompts (debug level - not shown in production with RUST_LOG=info)
     // Avoid logging raw prompts/state (may contain personal data). Log only lengths.
@@ -239,11 +737,30 @@ pub async fn ask_ai(
         system_instruction_len = system_instruction.len(),
         state_json_len = state_json.len(),
         prompt_len = prompt.len(),
+        redacted_values = redactor.len(),
         "AI prompt built"
     );
 
     // Call Gemini
-    let gemini_response = state.gemini.generate(&prompt, Some(&system_instruction)).await?;
+    let mut gemini_response = state.gemini.generate(&prompt, Some(&system_instruction)).await?;
+    restore_gemini_response(&mut redactor, &mut gemini_response);
+
+    // Record the model that actually answered (fallback model on primary failure).
+    // Bookkeeping only: the answer is already billed, so a failed write must not drop it.
+    if session_model.as_deref() != Some(gemini_response.model.as_str()) {
+        if let Err(e) = state
+            .supabase
+            .update(
+                "ai_sessions",
+                &format!("id=eq.{}&user_id=eq.{}", session_id, user.user_id),
+                &serde_json::json!({ "model": gemini_response.model }),
+                &user.token,
+            )
+            .await
+        {
+            tracing::warn!(session_id = %session_id, "Failed to record AI session model: {}", e);
+        }
+    }
 
     // Save user message (sanitized)
     let user_message = CreateAiMessage {
@@ -267,18 +784,29 @@ pub async fn ask_ai(
         .insert("ai_messages", &ai_message, &user.token)
         .await?;
 
-    // Push notification (best-effort)
-    send_chat_push_best_effort(
-        &state,
-        &user.token,
-        "ガチトレAI",
-        &gemini_response.answer_text,
-        serde_json::json!({
-            "session_id": session_id,
-            "kind": "ai_reply"
-        }),
-    )
-    .await;
+    // Fold older turns into the session summary (best-effort, off the request path)
+    tokio::spawn(refresh_session_summary(
+        state.clone(),
+        user.token.clone(),
+        session_id.clone(),
+        prompt_selection.clone(),
+        lang,
+    ));
+
+    // Index older records the sync above did not reach (best-effort; no-op once complete)
+    if let Err(e) = submit_history_job(state, user, HistoryJob::Backfill) {
+        tracing::debug!(user_id = %user.user_id, "History backfill not queued: {}", e);
+    }
+    // A continued session's doc must include the new messages
+    if continues_session {
+        tokio::spawn(reindex_history(
+            state.clone(),
+            user.token.clone(),
+            user.user_id.clone(),
+            SourceType::AiSession,
+            format!("id=eq.{}", session_id),
+        ));
+    }
 
     // Save recommendations
     let mut recommendations = Vec::new();
@@ -299,29 +827,37 @@ pub async fn ask_ai(
         });
     }
 
+    record_safety_event(state, user, Some(&session_id), "ask", &decision).await;
+
     tracing::info!(
         user_id = %user.user_id,
         session_id = %session_id,
         "AI ask completed"
     );
 
-    Ok(Json(AskResponse {
+    Ok(AskResponse {
         session_id,
         answer_text: gemini_response.answer_text,
         recommendations,
-        warnings: gemini_response.warnings,
-    }))
+        warnings: merge_warnings(&decision.warnings, gemini_response.warnings),
+        citations,
+    })
 }
 
 // =============================================================================
 // POST /v1/ai/plan/today - Generate today's workout plan
 // =============================================================================
 
-#[derive(Debug, Deserialize)]
+const PLAN_MAX_LIST_ITEMS: usize = 10;
+const PLAN_MAX_ITEM_CHARS: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PlanTodayRequest {
     pub muscle_groups: Option<Vec<String>>,
     pub duration_minutes: Option<i32>,
     pub equipment_available: Option<Vec<String>>,
+    /// UserState layout ("v1" / "v2"), defaults to v2
+    pub state_version: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -355,78 +891,155 @@ pub async fn plan_today(
     user: AuthUser,
     Json(req): Json<PlanTodayRequest>,
 ) -> AppResult<Json<PlanTodayResponse>> {
+    let response = run_plan_today(&state, &user, req).await?;
+
+    // Push notification (best-effort)
+    send_chat_push_best_effort(
+        &state,
+        &user.token,
+        "ガチトレAI",
+        &response.answer_text,
+        serde_json::json!({
+            "session_id": response.session_id,
+            "kind": "ai_plan"
+        }),
+    )
+    .await;
+
+    Ok(Json(response))
+}
+
+/// Plan flow shared by `POST /v1/ai/plan/today` and async AI jobs (no push notification)
+pub(crate) async fn run_plan_today(
+    state: &AppState,
+    user: &AuthUser,
+    req: PlanTodayRequest,
+) -> AppResult<PlanTodayResponse> {
+    // Free-text request fields go into the prompt: sanitize and run the safety policy
+    let sanitize_list = |items: &Option<Vec<String>>, field: &str| -> AppResult<Option<Vec<String>>> {
+        let Some(items) = items else {
+            return Ok(None);
+        };
+        if items.len() > PLAN_MAX_LIST_ITEMS
+            || items.iter().any(|i| i.chars().count() > PLAN_MAX_ITEM_CHARS)
+        {
+            return Err(AppError::Validation(format!(
+                "{} must have at most {} items of up to {} chars",
+                field, PLAN_MAX_LIST_ITEMS, PLAN_MAX_ITEM_CHARS
+            )));
+        }
+        Ok(Some(items.iter().map(|i| sanitize_user_input(i.trim())).collect()))
+    };
+    let state_version = StateVersion::from_request(req.state_version.as_deref())?;
+    let muscle_groups = sanitize_list(&req.muscle_groups, "muscle_groups")?;
+    let equipment_available = sanitize_list(&req.equipment_available, "equipment_available")?;
+
+    let request_text = muscle_groups
+        .iter()
+        .chain(equipment_available.iter())
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+
     // Generate user state using Supabase REST API
     let today = Utc::now().date_naive();
     let state_gen = StateGenerator::new(&state.supabase, &user.token);
-    let user_state = state_gen.generate(&user.user_id.clone(), today).await?;
+    let user_state = state_gen
+        .generate_cached(&user.user_id, today, state_version)
+        .await?;
+    let lang = user_state.profile.language;
+
+    let decision = state.safety.evaluate(&request_text, lang);
+    decision.log(&user.user_id, "plan_today");
+    let prompt_selection = state.prompts.select(&user.user_id);
+    match &decision.outcome {
+        PolicyOutcome::Block(message) => {
+            record_safety_event(state, user, None, "plan_today", &decision).await;
+            return Err(AppError::SafetyGuard(message.clone()));
+        }
+        // Canned safe answer: skip the model and return an empty plan, like `ask` does
+        PolicyOutcome::SafeAnswer(answer_text) => {
+            let session_data = CreateAiSession {
+                input_summary: Some(serde_json::json!({
+                    "muscle_groups": muscle_groups,
+                    "duration_minutes": req.duration_minutes,
+                })),
+                safety_flags: serde_json::to_value(decision.flags()).unwrap(),
+                ..CreateAiSession::new(
+                    &user.user_id,
+                    "plan_today",
+                    &user_state.version,
+                    &state.config.gemini_model,
+                    &prompt_selection,
+                )
+            };
+            let session: AiSessionResponse = state
+                .supabase
+                .insert("ai_sessions", &session_data, &user.token)
+                .await?;
+            let ai_message = CreateAiMessage {
+                session_id: session.id.clone(),
+                role: "assistant".to_string(),
+                content: answer_text.clone(),
+            };
+            let _: AiMessageResponse = state
+                .supabase
+                .insert("ai_messages", &ai_message, &user.token)
+                .await?;
+            record_safety_event(state, user, Some(&session.id), "plan_today", &decision).await;
+
+            return Ok(PlanTodayResponse {
+                session_id: session.id,
+                plan: WorkoutPlan {
+                    title: String::new(),
+                    estimated_duration_minutes: 0,
+                    exercises: Vec::new(),
+                    notes: None,
+                },
+                answer_text: answer_text.clone(),
+                warnings: decision.warnings.clone(),
+            });
+        }
+        PolicyOutcome::Allow => {}
+    }
 
-    // Build prompt
-    let state_json = serde_json::to_string_pretty(&user_state)
-        .map_err(|e| AppError::Internal(format!("Failed to serialize state: {}", e)))?;
+    // Build prompt (PII replaced with placeholders before it is sent to Gemini)
+    let mut redactor = Redactor::new();
+    let state_json = redacted_state_json(&mut redactor, &user_state)?;
 
-    let muscle_groups_str = req
-        .muscle_groups
+    let muscle_groups_str = muscle_groups
         .as_ref()
         .map(|m| m.join(", "))
-        .unwrap_or_else(|| "おまかせ".to_string());
+        .unwrap_or_else(|| lang.any_muscle_groups().to_string());
 
-    let duration_str = req
-        .duration_minutes
-        .map(|d| format!("{}分", d))
-        .unwrap_or_else(|| "60分程度".to_string());
+    let duration_str = lang.plan_duration(req.duration_minutes);
 
-    let equipment_str = req
-        .equipment_available
+    let equipment_str = equipment_available
         .as_ref()
         .map(|e| e.join(", "))
-        .unwrap_or_else(|| "制限なし".to_string());
-
-    let prompt = format!(
-        r#"【ユーザーの現在の状態】
-```json
-{}
-```
-
-【今日のトレーニングプランをリクエスト】
-- 鍛えたい部位: {}
-- 希望時間: {}
-- 利用可能な器具: {}
-
-上記を踏まえて、今日のトレーニングプランを作成してください。
-
-【出力形式】
-{{
-  "answer_text": "プランの説明",
-  "recommendations": [
-    {{
-      "kind": "workout",
-      "payload": {{
-        "title": "プランのタイトル",
-        "estimated_duration_minutes": 60,
-        "exercises": [
-          {{
-            "name": "種目名",
-            "muscle_tag": "chest",
-            "sets": 3,
-            "reps": "8-12",
-            "rest_sec": 90,
-            "notes": "フォームのポイントなど"
-          }}
-        ],
-        "notes": "全体的な注意点"
-      }}
-    }}
-  ],
-  "warnings": []
-}}"#,
-        state_json, muscle_groups_str, duration_str, equipment_str
-    );
+        .unwrap_or_else(|| lang.any_equipment().to_string());
+
+    let prompt = state.prompts.render(
+        &lang.template("plan_today"),
+        &prompt_selection,
+        &HashMap::from([
+            ("state_json", state_json),
+            ("muscle_groups", muscle_groups_str),
+            ("duration", duration_str),
+            ("equipment", equipment_str),
+        ]),
+    )?;
 
     // Get system instruction
-    let system_instruction = get_system_instruction(&user_state);
+    let system_instruction =
+        get_system_instruction(&state.prompts, &prompt_selection, &user_state)?;
+    let prompt = redactor.redact(&prompt);
+    let system_instruction = redactor.redact(&system_instruction);
 
     // Call Gemini
-    let gemini_response = state.gemini.generate(&prompt, Some(&system_instruction)).await?;
+    let mut gemini_response = state.gemini.generate(&prompt, Some(&system_instruction)).await?;
+    restore_gemini_response(&mut redactor, &mut gemini_response);
 
     // Extract workout plan from recommendations
     let plan = gemini_response
@@ -440,14 +1053,18 @@ pub async fn plan_today(
 
     // Create AI session via Supabase REST API
     let session_data = CreateAiSession {
-        user_id: user.user_id.clone(),
-        intent: "plan_today".to_string(),
-        model: state.config.gemini_model.clone(),
         input_summary: Some(serde_json::json!({
-            "muscle_groups": req.muscle_groups,
+            "muscle_groups": muscle_groups,
             "duration_minutes": req.duration_minutes,
         })),
-        safety_flags: serde_json::json!([]),
+        safety_flags: serde_json::to_value(decision.flags()).unwrap(),
+        ..CreateAiSession::new(
+            &user.user_id,
+            "plan_today",
+            &user_state.version,
+            &gemini_response.model,
+            &prompt_selection,
+        )
     };
     let session: AiSessionResponse = state
         .supabase
@@ -466,19 +1083,6 @@ pub async fn plan_today(
         .insert("ai_messages", &ai_message, &user.token)
         .await?;
 
-    // Push notification (best-effort)
-    send_chat_push_best_effort(
-        &state,
-        &user.token,
-        "ガチトレAI",
-        &gemini_response.answer_text,
-        serde_json::json!({
-            "session_id": session_id,
-            "kind": "ai_plan"
-        }),
-    )
-    .await;
-
     // Save recommendation
     let rec_data = CreateAiRecommendation {
         session_id: session_id.clone(),
@@ -490,22 +1094,24 @@ pub async fn plan_today(
         .insert("ai_recommendations", &rec_data, &user.token)
         .await?;
 
+    record_safety_event(state, user, Some(&session_id), "plan_today", &decision).await;
+
     tracing::info!(
         user_id = %user.user_id,
         session_id = %session_id,
         "AI plan_today completed"
     );
 
-    Ok(Json(PlanTodayResponse {
+    Ok(PlanTodayResponse {
         session_id,
         plan,
         answer_text: gemini_response.answer_text,
-        warnings: gemini_response.warnings,
-    }))
+        warnings: merge_warnings(&decision.warnings, gemini_response.warnings),
+    })
 }
 
 // =============================================================================
-// GET /v1/ai/history - Get AI conversation history
+// GET /v1/ai/history - Get AI conversation history (pinned first)
 // =============================================================================
 
 #[derive(Debug, Serialize)]
@@ -517,6 +1123,8 @@ pub struct AiHistoryResponse {
 pub struct AiSessionSummary {
     pub id: String,
     pub intent: String,
+    pub title: Option<String>,
+    pub pinned: bool,
     pub created_at: String,
 }
 
@@ -525,7 +1133,7 @@ pub async fn get_ai_history(
     user: AuthUser,
 ) -> AppResult<Json<AiHistoryResponse>> {
     let query = format!(
-        "user_id=eq.{}&select=id,intent,created_at&order=created_at.desc&limit=50",
+        "user_id=eq.{}&select=id,intent,title,pinned,created_at&order=pinned.desc,created_at.desc&limit=50",
         user.user_id
     );
     let sessions: Vec<AiSessionSummary> = state
@@ -535,3 +1143,29 @@ pub async fn get_ai_history(
 
     Ok(Json(AiHistoryResponse { sessions }))
 }
+
+// =============================================================================
+// POST /v1/ai/history/reindex - Rebuild the history retrieval index
+// =============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ReindexHistoryResponse {
+    /// "queued", or "running" if a history job for this user is already in progress
+    pub status: &'static str,
+}
+
+/// POST /v1/ai/history/reindex - Rebuild the retrieval index in the background
+/// (picks up edits to old records)
+pub async fn reindex_ai_history(
+    State(state): State<AppState>,
+    user: AuthUser,
+) -> AppResult<(StatusCode, Json<ReindexHistoryResponse>)> {
+    let status = if submit_history_job(&state, &user, HistoryJob::Rebuild)? {
+        tracing::info!(user_id = %user.user_id, "AI history index rebuild queued");
+        "queued"
+    } else {
+        "running"
+    };
+
+    Ok((StatusCode::ACCEPTED, Json(ReindexHistoryResponse { status })))
+}
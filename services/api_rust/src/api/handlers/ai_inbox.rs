@@ -3,6 +3,7 @@ use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::weekly_reports::deliver_pending_weekly_report;
 use crate::{api::middleware::AuthUser, error::AppResult, AppState};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,6 +21,12 @@ pub async fn get_ai_inbox(
     State(state): State<AppState>,
     user: AuthUser,
 ) -> AppResult<Json<Vec<AiInboxMessage>>> {
+    // Weekly digests queued by the cron job are handed to the AI job workers on fetch
+    // and delivered on a later one (best-effort)
+    if let Err(e) = deliver_pending_weekly_report(&state, &user).await {
+        tracing::warn!(user_id = %user.user_id, "Weekly report delivery failed: {}", e);
+    }
+
     let query = format!(
         "user_id=eq.{}&consumed_at=is.null&select=id,content,kind,meal_type,date,created_at&order=created_at.asc&limit=20",
         user.user_id
@@ -0,0 +1,418 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    api::{middleware::AuthUser, validation::validate_uuid},
+    error::{AppError, AppResult},
+    AppState,
+};
+
+use super::ai::{run_ask, run_plan_today, send_chat_push_best_effort, AskRequest, PlanTodayRequest};
+
+/// Jobs still queued (since created_at) or running (since started_at) after this
+/// long are reported as failed (the worker was lost, e.g. the server restarted).
+/// An expired queued job is never started: the worker only claims queued rows.
+const JOB_EXPIRY_MINUTES: i64 = 10;
+
+const JOB_COLUMNS: &str = "id,request_id,kind,status,result,error,created_at,started_at,finished_at";
+
+// =============================================================================
+// Request/Response DTOs
+// =============================================================================
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "input", rename_all = "snake_case")]
+pub enum AiJobInput {
+    Ask(AskRequest),
+    PlanToday(PlanTodayRequest),
+}
+
+impl AiJobInput {
+    fn kind(&self) -> &'static str {
+        match self {
+            AiJobInput::Ask(_) => "ask",
+            AiJobInput::PlanToday(_) => "plan_today",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateAiJobRequest {
+    /// Client-generated id; retrying with the same id returns the existing job
+    pub request_id: Uuid,
+    #[serde(flatten)]
+    pub job: AiJobInput,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AiJobResponse {
+    #[serde(rename(serialize = "job_id"))]
+    pub id: String,
+    pub request_id: String,
+    pub kind: String,
+    /// "queued" / "running" / "succeeded" / "failed"
+    pub status: String,
+    /// AskResponse or PlanTodayResponse once succeeded
+    pub result: Option<serde_json::Value>,
+    /// ErrorResponse once failed
+    pub error: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl AiJobResponse {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        let since = match self.status.as_str() {
+            "queued" => self.created_at,
+            "running" => self.started_at.unwrap_or(self.created_at),
+            _ => return false,
+        };
+        now - since > Duration::minutes(JOB_EXPIRY_MINUTES)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CreateAiJob<'a> {
+    user_id: &'a str,
+    request_id: Uuid,
+    kind: &'a str,
+    status: &'a str,
+    request: &'a AiJobInput,
+}
+
+// =============================================================================
+// POST /v1/ai/jobs - Submit ask / plan_today to run in the background
+// =============================================================================
+
+pub async fn create_ai_job(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Json(req): Json<CreateAiJobRequest>,
+) -> AppResult<(StatusCode, Json<AiJobResponse>)> {
+    // Retried submit: return the stored job instead of calling the model again
+    if let Some(existing) = find_job_by_request_id(&state, &user, req.request_id).await? {
+        return Ok((StatusCode::OK, Json(expire_if_stale(&state, &user, existing).await)));
+    }
+
+    let kind = req.job.kind();
+    let inserted: AppResult<AiJobResponse> = state
+        .supabase
+        .insert(
+            "ai_jobs",
+            &CreateAiJob {
+                user_id: &user.user_id,
+                request_id: req.request_id,
+                kind,
+                status: "queued",
+                request: &req.job,
+            },
+            &user.token,
+        )
+        .await;
+    let job = match inserted {
+        Ok(job) => job,
+        Err(e) => {
+            // Concurrent retry won the unique (user_id, request_id) race
+            return match find_job_by_request_id(&state, &user, req.request_id).await? {
+                Some(existing) => Ok((StatusCode::OK, Json(existing))),
+                None => Err(e),
+            };
+        }
+    };
+
+    let job_id = job.id.clone();
+    let submitted = state.ai_jobs.try_submit(run_ai_job(
+        state.clone(),
+        user.clone(),
+        job_id.clone(),
+        req.job,
+    ));
+    if !submitted {
+        // Drop the row so the client can retry later with the same request_id
+        if let Err(e) = state
+            .supabase
+            .delete(
+                "ai_jobs",
+                &format!("id=eq.{}&user_id=eq.{}", job_id, user.user_id),
+                &user.token,
+            )
+            .await
+        {
+            tracing::warn!(job_id = %job_id, "Failed to delete rejected AI job: {}", e);
+        }
+        return Err(AppError::Unavailable("AI job queue is full".to_string()));
+    }
+
+    tracing::info!(user_id = %user.user_id, job_id = %job_id, kind = kind, "AI job queued");
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+// =============================================================================
+// GET /v1/ai/jobs/:id - Poll job status / result
+// =============================================================================
+
+pub async fn get_ai_job(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(job_id): Path<String>,
+) -> AppResult<Json<AiJobResponse>> {
+    let job_id = validate_uuid(&job_id)?;
+
+    let job: Option<AiJobResponse> = state
+        .supabase
+        .select_single(
+            "ai_jobs",
+            &format!(
+                "id=eq.{}&user_id=eq.{}&select={}",
+                job_id, user.user_id, JOB_COLUMNS
+            ),
+            &user.token,
+        )
+        .await?;
+    let job = job.ok_or_else(|| AppError::NotFound("AI job not found".to_string()))?;
+
+    Ok(Json(expire_if_stale(&state, &user, job).await))
+}
+
+// =============================================================================
+// Worker
+// =============================================================================
+
+async fn run_ai_job(state: AppState, user: AuthUser, job_id: String, input: AiJobInput) {
+    let job_kind = input.kind();
+    let query = format!("id=eq.{}&user_id=eq.{}", job_id, user.user_id);
+
+    // Claim the job: only a still-queued row may start (an expired one stays failed,
+    // so the model is never called for a job the client was told had failed)
+    let claimed: AppResult<Vec<serde_json::Value>> = state
+        .supabase
+        .update_returning(
+            "ai_jobs",
+            &format!("{}&status=eq.queued&select=id", query),
+            &serde_json::json!({
+                "status": "running",
+                "started_at": Utc::now().to_rfc3339(),
+            }),
+            &user.token,
+        )
+        .await;
+    match claimed {
+        Ok(rows) if !rows.is_empty() => {}
+        Ok(_) => {
+            tracing::warn!(job_id = %job_id, "AI job is no longer queued (expired?); skipping");
+            return;
+        }
+        Err(e) => {
+            tracing::warn!(job_id = %job_id, "Failed to mark AI job running; skipping: {}", e);
+            return;
+        }
+    }
+
+    let (mut update, push) = match execute_job(&state, &user, input).await {
+        Ok((result, session_id, answer_text)) => (
+            serde_json::json!({ "status": "succeeded", "result": result }),
+            Some((session_id, answer_text)),
+        ),
+        Err(e) => (
+            serde_json::json!({ "status": "failed", "error": e.to_response_body() }),
+            None,
+        ),
+    };
+    let succeeded = push.is_some();
+
+    update["finished_at"] = serde_json::json!(Utc::now().to_rfc3339());
+    // Never overwrite a job that was expired (failed) while running
+    let stored: AppResult<Vec<serde_json::Value>> = state
+        .supabase
+        .update_returning(
+            "ai_jobs",
+            &format!("{}&status=eq.running&select=id", query),
+            &update,
+            &user.token,
+        )
+        .await;
+    match stored {
+        Ok(rows) if !rows.is_empty() => {}
+        Ok(_) => {
+            tracing::warn!(job_id = %job_id, "AI job expired while running; result discarded");
+            return;
+        }
+        Err(e) => {
+            tracing::error!(job_id = %job_id, "Failed to store AI job result: {}", e);
+            return;
+        }
+    }
+
+    tracing::info!(
+        user_id = %user.user_id,
+        job_id = %job_id,
+        kind = job_kind,
+        succeeded = succeeded,
+        "AI job finished"
+    );
+
+    // Push notification (best-effort); the client fetches the result by job_id
+    let (session_id, body) = push.unwrap_or_else(|| {
+        (
+            String::new(),
+            "AIの応答を取得できませんでした。もう一度お試しください。".to_string(),
+        )
+    });
+    send_chat_push_best_effort(
+        &state,
+        &user.token,
+        "ガチトレAI",
+        &body,
+        serde_json::json!({
+            "kind": "ai_job",
+            "job_id": job_id,
+            "job_kind": job_kind,
+            "status": if succeeded { "succeeded" } else { "failed" },
+            "session_id": session_id,
+        }),
+    )
+    .await;
+}
+
+/// Run the job and return (result JSON, session_id, answer_text for the push)
+async fn execute_job(
+    state: &AppState,
+    user: &AuthUser,
+    input: AiJobInput,
+) -> AppResult<(serde_json::Value, String, String)> {
+    let to_json = |e: serde_json::Error| {
+        AppError::Internal(format!("Failed to serialize AI job result: {}", e))
+    };
+    match input {
+        AiJobInput::Ask(req) => {
+            let res = run_ask(state, user, req).await?;
+            let (session_id, answer_text) = (res.session_id.clone(), res.answer_text.clone());
+            Ok((serde_json::to_value(res).map_err(to_json)?, session_id, answer_text))
+        }
+        AiJobInput::PlanToday(req) => {
+            let res = run_plan_today(state, user, req).await?;
+            let (session_id, answer_text) = (res.session_id.clone(), res.answer_text.clone());
+            Ok((serde_json::to_value(res).map_err(to_json)?, session_id, answer_text))
+        }
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+async fn find_job_by_request_id(
+    state: &AppState,
+    user: &AuthUser,
+    request_id: Uuid,
+) -> AppResult<Option<AiJobResponse>> {
+    state
+        .supabase
+        .select_single(
+            "ai_jobs",
+            &format!(
+                "user_id=eq.{}&request_id=eq.{}&select={}",
+                user.user_id, request_id, JOB_COLUMNS
+            ),
+            &user.token,
+        )
+        .await
+}
+
+/// Report a job whose worker is gone as failed (and persist it, best-effort)
+async fn expire_if_stale(state: &AppState, user: &AuthUser, job: AiJobResponse) -> AiJobResponse {
+    let now = Utc::now();
+    if !job.is_expired(now) {
+        return job;
+    }
+
+    let error = serde_json::json!({
+        "error": "job_expired",
+        "message": "AIの処理がタイムアウトしました。もう一度お試しください。",
+    });
+    // Conditional on the status we saw: a job the worker just claimed or finished is
+    // returned as stored instead of being reported failed
+    let expired: AppResult<Vec<AiJobResponse>> = state
+        .supabase
+        .update_returning(
+            "ai_jobs",
+            &format!(
+                "id=eq.{}&user_id=eq.{}&status=eq.{}&select={}",
+                job.id, user.user_id, job.status, JOB_COLUMNS
+            ),
+            &serde_json::json!({
+                "status": "failed",
+                "error": error,
+                "finished_at": now.to_rfc3339(),
+            }),
+            &user.token,
+        )
+        .await;
+
+    match expired {
+        Ok(rows) => match rows.into_iter().next() {
+            Some(updated) => updated,
+            None => {
+                let current: AppResult<Option<AiJobResponse>> = state
+                    .supabase
+                    .select_single(
+                        "ai_jobs",
+                        &format!(
+                            "id=eq.{}&user_id=eq.{}&select={}",
+                            job.id, user.user_id, JOB_COLUMNS
+                        ),
+                        &user.token,
+                    )
+                    .await;
+                match current {
+                    Ok(Some(current)) => current,
+                    _ => job,
+                }
+            }
+        },
+        Err(e) => {
+            // Not persisted: the row may still be claimed, so don't report it as failed
+            tracing::warn!(job_id = %job.id, "Failed to expire AI job: {}", e);
+            job
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(status: &str, created_min_ago: i64, started_min_ago: Option<i64>) -> AiJobResponse {
+        let now = Utc::now();
+        AiJobResponse {
+            id: "j".to_string(),
+            request_id: "r".to_string(),
+            kind: "ask".to_string(),
+            status: status.to_string(),
+            result: None,
+            error: None,
+            created_at: now - Duration::minutes(created_min_ago),
+            started_at: started_min_ago.map(|m| now - Duration::minutes(m)),
+            finished_at: None,
+        }
+    }
+
+    #[test]
+    fn test_job_expiry() {
+        let now = Utc::now();
+        assert!(job("queued", 11, None).is_expired(now));
+        assert!(!job("queued", 5, None).is_expired(now));
+        // Running jobs are measured from started_at, not from the queue wait
+        assert!(!job("running", 15, Some(2)).is_expired(now));
+        assert!(job("running", 25, Some(11)).is_expired(now));
+        assert!(!job("failed", 60, Some(50)).is_expired(now));
+        assert!(!job("succeeded", 60, Some(50)).is_expired(now));
+    }
+}
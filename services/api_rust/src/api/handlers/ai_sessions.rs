@@ -0,0 +1,387 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{middleware::AuthUser, validation::validate_uuid},
+    error::{AppError, AppResult},
+    state::retrieval::{query_term_groups, HistoryIndex, SourceType},
+    AppState,
+};
+
+// =============================================================================
+// GET /v1/ai/sessions/:id - session detail (messages + recommendations)
+// PATCH /v1/ai/sessions/:id - rename / pin
+// DELETE /v1/ai/sessions/:id - delete (messages and recommendations cascade)
+// GET /v1/ai/sessions/search - search the user's past messages
+// =============================================================================
+
+const MAX_TITLE_CHARS: usize = 100;
+const MAX_SESSION_MESSAGES: usize = 500;
+const SEARCH_DEFAULT_LIMIT: usize = 20;
+const SEARCH_MAX_LIMIT: usize = 50;
+/// Characters of context shown on each side of the first match
+const SNIPPET_RADIUS: usize = 40;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AiSessionInfo {
+    pub id: String,
+    pub intent: String,
+    pub title: Option<String>,
+    pub pinned: bool,
+    pub model: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AiSessionMessage {
+    pub id: String,
+    pub role: String,
+    pub content: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AiSessionRecommendation {
+    pub id: String,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub is_applied: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AiSessionDetailResponse {
+    pub session: AiSessionInfo,
+    pub messages: Vec<AiSessionMessage>,
+    pub recommendations: Vec<AiSessionRecommendation>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateAiSessionRequest {
+    /// New title; empty string clears it
+    pub title: Option<String>,
+    pub pinned: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeleteAiSessionResponse {
+    pub success: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchAiSessionsQuery {
+    pub q: String,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchAiSessionsResponse {
+    pub hits: Vec<AiMessageHit>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AiMessageHit {
+    pub session_id: String,
+    pub session_title: Option<String>,
+    pub intent: String,
+    pub message_id: String,
+    pub role: String,
+    /// Excerpt around the first matched term
+    pub snippet: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageSearchRow {
+    id: String,
+    session_id: String,
+    role: String,
+    content: String,
+    created_at: String,
+    ai_sessions: SessionRef,
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionRef {
+    title: Option<String>,
+    intent: String,
+}
+
+// =============================================================================
+// Handlers
+// =============================================================================
+
+/// GET /v1/ai/sessions/:id - Session with all of its messages and recommendations
+pub async fn get_ai_session(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(session_id): Path<String>,
+) -> AppResult<Json<AiSessionDetailResponse>> {
+    let session_id = validate_uuid(&session_id)?;
+    let session = fetch_own_session(&state, &user, &session_id.to_string()).await?;
+
+    let messages_query = format!(
+        "session_id=eq.{}&select=id,role,content,created_at&order=created_at.asc&limit={}",
+        session_id, MAX_SESSION_MESSAGES
+    );
+    let recommendations_query = format!(
+        "session_id=eq.{}&select=id,kind,payload,is_applied,created_at&order=created_at.asc",
+        session_id
+    );
+    let (messages, recommendations) = tokio::try_join!(
+        state
+            .supabase
+            .select::<AiSessionMessage>("ai_messages", &messages_query, &user.token),
+        state.supabase.select::<AiSessionRecommendation>(
+            "ai_recommendations",
+            &recommendations_query,
+            &user.token,
+        ),
+    )?;
+
+    Ok(Json(AiSessionDetailResponse {
+        session,
+        messages,
+        recommendations,
+    }))
+}
+
+/// PATCH /v1/ai/sessions/:id - Rename and/or pin a session
+pub async fn update_ai_session(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(session_id): Path<String>,
+    Json(req): Json<UpdateAiSessionRequest>,
+) -> AppResult<Json<AiSessionInfo>> {
+    let session_id = validate_uuid(&session_id)?.to_string();
+
+    let mut update = serde_json::Map::new();
+    if let Some(title) = &req.title {
+        let title = title.trim();
+        if title.chars().count() > MAX_TITLE_CHARS {
+            return Err(AppError::Validation(format!(
+                "title is too long (max {} chars)",
+                MAX_TITLE_CHARS
+            )));
+        }
+        let value = if title.is_empty() {
+            serde_json::Value::Null
+        } else {
+            serde_json::Value::String(title.to_string())
+        };
+        update.insert("title".to_string(), value);
+    }
+    if let Some(pinned) = req.pinned {
+        update.insert("pinned".to_string(), serde_json::Value::Bool(pinned));
+    }
+    if update.is_empty() {
+        return Err(AppError::Validation(
+            "title or pinned is required".to_string(),
+        ));
+    }
+
+    // Ownership check first so an unknown id is a 404, not a silent no-op
+    fetch_own_session(&state, &user, &session_id).await?;
+    state
+        .supabase
+        .update(
+            "ai_sessions",
+            &format!("id=eq.{}&user_id=eq.{}", session_id, user.user_id),
+            &serde_json::Value::Object(update),
+            &user.token,
+        )
+        .await?;
+
+    let session = fetch_own_session(&state, &user, &session_id).await?;
+    Ok(Json(session))
+}
+
+/// DELETE /v1/ai/sessions/:id - Delete a session (messages and recommendations cascade)
+pub async fn delete_ai_session(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(session_id): Path<String>,
+) -> AppResult<Json<DeleteAiSessionResponse>> {
+    let session_id = validate_uuid(&session_id)?.to_string();
+    fetch_own_session(&state, &user, &session_id).await?;
+
+    // Drop it from the retrieval index so deleted conversations are never cited
+    HistoryIndex::new(&state.supabase, &user.token, None)
+        .remove(&user.user_id, SourceType::AiSession, &session_id)
+        .await?;
+
+    state
+        .supabase
+        .delete(
+            "ai_sessions",
+            &format!("id=eq.{}&user_id=eq.{}", session_id, user.user_id),
+            &user.token,
+        )
+        .await?;
+
+    tracing::info!(user_id = %user.user_id, session_id = %session_id, "AI session deleted");
+
+    Ok(Json(DeleteAiSessionResponse { success: true }))
+}
+
+/// GET /v1/ai/sessions/search?q=...&limit=20 - Search past messages (every word must match, via any alias)
+pub async fn search_ai_sessions(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(params): Query<SearchAiSessionsQuery>,
+) -> AppResult<Json<SearchAiSessionsResponse>> {
+    if params.q.trim().is_empty() {
+        return Err(AppError::Validation("q is required".to_string()));
+    }
+    let limit = params
+        .limit
+        .unwrap_or(SEARCH_DEFAULT_LIMIT)
+        .clamp(1, SEARCH_MAX_LIMIT);
+
+    let groups = query_term_groups(&params.q);
+    if groups.is_empty() {
+        return Ok(Json(SearchAiSessionsResponse { hits: Vec::new() }));
+    }
+    let and = match_all_groups(&groups);
+    let terms: Vec<String> = groups.into_iter().flatten().collect();
+
+    let rows: Vec<MessageSearchRow> = state
+        .supabase
+        .select(
+            "ai_messages",
+            &format!(
+                "select=id,session_id,role,content,created_at,ai_sessions!inner(title,intent)&ai_sessions.user_id=eq.{}&and=({})&role=neq.system&order=created_at.desc&limit={}",
+                user.user_id,
+                urlencoding::encode(&and),
+                limit
+            ),
+            &user.token,
+        )
+        .await?;
+
+    let hits = rows
+        .into_iter()
+        .map(|row| AiMessageHit {
+            snippet: snippet_around(&row.content, &terms, SNIPPET_RADIUS),
+            session_id: row.session_id,
+            session_title: row.ai_sessions.title,
+            intent: row.ai_sessions.intent,
+            message_id: row.id,
+            role: row.role,
+            created_at: row.created_at,
+        })
+        .collect();
+
+    Ok(Json(SearchAiSessionsResponse { hits }))
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Load the session, 404 if it does not exist or belongs to someone else
+async fn fetch_own_session(
+    state: &AppState,
+    user: &AuthUser,
+    session_id: &str,
+) -> AppResult<AiSessionInfo> {
+    let session: Option<AiSessionInfo> = state
+        .supabase
+        .select_single(
+            "ai_sessions",
+            &format!(
+                "id=eq.{}&user_id=eq.{}&select=id,intent,title,pinned,model,created_at",
+                session_id, user.user_id
+            ),
+            &user.token,
+        )
+        .await?;
+    session.ok_or_else(|| AppError::NotFound("AI session not found".to_string()))
+}
+
+/// Body of an `and=(...)` filter: every word must match, through any of its aliases
+fn match_all_groups(groups: &[Vec<String>]) -> String {
+    groups
+        .iter()
+        .map(|group| {
+            let alternatives = group
+                .iter()
+                .map(|t| format!("content.ilike.*{}*", t))
+                .collect::<Vec<_>>();
+            match alternatives.as_slice() {
+                [single] => single.clone(),
+                _ => format!("or({})", alternatives.join(",")),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Excerpt of `content` centered on the earliest (case-insensitive) term match
+fn snippet_around(content: &str, terms: &[String], radius: usize) -> String {
+    let chars: Vec<char> = content.chars().collect();
+    let lower: Vec<char> = content.to_lowercase().chars().collect();
+    // Lowercasing can change the char count (rare); fall back to the head of the text
+    let pos = if lower.len() == chars.len() {
+        terms
+            .iter()
+            .filter_map(|t| {
+                let needle: Vec<char> = t.chars().collect();
+                (!needle.is_empty())
+                    .then(|| lower.windows(needle.len()).position(|w| w == needle.as_slice()))
+                    .flatten()
+            })
+            .min()
+            .unwrap_or(0)
+    } else {
+        0
+    };
+
+    let start = pos.saturating_sub(radius);
+    let end = (pos + radius).min(chars.len());
+    let mut snippet: String = chars[start..end].iter().collect();
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_all_groups() {
+        // "ベンチ" also finds messages that only say "ベンチプレス" (and vice versa)
+        let groups = query_term_groups("ベンチ 100kg");
+        assert_eq!(
+            match_all_groups(&groups),
+            "or(content.ilike.*ベンチプレス*,content.ilike.*ベンチ*),content.ilike.*100kg*"
+        );
+        let groups = query_term_groups("bench");
+        assert_eq!(groups.len(), 1);
+        assert!(match_all_groups(&groups).starts_with("or("));
+    }
+
+    #[test]
+    fn test_snippet_around() {
+        let text = "今日はベンチプレスを100kgで5回やりました。次回はスクワットを増やしたいです。";
+        let terms = vec!["スクワット".to_string()];
+        let snippet = snippet_around(text, &terms, 5);
+        assert!(snippet.starts_with('…'));
+        assert!(snippet.contains("スクワット"));
+        assert!(snippet.ends_with('…'));
+
+        // Short text: no ellipsis
+        assert_eq!(snippet_around("Bench 100kg", &["bench".to_string()], 40), "Bench 100kg");
+
+        // No match: head of the text
+        assert_eq!(snippet_around("abcdefgh", &["zz".to_string()], 3), "abc…");
+    }
+}
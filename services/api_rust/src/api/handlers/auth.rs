@@ -1,7 +1,11 @@
-use axum::{extract::{rejection::JsonRejection, State}, Json};
+use axum::{extract::{rejection::JsonRejection, Path, Query, State}, Extension, Json};
 use serde::{Deserialize, Serialize};
 
-use crate::{error::AppResult, AppState};
+use crate::{
+    api::middleware::{fetch_mfa_factors, AuthUser, MfaFactor},
+    error::{AppError, AppResult},
+    AppState,
+};
 
 // =============================================================================
 // Request/Response DTOs
@@ -19,6 +23,16 @@ pub struct SignInRequest {
     pub password: String,
 }
 
+/// Google / Apple native sign-in result
+#[derive(Debug, Deserialize)]
+pub struct IdTokenSignInRequest {
+    pub id_token: String,
+    /// Raw nonce; the id_token carries its SHA-256. Required for Apple.
+    pub nonce: Option<String>,
+    /// Google only: OAuth access token issued together with the id_token (optional)
+    pub access_token: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub access_token: String,
@@ -49,6 +63,93 @@ pub struct MessageResponse {
     pub message: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeEmailRequest {
+    pub new_email: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangeEmailResponse {
+    pub message: String,
+    /// Pending until the confirmation link is opened
+    pub new_email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SignOutSessionsQuery {
+    /// "global" (default, includes this device) or "others"
+    pub scope: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionsResponse {
+    pub sessions: Vec<SessionInfo>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaStatusResponse {
+    /// AAL of the access token used for this request
+    pub current_level: String,
+    /// Highest AAL reachable ("aal2" once a TOTP factor is verified)
+    pub next_level: String,
+    pub factors: Vec<MfaFactor>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MfaEnrollRequest {
+    pub friendly_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaEnrollResponse {
+    pub factor_id: String,
+    /// SVG data URL for the authenticator app
+    pub qr_code: String,
+    /// Manual-entry secret (base32)
+    pub secret: String,
+    pub uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MfaChallengeRequest {
+    pub factor_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaChallengeResponse {
+    pub challenge_id: String,
+    /// Unix seconds
+    pub expires_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MfaVerifyRequest {
+    pub factor_id: String,
+    /// Omit to challenge and verify in one call
+    pub challenge_id: Option<String>,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub created_at: String,
+    pub last_active_at: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    /// "aal1" (password / social) or "aal2" (MFA verified)
+    pub aal: Option<String>,
+    /// The session of the access token used for this request
+    #[serde(default)]
+    pub current: bool,
+}
+
 // =============================================================================
 // Supabase Auth API responses
 // =============================================================================
@@ -66,6 +167,25 @@ struct SupabaseAuthResponse {
     user: SupabaseUser,
 }
 
+#[derive(Debug, Deserialize)]
+struct SupabaseTotpEnrollResponse {
+    id: String,
+    totp: SupabaseTotp,
+}
+
+#[derive(Debug, Deserialize)]
+struct SupabaseTotp {
+    qr_code: String,
+    secret: String,
+    uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SupabaseChallengeResponse {
+    id: String,
+    expires_at: i64,
+}
+
 #[derive(Debug, Deserialize)]
 struct SupabaseUser {
     id: String,
@@ -121,6 +241,24 @@ fn translate_auth_error(error: &str) -> String {
     if error_lower.contains("email") && (error_lower.contains("invalid") || error_lower.contains("format")) {
         return "有効なメールアドレスを入力してください".to_string();
     }
+    if error_lower.contains("totp") && error_lower.contains("invalid") {
+        return "認証コードが正しくありません".to_string();
+    }
+    if error_lower.contains("challenge") && error_lower.contains("expired") {
+        return "認証コードの有効期限が切れました。もう一度お試しください".to_string();
+    }
+    if error_lower.contains("friendly name") {
+        return "同じ名前の認証アプリが既に登録されています".to_string();
+    }
+    if error_lower.contains("maximum") && error_lower.contains("factor") {
+        return "登録できる認証アプリの上限に達しています".to_string();
+    }
+    if error_lower.contains("factor") && error_lower.contains("not found") {
+        return "二段階認証の設定が見つかりません".to_string();
+    }
+    if error_lower.contains("aal2") {
+        return "この操作には二段階認証が必要です".to_string();
+    }
     if error_lower.contains("already registered") || error_lower.contains("already exists") {
         return "このメールアドレスは既に登録されています".to_string();
     }
@@ -136,6 +274,27 @@ fn translate_auth_error(error: &str) -> String {
     if error_lower.contains("user not found") {
         return "ユーザーが見つかりません".to_string();
     }
+    if error_lower.contains("password") && (error_lower.contains("same") || error_lower.contains("different")) {
+        return "新しいパスワードは現在のパスワードと異なるものにしてください".to_string();
+    }
+    if error_lower.contains("reauthentication") {
+        return "セキュリティのため、再ログインしてから変更してください".to_string();
+    }
+    if error_lower.contains("nonce") {
+        return "ログイン情報の検証に失敗しました。もう一度お試しください".to_string();
+    }
+    if error_lower.contains("provider") && (error_lower.contains("not enabled") || error_lower.contains("unsupported")) {
+        return "このログイン方法は現在利用できません".to_string();
+    }
+    if error_lower.contains("already linked") || error_lower.contains("identity_already_exists") {
+        return "このアカウントは既に別のユーザーに連携されています".to_string();
+    }
+    if error_lower.contains("unverified email") || error_lower.contains("email not verified") {
+        return "メールアドレスが確認されていないため連携できません".to_string();
+    }
+    if error_lower.contains("id token") || error_lower.contains("id_token") {
+        return "ソーシャルログインの認証情報が無効です。もう一度お試しください".to_string();
+    }
     if error_lower.contains("token") && error_lower.contains("expired") {
         return "セッションの有効期限が切れました。再度ログインしてください".to_string();
     }
@@ -144,6 +303,183 @@ fn translate_auth_error(error: &str) -> String {
     "認証エラーが発生しました。入力内容をご確認ください".to_string()
 }
 
+/// Social providers accepted by POST /auth/google and POST /auth/apple
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IdTokenProvider {
+    Google,
+    Apple,
+}
+
+impl IdTokenProvider {
+    fn as_str(self) -> &'static str {
+        match self {
+            IdTokenProvider::Google => "google",
+            IdTokenProvider::Apple => "apple",
+        }
+    }
+
+    fn issuers(self) -> &'static [&'static str] {
+        match self {
+            IdTokenProvider::Google => &["https://accounts.google.com", "accounts.google.com"],
+            IdTokenProvider::Apple => &["https://appleid.apple.com"],
+        }
+    }
+}
+
+/// Unverified id_token claims, used only for early input checks
+/// (Supabase verifies the signature, audience and nonce)
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    iss: Option<String>,
+    /// Apple sends "true" / "false" as strings
+    email_verified: Option<serde_json::Value>,
+}
+
+impl IdTokenClaims {
+    fn email_verified(&self) -> bool {
+        match &self.email_verified {
+            Some(serde_json::Value::Bool(b)) => *b,
+            Some(serde_json::Value::String(s)) => s == "true",
+            _ => false,
+        }
+    }
+}
+
+fn decode_id_token_claims(id_token: &str) -> Option<IdTokenClaims> {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+
+    let mut parts = id_token.split('.');
+    let (Some(_), Some(payload), Some(_), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        return None;
+    };
+    let bytes = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/// Validate the request shape before calling Supabase
+fn validate_id_token_request(
+    provider: IdTokenProvider,
+    req: &IdTokenSignInRequest,
+) -> Result<IdTokenClaims, String> {
+    if req.id_token.is_empty() || req.id_token.len() > 8192 {
+        return Err("id_token is invalid".to_string());
+    }
+    let claims = decode_id_token_claims(&req.id_token).ok_or_else(|| "id_token is invalid".to_string())?;
+    if !claims
+        .iss
+        .as_deref()
+        .is_some_and(|iss| provider.issuers().contains(&iss))
+    {
+        return Err(format!("id_token was not issued by {}", provider.as_str()));
+    }
+
+    match req.nonce.as_deref() {
+        Some(nonce) if nonce.is_empty() || nonce.len() > 256 => Err("nonce is invalid".to_string()),
+        None if provider == IdTokenProvider::Apple => Err("nonce is required for Apple".to_string()),
+        _ => Ok(claims),
+    }
+}
+
+/// Map a non-2xx Supabase Auth response: 5xx -> UpstreamAuth, otherwise a translated AuthError
+fn auth_upstream_error(context: &str, status: reqwest::StatusCode, body: &str) -> AppError {
+    tracing::warn!(
+        "{} upstream non-2xx (status={} body_len={})",
+        context,
+        status.as_u16(),
+        body.len()
+    );
+    if status.is_server_error() {
+        return AppError::UpstreamAuth(format!(
+            "{} upstream failed: status={}",
+            context,
+            status.as_u16()
+        ));
+    }
+    let raw_error = serde_json::from_str::<SupabaseError>(body)
+        .ok()
+        .and_then(|e| e.error_description.or(e.msg).or(e.error))
+        .unwrap_or_default();
+    AppError::AuthError(translate_auth_error(&raw_error))
+}
+
+/// new_password: 1-72 bytes (bcrypt limit) and different from the current one
+fn validate_password_change(req: &ChangePasswordRequest) -> AppResult<()> {
+    if req.new_password.is_empty() || req.new_password.len() > 72 {
+        return Err(AppError::Validation(
+            "new_password must be 1-72 bytes".to_string(),
+        ));
+    }
+    if req.new_password == req.current_password {
+        return Err(AppError::Validation(translate_auth_error(
+            "New password should be different from the old password",
+        )));
+    }
+    Ok(())
+}
+
+/// Trimmed, lowercased new address; rejects malformed input and the current address
+fn normalize_new_email(new_email: &str, current_email: &str) -> AppResult<String> {
+    let new_email = new_email.trim().to_lowercase();
+    let valid_shape = new_email.len() <= 254
+        && new_email
+            .split_once('@')
+            .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'));
+    if !valid_shape {
+        return Err(AppError::Validation(translate_auth_error("invalid email format")));
+    }
+    if new_email == current_email.trim().to_lowercase() {
+        return Err(AppError::Validation(
+            "現在と同じメールアドレスです".to_string(),
+        ));
+    }
+    Ok(new_email)
+}
+
+/// DELETE /account/sessions scope (default "global") and its response message
+fn parse_sign_out_scope(scope: Option<&str>) -> AppResult<(&'static str, &'static str)> {
+    match scope.unwrap_or("global") {
+        "global" => Ok(("global", "すべての端末からログアウトしました")),
+        "others" => Ok(("others", "他の端末からログアウトしました")),
+        _ => Err(AppError::Validation(
+            "Invalid scope. Allowed: global, others".to_string(),
+        )),
+    }
+}
+
+/// 6-digit TOTP code; spaces (as shown by some authenticator apps) are ignored
+fn normalize_totp_code(code: &str) -> AppResult<String> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != 6 || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AppError::Validation("認証コードは6桁の数字で入力してください".to_string()));
+    }
+    Ok(code)
+}
+
+/// Factor / challenge ids are interpolated into Supabase Auth URLs
+fn parse_mfa_id(field: &str, value: &str) -> AppResult<String> {
+    uuid::Uuid::parse_str(value.trim())
+        .map(|id| id.to_string())
+        .map_err(|_| AppError::Validation(format!("Invalid {}", field)))
+}
+
+/// Sign out in Supabase Auth: scope "local" (this session), "others" or "global"
+pub(crate) async fn logout_sessions(state: &AppState, token: &str, scope: &str) -> AppResult<()> {
+    let url = format!("{}/auth/v1/logout?scope={}", state.config.supabase_url, scope);
+    let response = reqwest::Client::new()
+        .post(&url)
+        .header("apikey", &state.config.supabase_anon_key)
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(auth_upstream_error("auth.logout", status, &body));
+    }
+    Ok(())
+}
+
 // =============================================================================
 // Handlers
 // =============================================================================
@@ -154,7 +490,7 @@ pub async fn signup(
     payload: Result<Json<SignUpRequest>, JsonRejection>,
 ) -> AppResult<Json<AuthResponse>> {
     let Json(req) = payload.map_err(|_| {
-        crate::error::AppError::BadRequest("リクエスト形式が不正です（email/password を確認してください）".to_string())
+        AppError::BadRequest("リクエスト形式が不正です（email/password を確認してください）".to_string())
     })?;
 
     // Marker log to correlate with 500s (safe: does not print password/token)
@@ -204,7 +540,7 @@ pub async fn signup(
                 status.as_u16(),
                 response_text.len()
             );
-            return Err(crate::error::AppError::UpstreamAuth(format!(
+            return Err(AppError::UpstreamAuth(format!(
                 "signup upstream failed: status={} body_len={}",
                 status.as_u16(),
                 response_text.len()
@@ -213,7 +549,7 @@ pub async fn signup(
 
         // Convert common Supabase errors to user-friendly Japanese messages
         let user_message = translate_auth_error(&raw_error);
-        return Err(crate::error::AppError::BadRequest(user_message));
+        return Err(AppError::BadRequest(user_message));
     }
 
     // Parse the response (Supabase may return different shapes depending on settings)
@@ -228,7 +564,7 @@ pub async fn signup(
             e,
             response_text.len()
         );
-        crate::error::AppError::UpstreamAuth(format!(
+        AppError::UpstreamAuth(format!(
             "invalid signup response: {} (body_len={})",
             e,
             response_text.len()
@@ -256,7 +592,7 @@ pub async fn signup(
                 "Signup succeeded but no access token returned (email confirmation may be required)"
             );
             eprintln!("[auth.signup] no access token returned (email confirmation may be required)");
-            return Err(crate::error::AppError::AuthError(
+            return Err(AppError::AuthError(
                 "メール確認が必要です。メールを確認してください。".to_string(),
             ));
         }
@@ -311,7 +647,7 @@ pub async fn signin(
             .unwrap_or_else(|| "Invalid login credentials".to_string());
 
         let user_message = translate_auth_error(&raw_error);
-        return Err(crate::error::AppError::AuthError(user_message));
+        return Err(AppError::AuthError(user_message));
     }
 
     let auth_response: SupabaseAuthResponse = response.json().await?;
@@ -328,6 +664,110 @@ pub async fn signin(
     }))
 }
 
+/// POST /auth/google - Sign in with a Google id_token (native Google Sign-In)
+pub async fn signin_google(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    payload: Result<Json<IdTokenSignInRequest>, JsonRejection>,
+) -> AppResult<Json<AuthResponse>> {
+    signin_with_id_token(&state, &headers, IdTokenProvider::Google, payload).await
+}
+
+/// POST /auth/apple - Sign in with an Apple id_token (Sign in with Apple, nonce required)
+pub async fn signin_apple(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    payload: Result<Json<IdTokenSignInRequest>, JsonRejection>,
+) -> AppResult<Json<AuthResponse>> {
+    signin_with_id_token(&state, &headers, IdTokenProvider::Apple, payload).await
+}
+
+/// Exchange a provider id_token through Supabase `grant_type=id_token`.
+///
+/// Without an Authorization header, Supabase signs the user in (creating the account
+/// on first use; an existing account with the same verified email gets the identity
+/// linked automatically). With `Authorization: Bearer <access_token>` the identity is
+/// explicitly linked to that signed-in account, which requires a verified email.
+async fn signin_with_id_token(
+    state: &AppState,
+    headers: &axum::http::HeaderMap,
+    provider: IdTokenProvider,
+    payload: Result<Json<IdTokenSignInRequest>, JsonRejection>,
+) -> AppResult<Json<AuthResponse>> {
+    let Json(req) = payload.map_err(|_| {
+        AppError::BadRequest("リクエスト形式が不正です（id_token を確認してください）".to_string())
+    })?;
+
+    let claims = validate_id_token_request(provider, &req).map_err(|e| {
+        tracing::debug!("auth.{} rejected: {}", provider.as_str(), e);
+        AppError::AuthError(translate_auth_error(&e))
+    })?;
+
+    let link_token = headers
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .filter(|t| !t.is_empty());
+    if link_token.is_some() && !claims.email_verified() {
+        return Err(AppError::AuthError(translate_auth_error(
+            "unverified email",
+        )));
+    }
+
+    let mut body = serde_json::json!({
+        "provider": provider.as_str(),
+        "id_token": req.id_token,
+    });
+    if let Some(nonce) = &req.nonce {
+        body["nonce"] = serde_json::json!(nonce);
+    }
+    if let (IdTokenProvider::Google, Some(access_token)) = (provider, &req.access_token) {
+        body["access_token"] = serde_json::json!(access_token);
+    }
+    if link_token.is_some() {
+        body["link_identity"] = serde_json::json!(true);
+    }
+
+    let url = format!("{}/auth/v1/token?grant_type=id_token", state.config.supabase_url);
+
+    let client = reqwest::Client::new();
+    let mut request = client
+        .post(&url)
+        .header("apikey", &state.config.supabase_anon_key)
+        .header("Content-Type", "application/json");
+    if let Some(token) = link_token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    let response = request.json(&body).send().await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(auth_upstream_error(&format!("auth.{}", provider.as_str()), status, &body));
+    }
+
+    let auth_response: SupabaseAuthResponse = response.json().await?;
+
+    tracing::info!(
+        "auth.{} successful for user: {} (linked={})",
+        provider.as_str(),
+        auth_response.user.id,
+        link_token.is_some()
+    );
+
+    Ok(Json(AuthResponse {
+        access_token: auth_response.access_token,
+        refresh_token: auth_response.refresh_token,
+        token_type: auth_response.token_type,
+        expires_in: auth_response.expires_in,
+        user: UserInfo {
+            id: auth_response.user.id,
+            // Apple users may hide their email
+            email: auth_response.user.email.unwrap_or_default(),
+        },
+    }))
+}
+
 /// POST /auth/refresh
 pub async fn refresh_token(
     State(state): State<AppState>,
@@ -358,7 +798,7 @@ pub async fn refresh_token(
             .unwrap_or_else(|| "Token refresh failed".to_string());
 
         let user_message = translate_auth_error(&raw_error);
-        return Err(crate::error::AppError::AuthError(user_message));
+        return Err(AppError::AuthError(user_message));
     }
 
     let auth_response: SupabaseAuthResponse = response.json().await?;
@@ -431,10 +871,525 @@ pub async fn reset_password(
             .unwrap_or_else(|| "Password reset failed".to_string());
 
         let user_message = translate_auth_error(&raw_error);
-        return Err(crate::error::AppError::AuthError(user_message));
+        return Err(AppError::AuthError(user_message));
     }
 
     Ok(Json(MessageResponse {
         message: "パスワードリセットメールを送信しました".to_string(),
     }))
 }
+
+// =============================================================================
+// Account security (auth required): /v1/account/*
+// =============================================================================
+
+/// POST /account/password - Change password after checking the current one
+///
+/// Other devices are signed out afterwards; this session stays valid.
+pub async fn change_password(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    payload: Result<Json<ChangePasswordRequest>, JsonRejection>,
+) -> AppResult<Json<MessageResponse>> {
+    let Json(req) = payload.map_err(|_| {
+        AppError::BadRequest(
+            "リクエスト形式が不正です（current_password/new_password を確認してください）".to_string(),
+        )
+    })?;
+    validate_password_change(&req)?;
+    if user.email.is_empty() {
+        return Err(AppError::BadRequest(
+            "このアカウントはパスワードでのログインに対応していません".to_string(),
+        ));
+    }
+
+    // Current-password check: a password grant for the same email
+    let client = reqwest::Client::new();
+    let url = format!("{}/auth/v1/token?grant_type=password", state.config.supabase_url);
+    let response = client
+        .post(&url)
+        .header("apikey", &state.config.supabase_anon_key)
+        .header("Content-Type", "application/json")
+        .json(&serde_json::json!({
+            "email": user.email,
+            "password": req.current_password
+        }))
+        .send()
+        .await?;
+
+    let status = response.status();
+    if status.is_server_error() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(auth_upstream_error("auth.password.verify", status, &body));
+    }
+    if !status.is_success() {
+        return Err(AppError::AuthError(
+            "現在のパスワードが正しくありません".to_string(),
+        ));
+    }
+    // The check created a session of its own; end it
+    if let Ok(verify) = response.json::<SupabaseAuthResponse>().await {
+        if let Err(e) = logout_sessions(&state, &verify.access_token, "local").await {
+            tracing::warn!("auth.password: failed to end verification session: {}", e);
+        }
+    }
+
+    let url = format!("{}/auth/v1/user", state.config.supabase_url);
+    let response = client
+        .put(&url)
+        .header("apikey", &state.config.supabase_anon_key)
+        .header("Authorization", format!("Bearer {}", user.token))
+        .header("Content-Type", "application/json")
+        .json(&serde_json::json!({ "password": req.new_password }))
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(auth_upstream_error("auth.password", status, &body));
+    }
+
+    if let Err(e) = logout_sessions(&state, &user.token, "others").await {
+        tracing::warn!("auth.password: failed to sign out other sessions: {}", e);
+    }
+
+    tracing::info!("Password changed for user: {}", user.user_id);
+
+    Ok(Json(MessageResponse {
+        message: "パスワードを変更しました。他の端末からはログアウトされます".to_string(),
+    }))
+}
+
+/// POST /account/email - Request an email change (completed via the confirmation link)
+pub async fn change_email(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    payload: Result<Json<ChangeEmailRequest>, JsonRejection>,
+) -> AppResult<Json<ChangeEmailResponse>> {
+    let Json(req) = payload.map_err(|_| {
+        AppError::BadRequest("リクエスト形式が不正です（new_email を確認してください）".to_string())
+    })?;
+    let new_email = normalize_new_email(&req.new_email, &user.email)?;
+
+    let url = format!("{}/auth/v1/user", state.config.supabase_url);
+    let response = reqwest::Client::new()
+        .put(&url)
+        .header("apikey", &state.config.supabase_anon_key)
+        .header("Authorization", format!("Bearer {}", user.token))
+        .header("Content-Type", "application/json")
+        .json(&serde_json::json!({ "email": new_email }))
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(auth_upstream_error("auth.email", status, &body));
+    }
+
+    tracing::info!("Email change requested for user: {}", user.user_id);
+
+    Ok(Json(ChangeEmailResponse {
+        message: "確認メールを送信しました。メール内のリンクから変更を完了してください".to_string(),
+        new_email,
+    }))
+}
+
+/// GET /account/sessions - Active sessions (devices with a usable refresh token)
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> AppResult<Json<SessionsResponse>> {
+    let mut sessions: Vec<SessionInfo> = state
+        .supabase
+        .rpc("list_my_sessions", &serde_json::json!({}), &user.token)
+        .await?;
+
+    for session in &mut sessions {
+        session.current = user.session_id.as_deref() == Some(session.id.as_str());
+    }
+
+    Ok(Json(SessionsResponse { sessions }))
+}
+
+/// DELETE /account/sessions?scope=global|others - Sign out everywhere (or all other devices)
+pub async fn sign_out_sessions(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<SignOutSessionsQuery>,
+) -> AppResult<Json<MessageResponse>> {
+    let (scope, message) = parse_sign_out_scope(params.scope.as_deref())?;
+
+    logout_sessions(&state, &user.token, scope).await?;
+
+    tracing::info!("Signed out sessions (scope={}) for user: {}", scope, user.user_id);
+
+    Ok(Json(MessageResponse {
+        message: message.to_string(),
+    }))
+}
+
+// =============================================================================
+// MFA / TOTP (auth required): /v1/account/mfa/*
+// =============================================================================
+
+/// GET /account/mfa - Enrolled TOTP factors and assurance levels
+pub async fn get_mfa_status(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> AppResult<Json<MfaStatusResponse>> {
+    let factors: Vec<MfaFactor> = fetch_mfa_factors(&state, &user.token)
+        .await?
+        .into_iter()
+        .filter(|f| f.factor_type == "totp")
+        .collect();
+
+    let next_level = if factors.iter().any(MfaFactor::is_verified) { "aal2" } else { "aal1" };
+
+    Ok(Json(MfaStatusResponse {
+        current_level: user.aal.clone().unwrap_or_else(|| "aal1".to_string()),
+        next_level: next_level.to_string(),
+        factors,
+    }))
+}
+
+/// POST /account/mfa/enroll - Start TOTP enrollment (finished by /account/mfa/verify)
+pub async fn enroll_mfa(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    payload: Result<Json<MfaEnrollRequest>, JsonRejection>,
+) -> AppResult<Json<MfaEnrollResponse>> {
+    let Json(req) = payload.map_err(|_| {
+        AppError::BadRequest("リクエスト形式が不正です（friendly_name を確認してください）".to_string())
+    })?;
+    let friendly_name = req
+        .friendly_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .unwrap_or("Authenticator")
+        .to_string();
+    if friendly_name.chars().count() > 50 {
+        return Err(AppError::Validation(
+            "friendly_name must be 50 characters or less".to_string(),
+        ));
+    }
+
+    let client = reqwest::Client::new();
+
+    // Abandoned enrollments would otherwise block a retry with the same name
+    let stale: Vec<MfaFactor> = fetch_mfa_factors(&state, &user.token)
+        .await?
+        .into_iter()
+        .filter(|f| f.factor_type == "totp" && !f.is_verified())
+        .collect();
+    for factor in stale {
+        let url = format!("{}/auth/v1/factors/{}", state.config.supabase_url, factor.id);
+        let result = client
+            .delete(&url)
+            .header("apikey", &state.config.supabase_anon_key)
+            .header("Authorization", format!("Bearer {}", user.token))
+            .send()
+            .await;
+        if let Err(e) = result {
+            tracing::warn!("auth.mfa.enroll: failed to remove unverified factor: {}", e);
+        }
+    }
+
+    let url = format!("{}/auth/v1/factors", state.config.supabase_url);
+    let response = client
+        .post(&url)
+        .header("apikey", &state.config.supabase_anon_key)
+        .header("Authorization", format!("Bearer {}", user.token))
+        .header("Content-Type", "application/json")
+        .json(&serde_json::json!({
+            "factor_type": "totp",
+            "friendly_name": friendly_name,
+            "issuer": "ガチトレ"
+        }))
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(auth_upstream_error("auth.mfa.enroll", status, &body));
+    }
+
+    let enrolled: SupabaseTotpEnrollResponse = response.json().await?;
+
+    tracing::info!("MFA enrollment started for user: {}", user.user_id);
+
+    Ok(Json(MfaEnrollResponse {
+        factor_id: enrolled.id,
+        qr_code: enrolled.totp.qr_code,
+        secret: enrolled.totp.secret,
+        uri: enrolled.totp.uri,
+    }))
+}
+
+async fn create_mfa_challenge(
+    state: &AppState,
+    token: &str,
+    factor_id: &str,
+) -> AppResult<SupabaseChallengeResponse> {
+    let url = format!("{}/auth/v1/factors/{}/challenge", state.config.supabase_url, factor_id);
+    let response = reqwest::Client::new()
+        .post(&url)
+        .header("apikey", &state.config.supabase_anon_key)
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(auth_upstream_error("auth.mfa.challenge", status, &body));
+    }
+
+    Ok(response.json().await?)
+}
+
+/// POST /account/mfa/challenge - Create a challenge for a TOTP factor
+pub async fn challenge_mfa(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    payload: Result<Json<MfaChallengeRequest>, JsonRejection>,
+) -> AppResult<Json<MfaChallengeResponse>> {
+    let Json(req) = payload.map_err(|_| {
+        AppError::BadRequest("リクエスト形式が不正です（factor_id を確認してください）".to_string())
+    })?;
+    let factor_id = parse_mfa_id("factor_id", &req.factor_id)?;
+    let challenge = create_mfa_challenge(&state, &user.token, &factor_id).await?;
+
+    Ok(Json(MfaChallengeResponse {
+        challenge_id: challenge.id,
+        expires_at: challenge.expires_at,
+    }))
+}
+
+/// POST /account/mfa/verify - Verify a TOTP code
+///
+/// Completes enrollment for a new factor, or steps the session up to AAL2.
+/// Returns a new token pair; the client must replace its stored tokens.
+pub async fn verify_mfa(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    payload: Result<Json<MfaVerifyRequest>, JsonRejection>,
+) -> AppResult<Json<AuthResponse>> {
+    let Json(req) = payload.map_err(|_| {
+        AppError::BadRequest("リクエスト形式が不正です（factor_id/code を確認してください）".to_string())
+    })?;
+    let factor_id = parse_mfa_id("factor_id", &req.factor_id)?;
+    let code = normalize_totp_code(&req.code)?;
+    let challenge_id = match req.challenge_id.as_deref() {
+        Some(id) => parse_mfa_id("challenge_id", id)?,
+        None => create_mfa_challenge(&state, &user.token, &factor_id).await?.id,
+    };
+
+    let url = format!("{}/auth/v1/factors/{}/verify", state.config.supabase_url, factor_id);
+    let response = reqwest::Client::new()
+        .post(&url)
+        .header("apikey", &state.config.supabase_anon_key)
+        .header("Authorization", format!("Bearer {}", user.token))
+        .header("Content-Type", "application/json")
+        .json(&serde_json::json!({
+            "challenge_id": challenge_id,
+            "code": code
+        }))
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(auth_upstream_error("auth.mfa.verify", status, &body));
+    }
+
+    let auth_response: SupabaseAuthResponse = response.json().await?;
+
+    tracing::info!("MFA verified (aal2) for user: {}", user.user_id);
+
+    Ok(Json(AuthResponse {
+        access_token: auth_response.access_token,
+        refresh_token: auth_response.refresh_token,
+        token_type: auth_response.token_type,
+        expires_in: auth_response.expires_in,
+        user: UserInfo {
+            id: auth_response.user.id,
+            email: auth_response.user.email.unwrap_or_default(),
+        },
+    }))
+}
+
+/// DELETE /account/mfa/:factor_id - Remove a TOTP factor (AAL2 required)
+pub async fn unenroll_mfa(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(factor_id): Path<String>,
+) -> AppResult<Json<MessageResponse>> {
+    let factor_id = parse_mfa_id("factor_id", &factor_id)?;
+
+    let url = format!("{}/auth/v1/factors/{}", state.config.supabase_url, factor_id);
+    let response = reqwest::Client::new()
+        .delete(&url)
+        .header("apikey", &state.config.supabase_anon_key)
+        .header("Authorization", format!("Bearer {}", user.token))
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(auth_upstream_error("auth.mfa.unenroll", status, &body));
+    }
+
+    tracing::info!("MFA factor removed for user: {}", user.user_id);
+
+    Ok(Json(MessageResponse {
+        message: "二段階認証を解除しました".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+
+    fn id_token(claims: serde_json::Value) -> String {
+        format!(
+            "eyJhbGciOiJSUzI1NiJ9.{}.sig",
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        )
+    }
+
+    fn request(id_token: String, nonce: Option<&str>) -> IdTokenSignInRequest {
+        IdTokenSignInRequest {
+            id_token,
+            nonce: nonce.map(String::from),
+            access_token: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_id_token_request() {
+        let apple = id_token(serde_json::json!({ "iss": "https://appleid.apple.com", "email_verified": "true" }));
+        let claims = validate_id_token_request(IdTokenProvider::Apple, &request(apple.clone(), Some("n0nce"))).unwrap();
+        assert!(claims.email_verified());
+
+        // Apple requires a nonce; issuer must match the endpoint
+        assert!(validate_id_token_request(IdTokenProvider::Apple, &request(apple.clone(), None)).is_err());
+        assert!(validate_id_token_request(IdTokenProvider::Google, &request(apple, None)).is_err());
+
+        let google = id_token(serde_json::json!({ "iss": "accounts.google.com", "email_verified": false }));
+        let claims = validate_id_token_request(IdTokenProvider::Google, &request(google, None)).unwrap();
+        assert!(!claims.email_verified());
+
+        assert!(validate_id_token_request(IdTokenProvider::Google, &request("not-a-jwt".to_string(), None)).is_err());
+    }
+
+    #[test]
+    fn test_translate_id_token_errors() {
+        assert_eq!(translate_auth_error("Nonces mismatch"), "ログイン情報の検証に失敗しました。もう一度お試しください");
+        assert_eq!(translate_auth_error("Bad ID token"), "ソーシャルログインの認証情報が無効です。もう一度お試しください");
+        assert_eq!(translate_auth_error("Provider (issuer \"apple\") is not enabled"), "このログイン方法は現在利用できません");
+        assert_eq!(translate_auth_error("Identity is already linked to another user"), "このアカウントは既に別のユーザーに連携されています");
+    }
+
+    #[test]
+    fn test_normalize_totp_code() {
+        assert_eq!(normalize_totp_code("123 456").unwrap(), "123456");
+        assert!(normalize_totp_code("12345").is_err());
+        assert!(normalize_totp_code("12a456").is_err());
+        assert!(normalize_totp_code("１２３４５６").is_err());
+        assert_eq!(translate_auth_error("Invalid TOTP code entered"), "認証コードが正しくありません");
+        assert_eq!(
+            translate_auth_error("A factor with the friendly name \"Authenticator\" for this user already exists"),
+            "同じ名前の認証アプリが既に登録されています"
+        );
+    }
+
+    #[test]
+    fn test_validate_password_change() {
+        let req = |current: &str, new: &str| ChangePasswordRequest {
+            current_password: current.to_string(),
+            new_password: new.to_string(),
+        };
+        assert!(validate_password_change(&req("old-pass", "new-pass")).is_ok());
+        assert!(validate_password_change(&req("old-pass", &"a".repeat(72))).is_ok());
+        assert!(validate_password_change(&req("old-pass", &"a".repeat(73))).is_err());
+        assert!(validate_password_change(&req("old-pass", "")).is_err());
+        // 72 is a byte limit: 25 × 3-byte characters is too long
+        assert!(validate_password_change(&req("old-pass", &"あ".repeat(25))).is_err());
+        match validate_password_change(&req("same-pass", "same-pass")) {
+            Err(AppError::Validation(msg)) => {
+                assert_eq!(msg, "新しいパスワードは現在のパスワードと異なるものにしてください")
+            }
+            other => panic!("unexpected: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_normalize_new_email() {
+        assert_eq!(
+            normalize_new_email("  New@Example.com ", "old@example.com").unwrap(),
+            "new@example.com"
+        );
+        for invalid in ["", "no-at-sign", "@example.com", "user@localhost"] {
+            assert!(normalize_new_email(invalid, "old@example.com").is_err(), "{}", invalid);
+        }
+        match normalize_new_email("Old@Example.com", "old@example.com") {
+            Err(AppError::Validation(msg)) => assert_eq!(msg, "現在と同じメールアドレスです"),
+            other => panic!("unexpected: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_sign_out_scope() {
+        assert_eq!(parse_sign_out_scope(None).unwrap().0, "global");
+        assert_eq!(parse_sign_out_scope(Some("global")).unwrap().0, "global");
+        assert_eq!(parse_sign_out_scope(Some("others")).unwrap().0, "others");
+        // "local" would only end this session; it is not an account-security action
+        assert!(parse_sign_out_scope(Some("local")).is_err());
+        assert!(parse_sign_out_scope(Some("GLOBAL")).is_err());
+    }
+
+    #[test]
+    fn test_auth_upstream_error_mapping() {
+        let err = auth_upstream_error("auth.test", reqwest::StatusCode::BAD_GATEWAY, "<html>");
+        assert!(matches!(err, AppError::UpstreamAuth(_)));
+        let err = auth_upstream_error(
+            "auth.test",
+            reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            r#"{"msg":"Invalid login credentials"}"#,
+        );
+        assert!(matches!(err, AppError::UpstreamAuth(_)));
+
+        match auth_upstream_error(
+            "auth.test",
+            reqwest::StatusCode::BAD_REQUEST,
+            r#"{"error":"invalid_grant","error_description":"Invalid login credentials"}"#,
+        ) {
+            AppError::AuthError(msg) => {
+                assert_eq!(msg, "メールアドレスまたはパスワードが正しくありません")
+            }
+            other => panic!("unexpected: {:?}", other),
+        }
+        match auth_upstream_error(
+            "auth.test",
+            reqwest::StatusCode::UNPROCESSABLE_ENTITY,
+            r#"{"code":422,"msg":"New password should be different from the old password."}"#,
+        ) {
+            AppError::AuthError(msg) => {
+                assert_eq!(msg, "新しいパスワードは現在のパスワードと異なるものにしてください")
+            }
+            other => panic!("unexpected: {:?}", other),
+        }
+        // Unparseable 4xx body: generic translated message
+        assert!(matches!(
+            auth_upstream_error("auth.test", reqwest::StatusCode::UNAUTHORIZED, ""),
+            AppError::AuthError(_)
+        ));
+    }
+}
@@ -8,6 +8,7 @@ use crate::{
     api::middleware::AuthUser,
     api::validation::validate_date_ymd,
     error::AppResult,
+    state::{invalidate_user_state, retrieval::SourceType},
     AppState,
 };
 
@@ -214,8 +215,19 @@ pub async fn log_metrics(
             .supabase
             .update("body_metrics", &check_query, &update_data, &user.token)
             .await?;
+
+        // The day's history doc still holds the old values (best-effort)
+        tokio::spawn(super::ai::reindex_history(
+            state.clone(),
+            user.token.clone(),
+            user.user_id.clone(),
+            SourceType::BodyMetric,
+            format!("date=eq.{}", date_str),
+        ));
     }
 
+    invalidate_user_state(&user.user_id).await;
+
     Ok(Json(MessageResponse {
         message: "Metrics logged successfully".to_string(),
     }))
@@ -0,0 +1,427 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    api::{middleware::AuthUser, validation::validate_date_ymd},
+    domain::services::data_export::{
+        csv_line, meal_rows, metric_rows, workout_rows, MEAL_COLUMNS, METRIC_COLUMNS,
+        WORKOUT_COLUMNS,
+    },
+    error::{AppError, AppResult},
+    AppState,
+};
+
+// =============================================================================
+// GET /v1/export/{workouts,meals,metrics}.csv - CSV exports
+// GET /v1/export/archive.json - full JSON archive
+// Responses are streamed page by page, so large accounts never sit in memory.
+// =============================================================================
+
+const WORKOUTS_SELECT: &str = "select=id,date,start_time,end_time,perceived_fatigue,note,\
+    workout_exercises(exercise_id,exercise_order,custom_exercise_name,muscle_tag,modality,exercises(name),\
+    workout_sets(set_index,weight_kg,reps,rpe,rest_sec,is_warmup,is_dropset),\
+    workout_cardio(duration_sec,distance_m,elevation_gain_m,avg_heart_rate,max_heart_rate,calories_kcal))\
+    &workout_exercises.order=exercise_order.asc&workout_exercises.workout_sets.order=set_index.asc\
+    &order=date.asc,id.asc";
+const MEALS_SELECT: &str = "select=id,date,time,meal_type,note,\
+    meal_items(name,quantity,unit,calories,protein_g,fat_g,carbs_g,fiber_g)\
+    &order=date.asc,time.asc,id.asc";
+const METRICS_SELECT: &str =
+    "select=date,weight_kg,bodyfat_pct,sleep_hours,steps,note&order=date.asc";
+
+/// Rows per PostgREST page (kept within the default max-rows of 1000)
+const WORKOUTS_PAGE: usize = 100;
+const DEFAULT_PAGE: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// Inclusive YYYY-MM-DD bounds (optional)
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+// =============================================================================
+// Handlers
+// =============================================================================
+
+/// GET /v1/export/workouts.csv - One row per set (cardio entries: one row per activity)
+pub async fn export_workouts_csv(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<ExportQuery>,
+) -> AppResult<Response> {
+    let query = format!(
+        "user_id=eq.{}{}&{}",
+        user.user_id,
+        date_filter(&params)?,
+        WORKOUTS_SELECT
+    );
+    let cursor = ExportCursor::csv(
+        state,
+        &user,
+        WORKOUT_COLUMNS,
+        workout_rows,
+        ExportSection::list("workouts", "workouts", query, WORKOUTS_PAGE),
+    );
+    Ok(stream_response(
+        cursor,
+        "text/csv; charset=utf-8",
+        "workouts.csv",
+    ))
+}
+
+/// GET /v1/export/meals.csv - One row per meal item
+pub async fn export_meals_csv(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<ExportQuery>,
+) -> AppResult<Response> {
+    let query = format!(
+        "user_id=eq.{}{}&{}",
+        user.user_id,
+        date_filter(&params)?,
+        MEALS_SELECT
+    );
+    let cursor = ExportCursor::csv(
+        state,
+        &user,
+        MEAL_COLUMNS,
+        meal_rows,
+        ExportSection::list("meals", "meals", query, DEFAULT_PAGE),
+    );
+    Ok(stream_response(
+        cursor,
+        "text/csv; charset=utf-8",
+        "meals.csv",
+    ))
+}
+
+/// GET /v1/export/metrics.csv - One row per body-metrics day
+pub async fn export_metrics_csv(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<ExportQuery>,
+) -> AppResult<Response> {
+    let query = format!(
+        "user_id=eq.{}{}&{}",
+        user.user_id,
+        date_filter(&params)?,
+        METRICS_SELECT
+    );
+    let cursor = ExportCursor::csv(
+        state,
+        &user,
+        METRIC_COLUMNS,
+        metric_rows,
+        ExportSection::list("body_metrics", "body_metrics", query, DEFAULT_PAGE),
+    );
+    Ok(stream_response(
+        cursor,
+        "text/csv; charset=utf-8",
+        "metrics.csv",
+    ))
+}
+
+/// GET /v1/export/archive.json - Everything the user owns as one JSON document
+///
+/// Profile, custom exercises, workouts, meals, body metrics / measurements,
+/// AI conversations (without system prompts) and posts.
+pub async fn export_archive(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> AppResult<Response> {
+    let uid = &user.user_id;
+    let sections = vec![
+        ExportSection::single(
+            "profile",
+            "user_profiles",
+            format!("user_id=eq.{}&select=*", uid),
+        ),
+        ExportSection::list(
+            "custom_exercises",
+            "exercises",
+            format!(
+                "created_by=eq.{}&select=id,name,name_en,primary_muscle,secondary_muscles,equipment,modality,created_at&order=created_at.asc,id.asc",
+                uid
+            ),
+            DEFAULT_PAGE,
+        ),
+        ExportSection::list(
+            "workouts",
+            "workouts",
+            format!("user_id=eq.{}&{}", uid, WORKOUTS_SELECT),
+            WORKOUTS_PAGE,
+        ),
+        ExportSection::list(
+            "meals",
+            "meals",
+            format!("user_id=eq.{}&{}", uid, MEALS_SELECT),
+            DEFAULT_PAGE,
+        ),
+        ExportSection::list(
+            "body_metrics",
+            "body_metrics",
+            format!("user_id=eq.{}&{}", uid, METRICS_SELECT),
+            DEFAULT_PAGE,
+        ),
+        ExportSection::list(
+            "body_measurements",
+            "body_measurements",
+            format!(
+                "user_id=eq.{}&select=date,site,side,value_cm&order=date.asc,site.asc,side.asc",
+                uid
+            ),
+            DEFAULT_PAGE,
+        ),
+        ExportSection::list(
+            "ai_sessions",
+            "ai_sessions",
+            format!(
+                "user_id=eq.{}&select=id,intent,title,model,created_at,ai_messages(role,content,created_at)&ai_messages.role=neq.system&ai_messages.order=created_at.asc&order=created_at.asc,id.asc",
+                uid
+            ),
+            WORKOUTS_PAGE,
+        ),
+        ExportSection::list(
+            "posts",
+            "posts",
+            format!(
+                "user_id=eq.{}&select=id,content,image_path,created_at&order=created_at.asc,id.asc",
+                uid
+            ),
+            DEFAULT_PAGE,
+        ),
+    ];
+
+    let cursor = ExportCursor {
+        state,
+        token: user.token.clone(),
+        format: ExportFormat::Json {
+            header: serde_json::json!({
+                "format": "gachitore-export",
+                "version": 1,
+                "exported_at": chrono::Utc::now().to_rfc3339(),
+                "user_id": user.user_id,
+            }),
+        },
+        sections,
+        section: 0,
+        offset: 0,
+        stage: Stage::Start,
+    };
+    Ok(stream_response(cursor, "application/json", "archive.json"))
+}
+
+// =============================================================================
+// Streaming
+// =============================================================================
+
+/// One table read page by page
+struct ExportSection {
+    key: &'static str,
+    table: &'static str,
+    /// Filter + select + order, without limit/offset
+    query: String,
+    page_size: usize,
+    /// Single row (written as an object / null instead of an array)
+    single: bool,
+}
+
+impl ExportSection {
+    fn list(key: &'static str, table: &'static str, query: String, page_size: usize) -> Self {
+        Self {
+            key,
+            table,
+            query,
+            page_size,
+            single: false,
+        }
+    }
+
+    fn single(key: &'static str, table: &'static str, query: String) -> Self {
+        Self {
+            key,
+            table,
+            query,
+            page_size: 1,
+            single: true,
+        }
+    }
+}
+
+enum ExportFormat {
+    Csv {
+        columns: &'static [&'static str],
+        rows: fn(&Value) -> Vec<Vec<String>>,
+    },
+    /// Header fields, then one key per section
+    Json { header: Value },
+}
+
+enum Stage {
+    Start,
+    Body,
+    Done,
+}
+
+struct ExportCursor {
+    state: AppState,
+    token: String,
+    format: ExportFormat,
+    sections: Vec<ExportSection>,
+    section: usize,
+    offset: usize,
+    stage: Stage,
+}
+
+impl ExportCursor {
+    fn csv(
+        state: AppState,
+        user: &AuthUser,
+        columns: &'static [&'static str],
+        rows: fn(&Value) -> Vec<Vec<String>>,
+        section: ExportSection,
+    ) -> Self {
+        Self {
+            state,
+            token: user.token.clone(),
+            format: ExportFormat::Csv { columns, rows },
+            sections: vec![section],
+            section: 0,
+            offset: 0,
+            stage: Stage::Start,
+        }
+    }
+
+    /// Next chunk of the document; None when finished
+    async fn next_chunk(&mut self) -> Option<AppResult<String>> {
+        match self.stage {
+            Stage::Done => return None,
+            Stage::Start => {
+                self.stage = Stage::Body;
+                return Some(Ok(match &self.format {
+                    // BOM so spreadsheet apps read Japanese text as UTF-8
+                    ExportFormat::Csv { columns, .. } => format!("\u{feff}{}", csv_line(columns)),
+                    ExportFormat::Json { header } => {
+                        let header = header.to_string();
+                        header[..header.len() - 1].to_string()
+                    }
+                }));
+            }
+            Stage::Body => {}
+        }
+
+        let Some(section) = self.sections.get(self.section) else {
+            self.stage = Stage::Done;
+            return Some(Ok(match self.format {
+                ExportFormat::Csv { .. } => String::new(),
+                ExportFormat::Json { .. } => "}\n".to_string(),
+            }));
+        };
+
+        let query = format!(
+            "{}&limit={}&offset={}",
+            section.query, section.page_size, self.offset
+        );
+        let rows: Vec<Value> = match self
+            .state
+            .supabase
+            .select(section.table, &query, &self.token)
+            .await
+        {
+            Ok(rows) => rows,
+            Err(e) => {
+                tracing::error!("Export of {} failed: {}", section.table, e);
+                self.stage = Stage::Done;
+                return Some(Err(e));
+            }
+        };
+        let last_page = section.single || rows.len() < section.page_size;
+
+        let mut out = String::new();
+        match &self.format {
+            ExportFormat::Csv { rows: render, .. } => {
+                for row in &rows {
+                    for line in render(row) {
+                        out.push_str(&csv_line(&line));
+                    }
+                }
+            }
+            ExportFormat::Json { .. } if section.single => {
+                let row = rows.first().unwrap_or(&Value::Null);
+                out.push_str(&format!(",\"{}\":{}", section.key, row));
+            }
+            ExportFormat::Json { .. } => {
+                if self.offset == 0 {
+                    out.push_str(&format!(",\"{}\":[", section.key));
+                }
+                for (i, row) in rows.iter().enumerate() {
+                    if self.offset > 0 || i > 0 {
+                        out.push(',');
+                    }
+                    out.push_str(&row.to_string());
+                }
+                if last_page {
+                    out.push(']');
+                }
+            }
+        }
+
+        if last_page {
+            self.section += 1;
+            self.offset = 0;
+        } else {
+            self.offset += rows.len();
+        }
+        Some(Ok(out))
+    }
+}
+
+fn stream_response(cursor: ExportCursor, content_type: &'static str, name: &str) -> Response {
+    let stream = futures::stream::unfold(cursor, |mut cursor| async move {
+        cursor.next_chunk().await.map(|chunk| (chunk, cursor))
+    });
+    let filename = format!("gachitore-{}-{}", chrono::Utc::now().format("%Y%m%d"), name);
+
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+            (header::CACHE_CONTROL, "no-store".to_string()),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response()
+}
+
+/// Optional inclusive date bounds as PostgREST filters
+fn date_filter(params: &ExportQuery) -> AppResult<String> {
+    let from = params.from.as_deref().map(validate_date_ymd).transpose()?;
+    let to = params.to.as_deref().map(validate_date_ymd).transpose()?;
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            return Err(AppError::Validation(
+                "from must be on or before to".to_string(),
+            ));
+        }
+    }
+
+    let mut filter = String::new();
+    if let Some(from) = from {
+        filter.push_str(&format!("&date=gte.{}", from.format("%Y-%m-%d")));
+    }
+    if let Some(to) = to {
+        filter.push_str(&format!("&date=lte.{}", to.format("%Y-%m-%d")));
+    }
+    Ok(filter)
+}
@@ -0,0 +1,570 @@
+use axum::{
+    extract::{Multipart, State},
+    Extension, Json,
+};
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+use super::workouts::{
+    load_exercise_candidates, new_exercise_data, resolve_exercise, CreateExerciseRequest,
+    ExerciseCandidate,
+};
+use crate::{
+    api::middleware::AuthUser,
+    domain::services::cardio::{CardioMetrics, MODALITY_CARDIO, MODALITY_STRENGTH},
+    domain::services::workout_import::{
+        parse_export, split_equipment, suggest_muscle, ImportOptions, ImportedExercise, RowError,
+    },
+    error::{AppError, AppResult},
+    state::invalidate_user_state,
+    AppState,
+};
+
+// =============================================================================
+// POST /v1/import/workouts - import workout history from Strong / Hevy CSV exports
+// =============================================================================
+
+/// Upload limit for the CSV (also applied as the route's body limit)
+pub const MAX_IMPORT_BYTES: usize = 10 * 1024 * 1024;
+const MAX_IMPORT_WORKOUTS: usize = 5000;
+/// Workouts per import_workouts RPC call (each call is one transaction)
+const IMPORT_BATCH_WORKOUTS: usize = 200;
+const MAX_REPORTED_ROWS: usize = 200;
+
+#[derive(Debug, Serialize)]
+pub struct ImportWorkoutsResponse {
+    /// "strong" or "hevy"
+    pub source: String,
+    pub dry_run: bool,
+    /// Workouts imported (or that would be imported)
+    pub workouts: usize,
+    pub exercises: usize,
+    pub sets: usize,
+    pub cardio: usize,
+    /// Workouts already present (same start time), not imported again
+    pub skipped_duplicates: usize,
+    pub first_date: Option<String>,
+    pub last_date: Option<String>,
+    /// Export names resolved to existing exercises
+    pub matched_exercises: Vec<MatchedExercise>,
+    /// Custom exercises created (dry run: that would be created, without an id)
+    pub created_exercises: Vec<CreatedExercise>,
+    /// Rows that were not imported, with the reason
+    pub unmatched_rows: Vec<UnmatchedRow>,
+    /// Total unmatched rows (`unmatched_rows` is capped)
+    pub unmatched_total: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MatchedExercise {
+    pub name: String,
+    pub exercise_id: String,
+    pub exercise_name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedExercise {
+    pub name: String,
+    pub exercise_id: Option<String>,
+    pub primary_muscle: String,
+    pub equipment: Option<String>,
+    pub modality: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnmatchedRow {
+    pub line: usize,
+    pub input: String,
+    pub message: String,
+}
+
+impl From<RowError> for UnmatchedRow {
+    fn from(e: RowError) -> Self {
+        Self {
+            line: e.line,
+            input: e.input,
+            message: e.message,
+        }
+    }
+}
+
+/// How an export exercise name is written to workout_exercises
+enum Resolution<'a> {
+    Existing(&'a ExerciseCandidate),
+    /// Index into `new_exercises`
+    New(usize),
+}
+
+struct NewExercise {
+    id: String,
+    data: serde_json::Value,
+    report: CreatedExercise,
+}
+
+// =============================================================================
+// Handler
+// =============================================================================
+
+/// POST /v1/import/workouts - Import a Strong / Hevy CSV export
+///
+/// Multipart fields:
+/// - `file` (required): the CSV export
+/// - `dry_run`: "true" to only return the report
+/// - `weight_unit`: "kg" (default) or "lb" - Strong only, Hevy states it in the header
+/// - `distance_unit`: "km" (default) or "mi" - Strong only
+/// - `utc_offset_minutes`: offset of the export's local timestamps (default 0)
+/// - `muscle_overrides`: JSON object `{ "<export name>": "<primary_muscle>" }` for
+///   exercises that have to be created and whose muscle can't be guessed
+pub async fn import_workouts(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    mut multipart: Multipart,
+) -> AppResult<Json<ImportWorkoutsResponse>> {
+    let mut file: Option<Vec<u8>> = None;
+    let mut dry_run = false;
+    let mut options = ImportOptions::default();
+    let mut muscle_overrides: HashMap<String, String> = HashMap::new();
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Invalid multipart: {}", e)))?
+    {
+        let name = field.name().unwrap_or("").to_string();
+        if name == "file" {
+            let data = field
+                .bytes()
+                .await
+                .map_err(|e| AppError::BadRequest(format!("Failed to read file: {}", e)))?;
+            file = Some(data.to_vec());
+            continue;
+        }
+
+        let value = field
+            .text()
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Failed to read {}: {}", name, e)))?;
+        let value = value.trim();
+        match name.as_str() {
+            "dry_run" => dry_run = matches!(value, "true" | "1"),
+            "weight_unit" => {
+                options.weight_in_lb = match value {
+                    "kg" => false,
+                    "lb" | "lbs" => true,
+                    _ => {
+                        return Err(AppError::Validation(
+                            "Invalid weight_unit. Allowed: kg, lb".to_string(),
+                        ))
+                    }
+                }
+            }
+            "distance_unit" => {
+                options.distance_in_mi = match value {
+                    "km" => false,
+                    "mi" => true,
+                    _ => {
+                        return Err(AppError::Validation(
+                            "Invalid distance_unit. Allowed: km, mi".to_string(),
+                        ))
+                    }
+                }
+            }
+            "utc_offset_minutes" => {
+                options.utc_offset = value
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|m| (-12 * 60..=14 * 60).contains(m))
+                    .and_then(|m| FixedOffset::east_opt(m * 60))
+                    .ok_or_else(|| {
+                        AppError::Validation(
+                            "utc_offset_minutes must be between -720 and 840".to_string(),
+                        )
+                    })?;
+            }
+            "muscle_overrides" => {
+                muscle_overrides = serde_json::from_str(value).map_err(|_| {
+                    AppError::Validation(
+                        "muscle_overrides must be a JSON object of name -> muscle".to_string(),
+                    )
+                })?;
+            }
+            _ => {}
+        }
+    }
+
+    let file = file.ok_or_else(|| AppError::BadRequest("file is required".to_string()))?;
+    if file.is_empty() {
+        return Err(AppError::BadRequest("file is empty".to_string()));
+    }
+    if file.len() > MAX_IMPORT_BYTES {
+        return Err(AppError::BadRequest(format!(
+            "File is too large (max {}MB)",
+            MAX_IMPORT_BYTES / 1024 / 1024
+        )));
+    }
+    let text = String::from_utf8(file)
+        .map_err(|_| AppError::BadRequest("CSV must be UTF-8".to_string()))?;
+
+    let parsed = parse_export(&text, &options).map_err(AppError::Validation)?;
+    if parsed.workouts.len() > MAX_IMPORT_WORKOUTS {
+        return Err(AppError::Validation(format!(
+            "Too many workouts in one import (max {}). Split the file by date.",
+            MAX_IMPORT_WORKOUTS
+        )));
+    }
+
+    let mut unmatched: Vec<UnmatchedRow> = parsed.errors.into_iter().map(Into::into).collect();
+
+    // Workouts already imported (same start time) are skipped, so re-uploading is safe
+    let existing = existing_start_times(&state, &user, &parsed.workouts).await?;
+    let total_parsed = parsed.workouts.len();
+    let workouts: Vec<_> = parsed
+        .workouts
+        .into_iter()
+        .filter(|w| !existing.contains(&w.start_time.timestamp()))
+        .collect();
+    let skipped_duplicates = total_parsed - workouts.len();
+
+    // Resolve every distinct export name once
+    let candidates = load_exercise_candidates(&state, &user, None).await?;
+    let mut names: BTreeMap<&str, Vec<&ImportedExercise>> = BTreeMap::new();
+    for exercise in workouts.iter().flat_map(|w| &w.exercises) {
+        names
+            .entry(exercise.name.as_str())
+            .or_default()
+            .push(exercise);
+    }
+
+    let mut resolutions: HashMap<&str, Resolution> = HashMap::new();
+    let mut matched_exercises = Vec::new();
+    let mut new_exercises: Vec<NewExercise> = Vec::new();
+    for (name, occurrences) in &names {
+        let (base_name, equipment) = split_equipment(name);
+        let existing = resolve_exercise(name, &candidates)
+            .or_else(|| resolve_exercise(base_name, &candidates));
+        if let Some(c) = existing {
+            matched_exercises.push(MatchedExercise {
+                name: name.to_string(),
+                exercise_id: c.id.clone(),
+                exercise_name: c.name.clone(),
+            });
+            resolutions.insert(name, Resolution::Existing(c));
+            continue;
+        }
+
+        // Cardio when the export only has distance / duration for it
+        let modality = if occurrences.iter().all(|e| e.sets.is_empty()) {
+            MODALITY_CARDIO
+        } else {
+            MODALITY_STRENGTH
+        };
+        let primary_muscle = muscle_overrides
+            .get(*name)
+            .map(String::as_str)
+            .or_else(|| (modality == MODALITY_CARDIO).then_some("cardio"))
+            .or_else(|| suggest_muscle(name));
+        let Some(primary_muscle) = primary_muscle else {
+            report_lines(
+                &mut unmatched,
+                occurrences,
+                name,
+                "No matching exercise. Set its muscle in muscle_overrides to create it.",
+            );
+            continue;
+        };
+
+        let req = CreateExerciseRequest {
+            name: name.to_string(),
+            primary_muscle: primary_muscle.to_string(),
+            equipment: equipment.map(String::from),
+            secondary_muscles: None,
+            modality: Some(modality.to_string()),
+        };
+        let mut data = match new_exercise_data(&req, &user.user_id) {
+            Ok(data) => data,
+            Err(e) => {
+                report_lines(&mut unmatched, occurrences, name, &e.to_string());
+                continue;
+            }
+        };
+        let id = Uuid::new_v4().to_string();
+        data["id"] = serde_json::json!(id);
+        new_exercises.push(NewExercise {
+            id: id.clone(),
+            data,
+            report: CreatedExercise {
+                name: name.to_string(),
+                exercise_id: (!dry_run).then_some(id),
+                primary_muscle: primary_muscle.to_string(),
+                equipment: equipment.map(String::from),
+                modality: modality.to_string(),
+            },
+        });
+        resolutions.insert(name, Resolution::New(new_exercises.len() - 1));
+    }
+
+    // Build rows
+    let mut workout_rows = Vec::new();
+    let mut exercise_rows = Vec::new();
+    let mut set_rows = Vec::new();
+    let mut cardio_rows = Vec::new();
+    let mut dates = Vec::new();
+    // Rows are appended workout by workout: child row counts after each workout,
+    // so batches can be cut at workout boundaries
+    let mut row_ends: Vec<(usize, usize, usize)> = Vec::new();
+
+    for workout in &workouts {
+        let workout_id = Uuid::new_v4().to_string();
+        let mut order = 0;
+
+        for exercise in &workout.exercises {
+            let Some(resolution) = resolutions.get(exercise.name.as_str()) else {
+                continue;
+            };
+            let (exercise_id, muscle_tag, modality) = match resolution {
+                Resolution::Existing(c) => (&c.id, &c.primary_muscle, c.modality.as_str()),
+                Resolution::New(i) => {
+                    let n = &new_exercises[*i];
+                    (&n.id, &n.report.primary_muscle, n.report.modality.as_str())
+                }
+            };
+
+            let entry_id = Uuid::new_v4().to_string();
+            let has_entry = if modality == MODALITY_CARDIO {
+                report_entry_lines(
+                    &mut unmatched,
+                    &exercise.set_lines,
+                    &exercise.name,
+                    "Weight × reps rows are not imported for cardio exercises",
+                );
+                match exercise.cardio {
+                    Some(c) => {
+                        let metrics = CardioMetrics {
+                            duration_sec: c.duration_sec,
+                            distance_m: c.distance_m,
+                            ..Default::default()
+                        };
+                        match metrics.validate() {
+                            Ok(()) => {
+                                cardio_rows.push(serde_json::json!({
+                                    "workout_exercise_id": entry_id,
+                                    "duration_sec": c.duration_sec,
+                                    "distance_m": c.distance_m,
+                                }));
+                                true
+                            }
+                            Err(msg) => {
+                                report_entry_lines(
+                                    &mut unmatched,
+                                    &exercise.cardio_lines,
+                                    &exercise.name,
+                                    &msg,
+                                );
+                                false
+                            }
+                        }
+                    }
+                    None => false,
+                }
+            } else {
+                report_entry_lines(
+                    &mut unmatched,
+                    &exercise.cardio_lines,
+                    &exercise.name,
+                    "Distance / duration rows are only imported for cardio exercises",
+                );
+                for (set_idx, set) in exercise.sets.iter().enumerate() {
+                    set_rows.push(serde_json::json!({
+                        "id": Uuid::new_v4().to_string(),
+                        "workout_exercise_id": entry_id,
+                        "set_index": (set_idx + 1) as i32,
+                        "weight_kg": set.weight_kg,
+                        "reps": set.reps,
+                        "rpe": set.rpe,
+                        "is_warmup": set.is_warmup,
+                        "is_dropset": set.is_dropset
+                    }));
+                }
+                !exercise.sets.is_empty()
+            };
+            if !has_entry {
+                continue;
+            }
+
+            exercise_rows.push(serde_json::json!({
+                "id": entry_id,
+                "workout_id": workout_id,
+                "exercise_id": exercise_id,
+                "custom_exercise_name": serde_json::Value::Null,
+                "muscle_tag": muscle_tag,
+                "modality": modality,
+                "exercise_order": order
+            }));
+            order += 1;
+        }
+
+        if order == 0 {
+            continue;
+        }
+        dates.push(workout.date);
+        workout_rows.push(serde_json::json!({
+            "id": workout_id,
+            "user_id": user.user_id,
+            "date": workout.date.format("%Y-%m-%d").to_string(),
+            "start_time": workout.start_time.to_rfc3339(),
+            "end_time": workout.end_time.map(|t| t.to_rfc3339()),
+            "note": import_note(&workout.title, workout.note.as_deref()),
+        }));
+        row_ends.push((exercise_rows.len(), set_rows.len(), cardio_rows.len()));
+    }
+
+    // Exercises that ended up with no importable rows aren't created
+    let used: HashSet<&str> = exercise_rows
+        .iter()
+        .filter_map(|r| r["exercise_id"].as_str())
+        .collect();
+    let (new_exercises, _): (Vec<NewExercise>, Vec<NewExercise>) = new_exercises
+        .into_iter()
+        .partition(|n| used.contains(n.id.as_str()));
+
+    if !dry_run && !workout_rows.is_empty() {
+        let exercise_data: Vec<serde_json::Value> =
+            new_exercises.iter().map(|n| n.data.clone()).collect();
+        // Each batch holds complete workouts and commits atomically: after a failure the
+        // earlier batches are whole, so re-uploading skips them and imports the rest
+        let mut start = (0, 0, 0);
+        let batches = workout_rows
+            .chunks(IMPORT_BATCH_WORKOUTS)
+            .zip(row_ends.chunks(IMPORT_BATCH_WORKOUTS));
+        for (batch, (workouts_chunk, ends)) in batches.enumerate() {
+            let Some(&end) = ends.last() else {
+                continue;
+            };
+            let exercises: &[serde_json::Value] = if batch == 0 { &exercise_data } else { &[] };
+            let result: AppResult<serde_json::Value> = state
+                .supabase
+                .rpc(
+                    "import_workouts",
+                    &serde_json::json!({
+                        "p_exercises": exercises,
+                        "p_workouts": workouts_chunk,
+                        "p_workout_exercises": &exercise_rows[start.0..end.0],
+                        "p_sets": &set_rows[start.1..end.1],
+                        "p_cardio": &cardio_rows[start.2..end.2],
+                    }),
+                    &user.token,
+                )
+                .await;
+            if let Err(e) = result {
+                let imported = batch * IMPORT_BATCH_WORKOUTS;
+                tracing::warn!(
+                    "Workout import failed: user={} imported_workouts={} of {}: {}",
+                    user.user_id,
+                    imported,
+                    workout_rows.len(),
+                    e
+                );
+                if imported > 0 {
+                    invalidate_user_state(&user.user_id).await;
+                }
+                return Err(e);
+            }
+            start = end;
+        }
+        invalidate_user_state(&user.user_id).await;
+    }
+
+    unmatched.sort_by_key(|r| r.line);
+    let unmatched_total = unmatched.len();
+    unmatched.truncate(MAX_REPORTED_ROWS);
+
+    Ok(Json(ImportWorkoutsResponse {
+        source: parsed.source.as_str().to_string(),
+        dry_run,
+        workouts: workout_rows.len(),
+        exercises: exercise_rows.len(),
+        sets: set_rows.len(),
+        cardio: cardio_rows.len(),
+        skipped_duplicates,
+        first_date: dates.iter().min().map(|d| d.format("%Y-%m-%d").to_string()),
+        last_date: dates.iter().max().map(|d| d.format("%Y-%m-%d").to_string()),
+        matched_exercises,
+        created_exercises: new_exercises.into_iter().map(|n| n.report).collect(),
+        unmatched_rows: unmatched,
+        unmatched_total,
+    }))
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Start times (unix seconds) of the user's workouts within the export's date range
+async fn existing_start_times(
+    state: &AppState,
+    user: &AuthUser,
+    workouts: &[crate::domain::services::workout_import::ImportedWorkout],
+) -> AppResult<HashSet<i64>> {
+    let (Some(first), Some(last)) = (
+        workouts.iter().map(|w| w.date).min(),
+        workouts.iter().map(|w| w.date).max(),
+    ) else {
+        return Ok(HashSet::new());
+    };
+    // One day of slack on each side for timezone differences
+    let query = format!(
+        "user_id=eq.{}&date=gte.{}&date=lte.{}&start_time=not.is.null&select=start_time",
+        user.user_id,
+        (first - chrono::Duration::days(1)).format("%Y-%m-%d"),
+        (last + chrono::Duration::days(1)).format("%Y-%m-%d"),
+    );
+    let rows: Vec<serde_json::Value> = state
+        .supabase
+        .select("workouts", &query, &user.token)
+        .await?;
+
+    Ok(rows
+        .iter()
+        .filter_map(|r| r["start_time"].as_str())
+        .filter_map(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.timestamp())
+        .collect())
+}
+
+/// Workout title + notes from the export, within the workouts.note limit
+fn import_note(title: &str, note: Option<&str>) -> Option<String> {
+    let text = match (title.is_empty(), note) {
+        (true, None) => return None,
+        (false, None) => title.to_string(),
+        (true, Some(n)) => n.to_string(),
+        (false, Some(n)) => format!("{}\n{}", title, n),
+    };
+    Some(text.chars().take(5000).collect())
+}
+
+fn report_lines(
+    unmatched: &mut Vec<UnmatchedRow>,
+    occurrences: &[&ImportedExercise],
+    name: &str,
+    message: &str,
+) {
+    for e in occurrences {
+        report_entry_lines(unmatched, &e.set_lines, name, message);
+        report_entry_lines(unmatched, &e.cardio_lines, name, message);
+    }
+}
+
+fn report_entry_lines(
+    unmatched: &mut Vec<UnmatchedRow>,
+    lines: &[usize],
+    name: &str,
+    message: &str,
+) {
+    unmatched.extend(lines.iter().map(|&line| UnmatchedRow {
+        line,
+        input: name.to_string(),
+        message: message.to_string(),
+    }));
+}
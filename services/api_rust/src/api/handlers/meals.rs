@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Multipart, Path, Query, State},
     Extension, Json,
 };
 use chrono::NaiveDate;
@@ -10,6 +10,13 @@ use crate::{
     api::middleware::AuthUser,
     api::validation::{validate_date_ymd, validate_uuid},
     error::{AppError, AppResult},
+    state::{
+        invalidate_user_state,
+        retrieval::{HistoryIndex, SourceType},
+        persona::Language,
+        safety::PolicyOutcome,
+        sanitize_user_input,
+    },
     AppState,
 };
 
@@ -64,7 +71,7 @@ pub struct LogMealRequest {
     pub items: Vec<LogMealItemRequest>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct LogMealItemRequest {
     pub name: String,
     pub quantity: Option<f64>,
@@ -358,11 +365,18 @@ pub async fn delete_meal(
 
     let query = format!("id=eq.{}&user_id=eq.{}", validated_id, user.user_id);
 
+    // Drop it from the retrieval index so deleted meals are never cited
+    HistoryIndex::new(&state.supabase, &user.token, None)
+        .remove(&user.user_id, SourceType::Meal, &validated_id.to_string())
+        .await?;
+
     state
         .supabase
         .delete("meals", &query, &user.token)
         .await?;
 
+    invalidate_user_state(&user.user_id).await;
+
     Ok(Json(MessageResponse {
         message: "Meal deleted successfully".to_string(),
     }))
@@ -551,8 +565,572 @@ pub async fn log_meal(
             .await?;
     }
 
+    invalidate_user_state(&user.user_id).await;
+
     Ok(Json(LogMealResponse {
         meal_id,
         message: "Meal logged successfully".to_string(),
     }))
 }
+
+// =============================================================================
+// POST /v1/ai/meal/photo - Estimate meal items from a photo
+// =============================================================================
+
+/// Max number of candidate items returned from a single estimate
+const MAX_ESTIMATED_ITEMS: usize = 20;
+
+const MEAL_PHOTO_SYSTEM_INSTRUCTION: &str = r#"
+あなたは管理栄養士です。食事の写真から、写っている料理・食品ごとに量と栄養素を推定してください。
+
+【ルール】
+- 料理は日本語の一般的な名称で書く（例: 鶏むね肉のグリル、白米、味噌汁）
+- 量は見た目から推定し、quantity と unit（g / ml / 個 / 杯 / serving など）で表す
+- カロリーはkcal、たんぱく質・脂質・炭水化物・食物繊維はg
+- 判別できないものは推測で埋めず、含めない
+- 食べ物が写っていない場合は items を空にする
+
+【出力フォーマット】
+{
+  "items": [
+    {"name": "白米", "quantity": 200, "unit": "g", "calories": 312, "protein_g": 5.0, "fat_g": 0.6, "carbs_g": 74.2, "fiber_g": 0.6}
+  ],
+  "notes": "推定の前提や注意点（任意）"
+}
+"#;
+
+#[derive(Debug, Serialize)]
+pub struct MealEstimateResponse {
+    /// Editable candidates; send them back as `items` of POST /log/meal once confirmed
+    pub items: Vec<LogMealItemRequest>,
+    pub notes: Option<String>,
+}
+
+/// Raw model output (numbers may come back as floats or be missing)
+#[derive(Debug, Deserialize)]
+struct MealEstimate {
+    #[serde(default)]
+    items: Vec<EstimatedMealItem>,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    meal_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EstimatedMealItem {
+    name: String,
+    quantity: Option<f64>,
+    /// Estimated weight in grams (used to resolve against the foods table)
+    grams: Option<f64>,
+    unit: Option<String>,
+    calories: Option<f64>,
+    protein_g: Option<f64>,
+    fat_g: Option<f64>,
+    carbs_g: Option<f64>,
+    fiber_g: Option<f64>,
+}
+
+/// Drop non-finite values and clamp into [0, max] (1 decimal)
+fn clamp_estimate(v: Option<f64>, max: f64) -> Option<f64> {
+    v.filter(|x| x.is_finite())
+        .map(|x| (x.clamp(0.0, max) * 10.0).round() / 10.0)
+}
+
+impl EstimatedMealItem {
+    /// Clamp model estimates into the ranges accepted by `log_meal`
+    fn into_candidate(self) -> Option<LogMealItemRequest> {
+        let name: String = self.name.trim().chars().take(200).collect();
+        if name.is_empty() {
+            return None;
+        }
+
+        let unit = self
+            .unit
+            .map(|u| u.trim().chars().take(20).collect::<String>())
+            .filter(|u| !u.is_empty());
+
+        Some(LogMealItemRequest {
+            name,
+            quantity: clamp_estimate(self.quantity, 10000.0),
+            unit,
+            calories: clamp_estimate(self.calories, 50000.0).map(|c| c.round() as i32),
+            protein_g: clamp_estimate(self.protein_g, 1000.0),
+            fat_g: clamp_estimate(self.fat_g, 1000.0),
+            carbs_g: clamp_estimate(self.carbs_g, 1000.0),
+            fiber_g: clamp_estimate(self.fiber_g, 200.0),
+        })
+    }
+}
+
+impl From<MealEstimate> for MealEstimateResponse {
+    fn from(estimate: MealEstimate) -> Self {
+        let items = estimate
+            .items
+            .into_iter()
+            .filter_map(EstimatedMealItem::into_candidate)
+            .take(MAX_ESTIMATED_ITEMS)
+            .collect();
+
+        Self {
+            items,
+            notes: estimate
+                .notes
+                .map(|n| n.trim().to_string())
+                .filter(|n| !n.is_empty()),
+        }
+    }
+}
+
+/// POST /ai/meal/photo - Estimate meal items and macros from a photo
+///
+/// Multipart fields: `image` (required), `hint` (optional free text such as "ラーメン大盛り").
+/// Nothing is stored; the client confirms the candidates via POST /log/meal.
+pub async fn analyze_meal_photo(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    mut multipart: Multipart,
+) -> AppResult<Json<MealEstimateResponse>> {
+    let mut image_bytes: Option<Vec<u8>> = None;
+    let mut hint: Option<String> = None;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Invalid multipart: {}", e)))?
+    {
+        let name = field.name().unwrap_or("").to_string();
+
+        match name.as_str() {
+            "image" => {
+                let data = field
+                    .bytes()
+                    .await
+                    .map_err(|e| AppError::BadRequest(format!("Failed to read image: {}", e)))?;
+                if !data.is_empty() {
+                    image_bytes = Some(data.to_vec());
+                }
+            }
+            "hint" => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::BadRequest(format!("Failed to read hint: {}", e)))?;
+                hint = Some(text);
+            }
+            _ => {}
+        }
+    }
+
+    let bytes = image_bytes.ok_or_else(|| AppError::BadRequest("image is required".to_string()))?;
+    if bytes.len() > 10 * 1024 * 1024 {
+        return Err(AppError::BadRequest("image is too large (max 10MB)".to_string()));
+    }
+
+    // SECURITY: Validate image format using magic bytes (not client-provided Content-Type)
+    let content_type = crate::media::detect_format(&bytes)?.mime_type();
+
+    if hint.as_ref().is_some_and(|h| h.chars().count() > 200) {
+        return Err(AppError::Validation("hint is too long (max 200 chars)".to_string()));
+    }
+    let hint = hint
+        .map(|h| sanitize_user_input(h.trim()))
+        .filter(|h| !h.is_empty());
+
+    let prompt = match hint.as_deref() {
+        Some(h) => format!(
+            "この食事の写真から品目ごとの量と栄養素を推定して、JSONで返してください。\n\n【ユーザーの補足】\n{}",
+            h
+        ),
+        None => "この食事の写真から品目ごとの量と栄養素を推定して、JSONで返してください。".to_string(),
+    };
+
+    let estimate: MealEstimate = state
+        .gemini
+        .generate_json_with_image(
+            &prompt,
+            &bytes,
+            content_type,
+            Some(MEAL_PHOTO_SYSTEM_INSTRUCTION),
+        )
+        .await?;
+
+    let response = MealEstimateResponse::from(estimate);
+
+    tracing::info!(
+        user_id = %user.user_id,
+        item_count = response.items.len(),
+        "Meal photo analyzed"
+    );
+
+    Ok(Json(response))
+}
+
+// =============================================================================
+// POST /v1/ai/meal/parse - Parse a free-text meal description
+// =============================================================================
+
+const MEAL_TEXT_SYSTEM_INSTRUCTION: &str = r#"
+あなたは管理栄養士です。ユーザーが自然文で書いた食事内容を、品目ごとの構造化データに変換してください。
+
+【ルール】
+- 品目名は食品成分表にあるような一般的な名称に正規化する（例: 「鶏むね」→「鶏むね肉」、「ご飯」→「白米」）
+- 量が書かれていればそれを使い、なければ一般的な1人前を推定する
+- 「大盛り」「小盛り」「半分」などの表現は量に反映する（例: ご飯大盛り ≒ 300g）
+- grams には品目の推定重量（g）を必ず入れる
+- カロリーはkcal、たんぱく質・脂質・炭水化物・食物繊維はg
+- 食事のタイミングが書かれていれば meal_type を breakfast / lunch / dinner / snack / pre_workout / post_workout のいずれかで返す。不明なら null
+
+【出力フォーマット】
+{
+  "meal_type": "lunch",
+  "items": [
+    {"name": "鶏むね肉", "quantity": 200, "unit": "g", "grams": 200, "calories": 210, "protein_g": 46.6, "fat_g": 3.8, "carbs_g": 0.2, "fiber_g": 0}
+  ],
+  "notes": "推定の前提や注意点（任意）"
+}
+"#;
+
+#[derive(Debug, Deserialize)]
+pub struct ParseMealTextRequest {
+    pub text: String,
+    /// If provided, overrides the meal type inferred from the text
+    pub meal_type: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParseMealTextResponse {
+    pub meal_type: Option<String>,
+    pub items: Vec<ParsedMealItem>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParsedMealItem {
+    /// Same shape as POST /log/meal `items[]`
+    #[serde(flatten)]
+    pub item: LogMealItemRequest,
+    pub grams: Option<f64>,
+    /// "database" when macros come from the foods table, "ai" when estimated by the model
+    pub source: String,
+    pub food_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct FoodRow {
+    id: String,
+    name: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    serving_unit: Option<String>,
+    serving_grams: Option<f64>,
+    calories_per_100g: f64,
+    protein_g_per_100g: f64,
+    fat_g_per_100g: f64,
+    carbs_g_per_100g: f64,
+    fiber_g_per_100g: f64,
+}
+
+impl FoodRow {
+    fn matches(&self, name: &str) -> bool {
+        self.name == name || self.aliases.iter().any(|a| a == name)
+    }
+
+    /// Resolve grams from explicit grams, or from quantity x serving size
+    fn grams_for(&self, grams: Option<f64>, quantity: Option<f64>, unit: Option<&str>) -> Option<f64> {
+        if let Some(g) = grams.filter(|g| *g > 0.0) {
+            return Some(g);
+        }
+        match (quantity, unit, self.serving_unit.as_deref(), self.serving_grams) {
+            (Some(q), Some("g"), _, _) => Some(q),
+            (Some(q), Some(u), Some(su), Some(sg)) if u == su => Some(q * sg),
+            _ => None,
+        }
+    }
+}
+
+/// Strip characters that have meaning inside PostgREST filter values
+fn postgrest_quote(value: &str) -> String {
+    let cleaned: String = value
+        .chars()
+        .filter(|c| !matches!(c, '"' | '\\' | '{' | '}' | '(' | ')' | ','))
+        .collect();
+    format!("\"{}\"", cleaned)
+}
+
+/// Look up foods whose name or aliases match any of `names`
+async fn lookup_foods(state: &AppState, names: &[String], token: &str) -> AppResult<Vec<FoodRow>> {
+    let quoted: Vec<String> = names
+        .iter()
+        .map(|n| postgrest_quote(n))
+        .filter(|q| q.len() > 2)
+        .collect();
+    if quoted.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let filter = format!(
+        "(name.in.({}),aliases.ov.{{{}}})",
+        quoted.join(","),
+        quoted.join(",")
+    );
+    let query = format!(
+        "select=id,name,aliases,serving_unit,serving_grams,calories_per_100g,protein_g_per_100g,fat_g_per_100g,carbs_g_per_100g,fiber_g_per_100g&or={}&limit=100",
+        urlencoding::encode(&filter)
+    );
+
+    state.supabase.select("foods", &query, token).await
+}
+
+/// Replace the model's macros with `foods` values when the item matches a food and its
+/// weight can be resolved; otherwise keep the model estimates
+fn resolve_parsed_item(
+    foods: &[FoodRow],
+    grams: Option<f64>,
+    mut item: LogMealItemRequest,
+) -> ParsedMealItem {
+    let food = foods.iter().find(|f| f.matches(&item.name));
+    let resolved = food.and_then(|f| {
+        f.grams_for(grams, item.quantity, item.unit.as_deref())
+            .map(|g| (f, g))
+    });
+
+    match resolved {
+        Some((food, g)) => {
+            let ratio = g / 100.0;
+            item.name = food.name.clone();
+            item.calories = clamp_estimate(Some(food.calories_per_100g * ratio), 50000.0)
+                .map(|c| c.round() as i32);
+            item.protein_g = clamp_estimate(Some(food.protein_g_per_100g * ratio), 1000.0);
+            item.fat_g = clamp_estimate(Some(food.fat_g_per_100g * ratio), 1000.0);
+            item.carbs_g = clamp_estimate(Some(food.carbs_g_per_100g * ratio), 1000.0);
+            item.fiber_g = clamp_estimate(Some(food.fiber_g_per_100g * ratio), 200.0);
+            ParsedMealItem {
+                item,
+                grams: Some(g),
+                source: "database".to_string(),
+                food_id: Some(food.id.clone()),
+            }
+        }
+        None => ParsedMealItem {
+            item,
+            grams,
+            source: "ai".to_string(),
+            food_id: None,
+        },
+    }
+}
+
+/// POST /ai/meal/parse - Parse "鶏むね200gとご飯大盛り、味噌汁" into meal items
+///
+/// Items are resolved against the `foods` table when possible; otherwise the model's
+/// estimates are returned. Nothing is stored; the client confirms via POST /log/meal.
+pub async fn parse_meal_text(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<ParseMealTextRequest>,
+) -> AppResult<Json<ParseMealTextResponse>> {
+    let text = req.text.trim();
+    if text.is_empty() {
+        return Err(AppError::Validation("text cannot be empty".to_string()));
+    }
+    if text.chars().count() > 500 {
+        return Err(AppError::Validation("text is too long (max 500 chars)".to_string()));
+    }
+    if let Some(meal_type) = req.meal_type.as_deref() {
+        validate_meal_type(meal_type)?;
+    }
+
+    let sanitized = sanitize_user_input(text);
+    // Only `block` applies here (block messages are not localized)
+    let decision = state.safety.evaluate(&sanitized, Language::default());
+    decision.log(&user.user_id, "meal_parse");
+    if let PolicyOutcome::Block(message) = decision.outcome {
+        return Err(AppError::SafetyGuard(message));
+    }
+
+    let prompt = format!(
+        "次の食事内容を品目ごとに分解して、JSONで返してください。\n\n【食事内容】\n{}",
+        sanitized
+    );
+
+    let estimate: MealEstimate = state
+        .gemini
+        .generate_json(&prompt, Some(MEAL_TEXT_SYSTEM_INSTRUCTION))
+        .await?;
+
+    let meal_type = req
+        .meal_type
+        .map(|m| m.to_lowercase())
+        .or_else(|| {
+            estimate
+                .meal_type
+                .as_deref()
+                .map(str::to_lowercase)
+                .filter(|m| validate_meal_type(m).is_ok())
+        });
+    let notes = estimate
+        .notes
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty());
+
+    let parsed: Vec<(Option<f64>, LogMealItemRequest)> = estimate
+        .items
+        .into_iter()
+        .filter_map(|i| {
+            let grams = clamp_estimate(i.grams, 10000.0);
+            i.into_candidate().map(|c| (grams, c))
+        })
+        .take(MAX_ESTIMATED_ITEMS)
+        .collect();
+
+    // Food DB resolution is best-effort: fall back to model estimates on failure
+    let names: Vec<String> = parsed.iter().map(|(_, i)| i.name.clone()).collect();
+    let foods = lookup_foods(&state, &names, &user.token)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Food lookup failed: {}", e);
+            Vec::new()
+        });
+
+    let items: Vec<ParsedMealItem> = parsed
+        .into_iter()
+        .map(|(grams, item)| resolve_parsed_item(&foods, grams, item))
+        .collect();
+
+    tracing::info!(
+        user_id = %user.user_id,
+        item_count = items.len(),
+        resolved_count = items.iter().filter(|i| i.source == "database").count(),
+        "Meal text parsed"
+    );
+
+    Ok(Json(ParseMealTextResponse {
+        meal_type,
+        items,
+        notes,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn food(name: &str, aliases: &[&str], serving: Option<(&str, f64)>) -> FoodRow {
+        FoodRow {
+            id: format!("food-{}", name),
+            name: name.to_string(),
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            serving_unit: serving.map(|(u, _)| u.to_string()),
+            serving_grams: serving.map(|(_, g)| g),
+            calories_per_100g: 105.0,
+            protein_g_per_100g: 23.3,
+            fat_g_per_100g: 1.9,
+            carbs_g_per_100g: 0.1,
+            fiber_g_per_100g: 0.0,
+        }
+    }
+
+    fn estimated(value: serde_json::Value) -> Option<LogMealItemRequest> {
+        serde_json::from_value::<EstimatedMealItem>(value)
+            .unwrap()
+            .into_candidate()
+    }
+
+    #[test]
+    fn test_grams_for() {
+        let egg = food("卵", &[], Some(("個", 50.0)));
+        let no_serving = food("白米", &[], None);
+        // (food, grams, quantity, unit) -> grams
+        let cases = [
+            (&egg, Some(120.0), Some(2.0), Some("個"), Some(120.0)), // explicit grams win
+            (&egg, Some(0.0), Some(2.0), Some("個"), Some(100.0)),   // zero grams ignored
+            (&egg, None, Some(2.0), Some("個"), Some(100.0)),        // quantity x serving
+            (&egg, None, Some(0.5), Some("個"), Some(25.0)),
+            (&egg, None, Some(80.0), Some("g"), Some(80.0)),         // grams as the unit
+            (&egg, None, Some(2.0), Some("パック"), None),           // unknown unit
+            (&egg, None, None, Some("個"), None),                    // no quantity
+            (&egg, None, Some(2.0), None, None),                     // no unit
+            (&no_serving, None, Some(1.0), Some("杯"), None),        // no serving size
+            (&no_serving, None, Some(300.0), Some("g"), Some(300.0)),
+        ];
+        for (food, grams, quantity, unit, expected) in cases {
+            assert_eq!(
+                food.grams_for(grams, quantity, unit),
+                expected,
+                "{} grams={:?} quantity={:?} unit={:?}",
+                food.name,
+                grams,
+                quantity,
+                unit
+            );
+        }
+    }
+
+    #[test]
+    fn test_into_candidate_clamps_quantities_and_units() {
+        let item = estimated(serde_json::json!({
+            "name": "  鶏むね肉 ", "quantity": 200.04, "unit": " g ", "calories": 210.4
+        }))
+        .unwrap();
+        assert_eq!(item.name, "鶏むね肉");
+        assert_eq!(item.quantity, Some(200.0));
+        assert_eq!(item.unit.as_deref(), Some("g"));
+        assert_eq!(item.calories, Some(210));
+
+        let item = estimated(serde_json::json!({
+            "name": "白米", "quantity": -1.0, "unit": "  ", "protein_g": 5000.0
+        }))
+        .unwrap();
+        assert_eq!(item.quantity, Some(0.0));
+        assert_eq!(item.unit, None);
+        assert_eq!(item.protein_g, Some(1000.0));
+
+        assert!(estimated(serde_json::json!({ "name": "   " })).is_none());
+    }
+
+    #[test]
+    fn test_resolve_parsed_item() {
+        let foods = vec![
+            food("鶏むね肉", &["鶏むね", "chicken breast"], None),
+            food("卵", &[], Some(("個", 50.0))),
+        ];
+        let item = |name: &str, quantity: Option<f64>, unit: Option<&str>| LogMealItemRequest {
+            name: name.to_string(),
+            quantity,
+            unit: unit.map(String::from),
+            calories: Some(999),
+            protein_g: Some(1.0),
+            fat_g: None,
+            carbs_g: None,
+            fiber_g: None,
+        };
+
+        // Alias match: canonical name and per-100g macros scaled by weight
+        let parsed = resolve_parsed_item(&foods, Some(200.0), item("鶏むね", Some(200.0), Some("g")));
+        assert_eq!(parsed.source, "database");
+        assert_eq!(parsed.food_id.as_deref(), Some("food-鶏むね肉"));
+        assert_eq!(parsed.item.name, "鶏むね肉");
+        assert_eq!(parsed.grams, Some(200.0));
+        assert_eq!(parsed.item.calories, Some(210));
+        assert_eq!(parsed.item.protein_g, Some(46.6));
+
+        // Serving unit
+        let parsed = resolve_parsed_item(&foods, None, item("卵", Some(2.0), Some("個")));
+        assert_eq!(parsed.source, "database");
+        assert_eq!(parsed.grams, Some(100.0));
+
+        // Matched but the weight can't be resolved: keep the model estimates
+        let parsed = resolve_parsed_item(&foods, None, item("卵", Some(1.0), Some("パック")));
+        assert_eq!(parsed.source, "ai");
+        assert_eq!(parsed.item.calories, Some(999));
+
+        // Unmatched food
+        let parsed = resolve_parsed_item(&foods, Some(150.0), item("味噌汁", Some(1.0), Some("杯")));
+        assert_eq!(parsed.source, "ai");
+        assert_eq!(parsed.food_id, None);
+        assert_eq!(parsed.item.name, "味噌汁");
+        assert_eq!(parsed.grams, Some(150.0));
+        assert_eq!(parsed.item.calories, Some(999));
+    }
+}
@@ -0,0 +1,367 @@
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{
+    api::{middleware::AuthUser, validation::validate_date_ymd},
+    domain::services::body_composition::{
+        navy_bodyfat_pct, LengthUnit, Side, Site, MAX_CIRCUMFERENCE_CM, MIN_CIRCUMFERENCE_CM,
+    },
+    error::{AppError, AppResult},
+    AppState,
+};
+
+// =============================================================================
+// POST /v1/log/measurements - log body circumferences for a date
+// GET /v1/measurements/trends - per-site series + body-fat (logged / Navy estimate)
+// =============================================================================
+
+const MAX_MEASUREMENTS_PER_REQUEST: usize = 20;
+const TRENDS_DEFAULT_DAYS: i64 = 90;
+const TRENDS_MAX_DAYS: i64 = 730;
+
+#[derive(Debug, Deserialize)]
+pub struct LogMeasurementsRequest {
+    pub date: String,
+    /// "cm" (default) or "in"; values are stored in cm
+    pub unit: Option<String>,
+    pub measurements: Vec<MeasurementInput>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MeasurementInput {
+    /// neck / chest / waist / hips / arm / thigh
+    pub site: String,
+    /// left / right for arm and thigh, omitted for the others
+    pub side: Option<String>,
+    pub value: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogMeasurementsResponse {
+    pub date: String,
+    pub saved: usize,
+    pub bodyfat: BodyfatPoint,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BodyfatPoint {
+    pub date: String,
+    /// body_metrics.bodyfat_pct as entered by the user
+    pub logged_pct: Option<f64>,
+    /// U.S. Navy method from waist / neck (/ hips) and profile height
+    pub navy_estimate_pct: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MeasurementTrendsQuery {
+    pub days: Option<i64>,
+    /// Output unit: "cm" (default) or "in"
+    pub unit: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MeasurementTrendsResponse {
+    pub unit: String,
+    pub since: String,
+    pub series: Vec<MeasurementSeries>,
+    /// Dates with a logged or estimated body-fat value, oldest first
+    pub bodyfat: Vec<BodyfatPoint>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MeasurementSeries {
+    pub site: String,
+    pub side: String,
+    /// Oldest first
+    pub points: Vec<MeasurementPoint>,
+    pub first: f64,
+    pub latest: f64,
+    pub change: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MeasurementPoint {
+    pub date: String,
+    pub value: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct MeasurementRow {
+    date: String,
+    site: String,
+    side: String,
+    value_cm: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct BodyfatRow {
+    date: String,
+    bodyfat_pct: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfileRow {
+    sex: Option<String>,
+    height_cm: Option<i32>,
+}
+
+// =============================================================================
+// Handlers
+// =============================================================================
+
+/// POST /v1/log/measurements - Log circumferences (re-logging a site/side on the same date overwrites it)
+pub async fn log_measurements(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<LogMeasurementsRequest>,
+) -> AppResult<Json<LogMeasurementsResponse>> {
+    // Validate date format to prevent PostgREST query injection
+    let date_str = validate_date_ymd(&req.date)?.format("%Y-%m-%d").to_string();
+    let unit = parse_unit(req.unit.as_deref())?;
+
+    if req.measurements.is_empty() {
+        return Err(AppError::Validation("measurements is required".to_string()));
+    }
+    if req.measurements.len() > MAX_MEASUREMENTS_PER_REQUEST {
+        return Err(AppError::Validation(format!(
+            "Too many measurements (max {})",
+            MAX_MEASUREMENTS_PER_REQUEST
+        )));
+    }
+
+    let mut seen = HashSet::new();
+    let mut rows = Vec::with_capacity(req.measurements.len());
+    for m in &req.measurements {
+        let site = Site::parse(&m.site).ok_or_else(|| {
+            AppError::Validation(format!(
+                "Invalid site. Allowed: {:?}",
+                Site::ALLOWED
+            ))
+        })?;
+        let side = Side::for_site(site, m.side.as_deref()).map_err(AppError::Validation)?;
+        if !seen.insert((site, side)) {
+            return Err(AppError::Validation(format!(
+                "Duplicate measurement: {} {}",
+                site.as_str(),
+                side.as_str()
+            )));
+        }
+
+        let value_cm = unit.to_cm(m.value);
+        if !(MIN_CIRCUMFERENCE_CM..=MAX_CIRCUMFERENCE_CM).contains(&value_cm) {
+            return Err(AppError::Validation(format!(
+                "{} must be between {} and {} cm",
+                site.as_str(),
+                MIN_CIRCUMFERENCE_CM,
+                MAX_CIRCUMFERENCE_CM
+            )));
+        }
+
+        rows.push(serde_json::json!({
+            "user_id": user.user_id,
+            "date": date_str,
+            "site": site.as_str(),
+            "side": side.as_str(),
+            "value_cm": (value_cm * 10.0).round() / 10.0,
+        }));
+    }
+
+    state
+        .supabase
+        .upsert(
+            "body_measurements",
+            &rows,
+            "user_id,date,site,side",
+            &user.token,
+        )
+        .await?;
+
+    // Estimate with everything logged that day (earlier requests included)
+    let day_query = format!(
+        "user_id=eq.{}&date=eq.{}&select=date,site,side,value_cm",
+        user.user_id, date_str
+    );
+    let bodyfat_query = format!(
+        "user_id=eq.{}&date=eq.{}&select=date,bodyfat_pct",
+        user.user_id, date_str
+    );
+    let (day_rows, bodyfat_rows, profile) = tokio::try_join!(
+        state
+            .supabase
+            .select::<MeasurementRow>("body_measurements", &day_query, &user.token),
+        state
+            .supabase
+            .select::<BodyfatRow>("body_metrics", &bodyfat_query, &user.token),
+        fetch_profile(&state, &user),
+    )?;
+
+    let day_rows: Vec<&MeasurementRow> = day_rows.iter().collect();
+    let bodyfat = bodyfat_point(
+        date_str.clone(),
+        &day_rows,
+        bodyfat_rows.first().and_then(|r| r.bodyfat_pct),
+        profile.as_ref(),
+    );
+
+    Ok(Json(LogMeasurementsResponse {
+        date: date_str,
+        saved: rows.len(),
+        bodyfat,
+    }))
+}
+
+/// GET /v1/measurements/trends?days=90&unit=cm - Measurement series and body-fat trend
+pub async fn get_measurement_trends(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<MeasurementTrendsQuery>,
+) -> AppResult<Json<MeasurementTrendsResponse>> {
+    let unit = parse_unit(params.unit.as_deref())?;
+    let days = params
+        .days
+        .unwrap_or(TRENDS_DEFAULT_DAYS)
+        .clamp(1, TRENDS_MAX_DAYS);
+    let since = (chrono::Utc::now().date_naive() - chrono::Duration::days(days - 1))
+        .format("%Y-%m-%d")
+        .to_string();
+
+    let measurements_query = format!(
+        "user_id=eq.{}&date=gte.{}&select=date,site,side,value_cm&order=date.asc",
+        user.user_id, since
+    );
+    let bodyfat_query = format!(
+        "user_id=eq.{}&date=gte.{}&bodyfat_pct=not.is.null&select=date,bodyfat_pct&order=date.asc",
+        user.user_id, since
+    );
+    let (rows, bodyfat_rows, profile) = tokio::try_join!(
+        state
+            .supabase
+            .select::<MeasurementRow>("body_measurements", &measurements_query, &user.token),
+        state
+            .supabase
+            .select::<BodyfatRow>("body_metrics", &bodyfat_query, &user.token),
+        fetch_profile(&state, &user),
+    )?;
+
+    let series = build_series(&rows, unit);
+
+    // Body fat per date: logged value and / or Navy estimate from that day's measurements
+    let mut rows_by_date: BTreeMap<&str, Vec<&MeasurementRow>> = BTreeMap::new();
+    for row in &rows {
+        rows_by_date.entry(row.date.as_str()).or_default().push(row);
+    }
+    let mut dates: Vec<&str> = rows_by_date.keys().copied().collect();
+    dates.extend(bodyfat_rows.iter().map(|r| r.date.as_str()));
+    dates.sort_unstable();
+    dates.dedup();
+
+    let bodyfat = dates
+        .into_iter()
+        .map(|date| {
+            let day_rows = rows_by_date.get(date).map(Vec::as_slice).unwrap_or(&[]);
+            let logged = bodyfat_rows
+                .iter()
+                .find(|r| r.date == date)
+                .and_then(|r| r.bodyfat_pct);
+            bodyfat_point(date.to_string(), day_rows, logged, profile.as_ref())
+        })
+        .filter(|p| p.logged_pct.is_some() || p.navy_estimate_pct.is_some())
+        .collect();
+
+    Ok(Json(MeasurementTrendsResponse {
+        unit: unit.as_str().to_string(),
+        since,
+        series,
+        bodyfat,
+    }))
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+fn parse_unit(value: Option<&str>) -> AppResult<LengthUnit> {
+    match value {
+        None => Ok(LengthUnit::default()),
+        Some(v) => LengthUnit::parse(v)
+            .ok_or_else(|| AppError::Validation("Invalid unit. Allowed: cm, in".to_string())),
+    }
+}
+
+async fn fetch_profile(state: &AppState, user: &AuthUser) -> AppResult<Option<ProfileRow>> {
+    state
+        .supabase
+        .select_single(
+            "user_profiles",
+            &format!("user_id=eq.{}&select=sex,height_cm", user.user_id),
+            &user.token,
+        )
+        .await
+}
+
+fn bodyfat_point(
+    date: String,
+    day_rows: &[&MeasurementRow],
+    logged_pct: Option<f64>,
+    profile: Option<&ProfileRow>,
+) -> BodyfatPoint {
+    let value = |site: Site| {
+        day_rows
+            .iter()
+            .find(|r| r.site == site.as_str())
+            .map(|r| r.value_cm)
+    };
+    let navy_estimate_pct = profile.and_then(|p| {
+        navy_bodyfat_pct(
+            p.sex.as_deref()?,
+            f64::from(p.height_cm?),
+            value(Site::Waist),
+            value(Site::Neck),
+            value(Site::Hips),
+        )
+    });
+
+    BodyfatPoint {
+        date,
+        logged_pct,
+        navy_estimate_pct,
+    }
+}
+
+/// One series per (site, side), in `unit`; rows must be ordered by date
+fn build_series(rows: &[MeasurementRow], unit: LengthUnit) -> Vec<MeasurementSeries> {
+    let mut grouped: BTreeMap<(Site, Side), Vec<MeasurementPoint>> = BTreeMap::new();
+    for row in rows {
+        let (Some(site), Some(side)) = (Site::parse(&row.site), Side::parse(&row.side)) else {
+            continue;
+        };
+        grouped.entry((site, side)).or_default().push(MeasurementPoint {
+            date: row.date.clone(),
+            value: round1(unit.convert_cm(row.value_cm)),
+        });
+    }
+
+    grouped
+        .into_iter()
+        .filter_map(|((site, side), points)| {
+            let first = points.first()?.value;
+            let latest = points.last()?.value;
+            Some(MeasurementSeries {
+                site: site.as_str().to_string(),
+                side: side.as_str().to_string(),
+                first,
+                latest,
+                change: round1(latest - first),
+                points,
+            })
+        })
+        .collect()
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
@@ -1,23 +1,37 @@
 mod ai;
 mod ai_inbox;
+mod ai_jobs;
+mod ai_sessions;
 mod auth;
 mod dashboard;
+mod exports;
+mod imports;
 mod meals;
+mod measurements;
 mod posts;
+mod progress_photos;
 mod push_tokens;
 mod subscriptions;
 mod support;
 mod users;
+mod weekly_reports;
 mod workouts;
 
 pub use ai::*;
 pub use ai_inbox::*;
+pub use ai_jobs::*;
+pub use ai_sessions::*;
 pub use auth::*;
 pub use dashboard::*;
+pub use exports::*;
+pub use imports::*;
 pub use meals::*;
+pub use measurements::*;
 pub use posts::*;
+pub use progress_photos::*;
 pub use push_tokens::*;
 pub use subscriptions::*;
 pub use support::*;
 pub use users::*;
+pub use weekly_reports::*;
 pub use workouts::*;
@@ -3,15 +3,14 @@ use axum::{
     Extension, Json,
 };
 use futures::future::join_all;
-use image::ImageReader;
 use serde::{Deserialize, Serialize};
-use std::io::Cursor;
 use uuid::Uuid;
 
 use crate::{
     api::middleware::AuthUser,
     error::{AppError, AppResult},
     infrastructure::supabase::Post,
+    media::{self, MediaKind},
     AppState,
 };
 
@@ -239,44 +238,18 @@ pub async fn create_post(
         return Err(AppError::BadRequest("content is too long (max 1000 chars)".to_string()));
     }
 
-    // Upload image if provided (with thumbnail generation)
+    // Upload image if provided (re-encoded, EXIF stripped, size variants)
     let image_path = if let Some(bytes) = image_bytes {
         if bytes.len() > 10 * 1024 * 1024 {
             return Err(AppError::BadRequest("image is too large (max 10MB)".to_string()));
         }
 
-        // SECURITY: Validate image format using magic bytes (not client-provided Content-Type)
-        let (ext, validated_content_type) = validate_image_magic_bytes(&bytes)?;
-        let object_id = Uuid::new_v4().to_string();
-        let object_path = format!("{}/{}.{}", user.user_id, object_id, ext);
-
-        // Generate thumbnail (400px width, JPEG for speed)
-        let thumbnail_bytes = generate_thumbnail(&bytes, 400)?;
-        let thumbnail_path = format!("{}/{}_thumb.jpg", user.user_id, object_id);
-
-        // Upload original and thumbnail in parallel
-        let (original_result, thumbnail_result) = tokio::join!(
-            state.supabase.upload_object(
-                "user-photos",
-                &object_path,
-                bytes,
-                &validated_content_type,
-                &user.token
-            ),
-            state.supabase.upload_object(
-                "user-photos",
-                &thumbnail_path,
-                thumbnail_bytes,
-                "image/jpeg",
-                &user.token
-            )
-        );
-
-        original_result?;
-        // Thumbnail failure is non-critical, just log it
-        if let Err(e) = thumbnail_result {
-            tracing::warn!("Failed to upload thumbnail: {}", e);
-        }
+        // SECURITY: format is validated from magic bytes inside the pipeline
+        let processed = media::process(bytes, MediaKind::Post).await?;
+        let base = format!("{}/{}", user.user_id, Uuid::new_v4());
+        let object_path =
+            media::upload_variants(&state.supabase, "user-photos", &base, processed, &user.token)
+                .await?;
 
         Some(object_path)
     } else {
@@ -379,11 +352,9 @@ pub async fn delete_post(
         .next()
         .ok_or_else(|| AppError::NotFound("Post not found".to_string()))?;
 
-    // Delete image from Storage if exists
+    // Delete image (and all of its variants) from Storage if exists
     if let Some(ref path) = post.image_path {
-        let _ = state
-            .supabase
-            .delete_object("user-photos", path, &user.token)
+        media::delete_all(&state.supabase, "user-photos", path, MediaKind::Post, &user.token)
             .await;
     }
 
@@ -422,94 +393,14 @@ struct BlockedUserId {
     blocked_user_id: String,
 }
 
-/// Validate image format by checking magic bytes
-fn validate_image_magic_bytes(bytes: &[u8]) -> AppResult<(&'static str, String)> {
-    if bytes.len() < 12 {
-        return Err(AppError::BadRequest(
-            "ファイルが小さすぎます。有効な画像ファイルをアップロードしてください。".to_string(),
-        ));
-    }
-
-    // JPEG
-    if bytes.len() >= 3 && bytes[0] == 0xFF && bytes[1] == 0xD8 && bytes[2] == 0xFF {
-        return Ok(("jpg", "image/jpeg".to_string()));
-    }
-
-    // PNG
-    if bytes.len() >= 8
-        && bytes[0] == 0x89
-        && bytes[1] == 0x50
-        && bytes[2] == 0x4E
-        && bytes[3] == 0x47
-        && bytes[4] == 0x0D
-        && bytes[5] == 0x0A
-        && bytes[6] == 0x1A
-        && bytes[7] == 0x0A
-    {
-        return Ok(("png", "image/png".to_string()));
-    }
-
-    // WebP
-    if bytes.len() >= 12
-        && bytes[0] == 0x52
-        && bytes[1] == 0x49
-        && bytes[2] == 0x46
-        && bytes[3] == 0x46
-        && bytes[8] == 0x57
-        && bytes[9] == 0x45
-        && bytes[10] == 0x42
-        && bytes[11] == 0x50
-    {
-        return Ok(("webp", "image/webp".to_string()));
-    }
-
-    // HEIC/HEIF
-    if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
-        let brand = &bytes[8..12];
-        if brand == b"heic" || brand == b"heix" || brand == b"mif1" || brand == b"hevc" {
-            return Ok(("heic", "image/heic".to_string()));
-        }
-    }
-
-    Err(AppError::BadRequest(
-        "サポートされていない画像形式です。JPEG、PNG、WebP、HEICのいずれかをアップロードしてください。".to_string(),
-    ))
-}
-
-/// Generate a thumbnail from image bytes (resized to max_width, JPEG output)
-fn generate_thumbnail(bytes: &[u8], max_width: u32) -> AppResult<Vec<u8>> {
-    let reader = ImageReader::new(Cursor::new(bytes))
-        .with_guessed_format()
-        .map_err(|e| AppError::BadRequest(format!("画像の読み込みに失敗しました: {}", e)))?;
-
-    let img = reader
-        .decode()
-        .map_err(|e| AppError::BadRequest(format!("画像のデコードに失敗しました: {}", e)))?;
-
-    // Only resize if wider than max_width
-    let resized = if img.width() > max_width {
-        img.thumbnail(max_width, max_width * 2) // Maintain aspect ratio
-    } else {
-        img
-    };
-
-    // Encode as JPEG (quality 80 - good balance of size/quality)
-    let mut output = Cursor::new(Vec::new());
-    resized
-        .write_to(&mut output, image::ImageFormat::Jpeg)
-        .map_err(|e| AppError::BadRequest(format!("サムネイル生成に失敗しました: {}", e)))?;
-
-    Ok(output.into_inner())
-}
-
 /// Get thumbnail path from original image path
 fn get_thumbnail_path(original_path: &str) -> String {
     // "user_id/uuid.jpg" -> "user_id/uuid_thumb.jpg"
-    if let Some(dot_pos) = original_path.rfind('.') {
-        format!("{}_thumb.jpg", &original_path[..dot_pos])
-    } else {
-        format!("{}_thumb.jpg", original_path)
-    }
+    media::variant_path(
+        media::base_path(original_path),
+        media::THUMB,
+        media::OutputFormat::Jpeg,
+    )
 }
 
 // =============================================================================
@@ -0,0 +1,554 @@
+use axum::{
+    extract::{Multipart, Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        middleware::AuthUser,
+        validation::{validate_date_ymd, validate_uuid},
+    },
+    error::{AppError, AppResult},
+    media::{self, MediaKind, OutputFormat},
+    AppState,
+};
+
+// =============================================================================
+// POST /v1/progress-photos - upload a front / side / back photo for a date
+// GET /v1/progress-photos - private timeline (photos grouped by date + that day's metrics)
+// GET /v1/progress-photos/compare - two dates side by side with metric deltas
+// DELETE /v1/progress-photos/:id - delete a photo (and all of its variants)
+// =============================================================================
+
+const BUCKET: &str = "user-photos";
+const SIGNED_URL_TTL_SECS: i32 = 3600;
+/// Upload limit for one photo (also applied as the upload route's body limit)
+pub const MAX_UPLOAD_BYTES: usize = 10 * 1024 * 1024;
+const POSES: &[&str] = &["front", "side", "back"];
+const TIMELINE_DEFAULT_LIMIT: usize = 90;
+const TIMELINE_MAX_LIMIT: usize = 300;
+
+#[derive(Debug, Deserialize)]
+struct ProgressPhotoRow {
+    id: String,
+    date: String,
+    pose: String,
+    object_path: String,
+    created_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct MetricsRow {
+    date: String,
+    weight_kg: Option<f64>,
+    bodyfat_pct: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub struct ProgressMetrics {
+    pub weight_kg: Option<f64>,
+    pub bodyfat_pct: Option<f64>,
+    /// weight × (1 - bodyfat%)
+    pub lean_mass_kg: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProgressPhotoItem {
+    pub id: String,
+    pub pose: String,
+    pub image_url: Option<String>,
+    pub medium_url: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProgressPhotoDay {
+    pub date: String,
+    /// That day's body_metrics (null if nothing was logged)
+    pub metrics: Option<ProgressMetrics>,
+    pub photos: Vec<ProgressPhotoItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UploadProgressPhotoResponse {
+    pub date: String,
+    pub photo: ProgressPhotoItem,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProgressTimelineQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProgressTimelineResponse {
+    /// Newest date first
+    pub days: Vec<ProgressPhotoDay>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompareProgressQuery {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ProgressDelta {
+    pub days: i64,
+    pub weight_kg: Option<f64>,
+    pub bodyfat_pct: Option<f64>,
+    pub lean_mass_kg: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompareProgressResponse {
+    pub from: ProgressPhotoDay,
+    pub to: ProgressPhotoDay,
+    /// `to` minus `from`
+    pub delta: ProgressDelta,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeleteProgressPhotoResponse {
+    pub success: bool,
+}
+
+// =============================================================================
+// Handlers
+// =============================================================================
+
+/// POST /v1/progress-photos - Upload a progress photo (multipart: image, pose, date?)
+///
+/// One photo per (date, pose): uploading again replaces the previous one.
+pub async fn upload_progress_photo(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    mut multipart: Multipart,
+) -> AppResult<(StatusCode, Json<UploadProgressPhotoResponse>)> {
+    validate_uuid(&user.user_id)?;
+
+    let mut image_bytes: Option<Vec<u8>> = None;
+    let mut pose: Option<String> = None;
+    let mut date: Option<String> = None;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Invalid multipart: {}", e)))?
+    {
+        let name = field.name().unwrap_or("").to_string();
+        match name.as_str() {
+            "image" => {
+                let data = field
+                    .bytes()
+                    .await
+                    .map_err(|e| AppError::BadRequest(format!("Failed to read image: {}", e)))?;
+                if !data.is_empty() {
+                    image_bytes = Some(data.to_vec());
+                }
+            }
+            "pose" | "date" => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| AppError::BadRequest(format!("Failed to read {}: {}", name, e)))?;
+                if name == "pose" {
+                    pose = Some(text.trim().to_string());
+                } else {
+                    date = Some(text.trim().to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let bytes = image_bytes.ok_or_else(|| AppError::BadRequest("image is required".to_string()))?;
+    if bytes.len() > MAX_UPLOAD_BYTES {
+        return Err(AppError::BadRequest("image is too large (max 10MB)".to_string()));
+    }
+    let pose = pose.ok_or_else(|| AppError::Validation("pose is required".to_string()))?;
+    if !POSES.contains(&pose.as_str()) {
+        return Err(AppError::Validation(format!(
+            "Invalid pose. Must be one of: {}",
+            POSES.join(", ")
+        )));
+    }
+    let date = match date.as_deref() {
+        Some(d) if !d.is_empty() => validate_date_ymd(d)?,
+        _ => chrono::Utc::now().date_naive(),
+    }
+    .format("%Y-%m-%d")
+    .to_string();
+
+    let processed = media::process(bytes, MediaKind::ProgressPhoto).await?;
+    let base = format!("{}/progress/{}", user.user_id, Uuid::new_v4());
+    let object_path =
+        media::upload_variants(&state.supabase, BUCKET, &base, processed, &user.token).await?;
+
+    let slot_query = format!(
+        "user_id=eq.{}&date=eq.{}&pose=eq.{}",
+        user.user_id, date, pose
+    );
+    let existing: Option<ProgressPhotoRow> = state
+        .supabase
+        .select_single(
+            "progress_photos",
+            &format!("{}&select=id,date,pose,object_path,created_at", slot_query),
+            &user.token,
+        )
+        .await?;
+
+    let saved = if let Some(old) = existing {
+        state
+            .supabase
+            .update(
+                "progress_photos",
+                &slot_query,
+                &serde_json::json!({ "object_path": object_path }),
+                &user.token,
+            )
+            .await?;
+        media::delete_all(
+            &state.supabase,
+            BUCKET,
+            &old.object_path,
+            MediaKind::ProgressPhoto,
+            &user.token,
+        )
+        .await;
+        ProgressPhotoRow {
+            object_path,
+            ..old
+        }
+    } else {
+        let insert_data = serde_json::json!({
+            "user_id": user.user_id,
+            "date": date,
+            "pose": pose,
+            "object_path": object_path,
+        });
+        let inserted: Result<ProgressPhotoRow, AppError> = state
+            .supabase
+            .insert("progress_photos", &insert_data, &user.token)
+            .await;
+        match inserted {
+            Ok(row) => row,
+            Err(e) => {
+                // Don't leave orphaned objects behind
+                media::delete_all(
+                    &state.supabase,
+                    BUCKET,
+                    &object_path,
+                    MediaKind::ProgressPhoto,
+                    &user.token,
+                )
+                .await;
+                return Err(e);
+            }
+        }
+    };
+
+    let photo = sign_photo(&state, &user.token, saved).await;
+    Ok((
+        StatusCode::CREATED,
+        Json(UploadProgressPhotoResponse { date, photo }),
+    ))
+}
+
+/// GET /v1/progress-photos?from=&to=&limit=90 - Private photo timeline with that day's metrics
+pub async fn get_progress_timeline(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<ProgressTimelineQuery>,
+) -> AppResult<Json<ProgressTimelineResponse>> {
+    validate_uuid(&user.user_id)?;
+
+    let mut query = format!(
+        "user_id=eq.{}&select=id,date,pose,object_path,created_at&order=date.desc,pose.asc&limit={}",
+        user.user_id,
+        params
+            .limit
+            .unwrap_or(TIMELINE_DEFAULT_LIMIT)
+            .clamp(1, TIMELINE_MAX_LIMIT)
+    );
+    if let Some(from) = params.from.as_deref() {
+        query.push_str(&format!("&date=gte.{}", validate_date_ymd(from)?));
+    }
+    if let Some(to) = params.to.as_deref() {
+        query.push_str(&format!("&date=lte.{}", validate_date_ymd(to)?));
+    }
+
+    let rows: Vec<ProgressPhotoRow> = state
+        .supabase
+        .select("progress_photos", &query, &user.token)
+        .await?;
+
+    let mut dates: Vec<String> = rows.iter().map(|r| r.date.clone()).collect();
+    dates.dedup();
+    let metrics = fetch_metrics(&state, &user, &dates).await?;
+
+    let days = build_days(&state, &user.token, rows, &metrics).await;
+    Ok(Json(ProgressTimelineResponse {
+        days: days.into_iter().rev().collect(),
+    }))
+}
+
+/// GET /v1/progress-photos/compare?from=YYYY-MM-DD&to=YYYY-MM-DD - Two dates side by side
+pub async fn compare_progress_photos(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Query(params): Query<CompareProgressQuery>,
+) -> AppResult<Json<CompareProgressResponse>> {
+    validate_uuid(&user.user_id)?;
+    let from = validate_date_ymd(&params.from)?;
+    let to = validate_date_ymd(&params.to)?;
+    if from == to {
+        return Err(AppError::Validation("from and to must be different dates".to_string()));
+    }
+    let (from_str, to_str) = (from.to_string(), to.to_string());
+
+    let rows: Vec<ProgressPhotoRow> = state
+        .supabase
+        .select(
+            "progress_photos",
+            &format!(
+                "user_id=eq.{}&date=in.({},{})&select=id,date,pose,object_path,created_at&order=pose.asc",
+                user.user_id, from_str, to_str
+            ),
+            &user.token,
+        )
+        .await?;
+    if rows.is_empty() {
+        return Err(AppError::NotFound(
+            "No progress photos for these dates".to_string(),
+        ));
+    }
+
+    let dates = vec![from_str.clone(), to_str.clone()];
+    let metrics = fetch_metrics(&state, &user, &dates).await?;
+    let mut days: HashMap<String, ProgressPhotoDay> = build_days(&state, &user.token, rows, &metrics)
+        .await
+        .into_iter()
+        .map(|day| (day.date.clone(), day))
+        .collect();
+
+    let mut take_day = |date: &str| {
+        days.remove(date).unwrap_or_else(|| ProgressPhotoDay {
+            date: date.to_string(),
+            metrics: metrics.get(date).copied(),
+            photos: Vec::new(),
+        })
+    };
+    let from_day = take_day(&from_str);
+    let to_day = take_day(&to_str);
+
+    let delta = metric_delta(
+        (to - from).num_days(),
+        from_day.metrics.as_ref(),
+        to_day.metrics.as_ref(),
+    );
+
+    Ok(Json(CompareProgressResponse {
+        from: from_day,
+        to: to_day,
+        delta,
+    }))
+}
+
+/// DELETE /v1/progress-photos/:id - Delete a progress photo and its stored variants
+pub async fn delete_progress_photo(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Path(photo_id): Path<String>,
+) -> AppResult<Json<DeleteProgressPhotoResponse>> {
+    let photo_id = validate_uuid(&photo_id)?;
+    validate_uuid(&user.user_id)?;
+
+    let query = format!("id=eq.{}&user_id=eq.{}", photo_id, user.user_id);
+    let photo: ProgressPhotoRow = state
+        .supabase
+        .select_single(
+            "progress_photos",
+            &format!("{}&select=id,date,pose,object_path,created_at", query),
+            &user.token,
+        )
+        .await?
+        .ok_or_else(|| AppError::NotFound("Progress photo not found".to_string()))?;
+
+    state
+        .supabase
+        .delete("progress_photos", &query, &user.token)
+        .await?;
+    media::delete_all(
+        &state.supabase,
+        BUCKET,
+        &photo.object_path,
+        MediaKind::ProgressPhoto,
+        &user.token,
+    )
+    .await;
+
+    Ok(Json(DeleteProgressPhotoResponse { success: true }))
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// body_metrics for the given dates, keyed by date
+async fn fetch_metrics(
+    state: &AppState,
+    user: &AuthUser,
+    dates: &[String],
+) -> AppResult<HashMap<String, ProgressMetrics>> {
+    if dates.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let rows: Vec<MetricsRow> = state
+        .supabase
+        .select(
+            "body_metrics",
+            &format!(
+                "user_id=eq.{}&date=in.({})&select=date,weight_kg,bodyfat_pct",
+                user.user_id,
+                dates.join(",")
+            ),
+            &user.token,
+        )
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|m| {
+            let metrics = ProgressMetrics {
+                weight_kg: m.weight_kg,
+                bodyfat_pct: m.bodyfat_pct,
+                lean_mass_kg: lean_mass(m.weight_kg, m.bodyfat_pct),
+            };
+            (m.date, metrics)
+        })
+        .collect())
+}
+
+/// Group photos by date (oldest first) and sign their URLs in parallel
+async fn build_days(
+    state: &AppState,
+    token: &str,
+    rows: Vec<ProgressPhotoRow>,
+    metrics: &HashMap<String, ProgressMetrics>,
+) -> Vec<ProgressPhotoDay> {
+    let mut by_date: BTreeMap<String, Vec<ProgressPhotoRow>> = BTreeMap::new();
+    for row in rows {
+        by_date.entry(row.date.clone()).or_default().push(row);
+    }
+
+    let days = by_date.into_iter().map(|(date, rows)| async move {
+        let photos = join_all(rows.into_iter().map(|row| sign_photo(state, token, row))).await;
+        ProgressPhotoDay {
+            metrics: metrics.get(&date).copied(),
+            date,
+            photos,
+        }
+    });
+    join_all(days).await
+}
+
+async fn sign_photo(state: &AppState, token: &str, row: ProgressPhotoRow) -> ProgressPhotoItem {
+    let base = media::base_path(&row.object_path);
+    let medium_path = media::variant_path(base, media::MEDIUM, OutputFormat::Jpeg);
+    let thumb_path = media::variant_path(base, media::THUMB, OutputFormat::Jpeg);
+    let (image, medium, thumb) = tokio::join!(
+        state
+            .supabase
+            .get_signed_url(BUCKET, &row.object_path, SIGNED_URL_TTL_SECS, token),
+        state
+            .supabase
+            .get_signed_url(BUCKET, &medium_path, SIGNED_URL_TTL_SECS, token),
+        state
+            .supabase
+            .get_signed_url(BUCKET, &thumb_path, SIGNED_URL_TTL_SECS, token),
+    );
+
+    ProgressPhotoItem {
+        id: row.id,
+        pose: row.pose,
+        image_url: image.ok(),
+        medium_url: medium.ok(),
+        thumbnail_url: thumb.ok(),
+        created_at: row.created_at,
+    }
+}
+
+fn lean_mass(weight_kg: Option<f64>, bodyfat_pct: Option<f64>) -> Option<f64> {
+    match (weight_kg, bodyfat_pct) {
+        (Some(w), Some(bf)) => Some(round1(w * (1.0 - bf / 100.0))),
+        _ => None,
+    }
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+/// `to - from` for each metric logged on both dates
+fn metric_delta(
+    days: i64,
+    from: Option<&ProgressMetrics>,
+    to: Option<&ProgressMetrics>,
+) -> ProgressDelta {
+    let diff = |pick: fn(&ProgressMetrics) -> Option<f64>| match (from.and_then(pick), to.and_then(pick)) {
+        (Some(a), Some(b)) => Some(round1(b - a)),
+        _ => None,
+    };
+    ProgressDelta {
+        days,
+        weight_kg: diff(|m| m.weight_kg),
+        bodyfat_pct: diff(|m| m.bodyfat_pct),
+        lean_mass_kg: diff(|m| m.lean_mass_kg),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(weight_kg: Option<f64>, bodyfat_pct: Option<f64>) -> ProgressMetrics {
+        ProgressMetrics {
+            weight_kg,
+            bodyfat_pct,
+            lean_mass_kg: lean_mass(weight_kg, bodyfat_pct),
+        }
+    }
+
+    #[test]
+    fn test_metric_delta() {
+        let before = metrics(Some(80.0), Some(20.0));
+        let after = metrics(Some(76.5), Some(16.0));
+        assert_eq!(before.lean_mass_kg, Some(64.0));
+        assert_eq!(
+            metric_delta(56, Some(&before), Some(&after)),
+            ProgressDelta {
+                days: 56,
+                weight_kg: Some(-3.5),
+                bodyfat_pct: Some(-4.0),
+                lean_mass_kg: Some(0.3),
+            }
+        );
+
+        // Only metrics logged on both dates produce a delta
+        let weight_only = metrics(Some(78.0), None);
+        let delta = metric_delta(7, Some(&before), Some(&weight_only));
+        assert_eq!(delta.weight_kg, Some(-2.0));
+        assert_eq!(delta.bodyfat_pct, None);
+        assert_eq!(delta.lean_mass_kg, None);
+        assert_eq!(metric_delta(7, None, Some(&after)).weight_kg, None);
+    }
+}
@@ -4,12 +4,18 @@ use axum::{
     Extension, Json,
 };
 use chrono::Datelike;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
     api::middleware::AuthUser,
     error::{AppError, AppResult},
+    media::{self, MediaKind},
+    state::{
+        invalidate_user_state,
+        persona::{Language, Persona},
+    },
     AppState,
 };
 
@@ -57,6 +63,18 @@ fn validate_sex(sex: &str) -> Result<(), AppError> {
     }
 }
 
+/// Validate a setting is one of the allowed (exact) values
+fn validate_allowed(value: &str, allowed: &[&str], field: &str) -> Result<(), AppError> {
+    if allowed.contains(&value) {
+        Ok(())
+    } else {
+        Err(AppError::Validation(format!(
+            "Invalid {}. Allowed: {:?}",
+            field, allowed
+        )))
+    }
+}
+
 /// Validate numeric range for physical attributes
 fn validate_range<T: PartialOrd + std::fmt::Display>(
     value: T,
@@ -93,6 +111,10 @@ pub struct UpdateProfileRequest {
     pub target_protein_g: Option<f64>,
     pub target_fat_g: Option<f64>,
     pub target_carbs_g: Option<f64>,
+    /// AI coach tone: "strict_coach" / "friendly_buddy" / "science"
+    pub ai_persona: Option<String>,
+    /// AI answer language: "ja" / "en"
+    pub ai_language: Option<String>,
 }
 
 // =============================================================================
@@ -119,6 +141,8 @@ pub struct UserProfile {
     pub target_protein_g: Option<f64>,
     pub target_fat_g: Option<f64>,
     pub target_carbs_g: Option<f64>,
+    pub ai_persona: Option<String>,
+    pub ai_language: Option<String>,
     pub avatar_url: Option<String>,
 }
 
@@ -228,6 +252,8 @@ pub async fn get_profile(
         target_protein_g: profile.as_ref().and_then(|p| p["target_protein_g"].as_f64()),
         target_fat_g: profile.as_ref().and_then(|p| p["target_fat_g"].as_f64()),
         target_carbs_g: profile.as_ref().and_then(|p| p["target_carbs_g"].as_f64()),
+        ai_persona: profile.as_ref().and_then(|p| p["ai_persona"].as_str().map(String::from)),
+        ai_language: profile.as_ref().and_then(|p| p["ai_language"].as_str().map(String::from)),
         avatar_url,
     }))
 }
@@ -404,6 +430,8 @@ pub async fn complete_onboarding(
             .await?;
     }
 
+    invalidate_user_state(&user.user_id).await;
+
     Ok(Json(MessageResponse {
         message: "Onboarding completed successfully".to_string(),
     }))
@@ -457,6 +485,13 @@ pub async fn update_profile(
         validate_range(c, 0.0, 1000.0, "target_carbs_g")?;
     }
 
+    if let Some(ref persona) = req.ai_persona {
+        validate_allowed(persona, Persona::ALLOWED, "ai_persona")?;
+    }
+    if let Some(ref language) = req.ai_language {
+        validate_allowed(language, Language::ALLOWED, "ai_language")?;
+    }
+
     let now = chrono::Utc::now();
     let today = now.format("%Y-%m-%d").to_string();
 
@@ -512,6 +547,12 @@ pub async fn update_profile(
         if let Some(target_carbs_g) = req.target_carbs_g {
             profile_updates.insert("target_carbs_g".to_string(), serde_json::json!(target_carbs_g));
         }
+        if let Some(ai_persona) = &req.ai_persona {
+            profile_updates.insert("ai_persona".to_string(), serde_json::json!(ai_persona));
+        }
+        if let Some(ai_language) = &req.ai_language {
+            profile_updates.insert("ai_language".to_string(), serde_json::json!(ai_language));
+        }
 
         let profile_data = serde_json::Value::Object(profile_updates);
         state
@@ -543,6 +584,8 @@ pub async fn update_profile(
             "target_protein_g": req.target_protein_g.unwrap_or(150.0),
             "target_fat_g": req.target_fat_g.unwrap_or(80.0),
             "target_carbs_g": req.target_carbs_g.unwrap_or(250.0),
+            "ai_persona": req.ai_persona.as_deref().unwrap_or(Persona::default().as_str()),
+            "ai_language": req.ai_language.as_deref().unwrap_or(Language::default().as_str()),
             "updated_at": now.to_rfc3339(),
         });
 
@@ -566,6 +609,8 @@ pub async fn update_profile(
             .await?;
     }
 
+    invalidate_user_state(&user.user_id).await;
+
     Ok(Json(MessageResponse {
         message: "Profile updated successfully".to_string(),
     }))
@@ -617,11 +662,11 @@ pub async fn upload_avatar(
         return Err(AppError::BadRequest("file is too large (max 5MB)".to_string()));
     }
 
-    // SECURITY: Validate image format using magic bytes (not client-provided Content-Type)
-    // This prevents file type mismatch attacks
-    let (ext, content_type) = validate_avatar_image(&bytes)?;
+    // SECURITY: format is validated from magic bytes (not client-provided Content-Type);
+    // the image is re-encoded, so EXIF (GPS etc.) is stripped
+    let processed = media::process(bytes, MediaKind::Avatar).await?;
 
-    // Delete old avatar if exists
+    // Delete old avatar (and its variants) if exists
     let profile_query = format!("user_id=eq.{}&select=avatar_path", user.user_id);
     let profiles: Vec<serde_json::Value> = state
         .supabase
@@ -630,21 +675,22 @@ pub async fn upload_avatar(
 
     if let Some(profile) = profiles.into_iter().next() {
         if let Some(old_path) = profile["avatar_path"].as_str() {
-            let _ = state
-                .supabase
-                .delete_object("user-photos", old_path, &user.token)
-                .await;
+            media::delete_all(
+                &state.supabase,
+                "user-photos",
+                old_path,
+                MediaKind::Avatar,
+                &user.token,
+            )
+            .await;
         }
     }
 
     // Upload new avatar
-    let object_id = Uuid::new_v4().to_string();
-    let object_path = format!("{}/avatar_{}.{}", user.user_id, object_id, ext);
-
-    state
-        .supabase
-        .upload_object("user-photos", &object_path, bytes, &content_type, &user.token)
-        .await?;
+    let base = format!("{}/avatar_{}", user.user_id, Uuid::new_v4());
+    let object_path =
+        media::upload_variants(&state.supabase, "user-photos", &base, processed, &user.token)
+            .await?;
 
     // Update user_profiles with new avatar_path
     let update_data = serde_json::json!({
@@ -671,46 +717,6 @@ pub async fn upload_avatar(
     Ok(Json(UploadAvatarResponse { avatar_url }))
 }
 
-/// Validate avatar image format
-fn validate_avatar_image(bytes: &[u8]) -> AppResult<(&'static str, String)> {
-    if bytes.len() < 12 {
-        return Err(AppError::BadRequest("Invalid image file".to_string()));
-    }
-
-    // JPEG
-    if bytes.len() >= 3 && bytes[0] == 0xFF && bytes[1] == 0xD8 && bytes[2] == 0xFF {
-        return Ok(("jpg", "image/jpeg".to_string()));
-    }
-
-    // PNG
-    if bytes.len() >= 8
-        && bytes[0] == 0x89
-        && bytes[1] == 0x50
-        && bytes[2] == 0x4E
-        && bytes[3] == 0x47
-    {
-        return Ok(("png", "image/png".to_string()));
-    }
-
-    // WebP
-    if bytes.len() >= 12
-        && bytes[0] == 0x52
-        && bytes[1] == 0x49
-        && bytes[2] == 0x46
-        && bytes[3] == 0x46
-        && bytes[8] == 0x57
-        && bytes[9] == 0x45
-        && bytes[10] == 0x42
-        && bytes[11] == 0x50
-    {
-        return Ok(("webp", "image/webp".to_string()));
-    }
-
-    Err(AppError::BadRequest(
-        "Unsupported image format. Use JPEG, PNG, or WebP.".to_string(),
-    ))
-}
-
 // =============================================================================
 // Get User Workout Dates (for profile grass display)
 // =============================================================================
@@ -840,3 +846,189 @@ pub async fn update_online_status(
 
     Ok(StatusCode::OK)
 }
+
+// =============================================================================
+// Account Deletion
+// =============================================================================
+
+/// Receipt returned by DELETE /v1/users/me (also stored in account_deletion_receipts)
+#[derive(Debug, Serialize)]
+pub struct AccountDeletionReceipt {
+    pub receipt_id: String,
+    pub user_id: String,
+    pub requested_at: String,
+    pub completed_at: String,
+    /// Table name -> deleted row count
+    pub deleted_rows: serde_json::Value,
+    pub storage_objects_deleted: usize,
+    pub storage_objects_failed: usize,
+    /// Always true: deleting auth.users drops every session / refresh token
+    pub sessions_revoked: bool,
+    /// Store subscriptions are billed by Apple / Google and must be cancelled there
+    pub had_active_subscription: bool,
+}
+
+/// DELETE /v1/users/me - Delete the account and all of its data
+///
+/// 1. Deletes the `user-photos` objects under `<user_id>/` (with retries) while the
+///    user still exists, so storage RLS (auth.uid() = folder owner) applies as usual
+/// 2. `delete_my_account` RPC: deletes all rows + auth.users in one transaction and
+///    records the receipt together with the storage result. Deleting auth.users also
+///    drops every session / refresh token.
+///
+/// Safe to retry: if step 2 fails the account is still usable, and a retry lists only
+/// the objects that are left and finishes the deletion.
+pub async fn delete_account(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+) -> AppResult<Json<AccountDeletionReceipt>> {
+    crate::api::validation::validate_uuid(&user.user_id)?;
+
+    let receipt_id = Uuid::new_v4().to_string();
+    let requested_at = chrono::Utc::now().to_rfc3339();
+
+    let subscription_query = format!("user_id=eq.{}&status=eq.active&select=id", user.user_id);
+    let had_active_subscription = !state
+        .supabase
+        .select::<serde_json::Value>("user_subscriptions", &subscription_query, &user.token)
+        .await?
+        .is_empty();
+
+    // 1) Storage (all objects live under "<user_id>/")
+    let paths = state
+        .supabase
+        .list_objects("user-photos", &user.user_id, &user.token)
+        .await?;
+    let results: Vec<bool> = futures::stream::iter(paths)
+        .map(|path| {
+            let state = &state;
+            let token = &user.token;
+            async move { delete_object_with_retry(state, &path, token).await }
+        })
+        .buffer_unordered(8)
+        .collect()
+        .await;
+    let (storage_objects_deleted, storage_objects_failed) = count_outcomes(&results);
+
+    // 2) Rows + auth user (+ sessions via cascade) + receipt
+    let result: serde_json::Value = state
+        .supabase
+        .rpc(
+            "delete_my_account",
+            &serde_json::json!({
+                "p_receipt_id": receipt_id,
+                "p_requested_at": requested_at,
+                "p_storage_objects_deleted": storage_objects_deleted,
+                "p_storage_objects_failed": storage_objects_failed,
+            }),
+            &user.token,
+        )
+        .await?;
+
+    invalidate_user_state(&user.user_id).await;
+
+    tracing::info!(
+        "Account deleted: user={} receipt={} storage_deleted={} storage_failed={}",
+        user.user_id,
+        receipt_id,
+        storage_objects_deleted,
+        storage_objects_failed
+    );
+
+    Ok(Json(AccountDeletionReceipt {
+        receipt_id,
+        user_id: user.user_id,
+        requested_at,
+        completed_at: result["completed_at"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+        deleted_rows: result["deleted_rows"].clone(),
+        storage_objects_deleted,
+        storage_objects_failed,
+        sessions_revoked: true,
+        had_active_subscription,
+    }))
+}
+
+/// Attempts per storage object during account deletion
+const STORAGE_DELETE_ATTEMPTS: u32 = 3;
+/// Backoff after the n-th failed attempt is n times this
+const STORAGE_DELETE_BACKOFF: std::time::Duration = std::time::Duration::from_millis(200);
+
+async fn delete_object_with_retry(state: &AppState, path: &str, token: &str) -> bool {
+    retry_with_backoff(STORAGE_DELETE_ATTEMPTS, STORAGE_DELETE_BACKOFF, |attempt| async move {
+        let result = state.supabase.delete_object("user-photos", path, token).await;
+        if let Err(e) = &result {
+            tracing::warn!(
+                "Account deletion: failed to delete {} (attempt {}/{}): {}",
+                path,
+                attempt,
+                STORAGE_DELETE_ATTEMPTS,
+                e
+            );
+        }
+        result
+    })
+    .await
+}
+
+/// Run `op` (given the 1-based attempt number) until it succeeds, at most `attempts` times
+async fn retry_with_backoff<F, Fut>(attempts: u32, base_delay: std::time::Duration, mut op: F) -> bool
+where
+    F: FnMut(u32) -> Fut,
+    Fut: std::future::Future<Output = AppResult<()>>,
+{
+    for attempt in 1..=attempts {
+        if op(attempt).await.is_ok() {
+            return true;
+        }
+        if attempt < attempts {
+            tokio::time::sleep(base_delay * attempt).await;
+        }
+    }
+    false
+}
+
+/// (succeeded, failed)
+fn count_outcomes(results: &[bool]) -> (usize, usize) {
+    let succeeded = results.iter().filter(|ok| **ok).count();
+    (succeeded, results.len() - succeeded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_retry_with_backoff() {
+        // (failures before success, attempts allowed) -> (succeeded, calls made)
+        let cases = [(0, 3, true, 1), (2, 3, true, 3), (3, 3, false, 3), (5, 1, false, 1)];
+        for (failures, attempts, expected, expected_calls) in cases {
+            let calls = AtomicU32::new(0);
+            let ok = retry_with_backoff(attempts, Duration::ZERO, |attempt| {
+                let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                assert_eq!(attempt, n);
+                async move {
+                    if n > failures {
+                        Ok(())
+                    } else {
+                        Err(AppError::SupabaseError("Storage delete error".to_string()))
+                    }
+                }
+            })
+            .await;
+            assert_eq!(ok, expected, "failures={} attempts={}", failures, attempts);
+            assert_eq!(calls.load(Ordering::SeqCst), expected_calls);
+        }
+    }
+
+    #[test]
+    fn test_count_outcomes() {
+        assert_eq!(count_outcomes(&[]), (0, 0));
+        assert_eq!(count_outcomes(&[true, true]), (2, 0));
+        assert_eq!(count_outcomes(&[true, false, true, false, false]), (2, 3));
+    }
+}
@@ -0,0 +1,358 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::{Datelike, Duration, NaiveDate, SecondsFormat, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{
+    api::middleware::AuthUser,
+    error::{AppError, AppResult},
+    redaction::Redactor,
+    state::persona::Language,
+    state::weekly_report::{
+        last_completed_week, today_jst, week_start_of, WeeklyReportBuilder, WeeklyStats,
+    },
+    AppState,
+};
+
+const WEEKLY_REPORT_KIND: &str = "weekly_report";
+const MAX_NARRATIVE_CHARS: usize = 1000;
+/// A `generating` claim older than this is treated as abandoned and claimed again
+const CLAIM_TIMEOUT_MINUTES: i64 = 10;
+
+// =============================================================================
+// Request/Response DTOs
+// =============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct WeeklyReportQuery {
+    pub week_start: Option<String>, // YYYY-MM-DD (Monday), defaults to last completed week
+}
+
+#[derive(Debug, Serialize)]
+pub struct WeeklyReportResponse {
+    pub week_start: NaiveDate,
+    /// "ready" (narrative written), "pending" (queued / being generated), "in_progress" (current week)
+    pub status: String,
+    pub narrative: Option<String>,
+    pub generated_at: Option<String>,
+    pub stats: WeeklyStats,
+}
+
+#[derive(Debug, Deserialize)]
+struct StoredWeeklyReport {
+    id: String,
+    week_start: NaiveDate,
+    status: String,
+    stats: Option<WeeklyStats>,
+    narrative: Option<String>,
+    generated_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WeeklyNarrativeOutput {
+    narrative: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReportProfile {
+    goal: String,
+    ai_language: Option<String>,
+}
+
+// =============================================================================
+// GET /v1/ai/reports/weekly - Weekly digest numbers (for charts) and narrative
+// =============================================================================
+
+pub async fn get_weekly_report(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Query(params): Query<WeeklyReportQuery>,
+) -> AppResult<Json<WeeklyReportResponse>> {
+    let today = today_jst();
+    let week_start = match params.week_start.as_deref() {
+        Some(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .map_err(|_| AppError::BadRequest("Invalid week_start format".to_string()))?,
+        None => last_completed_week(today),
+    };
+    if week_start.weekday() != Weekday::Mon {
+        return Err(AppError::BadRequest(
+            "week_start must be a Monday".to_string(),
+        ));
+    }
+    let current_week = week_start_of(today);
+    if week_start > current_week {
+        return Err(AppError::BadRequest(
+            "week_start is in the future".to_string(),
+        ));
+    }
+
+    let stored: Option<StoredWeeklyReport> = state
+        .supabase
+        .select_single(
+            "ai_weekly_reports",
+            &format!(
+                "user_id=eq.{}&week_start=eq.{}&select=id,week_start,status,stats,narrative,generated_at",
+                user.user_id, week_start
+            ),
+            &user.token,
+        )
+        .await?;
+
+    if let Some(report) = &stored {
+        if let (Some(stats), "ready") = (&report.stats, report.status.as_str()) {
+            return Ok(Json(WeeklyReportResponse {
+                week_start,
+                status: report.status.clone(),
+                narrative: report.narrative.clone(),
+                generated_at: report.generated_at.clone(),
+                stats: stats.clone(),
+            }));
+        }
+    }
+
+    // Not generated yet (or the week is still running): numbers only
+    let stats = WeeklyReportBuilder::new(&state.supabase, &user.token)
+        .build(&user.user_id, week_start)
+        .await?;
+    let status = if week_start == current_week {
+        "in_progress"
+    } else {
+        "pending"
+    };
+
+    Ok(Json(WeeklyReportResponse {
+        week_start,
+        status: status.to_string(),
+        narrative: None,
+        generated_at: None,
+        stats,
+    }))
+}
+
+// =============================================================================
+// Delivery (called from GET /v1/ai/inbox)
+// =============================================================================
+
+/// Claim the latest report queued by `run_weekly_report()` and write it on the AI
+/// job workers. Older pending weeks are left as is; only the most recent digest is
+/// worth sending.
+///
+/// The claim (`pending` → `generating`) keeps concurrent inbox polls from generating
+/// the same report twice; the digest shows up in the inbox on a later fetch.
+pub(crate) async fn deliver_pending_weekly_report(
+    state: &AppState,
+    user: &AuthUser,
+) -> AppResult<()> {
+    // Pending, or claimed by a worker that never finished (restart / crash)
+    let stale_before = (Utc::now() - Duration::minutes(CLAIM_TIMEOUT_MINUTES))
+        .to_rfc3339_opts(SecondsFormat::Secs, true);
+    let claimable = format!(
+        "or=(status.eq.pending,and(status.eq.generating,claimed_at.lt.{}))",
+        stale_before
+    );
+
+    let pending: Option<StoredWeeklyReport> = state
+        .supabase
+        .select_single(
+            "ai_weekly_reports",
+            &format!(
+                "user_id=eq.{}&{}&select=id,week_start,status,stats,narrative,generated_at&order=week_start.desc&limit=1",
+                user.user_id, claimable
+            ),
+            &user.token,
+        )
+        .await?;
+    let Some(report) = pending else {
+        return Ok(());
+    };
+
+    let claimed: Vec<serde_json::Value> = state
+        .supabase
+        .update_returning(
+            "ai_weekly_reports",
+            &format!("id=eq.{}&{}&select=id", report.id, claimable),
+            &serde_json::json!({
+                "status": "generating",
+                "claimed_at": Utc::now().to_rfc3339(),
+            }),
+            &user.token,
+        )
+        .await?;
+    if claimed.is_empty() {
+        // Another poll got there first
+        return Ok(());
+    }
+
+    let submitted = state.ai_jobs.try_submit(run_weekly_report_job(
+        state.clone(),
+        user.token.clone(),
+        user.user_id.clone(),
+        report.id.clone(),
+        report.week_start,
+    ));
+    if !submitted {
+        release_claim(state, &user.token, &report.id).await;
+        return Err(AppError::Unavailable("AI job queue is full".to_string()));
+    }
+    Ok(())
+}
+
+async fn run_weekly_report_job(
+    state: AppState,
+    access_token: String,
+    user_id: String,
+    report_id: String,
+    week_start: NaiveDate,
+) {
+    if let Err(e) = write_weekly_report(&state, &access_token, &user_id, &report_id, week_start).await {
+        tracing::warn!(user_id = %user_id, week_start = %week_start, "Weekly report delivery failed: {}", e);
+        // Back to pending: retried on the next inbox fetch
+        release_claim(&state, &access_token, &report_id).await;
+    }
+}
+
+async fn release_claim(state: &AppState, access_token: &str, report_id: &str) {
+    let result = state
+        .supabase
+        .update(
+            "ai_weekly_reports",
+            &format!("id=eq.{}&status=eq.generating", report_id),
+            &serde_json::json!({ "status": "pending", "claimed_at": null }),
+            access_token,
+        )
+        .await;
+    if let Err(e) = result {
+        tracing::warn!(report_id = %report_id, "Failed to release weekly report claim: {}", e);
+    }
+}
+
+async fn write_weekly_report(
+    state: &AppState,
+    access_token: &str,
+    user_id: &str,
+    report_id: &str,
+    week_start: NaiveDate,
+) -> AppResult<()> {
+    let stats = WeeklyReportBuilder::new(&state.supabase, access_token)
+        .build(user_id, week_start)
+        .await?;
+    if stats.is_empty() {
+        state
+            .supabase
+            .update(
+                "ai_weekly_reports",
+                &format!("id=eq.{}", report_id),
+                &serde_json::json!({ "status": "skipped" }),
+                access_token,
+            )
+            .await?;
+        return Ok(());
+    }
+
+    let profile: Option<ReportProfile> = state
+        .supabase
+        .select_single(
+            "user_profiles",
+            &format!("user_id=eq.{}&select=goal,ai_language", user_id),
+            access_token,
+        )
+        .await?;
+    let lang = Language::from_profile(profile.as_ref().and_then(|p| p.ai_language.as_deref()));
+    let goal = profile.map(|p| p.goal).unwrap_or_else(|| "health".to_string());
+
+    let prompt_selection = state.prompts.select(user_id);
+    let (narrative, model) =
+        match write_narrative(state, &stats, &goal, lang, &prompt_selection).await {
+            Ok((text, model)) => (text, Some(model)),
+            Err(e) => {
+                tracing::warn!(user_id = %user_id, "Weekly report narrative failed: {}", e);
+                (stats.fallback_narrative(lang), None)
+            }
+        };
+
+    // Inbox first: a failed post releases the claim and is retried on the next fetch
+    state
+        .supabase
+        .upsert(
+            "ai_inbox_messages",
+            &serde_json::json!({
+                "user_id": user_id,
+                "date": week_start,
+                "kind": WEEKLY_REPORT_KIND,
+                "meal_type": "",
+                "content": narrative,
+            }),
+            "user_id,date,kind,meal_type",
+            access_token,
+        )
+        .await?;
+
+    state
+        .supabase
+        .update(
+            "ai_weekly_reports",
+            &format!("id=eq.{}", report_id),
+            &serde_json::json!({
+                "status": "ready",
+                "stats": stats,
+                "narrative": narrative,
+                "model": model,
+                "prompt_version": prompt_selection.versions.get(&lang.template("weekly_report")),
+                "generated_at": Utc::now().to_rfc3339(),
+            }),
+            access_token,
+        )
+        .await?;
+
+    tracing::info!(
+        user_id = %user_id,
+        week_start = %week_start,
+        "Weekly report delivered"
+    );
+    Ok(())
+}
+
+async fn write_narrative(
+    state: &AppState,
+    stats: &WeeklyStats,
+    goal: &str,
+    lang: Language,
+    prompt_selection: &crate::state::prompts::PromptSelection,
+) -> AppResult<(String, String)> {
+    let stats_json = serde_json::to_string_pretty(stats)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize stats: {}", e)))?;
+
+    let prompt = state.prompts.render(
+        &lang.template("weekly_report"),
+        prompt_selection,
+        &HashMap::from([
+            ("week_start", stats.week_start.to_string()),
+            ("week_end", stats.week_end.to_string()),
+            ("goal", goal.to_string()),
+            ("stats_json", stats_json),
+        ]),
+    )?;
+
+    // Custom exercise names are free text
+    let mut redactor = Redactor::new();
+    let prompt = redactor.redact(&prompt);
+    let output = state
+        .gemini
+        .generate_json_with_model::<WeeklyNarrativeOutput>(&prompt, None)
+        .await?;
+    let narrative: String = redactor
+        .restore(&output.value.narrative)
+        .trim()
+        .chars()
+        .take(MAX_NARRATIVE_CHARS)
+        .collect();
+    if narrative.is_empty() {
+        return Err(AppError::GeminiApi(
+            "Empty weekly report narrative".to_string(),
+        ));
+    }
+    Ok((narrative, output.model))
+}
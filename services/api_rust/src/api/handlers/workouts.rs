@@ -8,7 +8,10 @@ use uuid::Uuid;
 use crate::{
     api::middleware::AuthUser,
     api::validation::{validate_date_ymd, validate_uuid},
+    domain::services::cardio::{CardioMetrics, ALLOWED_MODALITIES, MODALITY_CARDIO, MODALITY_STRENGTH},
+    domain::services::set_parser::{self, normalize_exercise_name, EXERCISE_ALIASES},
     error::AppResult,
+    state::invalidate_user_state,
     AppState,
 };
 
@@ -35,6 +38,8 @@ pub struct ExerciseResponse {
     pub primary_muscle: String,
     pub secondary_muscles: Vec<String>,
     pub equipment: Option<String>,
+    /// "strength" (sets) or "cardio" (distance / duration)
+    pub modality: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,6 +48,8 @@ pub struct CreateExerciseRequest {
     pub primary_muscle: String,
     pub equipment: Option<String>,
     pub secondary_muscles: Option<Vec<String>>,
+    /// Defaults to "strength"
+    pub modality: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -63,7 +70,17 @@ pub struct WorkoutListItem {
     pub name: String,
     pub exercise_count: i32,
     pub duration_minutes: i32,
+    /// Strength sets only (weight × reps); cardio is reported in `cardio`
     pub total_volume: f64,
+    pub cardio: Option<CardioSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CardioSummary {
+    pub activities: i32,
+    pub duration_sec: i32,
+    pub distance_m: f64,
+    pub calories_kcal: i32,
 }
 
 #[derive(Debug, Serialize)]
@@ -83,7 +100,22 @@ pub struct WorkoutExerciseDetail {
     pub exercise_id: Option<String>,
     pub exercise_name: String,
     pub muscle_tag: String,
+    pub modality: String,
     pub sets: Vec<WorkoutSetDetail>,
+    /// Present for cardio exercises
+    pub cardio: Option<CardioDetail>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CardioDetail {
+    pub duration_sec: i32,
+    pub distance_m: Option<f64>,
+    pub elevation_gain_m: Option<f64>,
+    pub avg_heart_rate: Option<i32>,
+    pub max_heart_rate: Option<i32>,
+    pub calories_kcal: Option<i32>,
+    pub pace_sec_per_km: Option<i32>,
+    pub speed_kmh: Option<f64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -106,15 +138,46 @@ pub struct LogWorkoutRequest {
     pub exercises: Vec<LogWorkoutExercise>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct LogWorkoutExercise {
     pub exercise_id: Option<String>,
     pub custom_name: Option<String>,
     pub muscle_tag: String,
+    /// Strength sets (must be empty for cardio)
+    #[serde(default)]
     pub sets: Vec<LogWorkoutSet>,
+    /// "strength" (default) or "cardio"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modality: Option<String>,
+    /// Required when modality is "cardio"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cardio: Option<LogWorkoutCardio>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogWorkoutCardio {
+    pub duration_sec: i32,
+    pub distance_m: Option<f64>,
+    pub elevation_gain_m: Option<f64>,
+    pub avg_heart_rate: Option<i32>,
+    pub max_heart_rate: Option<i32>,
+    pub calories_kcal: Option<i32>,
+}
+
+impl LogWorkoutCardio {
+    fn metrics(&self) -> CardioMetrics {
+        CardioMetrics {
+            duration_sec: self.duration_sec,
+            distance_m: self.distance_m,
+            elevation_gain_m: self.elevation_gain_m,
+            avg_heart_rate: self.avg_heart_rate,
+            max_heart_rate: self.max_heart_rate,
+            calories_kcal: self.calories_kcal,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct LogWorkoutSet {
     pub weight_kg: Option<f64>,
     pub reps: Option<i32>,
@@ -139,15 +202,37 @@ const ALLOWED_MUSCLE_GROUPS: &[&str] = &[
     "chest", "back", "shoulders", "biceps", "triceps", "forearms",
     "abs", "obliques", "quads", "hamstrings", "glutes", "calves",
     "traps", "lats", "lower_back", "hip_flexors", "adductors", "abductors",
+    "cardio",
 ];
 
+/// Cardio entry embedded from workout_exercises (one-to-one: object, or array from older PostgREST)
+fn embedded_cardio(value: &serde_json::Value) -> Option<CardioMetrics> {
+    let c = match value {
+        serde_json::Value::Array(arr) => arr.first()?,
+        serde_json::Value::Object(_) => value,
+        _ => return None,
+    };
+    Some(CardioMetrics {
+        duration_sec: c["duration_sec"].as_i64()? as i32,
+        distance_m: c["distance_m"].as_f64(),
+        elevation_gain_m: c["elevation_gain_m"].as_f64(),
+        avg_heart_rate: c["avg_heart_rate"].as_i64().map(|v| v as i32),
+        max_heart_rate: c["max_heart_rate"].as_i64().map(|v| v as i32),
+        calories_kcal: c["calories_kcal"].as_i64().map(|v| v as i32),
+    })
+}
+
+fn is_cardio(exercise: &serde_json::Value) -> bool {
+    exercise["modality"].as_str() == Some(MODALITY_CARDIO)
+}
+
 /// GET /exercises - Get all exercises
 pub async fn get_exercises(
     State(state): State<AppState>,
     Extension(user): Extension<AuthUser>,
     Query(params): Query<ExercisesQuery>,
 ) -> AppResult<Json<Vec<ExerciseResponse>>> {
-    let mut query = "select=id,name,name_en,primary_muscle,secondary_muscles,equipment".to_string();
+    let mut query = "select=id,name,name_en,primary_muscle,secondary_muscles,equipment,modality".to_string();
 
     if let Some(muscle_group) = &params.muscle_group {
         // Validate muscle_group to prevent injection
@@ -176,6 +261,7 @@ pub async fn get_exercises(
                 .map(|arr| arr.iter().filter_map(|v| v.as_str().map(String::from)).collect())
                 .unwrap_or_default(),
             equipment: e["equipment"].as_str().map(String::from),
+            modality: e["modality"].as_str().unwrap_or(MODALITY_STRENGTH).to_string(),
         })
         .collect();
 
@@ -190,7 +276,7 @@ pub async fn get_exercises_with_stats(
     // Get system exercises + user's custom exercises
     // NOTE: user-defined exercises are stored with created_by=user_id and is_system=false
     let exercises_query = format!(
-        "select=id,name,primary_muscle&or=(is_system.eq.true,created_by.eq.{})&order=name",
+        "select=id,name,primary_muscle&modality=eq.strength&or=(is_system.eq.true,created_by.eq.{})&order=name",
         user.user_id
     );
     let exercises: Vec<serde_json::Value> = state
@@ -206,7 +292,7 @@ pub async fn get_exercises_with_stats(
         .to_string();
 
     let workouts_query = format!(
-        "user_id=eq.{}&date=gte.{}&select=id,date,workout_exercises(exercise_id,workout_sets(weight_kg,reps))",
+        "user_id=eq.{}&date=gte.{}&select=id,date,workout_exercises(exercise_id,modality,workout_sets(weight_kg,reps))",
         user.user_id, thirty_days_ago
     );
     let workouts: Vec<serde_json::Value> = state
@@ -221,7 +307,7 @@ pub async fn get_exercises_with_stats(
         if let Some(exercises_arr) = workout["workout_exercises"].as_array() {
             for exercise in exercises_arr {
                 let exercise_id = exercise["exercise_id"].as_str().unwrap_or_default();
-                if exercise_id.is_empty() {
+                if exercise_id.is_empty() || is_cardio(exercise) {
                     continue;
                 }
 
@@ -283,6 +369,33 @@ pub async fn create_exercise(
     Extension(user): Extension<AuthUser>,
     Json(req): Json<CreateExerciseRequest>,
 ) -> AppResult<Json<ExerciseResponse>> {
+    let exercise_data = new_exercise_data(&req, &user.user_id)?;
+    let modality = exercise_data["modality"].as_str().unwrap_or(MODALITY_STRENGTH);
+
+    let created: serde_json::Value = state
+        .supabase
+        .insert("exercises", &exercise_data, &user.token)
+        .await?;
+
+    Ok(Json(ExerciseResponse {
+        id: created["id"].as_str().unwrap_or_default().to_string(),
+        name: created["name"].as_str().unwrap_or_default().to_string(),
+        name_en: created["name_en"].as_str().map(String::from),
+        primary_muscle: created["primary_muscle"].as_str().unwrap_or_default().to_string(),
+        secondary_muscles: created["secondary_muscles"]
+            .as_array()
+            .map(|arr| arr.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+            .unwrap_or_default(),
+        equipment: created["equipment"].as_str().map(String::from),
+        modality: created["modality"].as_str().unwrap_or(modality).to_string(),
+    }))
+}
+
+/// Validate a user-defined exercise (create_exercise rules) and build its `exercises` row
+pub(crate) fn new_exercise_data(
+    req: &CreateExerciseRequest,
+    user_id: &str,
+) -> AppResult<serde_json::Value> {
     let name = req.name.trim();
     if name.is_empty() {
         return Err(crate::error::AppError::Validation(
@@ -339,30 +452,22 @@ pub async fn create_exercise(
         }
     }
 
-    let exercise_data = serde_json::json!({
+    let modality = req.modality.as_deref().unwrap_or(MODALITY_STRENGTH);
+    if !ALLOWED_MODALITIES.contains(&modality) {
+        return Err(crate::error::AppError::Validation(format!(
+            "Invalid modality. Allowed: {:?}",
+            ALLOWED_MODALITIES
+        )));
+    }
+
+    Ok(serde_json::json!({
         "name": name,
         "primary_muscle": req.primary_muscle,
-        "secondary_muscles": req.secondary_muscles.unwrap_or_default(),
+        "secondary_muscles": req.secondary_muscles.clone().unwrap_or_default(),
         "equipment": req.equipment,
         "is_system": false,
-        "created_by": user.user_id,
-    });
-
-    let created: serde_json::Value = state
-        .supabase
-        .insert("exercises", &exercise_data, &user.token)
-        .await?;
-
-    Ok(Json(ExerciseResponse {
-        id: created["id"].as_str().unwrap_or_default().to_string(),
-        name: created["name"].as_str().unwrap_or_default().to_string(),
-        name_en: created["name_en"].as_str().map(String::from),
-        primary_muscle: created["primary_muscle"].as_str().unwrap_or_default().to_string(),
-        secondary_muscles: created["secondary_muscles"]
-            .as_array()
-            .map(|arr| arr.iter().filter_map(|v| v.as_str().map(String::from)).collect())
-            .unwrap_or_default(),
-        equipment: created["equipment"].as_str().map(String::from),
+        "created_by": user_id,
+        "modality": modality,
     }))
 }
 
@@ -376,7 +481,7 @@ pub async fn get_workouts(
     let offset = params.offset.unwrap_or(0);
 
     let query = format!(
-        "user_id=eq.{}&select=id,date,start_time,end_time,note,workout_exercises(id,exercise_id,muscle_tag,exercises(name),workout_sets(weight_kg,reps))&order=date.desc&limit={}&offset={}",
+        "user_id=eq.{}&select=id,date,start_time,end_time,note,workout_exercises(id,exercise_id,muscle_tag,modality,exercises(name),workout_sets(weight_kg,reps),workout_cardio(duration_sec,distance_m,calories_kcal))&order=date.desc&limit={}&offset={}",
         user.user_id, limit, offset
     );
 
@@ -391,10 +496,10 @@ pub async fn get_workouts(
             let exercises = w["workout_exercises"].as_array();
             let exercise_count = exercises.map(|e| e.len()).unwrap_or(0) as i32;
 
-            // Calculate total volume
+            // Calculate total volume (strength only; cardio has no weight × reps)
             let total_volume = exercises
                 .map(|ex_arr| {
-                    ex_arr.iter().map(|ex| {
+                    ex_arr.iter().filter(|ex| !is_cardio(ex)).map(|ex| {
                         ex["workout_sets"]
                             .as_array()
                             .map(|sets| {
@@ -411,6 +516,23 @@ pub async fn get_workouts(
                 })
                 .unwrap_or(0.0);
 
+            // Sum cardio activities
+            let cardio_entries: Vec<CardioMetrics> = exercises
+                .map(|ex_arr| {
+                    ex_arr
+                        .iter()
+                        .filter(|ex| is_cardio(ex))
+                        .filter_map(|ex| embedded_cardio(&ex["workout_cardio"]))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let cardio = (!cardio_entries.is_empty()).then(|| CardioSummary {
+                activities: cardio_entries.len() as i32,
+                duration_sec: cardio_entries.iter().map(|c| c.duration_sec).sum(),
+                distance_m: cardio_entries.iter().filter_map(|c| c.distance_m).sum(),
+                calories_kcal: cardio_entries.iter().filter_map(|c| c.calories_kcal).sum(),
+            });
+
             // Calculate duration
             let duration_minutes = {
                 let start = w["start_time"].as_str();
@@ -455,6 +577,7 @@ pub async fn get_workouts(
                 exercise_count,
                 duration_minutes,
                 total_volume,
+                cardio,
             }
         })
         .collect();
@@ -472,7 +595,7 @@ pub async fn get_workout_detail(
     crate::api::validation::validate_uuid(&user.user_id)?;
 
     let query = format!(
-        "id=eq.{}&user_id=eq.{}&select=id,date,start_time,end_time,perceived_fatigue,note,workout_exercises(id,exercise_id,custom_exercise_name,muscle_tag,exercises(name),workout_sets(set_index,weight_kg,reps,rpe,is_warmup,is_dropset))",
+        "id=eq.{}&user_id=eq.{}&select=id,date,start_time,end_time,perceived_fatigue,note,workout_exercises(id,exercise_id,custom_exercise_name,muscle_tag,modality,exercises(name),workout_sets(set_index,weight_kg,reps,rpe,is_warmup,is_dropset),workout_cardio(duration_sec,distance_m,elevation_gain_m,avg_heart_rate,max_heart_rate,calories_kcal))",
         workout_id, user.user_id
     );
 
@@ -518,7 +641,18 @@ pub async fn get_workout_detail(
                         exercise_id: e["exercise_id"].as_str().map(String::from),
                         exercise_name,
                         muscle_tag: e["muscle_tag"].as_str().unwrap_or_default().to_string(),
+                        modality: e["modality"].as_str().unwrap_or(MODALITY_STRENGTH).to_string(),
                         sets,
+                        cardio: embedded_cardio(&e["workout_cardio"]).map(|c| CardioDetail {
+                            pace_sec_per_km: c.pace_sec_per_km(),
+                            speed_kmh: c.speed_kmh(),
+                            duration_sec: c.duration_sec,
+                            distance_m: c.distance_m,
+                            elevation_gain_m: c.elevation_gain_m,
+                            avg_heart_rate: c.avg_heart_rate,
+                            max_heart_rate: c.max_heart_rate,
+                            calories_kcal: c.calories_kcal,
+                        }),
                     }
                 })
                 .collect()
@@ -565,6 +699,44 @@ pub async fn log_workout(
     }
 
     for (idx, ex) in req.exercises.iter().enumerate() {
+        // If exercise_id is provided, it must be a UUID
+        if let Some(ex_id) = ex.exercise_id.as_deref().filter(|s| !s.is_empty()) {
+            let _ = validate_uuid(ex_id)?;
+        }
+        match ex.modality.as_deref().unwrap_or(MODALITY_STRENGTH) {
+            MODALITY_CARDIO => {
+                let cardio = ex.cardio.as_ref().ok_or_else(|| {
+                    crate::error::AppError::Validation(format!(
+                        "Exercise {} is cardio and requires cardio",
+                        idx + 1
+                    ))
+                })?;
+                if !ex.sets.is_empty() {
+                    return Err(crate::error::AppError::Validation(format!(
+                        "Exercise {} is cardio and cannot have sets",
+                        idx + 1
+                    )));
+                }
+                cardio.metrics().validate().map_err(|msg| {
+                    crate::error::AppError::Validation(format!("Exercise {}: {}", idx + 1, msg))
+                })?;
+                continue;
+            }
+            MODALITY_STRENGTH => {
+                if ex.cardio.is_some() {
+                    return Err(crate::error::AppError::Validation(format!(
+                        "Exercise {} has cardio but modality is not cardio",
+                        idx + 1
+                    )));
+                }
+            }
+            _ => {
+                return Err(crate::error::AppError::Validation(format!(
+                    "Invalid modality. Allowed: {:?}",
+                    ALLOWED_MODALITIES
+                )));
+            }
+        }
         if ex.sets.is_empty() {
             return Err(crate::error::AppError::Validation(format!(
                 "Exercise {} must have at least one set",
@@ -577,9 +749,45 @@ pub async fn log_workout(
                 idx + 1
             )));
         }
-        // If exercise_id is provided, it must be a UUID
-        if let Some(ex_id) = ex.exercise_id.as_deref().filter(|s| !s.is_empty()) {
-            let _ = validate_uuid(ex_id)?;
+    }
+
+    // The entry's modality must match the referenced exercise (volume and stats filter on it)
+    let mut exercise_ids: Vec<&str> = req
+        .exercises
+        .iter()
+        .filter_map(|ex| ex.exercise_id.as_deref().filter(|s| !s.is_empty()))
+        .collect();
+    exercise_ids.sort_unstable();
+    exercise_ids.dedup();
+    if !exercise_ids.is_empty() {
+        let known: Vec<serde_json::Value> = state
+            .supabase
+            .select(
+                "exercises",
+                &format!("id=in.({})&select=id,modality", exercise_ids.join(",")),
+                &user.token,
+            )
+            .await?;
+        let modalities: std::collections::HashMap<&str, &str> = known
+            .iter()
+            .filter_map(|e| {
+                Some((e["id"].as_str()?, e["modality"].as_str().unwrap_or(MODALITY_STRENGTH)))
+            })
+            .collect();
+        for (idx, ex) in req.exercises.iter().enumerate() {
+            let Some(ex_id) = ex.exercise_id.as_deref().filter(|s| !s.is_empty()) else {
+                continue;
+            };
+            let expected = modalities.get(ex_id).ok_or_else(|| {
+                crate::error::AppError::Validation(format!("Exercise {} not found", idx + 1))
+            })?;
+            if ex.modality.as_deref().unwrap_or(MODALITY_STRENGTH) != *expected {
+                return Err(crate::error::AppError::Validation(format!(
+                    "Exercise {} is a {} exercise",
+                    idx + 1,
+                    expected
+                )));
+            }
         }
     }
 
@@ -604,6 +812,7 @@ pub async fn log_workout(
     // Collect all exercises and sets for batch insert
     let mut exercise_data_list: Vec<serde_json::Value> = Vec::new();
     let mut set_data_list: Vec<serde_json::Value> = Vec::new();
+    let mut cardio_data_list: Vec<serde_json::Value> = Vec::new();
 
     for (order, exercise) in req.exercises.iter().enumerate() {
         let exercise_entry_id = Uuid::new_v4().to_string();
@@ -619,11 +828,24 @@ pub async fn log_workout(
             "exercise_id": exercise_id,
             "custom_exercise_name": exercise.custom_name,
             "muscle_tag": exercise.muscle_tag,
+            "modality": exercise.modality.as_deref().unwrap_or(MODALITY_STRENGTH),
             "exercise_order": order as i32
         });
 
         exercise_data_list.push(exercise_data);
 
+        if let Some(cardio) = &exercise.cardio {
+            cardio_data_list.push(serde_json::json!({
+                "workout_exercise_id": exercise_entry_id,
+                "duration_sec": cardio.duration_sec,
+                "distance_m": cardio.distance_m,
+                "elevation_gain_m": cardio.elevation_gain_m,
+                "avg_heart_rate": cardio.avg_heart_rate,
+                "max_heart_rate": cardio.max_heart_rate,
+                "calories_kcal": cardio.calories_kcal
+            }));
+        }
+
         // Collect sets for this exercise
         for (set_idx, set) in exercise.sets.iter().enumerate() {
             let set_data = serde_json::json!({
@@ -649,10 +871,21 @@ pub async fn log_workout(
         .await?;
 
     // Batch insert sets (1 query instead of M)
-    state
-        .supabase
-        .insert_batch("workout_sets", &set_data_list, &user.token)
-        .await?;
+    if !set_data_list.is_empty() {
+        state
+            .supabase
+            .insert_batch("workout_sets", &set_data_list, &user.token)
+            .await?;
+    }
+
+    if !cardio_data_list.is_empty() {
+        state
+            .supabase
+            .insert_batch("workout_cardio", &cardio_data_list, &user.token)
+            .await?;
+    }
+
+    invalidate_user_state(&user.user_id).await;
 
     Ok(Json(LogWorkoutResponse {
         workout_id,
@@ -660,3 +893,195 @@ pub async fn log_workout(
     }))
 }
 
+
+// =============================================================================
+// POST /log/workout/parse - Preview workout shorthand ("ベンチ 80kg x 8 x 3")
+// =============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct ParseWorkoutTextRequest {
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParseWorkoutTextResponse {
+    pub exercises: Vec<ParsedWorkoutExercise>,
+    pub errors: Vec<WorkoutParseError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParsedWorkoutExercise {
+    /// Same shape as POST /log/workout `exercises[]`
+    #[serde(flatten)]
+    pub exercise: LogWorkoutExercise,
+    pub line: usize,
+    pub input: String,
+    /// Resolved `exercises.name`; None when nothing matched (client must pick muscle_tag)
+    pub matched_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkoutParseError {
+    pub line: usize,
+    pub input: String,
+    pub message: String,
+}
+
+pub(crate) struct ExerciseCandidate {
+    pub id: String,
+    pub name: String,
+    pub primary_muscle: String,
+    pub equipment: Option<String>,
+    pub modality: String,
+    /// Normalized name / name_en
+    keys: Vec<String>,
+}
+
+/// System exercises + user's custom exercises, optionally limited to one modality
+pub(crate) async fn load_exercise_candidates(
+    state: &AppState,
+    user: &AuthUser,
+    modality: Option<&str>,
+) -> AppResult<Vec<ExerciseCandidate>> {
+    let mut exercises_query = format!(
+        "select=id,name,name_en,primary_muscle,equipment,modality&or=(is_system.eq.true,created_by.eq.{})",
+        user.user_id
+    );
+    if let Some(modality) = modality {
+        exercises_query.push_str(&format!("&modality=eq.{}", modality));
+    }
+    let exercises: Vec<serde_json::Value> = state
+        .supabase
+        .select("exercises", &exercises_query, &user.token)
+        .await?;
+
+    Ok(exercises
+        .into_iter()
+        .map(|e| {
+            let name = e["name"].as_str().unwrap_or_default().to_string();
+            let mut keys = vec![normalize_exercise_name(&name)];
+            if let Some(name_en) = e["name_en"].as_str() {
+                keys.push(normalize_exercise_name(name_en));
+            }
+            ExerciseCandidate {
+                id: e["id"].as_str().unwrap_or_default().to_string(),
+                name,
+                primary_muscle: e["primary_muscle"].as_str().unwrap_or_default().to_string(),
+                equipment: e["equipment"].as_str().map(String::from),
+                modality: e["modality"].as_str().unwrap_or(MODALITY_STRENGTH).to_string(),
+                keys,
+            }
+        })
+        .collect())
+}
+
+/// Resolve a typed name to an exercise: exact name/name_en -> alias -> shortest prefix match
+pub(crate) fn resolve_exercise<'a>(typed: &str, candidates: &'a [ExerciseCandidate]) -> Option<&'a ExerciseCandidate> {
+    let key = normalize_exercise_name(typed);
+    if key.is_empty() {
+        return None;
+    }
+
+    let exact = |k: &str| candidates.iter().find(|c| c.keys.iter().any(|ck| ck == k));
+
+    if let Some(c) = exact(&key) {
+        return Some(c);
+    }
+
+    if let Some((_, canonical)) = EXERCISE_ALIASES
+        .iter()
+        .find(|(alias, _)| normalize_exercise_name(alias) == key)
+    {
+        if let Some(c) = exact(&normalize_exercise_name(canonical)) {
+            return Some(c);
+        }
+    }
+
+    if key.chars().count() < 2 {
+        return None;
+    }
+    candidates
+        .iter()
+        .filter_map(|c| {
+            c.keys
+                .iter()
+                .filter(|ck| ck.starts_with(&key))
+                .map(|ck| ck.chars().count())
+                .min()
+                .map(|len| (len, c))
+        })
+        .min_by_key(|(len, _)| *len)
+        .map(|(_, c)| c)
+}
+
+/// POST /log/workout/parse - Parse workout shorthand into a POST /log/workout preview
+///
+/// Deterministic (no LLM). Lines that fail to parse are returned in `errors`
+/// with a message; the rest are returned as editable exercises.
+pub async fn preview_workout_text(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthUser>,
+    Json(req): Json<ParseWorkoutTextRequest>,
+) -> AppResult<Json<ParseWorkoutTextResponse>> {
+    if req.text.trim().is_empty() {
+        return Err(crate::error::AppError::Validation(
+            "text cannot be empty".to_string(),
+        ));
+    }
+    if req.text.chars().count() > 5000 {
+        return Err(crate::error::AppError::Validation(
+            "text is too long (max 5000 chars)".to_string(),
+        ));
+    }
+
+    let candidates = load_exercise_candidates(&state, &user, Some(MODALITY_STRENGTH)).await?;
+
+    let (parsed, errors) = set_parser::parse_workout_text(&req.text, |name| {
+        resolve_exercise(name, &candidates)
+            .is_some_and(|c| c.equipment.as_deref() == Some("bodyweight"))
+    });
+
+    let exercises = parsed
+        .into_iter()
+        .map(|p| {
+            let matched = resolve_exercise(&p.name, &candidates);
+            let sets = p
+                .sets
+                .into_iter()
+                .map(|s| LogWorkoutSet {
+                    weight_kg: s.weight_kg,
+                    reps: s.reps,
+                    rpe: s.rpe,
+                    rest_sec: None,
+                    is_warmup: None,
+                    is_dropset: None,
+                })
+                .collect();
+
+            ParsedWorkoutExercise {
+                exercise: LogWorkoutExercise {
+                    exercise_id: matched.map(|c| c.id.clone()),
+                    custom_name: if matched.is_some() { None } else { Some(p.name.clone()) },
+                    muscle_tag: matched.map(|c| c.primary_muscle.clone()).unwrap_or_default(),
+                    sets,
+                    modality: None,
+                    cardio: None,
+                },
+                line: p.line,
+                input: p.input,
+                matched_name: matched.map(|c| c.name.clone()),
+            }
+        })
+        .collect();
+
+    let errors = errors
+        .into_iter()
+        .map(|e| WorkoutParseError {
+            line: e.line,
+            input: e.input,
+            message: e.message,
+        })
+        .collect();
+
+    Ok(Json(ParseWorkoutTextResponse { exercises, errors }))
+}
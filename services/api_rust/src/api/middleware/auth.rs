@@ -155,6 +155,12 @@ pub struct Claims {
     pub email: Option<String>,
     /// Role
     pub role: Option<String>,
+    /// Supabase Auth session (auth.sessions.id)
+    #[serde(default)]
+    pub session_id: Option<Uuid>,
+    /// Authenticator assurance level: "aal1" or "aal2" (MFA verified)
+    #[serde(default)]
+    pub aal: Option<String>,
 }
 
 /// Authenticated user extracted from JWT
@@ -165,6 +171,17 @@ pub struct AuthUser {
     pub role: Option<String>,
     /// Original access token for Supabase REST API calls (RLS)
     pub token: String,
+    /// Session the access token belongs to
+    pub session_id: Option<String>,
+    /// Authenticator assurance level of the session ("aal1" / "aal2")
+    pub aal: Option<String>,
+}
+
+impl AuthUser {
+    /// Whether the session has completed an MFA challenge
+    pub fn is_aal2(&self) -> bool {
+        self.aal.as_deref() == Some("aal2")
+    }
 }
 
 #[async_trait]
@@ -195,6 +212,8 @@ impl FromRequestParts<AppState> for AuthUser {
             email: claims.email.unwrap_or_default(),
             role: claims.role,
             token: token.to_string(),
+            session_id: claims.session_id.map(|id| id.to_string()),
+            aal: claims.aal,
         })
     }
 }
@@ -235,6 +254,8 @@ pub async fn auth_middleware(
                 email: claims.email.unwrap_or_default(),
                 role: claims.role,
                 token: token.to_string(),
+                session_id: claims.session_id.map(|id| id.to_string()),
+                aal: claims.aal,
             };
 
             // Add auth user to request extensions
@@ -249,6 +270,90 @@ pub async fn auth_middleware(
     }
 }
 
+// =============================================================================
+// MFA (AAL2)
+// =============================================================================
+
+/// MFA factor as returned in the Supabase Auth user object
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MfaFactor {
+    pub id: String,
+    pub factor_type: String,
+    /// "unverified" (enrollment not finished) or "verified"
+    pub status: String,
+    pub friendly_name: Option<String>,
+    pub created_at: Option<String>,
+}
+
+impl MfaFactor {
+    pub fn is_verified(&self) -> bool {
+        self.status == "verified"
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SupabaseUserFactors {
+    #[serde(default)]
+    factors: Option<Vec<MfaFactor>>,
+}
+
+/// MFA factors of the token's user (GET /auth/v1/user)
+pub(crate) async fn fetch_mfa_factors(state: &AppState, token: &str) -> Result<Vec<MfaFactor>, AppError> {
+    let url = format!("{}/auth/v1/user", state.config.supabase_url);
+    let response = reqwest::Client::new()
+        .get(&url)
+        .header("apikey", &state.config.supabase_anon_key)
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        return Err(AppError::InvalidToken("Session is no longer valid".to_string()));
+    }
+    if !status.is_success() {
+        return Err(AppError::UpstreamAuth(format!(
+            "auth.user upstream failed: status={}",
+            status.as_u16()
+        )));
+    }
+
+    let user: SupabaseUserFactors = response
+        .json()
+        .await
+        .map_err(|e| AppError::UpstreamAuth(format!("Failed to parse auth user: {}", e)))?;
+    Ok(user.factors.unwrap_or_default())
+}
+
+/// Step-up middleware for sensitive routes (place inside auth_middleware)
+///
+/// AAL2 sessions pass. AAL1 sessions pass only when the user has no verified
+/// factor (AAL1 is then the highest level they can reach); otherwise the client
+/// gets 403 `mfa_required` and should run the challenge/verify flow.
+pub async fn require_aal2(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let user = request
+        .extensions()
+        .get::<AuthUser>()
+        .cloned()
+        .ok_or(AppError::Unauthorized)?;
+
+    if !user.is_aal2() {
+        let factors = fetch_mfa_factors(&state, &user.token).await?;
+        if factors.iter().any(MfaFactor::is_verified) {
+            tracing::info!("AAL2 required but session is {:?}: user={}", user.aal, user.user_id);
+            return Err(AppError::MfaRequired(
+                "この操作には二段階認証が必要です。認証コードを入力してください".to_string(),
+            ));
+        }
+    }
+
+    Ok(next.run(request).await)
+}
+
 /// Validate JWT token and extract claims
 async fn validate_jwt(token: &str, state: &AppState) -> Result<Claims, AppError> {
     // Decode header to determine algorithm
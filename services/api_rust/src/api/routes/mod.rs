@@ -1,4 +1,5 @@
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
     routing::{delete, get, patch, post},
     Router,
@@ -7,7 +8,10 @@ use std::sync::Arc;
 use tower_governor::governor::GovernorConfigBuilder;
 use tower_governor::GovernorLayer;
 
-use crate::api::{handlers, middleware::auth_middleware};
+use crate::api::{
+    handlers,
+    middleware::{auth_middleware, require_aal2},
+};
 use crate::AppState;
 
 /// Create all API routes
@@ -18,14 +22,19 @@ pub fn create_routes(state: AppState) -> Router<AppState> {
         // Auth endpoints (no auth required)
         .nest("/auth", auth_routes())
         // Protected routes (auth required)
+        .nest("/account", account_routes(state.clone()))
         .nest("/users", users_routes(state.clone()))
         .nest("/meals", meals_routes(state.clone()))
         .nest("/exercises", exercises_routes(state.clone()))
         .nest("/workouts", workouts_routes(state.clone()))
         .nest("/dashboard", dashboard_routes(state.clone()))
         .nest("/log", log_routes(state.clone()))
+        .nest("/measurements", measurements_routes(state.clone()))
+        .nest("/import", import_routes(state.clone()))
+        .nest("/export", export_routes(state.clone()))
         .nest("/ai", ai_routes(state.clone()))
         .nest("/posts", posts_routes(state.clone()))
+        .nest("/progress-photos", progress_photos_routes(state.clone()))
         .nest("/comments", comments_routes(state.clone()))
         .nest("/support", support_routes(state.clone()))
         .nest("/subscriptions", subscriptions_routes(state.clone()))
@@ -59,12 +68,45 @@ fn auth_routes() -> Router<AppState> {
     Router::new()
         .route("/signup", post(handlers::signup))
         .route("/signin", post(handlers::signin))
+        .route("/google", post(handlers::signin_google))
+        .route("/apple", post(handlers::signin_apple))
         .route("/signout", post(handlers::signout))
         .route("/refresh", post(handlers::refresh_token))
         .route("/password/reset", post(handlers::reset_password))
         .layer(auth_rate_limit_layer)
 }
 
+/// /v1/account/* routes (auth required)
+/// パスワード・メールアドレス変更、セッション一覧、全端末ログアウト、二段階認証 (TOTP)
+/// SECURITY: パスワード照合・認証コード検証を含むため auth と同じレート制限 (5 req/s, burst 10) を適用
+/// SECURITY: 認証情報の変更は require_aal2（二段階認証を設定済みなら AAL2 必須）
+fn account_routes(state: AppState) -> Router<AppState> {
+    let aal2 = || middleware::from_fn_with_state(state.clone(), require_aal2);
+
+    let account_rate_limit = GovernorConfigBuilder::default()
+        .per_second(5)
+        .burst_size(10)
+        .finish()
+        .expect("Failed to create account rate limiter config");
+
+    Router::new()
+        .route("/password", post(handlers::change_password).route_layer(aal2()))
+        .route("/email", post(handlers::change_email).route_layer(aal2()))
+        .route(
+            "/sessions",
+            get(handlers::list_sessions).delete(handlers::sign_out_sessions),
+        )
+        .route("/mfa", get(handlers::get_mfa_status))
+        .route("/mfa/enroll", post(handlers::enroll_mfa).route_layer(aal2()))
+        .route("/mfa/challenge", post(handlers::challenge_mfa))
+        .route("/mfa/verify", post(handlers::verify_mfa))
+        .route("/mfa/:factor_id", delete(handlers::unenroll_mfa).route_layer(aal2()))
+        .layer(GovernorLayer {
+            config: Arc::new(account_rate_limit),
+        })
+        .route_layer(middleware::from_fn_with_state(state, auth_middleware))
+}
+
 /// /v1/users/* routes (auth required)
 fn users_routes(state: AppState) -> Router<AppState> {
     Router::new()
@@ -76,6 +118,11 @@ fn users_routes(state: AppState) -> Router<AppState> {
             "/push-token",
             post(handlers::upsert_push_token).delete(handlers::delete_push_token),
         )
+        .route(
+            "/me",
+            delete(handlers::delete_account)
+                .route_layer(middleware::from_fn_with_state(state.clone(), require_aal2)),
+        )
         .route("/me/sns-links", post(handlers::update_sns_links))
         .route("/me/online-status", post(handlers::update_online_status))
         .route("/:id/workout-dates", get(handlers::get_user_workout_dates))
@@ -121,8 +168,35 @@ fn dashboard_routes(state: AppState) -> Router<AppState> {
 fn log_routes(state: AppState) -> Router<AppState> {
     Router::new()
         .route("/workout", post(handlers::log_workout))
+        .route("/workout/parse", post(handlers::preview_workout_text))
         .route("/meal", post(handlers::log_meal))
         .route("/metrics", post(handlers::log_metrics))
+        .route("/measurements", post(handlers::log_measurements))
+        .route_layer(middleware::from_fn_with_state(state, auth_middleware))
+}
+
+/// /v1/measurements/* routes (auth required) - 体のサイズ計測
+fn measurements_routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/trends", get(handlers::get_measurement_trends))
+        .route_layer(middleware::from_fn_with_state(state, auth_middleware))
+}
+
+/// /v1/import/* routes (auth required) - 他アプリからの履歴インポート
+fn import_routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/workouts", post(handlers::import_workouts))
+        .layer(DefaultBodyLimit::max(handlers::MAX_IMPORT_BYTES + 64 * 1024))
+        .route_layer(middleware::from_fn_with_state(state, auth_middleware))
+}
+
+/// /v1/export/* routes (auth required) - データのエクスポート
+fn export_routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/workouts.csv", get(handlers::export_workouts_csv))
+        .route("/meals.csv", get(handlers::export_meals_csv))
+        .route("/metrics.csv", get(handlers::export_metrics_csv))
+        .route("/archive.json", get(handlers::export_archive))
         .route_layer(middleware::from_fn_with_state(state, auth_middleware))
 }
 
@@ -147,9 +221,23 @@ fn ai_routes(state: AppState) -> Router<AppState> {
     Router::new()
         .route("/ask", post(handlers::ask_ai))
         .route("/plan/today", post(handlers::plan_today))
+        .route("/jobs", post(handlers::create_ai_job))
         .route("/history", get(handlers::get_ai_history))
+        .route("/history/reindex", post(handlers::reindex_ai_history))
         .route("/inbox", get(handlers::get_ai_inbox))
+        .route("/reports/weekly", get(handlers::get_weekly_report))
+        .route("/meal/photo", post(handlers::analyze_meal_photo))
+        .route("/meal/parse", post(handlers::parse_meal_text))
         .layer(ai_rate_limit_layer)
+        // No model call below: keep these out of the strict AI rate limit
+        .route("/jobs/:id", get(handlers::get_ai_job))
+        .route("/sessions/search", get(handlers::search_ai_sessions))
+        .route(
+            "/sessions/:id",
+            get(handlers::get_ai_session)
+                .patch(handlers::update_ai_session)
+                .delete(handlers::delete_ai_session),
+        )
         .route_layer(middleware::from_fn_with_state(state, auth_middleware))
 }
 
@@ -163,6 +251,20 @@ fn posts_routes(state: AppState) -> Router<AppState> {
         .route_layer(middleware::from_fn_with_state(state, auth_middleware))
 }
 
+/// /v1/progress-photos/* routes (auth required) - 進捗写真（本人のみ）
+fn progress_photos_routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/",
+            get(handlers::get_progress_timeline).post(handlers::upload_progress_photo).layer(
+                DefaultBodyLimit::max(handlers::MAX_UPLOAD_BYTES + 64 * 1024),
+            ),
+        )
+        .route("/compare", get(handlers::compare_progress_photos))
+        .route("/:id", delete(handlers::delete_progress_photo))
+        .route_layer(middleware::from_fn_with_state(state, auth_middleware))
+}
+
 /// /v1/comments/* routes (auth required) - コメント操作
 fn comments_routes(state: AppState) -> Router<AppState> {
     Router::new()
@@ -179,10 +281,14 @@ fn support_routes(state: AppState) -> Router<AppState> {
 }
 
 /// /v1/subscriptions/* routes (auth required) - サブスクリプション管理
+/// SECURITY: 購入検証・解約は require_aal2（二段階認証を設定済みなら AAL2 必須）
 fn subscriptions_routes(state: AppState) -> Router<AppState> {
+    let aal2 = || middleware::from_fn_with_state(state.clone(), require_aal2);
+
     Router::new()
-        .route("/verify", post(handlers::verify_purchase))
-        .route("/me", get(handlers::get_my_subscription).delete(handlers::cancel_subscription))
+        .route("/verify", post(handlers::verify_purchase).route_layer(aal2()))
+        .route("/me", get(handlers::get_my_subscription))
+        .route("/me", delete(handlers::cancel_subscription).route_layer(aal2()))
         .route_layer(middleware::from_fn_with_state(state, auth_middleware))
 }
 
@@ -21,6 +21,26 @@ pub struct Config {
     // Gemini
     pub gemini_api_key: String,
     pub gemini_model: String,
+    /// Secondary model used when the primary fails (None = no fallback)
+    pub gemini_fallback_model: Option<String>,
+    pub gemini_max_concurrency: usize,
+    pub gemini_queue_timeout_ms: u64,
+    pub gemini_timeout_secs: u64,
+    pub gemini_max_retries: u32,
+
+    // Embeddings for history retrieval ("none" = lexical search only)
+    pub embedding_provider: String,
+    pub embedding_model: String,
+
+    // Prompt templates (versioned files + experiments.json)
+    pub prompts_dir: String,
+
+    // Safety policy rules (JSON)
+    pub safety_rules_path: String,
+
+    // Async AI jobs (background worker pool)
+    pub ai_job_workers: usize,
+    pub ai_job_queue_size: usize,
 }
 
 impl Config {
@@ -64,6 +84,42 @@ impl Config {
                 .map_err(|_| anyhow::anyhow!("GEMINI_API_KEY is required"))?,
             gemini_model: env::var("GEMINI_MODEL")
                 .unwrap_or_else(|_| "gemini-1.5-flash".to_string()),
+            gemini_fallback_model: env::var("GEMINI_FALLBACK_MODEL")
+                .ok()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty()),
+            gemini_max_concurrency: env::var("GEMINI_MAX_CONCURRENCY")
+                .unwrap_or_else(|_| "8".to_string())
+                .parse()?,
+            gemini_queue_timeout_ms: env::var("GEMINI_QUEUE_TIMEOUT_MS")
+                .unwrap_or_else(|_| "5000".to_string())
+                .parse()?,
+            gemini_timeout_secs: env::var("GEMINI_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()?,
+            gemini_max_retries: env::var("GEMINI_MAX_RETRIES")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()?,
+
+            // Embeddings
+            embedding_provider: env::var("EMBEDDING_PROVIDER").unwrap_or_else(|_| "none".to_string()),
+            embedding_model: env::var("EMBEDDING_MODEL")
+                .unwrap_or_else(|_| "text-embedding-004".to_string()),
+
+            // Prompt templates
+            prompts_dir: env::var("PROMPTS_DIR").unwrap_or_else(|_| "prompts".to_string()),
+
+            // Safety policy
+            safety_rules_path: env::var("SAFETY_RULES_PATH")
+                .unwrap_or_else(|_| "policies/safety_rules.json".to_string()),
+
+            // Async AI jobs
+            ai_job_workers: env::var("AI_JOB_WORKERS")
+                .unwrap_or_else(|_| "4".to_string())
+                .parse()?,
+            ai_job_queue_size: env::var("AI_JOB_QUEUE_SIZE")
+                .unwrap_or_else(|_| "100".to_string())
+                .parse()?,
         })
     }
 }
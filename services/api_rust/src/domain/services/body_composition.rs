@@ -0,0 +1,205 @@
+// Body circumference measurements and body-fat estimation
+// Sites / sides / units used by POST /log/measurements and the trend endpoint,
+// plus the U.S. Navy circumference method (waist, neck, hips and height).
+
+pub const IN_TO_CM: f64 = 2.54;
+
+/// Accepted circumference range (cm), rejects unit mix-ups like 80 "in" for a waist
+pub const MIN_CIRCUMFERENCE_CM: f64 = 10.0;
+pub const MAX_CIRCUMFERENCE_CM: f64 = 300.0;
+
+/// Measurement site
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Site {
+    Neck,
+    Chest,
+    Waist,
+    Hips,
+    Arm,
+    Thigh,
+}
+
+impl Site {
+    pub const ALLOWED: &'static [&'static str] = &["neck", "chest", "waist", "hips", "arm", "thigh"];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "neck" => Some(Site::Neck),
+            "chest" => Some(Site::Chest),
+            "waist" => Some(Site::Waist),
+            "hips" => Some(Site::Hips),
+            "arm" => Some(Site::Arm),
+            "thigh" => Some(Site::Thigh),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Site::Neck => "neck",
+            Site::Chest => "chest",
+            Site::Waist => "waist",
+            Site::Hips => "hips",
+            Site::Arm => "arm",
+            Site::Thigh => "thigh",
+        }
+    }
+
+    /// Limbs are measured per side, the trunk / neck on the midline
+    pub fn is_bilateral(self) -> bool {
+        matches!(self, Site::Arm | Site::Thigh)
+    }
+}
+
+/// Side of a measurement ("center" for midline sites)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Side {
+    Center,
+    Left,
+    Right,
+}
+
+impl Side {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Side::Center => "center",
+            Side::Left => "left",
+            Side::Right => "right",
+        }
+    }
+
+    /// Resolve the side for a site: limbs need left/right, midline sites take none (or "center")
+    pub fn for_site(site: Site, value: Option<&str>) -> Result<Self, String> {
+        match (site.is_bilateral(), value) {
+            (true, Some("left")) => Ok(Side::Left),
+            (true, Some("right")) => Ok(Side::Right),
+            (true, _) => Err(format!("{} requires side: left or right", site.as_str())),
+            (false, None | Some("center")) => Ok(Side::Center),
+            (false, Some(_)) => Err(format!("{} is measured without a side", site.as_str())),
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "center" => Some(Side::Center),
+            "left" => Some(Side::Left),
+            "right" => Some(Side::Right),
+            _ => None,
+        }
+    }
+}
+
+/// Length unit for input / output (stored as cm)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LengthUnit {
+    #[default]
+    Cm,
+    In,
+}
+
+impl LengthUnit {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "cm" => Some(LengthUnit::Cm),
+            "in" => Some(LengthUnit::In),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LengthUnit::Cm => "cm",
+            LengthUnit::In => "in",
+        }
+    }
+
+    pub fn to_cm(self, value: f64) -> f64 {
+        match self {
+            LengthUnit::Cm => value,
+            LengthUnit::In => value * IN_TO_CM,
+        }
+    }
+
+    /// Stored cm value expressed in this unit
+    pub fn convert_cm(self, value_cm: f64) -> f64 {
+        match self {
+            LengthUnit::Cm => value_cm,
+            LengthUnit::In => value_cm / IN_TO_CM,
+        }
+    }
+}
+
+/// U.S. Navy body-fat estimate (%), all lengths in cm.
+///
+/// Men: waist and neck; women: waist, hips and neck. Returns None when a required
+/// value is missing, the sex is not male/female, or the inputs are implausible.
+pub fn navy_bodyfat_pct(
+    sex: &str,
+    height_cm: f64,
+    waist_cm: Option<f64>,
+    neck_cm: Option<f64>,
+    hips_cm: Option<f64>,
+) -> Option<f64> {
+    let waist = waist_cm?;
+    let neck = neck_cm?;
+    if height_cm <= 0.0 {
+        return None;
+    }
+
+    let pct = match sex {
+        "male" => {
+            let girth = waist - neck;
+            if girth <= 0.0 {
+                return None;
+            }
+            495.0 / (1.0324 - 0.19077 * girth.log10() + 0.15456 * height_cm.log10()) - 450.0
+        }
+        "female" => {
+            let girth = waist + hips_cm? - neck;
+            if girth <= 0.0 {
+                return None;
+            }
+            495.0 / (1.29579 - 0.35004 * girth.log10() + 0.22100 * height_cm.log10()) - 450.0
+        }
+        _ => return None,
+    };
+
+    // Same range body_metrics.bodyfat_pct accepts
+    (1.0..=70.0)
+        .contains(&pct)
+        .then(|| (pct * 10.0).round() / 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_navy_bodyfat() {
+        // 178cm man, waist 86cm, neck 38cm -> ~17%
+        let male = navy_bodyfat_pct("male", 178.0, Some(86.0), Some(38.0), None).unwrap();
+        assert!((16.0..18.5).contains(&male), "{}", male);
+
+        // 165cm woman, waist 72cm, hips 98cm, neck 33cm -> ~27%
+        let female = navy_bodyfat_pct("female", 165.0, Some(72.0), Some(33.0), Some(98.0)).unwrap();
+        assert!((25.0..29.0).contains(&female), "{}", female);
+
+        assert_eq!(navy_bodyfat_pct("female", 165.0, Some(72.0), Some(33.0), None), None);
+        assert_eq!(navy_bodyfat_pct("other", 170.0, Some(80.0), Some(35.0), Some(95.0)), None);
+        assert_eq!(navy_bodyfat_pct("male", 178.0, Some(38.0), Some(40.0), None), None);
+    }
+
+    #[test]
+    fn test_sides_and_units() {
+        assert_eq!(Side::for_site(Site::Arm, Some("left")), Ok(Side::Left));
+        assert!(Side::for_site(Site::Thigh, None).is_err());
+        assert_eq!(Side::for_site(Site::Waist, None), Ok(Side::Center));
+        assert!(Side::for_site(Site::Waist, Some("left")).is_err());
+
+        assert!((LengthUnit::In.to_cm(10.0) - 25.4).abs() < 1e-9);
+        assert!((LengthUnit::In.convert_cm(25.4) - 10.0).abs() < 1e-9);
+        for s in Site::ALLOWED {
+            assert_eq!(Site::parse(s).map(Site::as_str), Some(*s));
+        }
+    }
+}
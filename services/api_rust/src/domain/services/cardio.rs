@@ -0,0 +1,133 @@
+// Cardio / endurance activities (runs, rides, rows, ...)
+// Logged as one `workout_cardio` row per workout exercise instead of weight × reps
+// sets, so volume / e1RM statistics never see them.
+
+pub const MODALITY_STRENGTH: &str = "strength";
+pub const MODALITY_CARDIO: &str = "cardio";
+pub const ALLOWED_MODALITIES: &[&str] = &[MODALITY_STRENGTH, MODALITY_CARDIO];
+
+/// 24h; longer entries are almost certainly a units mistake
+const MAX_DURATION_SEC: i32 = 24 * 60 * 60;
+/// 1000 km (ultra rides)
+const MAX_DISTANCE_M: f64 = 1_000_000.0;
+const MAX_ELEVATION_M: f64 = 20_000.0;
+const MIN_HEART_RATE: i32 = 30;
+const MAX_HEART_RATE: i32 = 250;
+const MAX_CALORIES_KCAL: i32 = 20_000;
+
+/// Raw cardio values as logged
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CardioMetrics {
+    pub duration_sec: i32,
+    pub distance_m: Option<f64>,
+    pub elevation_gain_m: Option<f64>,
+    pub avg_heart_rate: Option<i32>,
+    pub max_heart_rate: Option<i32>,
+    pub calories_kcal: Option<i32>,
+}
+
+impl CardioMetrics {
+    /// Range checks; returns a message suitable for a validation error
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_DURATION_SEC).contains(&self.duration_sec) {
+            return Err(format!(
+                "duration_sec must be between 1 and {}",
+                MAX_DURATION_SEC
+            ));
+        }
+        if let Some(d) = self.distance_m {
+            if !(0.0..=MAX_DISTANCE_M).contains(&d) {
+                return Err(format!("distance_m must be between 0 and {}", MAX_DISTANCE_M));
+            }
+        }
+        if let Some(e) = self.elevation_gain_m {
+            if !(0.0..=MAX_ELEVATION_M).contains(&e) {
+                return Err(format!(
+                    "elevation_gain_m must be between 0 and {}",
+                    MAX_ELEVATION_M
+                ));
+            }
+        }
+        for (name, hr) in [
+            ("avg_heart_rate", self.avg_heart_rate),
+            ("max_heart_rate", self.max_heart_rate),
+        ] {
+            if let Some(hr) = hr {
+                if !(MIN_HEART_RATE..=MAX_HEART_RATE).contains(&hr) {
+                    return Err(format!(
+                        "{} must be between {} and {}",
+                        name, MIN_HEART_RATE, MAX_HEART_RATE
+                    ));
+                }
+            }
+        }
+        if let (Some(avg), Some(max)) = (self.avg_heart_rate, self.max_heart_rate) {
+            if avg > max {
+                return Err("avg_heart_rate cannot exceed max_heart_rate".to_string());
+            }
+        }
+        if let Some(kcal) = self.calories_kcal {
+            if !(0..=MAX_CALORIES_KCAL).contains(&kcal) {
+                return Err(format!(
+                    "calories_kcal must be between 0 and {}",
+                    MAX_CALORIES_KCAL
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Pace in seconds per km (None without a distance)
+    pub fn pace_sec_per_km(&self) -> Option<i32> {
+        let km = self.distance_m? / 1000.0;
+        (km > 0.0).then(|| (self.duration_sec as f64 / km).round() as i32)
+    }
+
+    /// Average speed in km/h, 1 decimal (None without a distance)
+    pub fn speed_kmh(&self) -> Option<f64> {
+        let meters = self.distance_m?;
+        (meters > 0.0).then(|| {
+            let kmh = (meters / 1000.0) / (self.duration_sec as f64 / 3600.0);
+            (kmh * 10.0).round() / 10.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pace_and_speed() {
+        let run = CardioMetrics {
+            duration_sec: 25 * 60,
+            distance_m: Some(5000.0),
+            ..Default::default()
+        };
+        assert_eq!(run.pace_sec_per_km(), Some(300)); // 5:00 /km
+        assert_eq!(run.speed_kmh(), Some(12.0));
+
+        let row = CardioMetrics {
+            duration_sec: 600,
+            ..Default::default()
+        };
+        assert_eq!(row.pace_sec_per_km(), None);
+        assert_eq!(row.speed_kmh(), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let ok = CardioMetrics {
+            duration_sec: 1800,
+            distance_m: Some(10_000.0),
+            avg_heart_rate: Some(150),
+            max_heart_rate: Some(178),
+            calories_kcal: Some(600),
+            ..Default::default()
+        };
+        assert!(ok.validate().is_ok());
+        assert!(CardioMetrics { duration_sec: 0, ..ok }.validate().is_err());
+        assert!(CardioMetrics { avg_heart_rate: Some(190), ..ok }.validate().is_err());
+        assert!(CardioMetrics { distance_m: Some(-1.0), ..ok }.validate().is_err());
+    }
+}
@@ -0,0 +1,258 @@
+// Data export (CSV / JSON archive)
+// Flattens PostgREST rows (with embedded children) into CSV rows:
+// one row per workout set (or cardio entry), per meal item, per body-metrics day.
+
+use serde_json::Value;
+
+pub const WORKOUT_COLUMNS: &[&str] = &[
+    "date",
+    "start_time",
+    "end_time",
+    "workout_id",
+    "exercise_order",
+    "exercise",
+    "muscle_tag",
+    "modality",
+    "set_index",
+    "weight_kg",
+    "reps",
+    "rpe",
+    "rest_sec",
+    "is_warmup",
+    "is_dropset",
+    "duration_sec",
+    "distance_m",
+    "elevation_gain_m",
+    "avg_heart_rate",
+    "max_heart_rate",
+    "calories_kcal",
+    "perceived_fatigue",
+    "workout_note",
+];
+
+pub const MEAL_COLUMNS: &[&str] = &[
+    "date",
+    "time",
+    "meal_type",
+    "meal_id",
+    "item",
+    "quantity",
+    "unit",
+    "calories",
+    "protein_g",
+    "fat_g",
+    "carbs_g",
+    "fiber_g",
+    "meal_note",
+];
+
+pub const METRIC_COLUMNS: &[&str] = &[
+    "date",
+    "weight_kg",
+    "bodyfat_pct",
+    "sleep_hours",
+    "steps",
+    "note",
+];
+
+/// One row per set; cardio entries get one row with the set columns empty
+pub fn workout_rows(workout: &Value) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    for exercise in children(&workout["workout_exercises"]) {
+        let name = exercise["exercises"]["name"]
+            .as_str()
+            .or_else(|| exercise["custom_exercise_name"].as_str())
+            .unwrap_or_default();
+        let row = |set: &Value, cardio: &Value| -> Vec<String> {
+            vec![
+                cell(&workout["date"]),
+                cell(&workout["start_time"]),
+                cell(&workout["end_time"]),
+                cell(&workout["id"]),
+                cell(&exercise["exercise_order"]),
+                name.to_string(),
+                cell(&exercise["muscle_tag"]),
+                cell(&exercise["modality"]),
+                cell(&set["set_index"]),
+                cell(&set["weight_kg"]),
+                cell(&set["reps"]),
+                cell(&set["rpe"]),
+                cell(&set["rest_sec"]),
+                cell(&set["is_warmup"]),
+                cell(&set["is_dropset"]),
+                cell(&cardio["duration_sec"]),
+                cell(&cardio["distance_m"]),
+                cell(&cardio["elevation_gain_m"]),
+                cell(&cardio["avg_heart_rate"]),
+                cell(&cardio["max_heart_rate"]),
+                cell(&cardio["calories_kcal"]),
+                cell(&workout["perceived_fatigue"]),
+                cell(&workout["note"]),
+            ]
+        };
+
+        for cardio in children(&exercise["workout_cardio"]) {
+            rows.push(row(&Value::Null, cardio));
+        }
+        for set in children(&exercise["workout_sets"]) {
+            rows.push(row(set, &Value::Null));
+        }
+    }
+    rows
+}
+
+/// One row per meal item; a meal without items still gets one row
+pub fn meal_rows(meal: &Value) -> Vec<Vec<String>> {
+    let row = |item: &Value| -> Vec<String> {
+        vec![
+            cell(&meal["date"]),
+            cell(&meal["time"]),
+            cell(&meal["meal_type"]),
+            cell(&meal["id"]),
+            cell(&item["name"]),
+            cell(&item["quantity"]),
+            cell(&item["unit"]),
+            cell(&item["calories"]),
+            cell(&item["protein_g"]),
+            cell(&item["fat_g"]),
+            cell(&item["carbs_g"]),
+            cell(&item["fiber_g"]),
+            cell(&meal["note"]),
+        ]
+    };
+
+    let items = children(&meal["meal_items"]);
+    if items.is_empty() {
+        return vec![row(&Value::Null)];
+    }
+    items.into_iter().map(row).collect()
+}
+
+pub fn metric_rows(metric: &Value) -> Vec<Vec<String>> {
+    vec![METRIC_COLUMNS.iter().map(|c| cell(&metric[*c])).collect()]
+}
+
+/// Encode one CSV line (RFC 4180, CRLF)
+pub fn csv_line<S: AsRef<str>>(fields: &[S]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|f| escape_field(f.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+fn escape_field(value: &str) -> String {
+    // Spreadsheet formula injection: text cells starting with = + - @ are prefixed
+    // with ' (numbers such as "-1.5" are left alone)
+    let value =
+        if value.starts_with(['=', '+', '-', '@', '\t', '\r']) && value.parse::<f64>().is_err() {
+            format!("'{}", value)
+        } else {
+            value.to_string()
+        };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Embedded children (array; one-to-one embeds come back as an object)
+fn children(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(arr) => arr.iter().collect(),
+        Value::Object(_) => vec![value],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_csv_line_escaping() {
+        assert_eq!(
+            csv_line(&["a", "b,c", "say \"hi\""]),
+            "a,\"b,c\",\"say \"\"hi\"\"\"\r\n"
+        );
+        assert_eq!(
+            csv_line(&["=SUM(A1)", "-1.5", "line\nbreak"]),
+            "'=SUM(A1),-1.5,\"line\nbreak\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_workout_rows() {
+        let workout = json!({
+            "id": "w1",
+            "date": "2026-10-01",
+            "start_time": null,
+            "note": "good",
+            "workout_exercises": [
+                {
+                    "exercise_order": 0,
+                    "muscle_tag": "chest",
+                    "modality": "strength",
+                    "exercises": { "name": "ベンチプレス" },
+                    "workout_sets": [
+                        { "set_index": 1, "weight_kg": 80, "reps": 8, "is_warmup": false },
+                        { "set_index": 2, "weight_kg": 82.5, "reps": 6, "is_warmup": false }
+                    ],
+                    "workout_cardio": null
+                },
+                {
+                    "exercise_order": 1,
+                    "muscle_tag": "cardio",
+                    "modality": "cardio",
+                    "exercises": null,
+                    "custom_exercise_name": "Run",
+                    "workout_sets": [],
+                    "workout_cardio": { "duration_sec": 1800, "distance_m": 5000 }
+                }
+            ]
+        });
+
+        let rows = workout_rows(&workout);
+        assert_eq!(rows.len(), 3);
+        assert!(rows.iter().all(|r| r.len() == WORKOUT_COLUMNS.len()));
+        assert_eq!(rows[1][5], "ベンチプレス");
+        assert_eq!(rows[1][9], "82.5");
+        assert_eq!(rows[2][5], "Run");
+        assert_eq!(rows[2][8], "");
+        assert_eq!(rows[2][15], "1800");
+    }
+
+    #[test]
+    fn test_meal_rows() {
+        let empty =
+            json!({ "id": "m1", "date": "2026-10-01", "meal_type": "lunch", "meal_items": [] });
+        let rows = meal_rows(&empty);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].len(), MEAL_COLUMNS.len());
+
+        let meal = json!({
+            "id": "m2",
+            "date": "2026-10-01",
+            "meal_type": "dinner",
+            "meal_items": [
+                { "name": "ご飯", "calories": 250 },
+                { "name": "鶏むね", "calories": 180, "protein_g": 35.5 }
+            ]
+        });
+        let rows = meal_rows(&meal);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1][8], "35.5");
+    }
+}
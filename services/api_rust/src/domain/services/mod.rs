@@ -1,6 +1,12 @@
 // Domain services
 // Business logic that doesn't fit into handlers or infrastructure
 
+pub mod body_composition;
+pub mod cardio;
+pub mod data_export;
+pub mod set_parser;
+pub mod workout_import;
+
 use crate::state::calculate_e1rm;
 
 /// Calculate recommended weight for next set based on previous performance
@@ -0,0 +1,477 @@
+// Rule-based workout shorthand parser
+// Turns "ベンチ 80kg x 8 x 3, RPE8" / "squat 100x5,5,4" into structured sets
+// without an LLM round-trip. Exercise name resolution is done by the caller.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+use unicode_normalization::UnicodeNormalization;
+
+/// Max lines (= exercises) accepted per request (matches POST /log/workout)
+pub const MAX_LINES: usize = 50;
+/// Max sets per exercise (matches POST /log/workout)
+pub const MAX_SETS_PER_EXERCISE: usize = 50;
+
+const LB_TO_KG: f64 = 0.45359237;
+
+static RPE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:rpe|@)\s*(\d+(?:\.\d+)?)").expect("valid RPE regex"));
+
+/// Common shorthand -> canonical exercise name (seeded `exercises.name`)
+pub const EXERCISE_ALIASES: &[(&str, &str)] = &[
+    ("ベンチ", "ベンチプレス"),
+    ("bench", "ベンチプレス"),
+    ("bp", "ベンチプレス"),
+    ("sq", "スクワット"),
+    ("スク", "スクワット"),
+    ("デッド", "デッドリフト"),
+    ("dead", "デッドリフト"),
+    ("dl", "デッドリフト"),
+    ("ohp", "オーバーヘッドプレス"),
+    ("ショルダープレス", "ダンベルショルダープレス"),
+    ("rdl", "ルーマニアンデッドリフト"),
+    ("ラットプル", "ラットプルダウン"),
+    ("チンアップ", "チンニング"),
+    ("プルアップ", "懸垂"),
+    ("腕立て", "腕立て伏せ"),
+    ("pushup", "腕立て伏せ"),
+    ("レッグエクステ", "レッグエクステンション"),
+    ("ブルガリアン", "ブルガリアンスクワット"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedSet {
+    pub weight_kg: Option<f64>,
+    pub reps: Option<i32>,
+    pub rpe: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedExerciseLine {
+    /// 1-based line number in the input
+    pub line: usize,
+    pub input: String,
+    pub name: String,
+    pub sets: Vec<ParsedSet>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetParseError {
+    /// 1-based line number in the input
+    pub line: usize,
+    pub input: String,
+    pub message: String,
+}
+
+/// Normalize an exercise name for matching (NFKC, lowercase, no spaces/separators)
+pub fn normalize_exercise_name(name: &str) -> String {
+    name.nfkc()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace() && !matches!(c, '・' | '-' | '_' | '.'))
+        .collect()
+}
+
+/// Parse multi-line workout shorthand (one exercise per line or `;`-separated).
+///
+/// `is_bodyweight` is called with the raw exercise name; when it returns true,
+/// unit-less numbers are read as `reps x sets` instead of `weight x reps x sets`.
+pub fn parse_workout_text<F>(text: &str, is_bodyweight: F) -> (Vec<ParsedExerciseLine>, Vec<SetParseError>)
+where
+    F: Fn(&str) -> bool,
+{
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+
+    let lines = text
+        .split(['\n', ';'])
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, l)| !l.is_empty());
+
+    for (idx, line) in lines {
+        let line_no = idx + 1;
+        if parsed.len() + errors.len() >= MAX_LINES {
+            errors.push(SetParseError {
+                line: line_no,
+                input: line.to_string(),
+                message: format!("一度に入力できるのは{}種目までです", MAX_LINES),
+            });
+            break;
+        }
+
+        match parse_line(line, &is_bodyweight) {
+            Ok((name, sets)) => parsed.push(ParsedExerciseLine {
+                line: line_no,
+                input: line.to_string(),
+                name,
+                sets,
+            }),
+            Err(message) => errors.push(SetParseError {
+                line: line_no,
+                input: line.to_string(),
+                message,
+            }),
+        }
+    }
+
+    (parsed, errors)
+}
+
+fn parse_line<F>(line: &str, is_bodyweight: &F) -> Result<(String, Vec<ParsedSet>), String>
+where
+    F: Fn(&str) -> bool,
+{
+    let normalized: String = line
+        .nfkc()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            '×' | '✕' | '✖' | '*' => 'x',
+            '、' | '，' => ',',
+            _ => c,
+        })
+        .collect();
+
+    // Name = everything before the first digit / "@"
+    let spec_start = normalized
+        .find(|c: char| c.is_ascii_digit() || c == '@')
+        .unwrap_or(normalized.len());
+    let (raw_name, spec) = normalized.split_at(spec_start);
+
+    let mut name = raw_name.trim().trim_end_matches(['x', ',', ':']).trim().to_string();
+    let mut bodyweight = false;
+    for keyword in ["自重", "bodyweight", "bw"] {
+        if let Some(stripped) = name.strip_suffix(keyword) {
+            name = stripped.trim().to_string();
+            bodyweight = true;
+            break;
+        }
+    }
+    if name.is_empty() {
+        return Err("種目名がありません（例: ベンチ 80kg x 8 x 3）".to_string());
+    }
+    if name.chars().count() > 80 {
+        return Err("種目名が長すぎます（最大80文字）".to_string());
+    }
+    if spec.trim().is_empty() {
+        return Err("重量・回数がありません（例: ベンチ 80kg x 8 x 3）".to_string());
+    }
+
+    // Use the original (non-lowercased) spelling for display/matching when possible
+    let display_name = line
+        .nfkc()
+        .collect::<String>()
+        .chars()
+        .take(name.chars().count())
+        .collect::<String>();
+    let display_name = if normalize_exercise_name(&display_name) == normalize_exercise_name(&name) {
+        display_name.trim().to_string()
+    } else {
+        name.clone()
+    };
+
+    let bodyweight = bodyweight || is_bodyweight(&display_name);
+
+    let mut sets: Vec<ParsedSet> = Vec::new();
+    let mut carried_weight: Option<f64> = None;
+
+    for segment in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        // RPE ("rpe8", "@8.5") applies to this segment's sets, or to all sets so far
+        // when the segment contains nothing else ("..., RPE8")
+        let rpe = match RPE_REGEX.captures(segment) {
+            Some(c) => {
+                let v: f64 = c[1].parse().map_err(|_| format!("RPE「{}」を解釈できません", &c[1]))?;
+                if !(1.0..=10.0).contains(&v) {
+                    return Err("RPEは1〜10の範囲で入力してください".to_string());
+                }
+                Some(v)
+            }
+            None => None,
+        };
+        let rest = RPE_REGEX.replace_all(segment, " ");
+
+        let tokens = tokenize(&rest)?;
+        if tokens.is_empty() {
+            match rpe {
+                Some(v) => {
+                    if sets.is_empty() {
+                        return Err("RPEの前に重量・回数を入力してください".to_string());
+                    }
+                    for s in sets.iter_mut().filter(|s| s.rpe.is_none()) {
+                        s.rpe = Some(v);
+                    }
+                    continue;
+                }
+                None => continue,
+            }
+        }
+
+        let (weight, reps, set_count) = assign_slots(&tokens, bodyweight, carried_weight)?;
+
+        if let Some(w) = weight {
+            if !(0.0..=1000.0).contains(&w) {
+                return Err("重量は0〜1000kgの範囲で入力してください".to_string());
+            }
+            carried_weight = Some(w);
+        }
+        if !(1..=100).contains(&reps) {
+            return Err("回数は1〜100の範囲で入力してください".to_string());
+        }
+        if !(1..=20).contains(&set_count) {
+            return Err("セット数は1〜20の範囲で入力してください".to_string());
+        }
+
+        for _ in 0..set_count {
+            sets.push(ParsedSet {
+                weight_kg: weight,
+                reps: Some(reps),
+                rpe,
+            });
+        }
+        if sets.len() > MAX_SETS_PER_EXERCISE {
+            return Err(format!("セット数が多すぎます（最大{}セット）", MAX_SETS_PER_EXERCISE));
+        }
+    }
+
+    if sets.is_empty() {
+        return Err("重量・回数がありません（例: ベンチ 80kg x 8 x 3）".to_string());
+    }
+
+    Ok((display_name, sets))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unit {
+    None,
+    Kg,
+    Lb,
+    Reps,
+    Sets,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    value: f64,
+    unit: Unit,
+}
+
+/// Split a segment like "80kg x 8回 3セット" into numbers with optional units
+fn tokenize(segment: &str) -> Result<Vec<Token>, String> {
+    const UNITS: &[(&str, Unit)] = &[
+        ("kgs", Unit::Kg),
+        ("kg", Unit::Kg),
+        ("キロ", Unit::Kg),
+        ("lbs", Unit::Lb),
+        ("lb", Unit::Lb),
+        ("ポンド", Unit::Lb),
+        ("reps", Unit::Reps),
+        ("rep", Unit::Reps),
+        ("レップ", Unit::Reps),
+        ("回", Unit::Reps),
+        ("r", Unit::Reps),
+        ("sets", Unit::Sets),
+        ("set", Unit::Sets),
+        ("セット", Unit::Sets),
+        ("s", Unit::Sets),
+    ];
+
+    let mut tokens = Vec::new();
+    let mut rest = segment;
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() || c == 'x' {
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        if !c.is_ascii_digit() {
+            let snippet: String = rest.chars().take(8).collect();
+            return Err(format!("「{}」を解釈できません", snippet.trim()));
+        }
+
+        let end = rest
+            .find(|ch: char| !(ch.is_ascii_digit() || ch == '.'))
+            .unwrap_or(rest.len());
+        let value: f64 = rest[..end]
+            .parse()
+            .map_err(|_| format!("数値「{}」を解釈できません", &rest[..end]))?;
+        rest = rest[end..].trim_start();
+
+        let mut unit = Unit::None;
+        for (suffix, u) in UNITS {
+            if let Some(after) = rest.strip_prefix(suffix) {
+                // Avoid reading the "s"/"r" of a following word as a unit
+                let boundary = after
+                    .chars()
+                    .next()
+                    .map(|n| !n.is_ascii_alphabetic())
+                    .unwrap_or(true);
+                if boundary {
+                    unit = *u;
+                    rest = after;
+                    break;
+                }
+            }
+        }
+
+        tokens.push(Token { value, unit });
+    }
+
+    Ok(tokens)
+}
+
+/// Map tokens to (weight_kg, reps, sets)
+///
+/// Explicit units win; unit-less numbers fill the remaining slots in
+/// `weight x reps x sets` order (`reps x sets` for bodyweight exercises).
+/// A lone unit-less number reuses the previous segment's weight ("100x5,5,4").
+fn assign_slots(
+    tokens: &[Token],
+    bodyweight: bool,
+    carried_weight: Option<f64>,
+) -> Result<(Option<f64>, i32, i32), String> {
+    let mut weight: Option<f64> = None;
+    let mut reps: Option<f64> = None;
+    let mut sets: Option<f64> = None;
+    let mut unitless: Vec<f64> = Vec::new();
+
+    for t in tokens {
+        let slot = match t.unit {
+            Unit::Kg => Some((&mut weight, t.value)),
+            Unit::Lb => Some((&mut weight, (t.value * LB_TO_KG * 10.0).round() / 10.0)),
+            Unit::Reps => Some((&mut reps, t.value)),
+            Unit::Sets => Some((&mut sets, t.value)),
+            Unit::None => None,
+        };
+        match slot {
+            Some((target, value)) => {
+                if target.is_some() {
+                    return Err("同じ項目が2回指定されています".to_string());
+                }
+                *target = Some(value);
+            }
+            None => unitless.push(t.value),
+        }
+    }
+
+    let lone_number = unitless.len() == 1 && weight.is_none() && reps.is_none();
+    if lone_number && !bodyweight {
+        match carried_weight {
+            Some(w) => {
+                weight = Some(w);
+                reps = Some(unitless[0]);
+                unitless.clear();
+            }
+            None => return Err("重量と回数を入力してください（例: 80x8）".to_string()),
+        }
+    }
+
+    for value in unitless {
+        let slot = if !bodyweight && weight.is_none() {
+            &mut weight
+        } else if reps.is_none() {
+            &mut reps
+        } else if sets.is_none() {
+            &mut sets
+        } else {
+            return Err("数値が多すぎます（重量 x 回数 x セット数 の形式で入力してください）".to_string());
+        };
+        *slot = Some(value);
+    }
+
+    let reps = reps.ok_or_else(|| "回数がありません（例: 80kg x 8）".to_string())?;
+    let sets = sets.unwrap_or(1.0);
+    if reps.fract() != 0.0 || sets.fract() != 0.0 {
+        return Err("回数・セット数は整数で入力してください".to_string());
+    }
+
+    Ok((weight, reps as i32, sets as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(weight_kg: Option<f64>, reps: i32, rpe: Option<f64>) -> ParsedSet {
+        ParsedSet {
+            weight_kg,
+            reps: Some(reps),
+            rpe,
+        }
+    }
+
+    #[test]
+    fn test_parse_valid_lines() {
+        let cases: Vec<(&str, &str, Vec<ParsedSet>)> = vec![
+            (
+                "ベンチ 80kg x 8 x 3, RPE8",
+                "ベンチ",
+                vec![set(Some(80.0), 8, Some(8.0)); 3],
+            ),
+            (
+                "squat 100x5,5,4",
+                "squat",
+                vec![set(Some(100.0), 5, None), set(Some(100.0), 5, None), set(Some(100.0), 4, None)],
+            ),
+            (
+                "デッドリフト １２０ｋｇ × ５回 ２セット",
+                "デッドリフト",
+                vec![set(Some(120.0), 5, None); 2],
+            ),
+            (
+                "bench 80x8, 85x6@9",
+                "bench",
+                vec![set(Some(80.0), 8, None), set(Some(85.0), 6, Some(9.0))],
+            ),
+            ("ohp 135lb x 5", "ohp", vec![set(Some(61.2), 5, None)]),
+            ("懸垂 自重 10x3", "懸垂", vec![set(None, 10, None); 3]),
+        ];
+
+        for (input, name, sets) in cases {
+            let (parsed, errors) = parse_workout_text(input, |_| false);
+            assert!(errors.is_empty(), "{}: {:?}", input, errors);
+            assert_eq!(parsed.len(), 1, "{}", input);
+            assert_eq!(parsed[0].name, name, "{}", input);
+            assert_eq!(parsed[0].sets, sets, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_bodyweight_hint() {
+        let (parsed, errors) = parse_workout_text("チンニング 10x3", |name| name == "チンニング");
+        assert!(errors.is_empty());
+        assert_eq!(parsed[0].sets, vec![set(None, 10, None); 3]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            "80kg x 8",
+            "ベンチ",
+            "ベンチ 80",
+            "ベンチ 80x8x3x2",
+            "ベンチ 80x0",
+            "ベンチ 80x8, RPE11",
+            "ベンチ 80x8 たくさん",
+        ];
+
+        for input in cases {
+            let (parsed, errors) = parse_workout_text(input, |_| false);
+            assert!(parsed.is_empty(), "{} should not parse", input);
+            assert_eq!(errors.len(), 1, "{}", input);
+            assert_eq!(errors[0].line, 1);
+        }
+    }
+
+    #[test]
+    fn test_multiple_lines_keep_line_numbers() {
+        let (parsed, errors) = parse_workout_text("ベンチ 80x8\n\nsquat\ndl 140x5", |_| false);
+        assert_eq!(parsed.iter().map(|p| p.line).collect::<Vec<_>>(), vec![1, 4]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 3);
+    }
+
+    #[test]
+    fn test_normalize_exercise_name() {
+        assert_eq!(normalize_exercise_name("Bench Press"), "benchpress");
+        assert_eq!(normalize_exercise_name("Ｔ-バー・ロウ"), "tバーロウ");
+    }
+}
@@ -0,0 +1,708 @@
+// Workout history import from other trackers' CSV exports
+// Strong: Date, Workout Name, Duration, Exercise Name, Set Order, Weight, Reps, Distance, Seconds, ...
+// Hevy:   title, start_time, end_time, description, exercise_title, set_index, set_type,
+//         weight_kg | weight_lbs, reps, distance_km | distance_miles, duration_seconds, rpe
+// Rows are grouped into workouts (by start time + title) and exercises (by name).
+// Exercise name resolution and DB writes are done by the caller.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
+
+const LB_TO_KG: f64 = 0.45359237;
+const MI_TO_M: f64 = 1609.344;
+const MAX_WEIGHT_KG: f64 = 1000.0;
+const MAX_REPS: i32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    Strong,
+    Hevy,
+}
+
+impl ImportSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImportSource::Strong => "strong",
+            ImportSource::Hevy => "hevy",
+        }
+    }
+}
+
+/// Units / timezone for exports that don't state them (Strong)
+#[derive(Debug, Clone, Copy)]
+pub struct ImportOptions {
+    /// Strong weights are in the app's unit setting
+    pub weight_in_lb: bool,
+    /// Strong distances are in the app's unit setting
+    pub distance_in_mi: bool,
+    /// Export timestamps are local time without an offset
+    pub utc_offset: FixedOffset,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            weight_in_lb: false,
+            distance_in_mi: false,
+            utc_offset: FixedOffset::east_opt(0).expect("zero offset"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedSet {
+    pub weight_kg: Option<f64>,
+    pub reps: i32,
+    pub rpe: Option<f64>,
+    pub is_warmup: bool,
+    pub is_dropset: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImportedCardio {
+    pub duration_sec: i32,
+    pub distance_m: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedExercise {
+    pub name: String,
+    pub sets: Vec<ImportedSet>,
+    /// Distance / duration rows (summed when an activity spans several rows)
+    pub cardio: Option<ImportedCardio>,
+    /// Source lines of `sets` / `cardio`, for the unmatched-rows report
+    pub set_lines: Vec<usize>,
+    pub cardio_lines: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedWorkout {
+    pub date: NaiveDate,
+    pub start_time: DateTime<FixedOffset>,
+    pub end_time: Option<DateTime<FixedOffset>>,
+    pub title: String,
+    pub note: Option<String>,
+    pub exercises: Vec<ImportedExercise>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowError {
+    pub line: usize,
+    pub input: String,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub struct ParsedExport {
+    pub source: ImportSource,
+    pub workouts: Vec<ImportedWorkout>,
+    pub errors: Vec<RowError>,
+}
+
+/// Parse a Strong or Hevy CSV export (format detected from the header)
+pub fn parse_export(text: &str, options: &ImportOptions) -> Result<ParsedExport, String> {
+    let text = text.trim_start_matches('\u{feff}');
+    let delimiter = detect_delimiter(text);
+    let mut records = parse_csv(text, delimiter).into_iter();
+    let (_, header) = records.next().ok_or_else(|| "CSV is empty".to_string())?;
+    let columns = Columns::new(&header);
+
+    let (source, extract): (ImportSource, RowExtractor) =
+        if columns.has("exercise_title") && columns.has("start_time") {
+            (ImportSource::Hevy, hevy_row)
+        } else if columns.has("exercise name") && columns.has("date") {
+            (ImportSource::Strong, strong_row)
+        } else {
+            return Err("Unrecognized CSV format. Upload a Strong or Hevy export.".to_string());
+        };
+
+    let mut workouts: Vec<ImportedWorkout> = Vec::new();
+    let mut workout_index: HashMap<(i64, String), usize> = HashMap::new();
+    let mut errors = Vec::new();
+
+    for (line, record) in records {
+        if record.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+        let row = match extract(&columns, &record, options) {
+            RowResult::Row(row) => row,
+            RowResult::Skip => continue,
+            RowResult::Error(message) => {
+                errors.push(RowError {
+                    line,
+                    input: record.join(&delimiter.to_string()),
+                    message,
+                });
+                continue;
+            }
+        };
+
+        let key = (row.start_time.timestamp(), row.title.clone());
+        let idx = *workout_index.entry(key).or_insert_with(|| {
+            workouts.push(ImportedWorkout {
+                date: row.start_time.date_naive(),
+                start_time: row.start_time,
+                end_time: row.end_time,
+                title: row.title.clone(),
+                note: row.workout_note.clone(),
+                exercises: Vec::new(),
+            });
+            workouts.len() - 1
+        });
+        let workout = &mut workouts[idx];
+
+        let exercise = match workout
+            .exercises
+            .iter_mut()
+            .position(|e| e.name == row.exercise_name)
+        {
+            Some(pos) => &mut workout.exercises[pos],
+            None => {
+                workout.exercises.push(ImportedExercise {
+                    name: row.exercise_name.clone(),
+                    sets: Vec::new(),
+                    cardio: None,
+                    set_lines: Vec::new(),
+                    cardio_lines: Vec::new(),
+                });
+                workout.exercises.last_mut().expect("just pushed")
+            }
+        };
+        match row.entry {
+            RowEntry::Set(set) => {
+                exercise.sets.push(set);
+                exercise.set_lines.push(line);
+            }
+            RowEntry::Cardio(c) => {
+                exercise.cardio_lines.push(line);
+                let total = exercise.cardio.get_or_insert(ImportedCardio {
+                    duration_sec: 0,
+                    distance_m: None,
+                });
+                total.duration_sec += c.duration_sec;
+                if let Some(d) = c.distance_m {
+                    total.distance_m = Some(total.distance_m.unwrap_or(0.0) + d);
+                }
+            }
+        }
+    }
+
+    Ok(ParsedExport {
+        source,
+        workouts,
+        errors,
+    })
+}
+
+// =============================================================================
+// Exercise names
+// =============================================================================
+
+/// Split a tracker name like "Bench Press (Barbell)" into ("Bench Press", Some("barbell"))
+pub fn split_equipment(name: &str) -> (&str, Option<&'static str>) {
+    const EQUIPMENT: &[(&str, &str)] = &[
+        ("barbell", "barbell"),
+        ("dumbbell", "dumbbell"),
+        ("machine", "machine"),
+        ("smith machine", "machine"),
+        ("cable", "cable"),
+        ("bodyweight", "bodyweight"),
+        ("weighted", "bodyweight"),
+        ("assisted", "bodyweight"),
+        ("kettlebell", "kettlebell"),
+        ("band", "band"),
+    ];
+    let name = name.trim();
+    let Some(open) = name.rfind(" (").filter(|_| name.ends_with(')')) else {
+        return (name, None);
+    };
+    let suffix = name[open + 2..name.len() - 1].to_lowercase();
+    let equipment = EQUIPMENT
+        .iter()
+        .find(|(label, _)| *label == suffix)
+        .map(|(_, eq)| *eq);
+    (name[..open].trim_end(), equipment)
+}
+
+/// Best-effort primary muscle for an exercise we have to create (checked in order)
+pub fn suggest_muscle(name: &str) -> Option<&'static str> {
+    const KEYWORDS: &[(&str, &str)] = &[
+        ("leg curl", "hamstrings"),
+        ("romanian", "hamstrings"),
+        ("stiff leg", "hamstrings"),
+        ("good morning", "hamstrings"),
+        ("hip thrust", "glutes"),
+        ("glute", "glutes"),
+        ("calf", "calves"),
+        ("squat", "quads"),
+        ("leg press", "quads"),
+        ("leg extension", "quads"),
+        ("lunge", "quads"),
+        ("deadlift", "lower_back"),
+        ("back extension", "lower_back"),
+        ("hyperextension", "lower_back"),
+        ("tricep", "triceps"),
+        ("pushdown", "triceps"),
+        ("skull", "triceps"),
+        ("dip", "triceps"),
+        ("curl", "biceps"),
+        ("shrug", "traps"),
+        ("pulldown", "lats"),
+        ("pull up", "lats"),
+        ("pull-up", "lats"),
+        ("chin up", "lats"),
+        ("chin-up", "lats"),
+        ("row", "back"),
+        ("face pull", "shoulders"),
+        ("lateral raise", "shoulders"),
+        ("overhead press", "shoulders"),
+        ("shoulder press", "shoulders"),
+        ("military", "shoulders"),
+        ("bench", "chest"),
+        ("chest", "chest"),
+        ("fly", "chest"),
+        ("push up", "chest"),
+        ("push-up", "chest"),
+        ("crunch", "abs"),
+        ("plank", "abs"),
+        ("sit up", "abs"),
+        ("leg raise", "abs"),
+        ("ab ", "abs"),
+        ("russian twist", "obliques"),
+        ("wrist", "forearms"),
+        ("adductor", "adductors"),
+        ("abductor", "abductors"),
+    ];
+    let lower = format!("{} ", name.to_lowercase());
+    KEYWORDS
+        .iter()
+        .find(|(keyword, _)| lower.contains(keyword))
+        .map(|(_, muscle)| *muscle)
+}
+
+// =============================================================================
+// Row extraction
+// =============================================================================
+
+struct Row {
+    start_time: DateTime<FixedOffset>,
+    end_time: Option<DateTime<FixedOffset>>,
+    title: String,
+    workout_note: Option<String>,
+    exercise_name: String,
+    entry: RowEntry,
+}
+
+enum RowEntry {
+    Set(ImportedSet),
+    Cardio(ImportedCardio),
+}
+
+type RowExtractor = fn(&Columns, &[String], &ImportOptions) -> RowResult;
+
+enum RowResult {
+    Row(Row),
+    /// Not an exercise row (e.g. Strong "Rest Timer")
+    Skip,
+    Error(String),
+}
+
+fn strong_row(cols: &Columns, record: &[String], options: &ImportOptions) -> RowResult {
+    let set_order = cols.get(record, "set order");
+    if set_order.eq_ignore_ascii_case("rest timer") {
+        return RowResult::Skip;
+    }
+    let Some(start_time) = parse_local_time(cols.get(record, "date"), options.utc_offset) else {
+        return RowResult::Error("Invalid Date".to_string());
+    };
+    let end_time = parse_duration_text(cols.get(record, "duration"))
+        .filter(|d| *d > 0)
+        .map(|secs| start_time + Duration::seconds(secs));
+
+    let weight_unit = if options.weight_in_lb { LB_TO_KG } else { 1.0 };
+    let distance_unit = if options.distance_in_mi {
+        MI_TO_M
+    } else {
+        1000.0
+    };
+    let entry = match entry_from(
+        parse_number(cols.get(record, "weight")).map(|w| w * weight_unit),
+        cols.get(record, "reps"),
+        cols.get(record, "rpe"),
+        parse_number(cols.get(record, "distance")).map(|d| d * distance_unit),
+        cols.get(record, "seconds"),
+        match set_order.to_ascii_uppercase().as_str() {
+            "W" => (true, false),
+            "D" => (false, true),
+            _ => (false, false),
+        },
+    ) {
+        Ok(entry) => entry,
+        Err(message) => return RowResult::Error(message),
+    };
+
+    finish_row(
+        start_time,
+        end_time,
+        cols.get(record, "workout name"),
+        cols.get(record, "workout notes"),
+        cols.get(record, "exercise name"),
+        entry,
+    )
+}
+
+fn hevy_row(cols: &Columns, record: &[String], options: &ImportOptions) -> RowResult {
+    let Some(start_time) = parse_local_time(cols.get(record, "start_time"), options.utc_offset)
+    else {
+        return RowResult::Error("Invalid start_time".to_string());
+    };
+    let end_time = parse_local_time(cols.get(record, "end_time"), options.utc_offset);
+
+    let weight_kg = if cols.has("weight_kg") {
+        parse_number(cols.get(record, "weight_kg"))
+    } else {
+        parse_number(cols.get(record, "weight_lbs")).map(|w| w * LB_TO_KG)
+    };
+    let distance_m = if cols.has("distance_km") {
+        parse_number(cols.get(record, "distance_km")).map(|d| d * 1000.0)
+    } else {
+        parse_number(cols.get(record, "distance_miles")).map(|d| d * MI_TO_M)
+    };
+    let entry = match entry_from(
+        weight_kg,
+        cols.get(record, "reps"),
+        cols.get(record, "rpe"),
+        distance_m,
+        cols.get(record, "duration_seconds"),
+        match cols.get(record, "set_type") {
+            "warmup" => (true, false),
+            "dropset" => (false, true),
+            _ => (false, false),
+        },
+    ) {
+        Ok(entry) => entry,
+        Err(message) => return RowResult::Error(message),
+    };
+
+    finish_row(
+        start_time,
+        end_time,
+        cols.get(record, "title"),
+        cols.get(record, "description"),
+        cols.get(record, "exercise_title"),
+        entry,
+    )
+}
+
+fn finish_row(
+    start_time: DateTime<FixedOffset>,
+    end_time: Option<DateTime<FixedOffset>>,
+    title: &str,
+    note: &str,
+    exercise_name: &str,
+    entry: RowEntry,
+) -> RowResult {
+    let exercise_name = exercise_name.trim();
+    if exercise_name.is_empty() {
+        return RowResult::Error("Missing exercise name".to_string());
+    }
+    RowResult::Row(Row {
+        start_time,
+        end_time: end_time.filter(|e| *e > start_time),
+        title: title.trim().to_string(),
+        workout_note: Some(note.trim())
+            .filter(|n| !n.is_empty())
+            .map(String::from),
+        exercise_name: exercise_name.to_string(),
+        entry,
+    })
+}
+
+/// Weight × reps -> set; otherwise distance / duration -> cardio
+fn entry_from(
+    weight_kg: Option<f64>,
+    reps: &str,
+    rpe: &str,
+    distance_m: Option<f64>,
+    seconds: &str,
+    (is_warmup, is_dropset): (bool, bool),
+) -> Result<RowEntry, String> {
+    let reps = parse_number(reps)
+        .map(|r| r.round() as i32)
+        .filter(|r| *r > 0);
+    let seconds = parse_number(seconds)
+        .map(|s| s.round() as i32)
+        .filter(|s| *s > 0);
+    let distance_m = distance_m.filter(|d| *d > 0.0);
+
+    if let Some(reps) = reps {
+        let weight_kg = weight_kg.filter(|w| *w > 0.0);
+        if reps > MAX_REPS || weight_kg.is_some_and(|w| w > MAX_WEIGHT_KG) {
+            return Err("Weight or reps out of range".to_string());
+        }
+        return Ok(RowEntry::Set(ImportedSet {
+            weight_kg: weight_kg.map(|w| (w * 100.0).round() / 100.0),
+            reps,
+            rpe: parse_number(rpe).filter(|r| (1.0..=10.0).contains(r)),
+            is_warmup,
+            is_dropset,
+        }));
+    }
+
+    match (seconds, distance_m) {
+        (Some(duration_sec), distance_m) => Ok(RowEntry::Cardio(ImportedCardio {
+            duration_sec,
+            distance_m: distance_m.map(|d| d.round()),
+        })),
+        (None, Some(_)) => Err("Distance without a duration".to_string()),
+        (None, None) => Err("No reps or duration".to_string()),
+    }
+}
+
+/// Header lookup by case-insensitive column name
+struct Columns(HashMap<String, usize>);
+
+impl Columns {
+    fn new(header: &[String]) -> Self {
+        Self(
+            header
+                .iter()
+                .enumerate()
+                .map(|(i, h)| (h.trim().to_lowercase(), i))
+                .collect(),
+        )
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    fn get<'a>(&self, record: &'a [String], name: &str) -> &'a str {
+        self.0
+            .get(name)
+            .and_then(|&i| record.get(i))
+            .map(|s| s.trim())
+            .unwrap_or("")
+    }
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    value
+        .parse::<f64>()
+        // Some locales export decimal commas ("72,5")
+        .or_else(|_| value.replace(',', ".").parse::<f64>())
+        .ok()
+        .filter(|v| v.is_finite())
+}
+
+/// Local timestamps as written by the trackers
+fn parse_local_time(value: &str, offset: FixedOffset) -> Option<DateTime<FixedOffset>> {
+    const FORMATS: &[&str] = &[
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%d %b %Y, %H:%M",
+        "%d %b %Y %H:%M",
+        "%b %d, %Y, %I:%M %p",
+    ];
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt);
+    }
+    FORMATS
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(value, f).ok())
+        .and_then(|naive| offset.from_local_datetime(&naive).single())
+}
+
+/// Strong durations: "1h 5m", "45m", "50s", or plain seconds
+fn parse_duration_text(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    if let Ok(secs) = value.parse::<i64>() {
+        return Some(secs);
+    }
+    let mut total = 0;
+    for token in value.split_whitespace() {
+        let (num, unit) = token.split_at(token.find(|c: char| !c.is_ascii_digit())?);
+        let n: i64 = num.parse().ok()?;
+        total += match unit {
+            "h" => n * 3600,
+            "m" | "min" => n * 60,
+            "s" => n,
+            _ => return None,
+        };
+    }
+    Some(total)
+}
+
+// =============================================================================
+// CSV
+// =============================================================================
+
+/// Older Strong exports use ';'
+fn detect_delimiter(text: &str) -> char {
+    let header = text.lines().next().unwrap_or("");
+    if header.matches(';').count() > header.matches(',').count() {
+        ';'
+    } else {
+        ','
+    }
+}
+
+/// Minimal RFC 4180 reader: quoted fields, "" escapes, newlines inside quotes.
+/// Returns (1-based line number where the record starts, fields).
+fn parse_csv(text: &str, delimiter: char) -> Vec<(usize, Vec<String>)> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut record)));
+                line += 1;
+                record_line = line;
+            }
+            c if c == delimiter => record.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_quotes() {
+        let rows = parse_csv(
+            "a,b,c\n\"x, y\",\"he said \"\"hi\"\"\",\"multi\nline\"\n1,2,3",
+            ',',
+        );
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1].1, vec!["x, y", "he said \"hi\"", "multi\nline"]);
+        assert_eq!(rows[2].0, 4);
+    }
+
+    #[test]
+    fn test_strong_export() {
+        let csv = "\u{feff}Date;Workout Name;Duration;Exercise Name;Set Order;Weight;Reps;Distance;Seconds;Notes;Workout Notes;RPE\n\
+            2024-01-15 08:30:00;Push;1h 5m;Bench Press (Barbell);W;100;10;0;0;;;\n\
+            2024-01-15 08:30:00;Push;1h 5m;Bench Press (Barbell);1;200;5;0;0;;;8\n\
+            2024-01-15 08:30:00;Push;1h 5m;Rest Timer;Rest Timer;0;0;0;90;;;\n\
+            2024-01-15 08:30:00;Push;1h 5m;Running;1;0;0;3.1;1800;;;\n\
+            2024-01-15 08:30:00;Push;1h 5m;Plank;1;0;0;0;0;;;\n";
+        let options = ImportOptions {
+            weight_in_lb: true,
+            distance_in_mi: true,
+            utc_offset: FixedOffset::east_opt(9 * 3600).unwrap(),
+        };
+        let parsed = parse_export(csv, &options).unwrap();
+        assert_eq!(parsed.source, ImportSource::Strong);
+        assert_eq!(parsed.workouts.len(), 1);
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].line, 6);
+
+        let w = &parsed.workouts[0];
+        assert_eq!(w.date.to_string(), "2024-01-15");
+        assert_eq!(w.start_time.to_rfc3339(), "2024-01-15T08:30:00+09:00");
+        assert_eq!((w.end_time.unwrap() - w.start_time).num_minutes(), 65);
+
+        let bench = &w.exercises[0];
+        assert_eq!(bench.sets.len(), 2);
+        assert!(bench.sets[0].is_warmup);
+        assert_eq!(bench.sets[1].weight_kg, Some(90.72));
+        assert_eq!(bench.sets[1].rpe, Some(8.0));
+
+        let run = &w.exercises[1];
+        assert_eq!(
+            run.cardio,
+            Some(ImportedCardio {
+                duration_sec: 1800,
+                distance_m: Some(4989.0)
+            })
+        );
+    }
+
+    #[test]
+    fn test_hevy_export() {
+        let csv = "title,start_time,end_time,description,exercise_title,superset_id,exercise_notes,set_index,set_type,weight_kg,reps,distance_km,duration_seconds,rpe\n\
+            Leg Day,\"15 Jan 2024, 18:00\",\"15 Jan 2024, 19:10\",,Squat (Barbell),,,0,warmup,60,8,,,\n\
+            Leg Day,\"15 Jan 2024, 18:00\",\"15 Jan 2024, 19:10\",,Squat (Barbell),,,1,normal,120,5,,,9\n\
+            Leg Day,\"15 Jan 2024, 18:00\",\"15 Jan 2024, 19:10\",,Squat (Barbell),,,2,dropset,100,8,,,\n\
+            Cardio,\"16 Jan 2024, 07:00\",,,Cycling,,,0,normal,,,10.5,1500,\n";
+        let parsed = parse_export(csv, &ImportOptions::default()).unwrap();
+        assert_eq!(parsed.source, ImportSource::Hevy);
+        assert!(parsed.errors.is_empty());
+        assert_eq!(parsed.workouts.len(), 2);
+
+        let squat = &parsed.workouts[0].exercises[0];
+        assert_eq!(squat.sets.len(), 3);
+        assert!(squat.sets[0].is_warmup && squat.sets[2].is_dropset);
+        assert_eq!(squat.sets[1].weight_kg, Some(120.0));
+
+        let ride = &parsed.workouts[1].exercises[0];
+        assert_eq!(ride.cardio.unwrap().distance_m, Some(10500.0));
+        assert_eq!(parsed.workouts[1].end_time, None);
+    }
+
+    #[test]
+    fn test_exercise_names() {
+        assert_eq!(
+            split_equipment("Bench Press (Barbell)"),
+            ("Bench Press", Some("barbell"))
+        );
+        assert_eq!(split_equipment("Running"), ("Running", None));
+        assert_eq!(split_equipment("Squat (Pause)"), ("Squat", None));
+        assert_eq!(suggest_muscle("Seated Leg Curl"), Some("hamstrings"));
+        assert_eq!(suggest_muscle("Hammer Curl"), Some("biceps"));
+        assert_eq!(suggest_muscle("Incline Bench Press"), Some("chest"));
+        assert_eq!(suggest_muscle("Zercher Carry"), None);
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        assert!(parse_export("foo,bar\n1,2\n", &ImportOptions::default()).is_err());
+        assert_eq!(parse_duration_text("1h 5m"), Some(3900));
+        assert_eq!(parse_duration_text("45m"), Some(2700));
+        assert_eq!(parse_duration_text("abc"), None);
+    }
+}
@@ -7,35 +7,9 @@ use serde::Serialize;
 use thiserror::Error;
 
 /// Mask sensitive data in error messages for logging
-/// Removes tokens, passwords, and other sensitive information
+/// Removes tokens, passwords, and other sensitive information (including PII)
 fn mask_sensitive_data(msg: &str) -> String {
-    let mut masked = msg.to_string();
-
-    // Mask JWT tokens (eyJ... pattern)
-    let jwt_regex = regex::Regex::new(r"eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+").ok();
-    if let Some(re) = jwt_regex {
-        masked = re.replace_all(&masked, "[MASKED_TOKEN]").to_string();
-    }
-
-    // Mask Bearer tokens
-    let bearer_regex = regex::Regex::new(r"Bearer\s+[A-Za-z0-9_.-]+").ok();
-    if let Some(re) = bearer_regex {
-        masked = re.replace_all(&masked, "Bearer [MASKED]").to_string();
-    }
-
-    // Mask signed URL tokens
-    let token_regex = regex::Regex::new(r"token=[A-Za-z0-9%_.-]+").ok();
-    if let Some(re) = token_regex {
-        masked = re.replace_all(&masked, "token=[MASKED]").to_string();
-    }
-
-    // Mask API keys (common patterns)
-    let apikey_regex = regex::Regex::new(r"(apikey|api_key|key)=[\w-]+").ok();
-    if let Some(re) = apikey_regex {
-        masked = re.replace_all(&masked, "$1=[MASKED]").to_string();
-    }
-
-    masked
+    crate::redaction::mask_sensitive(msg)
 }
 
 /// Application error types
@@ -53,6 +27,9 @@ pub enum AppError {
     #[error("Authentication error: {0}")]
     AuthError(String),
 
+    #[error("MFA required: {0}")]
+    MfaRequired(String),
+
     #[error("Upstream auth service error: {0}")]
     UpstreamAuth(String),
 
@@ -79,6 +56,9 @@ pub enum AppError {
 
     #[error("Safety guard triggered: {0}")]
     SafetyGuard(String),
+
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
 }
 
 /// Error response body
@@ -90,15 +70,17 @@ pub struct ErrorResponse {
     pub details: Option<serde_json::Value>,
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, error_type, message) = match &self {
+impl AppError {
+    /// Status, error type and client-facing message (internal details are logged, not returned)
+    pub fn public_parts(&self) -> (StatusCode, &'static str, String) {
+        match self {
             AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized", self.to_string()),
             AppError::InvalidToken(msg) => {
                 (StatusCode::UNAUTHORIZED, "invalid_token", msg.clone())
             }
             AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, "forbidden", msg.clone()),
             AppError::AuthError(msg) => (StatusCode::UNAUTHORIZED, "auth_error", msg.clone()),
+            AppError::MfaRequired(msg) => (StatusCode::FORBIDDEN, "mfa_required", msg.clone()),
             AppError::UpstreamAuth(msg) => {
                 tracing::error!("Upstream auth error: {}", mask_sensitive_data(msg));
                 (
@@ -150,7 +132,31 @@ impl IntoResponse for AppError {
                 "safety_guard",
                 format!("Safety check failed: {}", msg),
             ),
-        };
+            AppError::Unavailable(msg) => {
+                tracing::warn!("Service unavailable: {}", mask_sensitive_data(msg));
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "service_unavailable",
+                    "サーバーが混み合っています。しばらく待ってから再試行してください".to_string(),
+                )
+            }
+        }
+    }
+
+    /// Client-facing error body (also stored as the result of failed AI jobs)
+    pub fn to_response_body(&self) -> ErrorResponse {
+        let (_, error_type, message) = self.public_parts();
+        ErrorResponse {
+            error: error_type.to_string(),
+            message,
+            details: None,
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, error_type, message) = self.public_parts();
 
         let body = ErrorResponse {
             error: error_type.to_string(),
@@ -0,0 +1,141 @@
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::error::{AppError, AppResult};
+
+/// Dimension of `user_history_docs.embedding` (vector(768))
+pub const EMBEDDING_DIMENSIONS: usize = 768;
+
+pub type EmbedFuture<'a> = Pin<Box<dyn Future<Output = AppResult<Vec<f32>>> + Send + 'a>>;
+
+/// Text embedder used by history retrieval.
+/// Implementations must return `EMBEDDING_DIMENSIONS` values.
+pub trait Embedder: Send + Sync {
+    /// Stored in `user_history_docs.embedding_model`
+    fn model(&self) -> &str;
+
+    fn embed<'a>(&'a self, text: &'a str) -> EmbedFuture<'a>;
+}
+
+/// Build the embedder selected by `EMBEDDING_PROVIDER` (None = lexical search only)
+pub fn from_config(
+    provider: &str,
+    model: &str,
+    gemini_api_key: &str,
+) -> anyhow::Result<Option<std::sync::Arc<dyn Embedder>>> {
+    match provider {
+        "" | "none" => Ok(None),
+        "gemini" => Ok(Some(std::sync::Arc::new(GeminiEmbedder::new(
+            gemini_api_key,
+            model,
+        )))),
+        other => anyhow::bail!("Unknown EMBEDDING_PROVIDER: {}", other),
+    }
+}
+
+/// Gemini `embedContent` (e.g. text-embedding-004)
+pub struct GeminiEmbedder {
+    client: Client,
+    api_key: String,
+    model: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EmbedContentRequest<'a> {
+    content: EmbedContent<'a>,
+    output_dimensionality: usize,
+}
+
+#[derive(Serialize)]
+struct EmbedContent<'a> {
+    parts: Vec<EmbedPart<'a>>,
+}
+
+#[derive(Serialize)]
+struct EmbedPart<'a> {
+    text: &'a str,
+}
+
+#[derive(Deserialize)]
+struct EmbedContentResponse {
+    embedding: EmbeddingValues,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingValues {
+    values: Vec<f32>,
+}
+
+impl GeminiEmbedder {
+    pub fn new(api_key: &str, model: &str) -> Self {
+        Self {
+            client: Client::new(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+        }
+    }
+
+    async fn embed_text(&self, text: &str) -> AppResult<Vec<f32>> {
+        // SECURITY: header-based API key (see GeminiClient)
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:embedContent",
+            self.model
+        );
+        let request = EmbedContentRequest {
+            content: EmbedContent {
+                parts: vec![EmbedPart { text }],
+            },
+            output_dimensionality: EMBEDDING_DIMENSIONS,
+        };
+
+        let response = self
+            .client
+            .post(&url)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| AppError::GeminiApi(format!("Embedding request failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::GeminiApi(format!(
+                "Embedding API error: {} - {}",
+                status, body
+            )));
+        }
+
+        let body: EmbedContentResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::GeminiApi(format!("Failed to parse embedding: {}", e)))?;
+
+        if body.embedding.values.len() != EMBEDDING_DIMENSIONS {
+            return Err(AppError::GeminiApi(format!(
+                "Unexpected embedding size: {}",
+                body.embedding.values.len()
+            )));
+        }
+        Ok(body.embedding.values)
+    }
+}
+
+impl Embedder for GeminiEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed<'a>(&'a self, text: &'a str) -> EmbedFuture<'a> {
+        Box::pin(self.embed_text(text))
+    }
+}
+
+/// pgvector text literal (`[0.1,0.2,...]`)
+pub fn to_pgvector(values: &[f32]) -> String {
+    let parts: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", parts.join(","))
+}
@@ -1,111 +1,141 @@
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
+use reqwest::{Client, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
 
 use crate::error::{AppError, AppResult};
 
+/// Base delay for the exponential backoff between retries
+const RETRY_BASE_DELAY_MS: u64 = 500;
+/// Upper bound for a single backoff delay
+const RETRY_MAX_DELAY_MS: u64 = 8_000;
+
+/// Limits and models for `GeminiClient` (see `Config`)
+#[derive(Debug, Clone)]
+pub struct GeminiOptions {
+    pub model: String,
+    /// Tried once the primary model has failed (after its retries)
+    pub fallback_model: Option<String>,
+    /// Max in-flight Gemini calls across all requests
+    pub max_concurrency: usize,
+    /// How long a call may wait for a free slot before failing with 503
+    pub queue_timeout: Duration,
+    /// Per-attempt HTTP timeout
+    pub request_timeout: Duration,
+    /// Retries per model on 429 / 5xx / network errors
+    pub max_retries: u32,
+}
+
 /// Gemini API client
 #[derive(Clone)]
 pub struct GeminiClient {
     client: Client,
     api_key: String,
     model: String,
+    fallback_model: Option<String>,
+    permits: Arc<Semaphore>,
+    queue_timeout: Duration,
+    max_retries: u32,
+}
+
+/// Parsed model output plus the model that actually answered
+#[derive(Debug)]
+pub struct Generated<T> {
+    pub value: T,
+    pub model: String,
 }
 
 impl GeminiClient {
-    pub fn new(api_key: &str, model: &str) -> Self {
+    pub fn new(api_key: &str, options: GeminiOptions) -> Self {
+        let client = Client::builder()
+            .timeout(options.request_timeout)
+            .connect_timeout(Duration::from_secs(5))
+            .build()
+            .unwrap_or_else(|_| Client::new());
+
         Self {
-            client: Client::new(),
+            client,
             api_key: api_key.to_string(),
-            model: model.to_string(),
+            model: options.model,
+            fallback_model: options.fallback_model,
+            permits: Arc::new(Semaphore::new(options.max_concurrency.max(1))),
+            queue_timeout: options.queue_timeout,
+            max_retries: options.max_retries,
         }
     }
 
     /// Generate content with Gemini API
+    ///
+    /// `GeminiResponse::model` is set to the model that answered (primary or fallback).
     pub async fn generate(&self, prompt: &str, system_instruction: Option<&str>) -> AppResult<GeminiResponse> {
-        // SECURITY: Use header-based authentication instead of URL parameter to prevent:
-        // - API key exposure in HTTP logs
-        // - API key leakage via Referer headers
-        // - API key exposure in browser history
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
-            self.model
-        );
+        let generated = self
+            .generate_json_with_model::<GeminiResponse>(prompt, system_instruction)
+            .await?;
+        Ok(GeminiResponse {
+            model: generated.model,
+            ..generated.value
+        })
+    }
 
-        let mut contents = vec![Content {
+    /// Generate content and parse the JSON output into a caller-defined shape
+    ///
+    /// Use this when the prompt asks for a schema other than `GeminiResponse`
+    /// (e.g. meal item candidates).
+    pub async fn generate_json<T: DeserializeOwned>(
+        &self,
+        prompt: &str,
+        system_instruction: Option<&str>,
+    ) -> AppResult<T> {
+        Ok(self
+            .generate_json_with_model(prompt, system_instruction)
+            .await?
+            .value)
+    }
+
+    /// Same as `generate_json`, also reporting which model answered
+    pub async fn generate_json_with_model<T: DeserializeOwned>(
+        &self,
+        prompt: &str,
+        system_instruction: Option<&str>,
+    ) -> AppResult<Generated<T>> {
+        let contents = vec![Content {
             role: "user".to_string(),
-            parts: vec![Part {
-                text: prompt.to_string(),
-            }],
+            parts: vec![Part::text(prompt)],
         }];
 
-        let request = GenerateContentRequest {
-            contents,
-            system_instruction: system_instruction.map(|s| SystemInstruction {
-                parts: vec![Part { text: s.to_string() }],
-            }),
-            generation_config: Some(GenerationConfig {
-                temperature: Some(0.7),
-                top_p: Some(0.9),
-                top_k: Some(40),
-                max_output_tokens: Some(4096),
-                response_mime_type: Some("application/json".to_string()),
-            }),
-            safety_settings: Some(vec![
-                SafetySetting {
-                    category: "HARM_CATEGORY_HARASSMENT".to_string(),
-                    threshold: "BLOCK_MEDIUM_AND_ABOVE".to_string(),
-                },
-                SafetySetting {
-                    category: "HARM_CATEGORY_HATE_SPEECH".to_string(),
-                    threshold: "BLOCK_MEDIUM_AND_ABOVE".to_string(),
-                },
-                SafetySetting {
-                    category: "HARM_CATEGORY_SEXUALLY_EXPLICIT".to_string(),
-                    threshold: "BLOCK_MEDIUM_AND_ABOVE".to_string(),
-                },
-                SafetySetting {
-                    category: "HARM_CATEGORY_DANGEROUS_CONTENT".to_string(),
-                    threshold: "BLOCK_MEDIUM_AND_ABOVE".to_string(),
-                },
-            ]),
-        };
+        let (value, model) = self
+            .generate_parsed(contents, system_instruction, Some(default_safety_settings()))
+            .await?;
 
-        let response = self
-            .client
-            .post(&url)
-            .header("x-goog-api-key", &self.api_key)
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| AppError::GeminiApi(format!("Request failed: {}", e)))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(AppError::GeminiApi(format!(
-                "API error: {} - {}",
-                status, body
-            )));
-        }
-
-        let api_response: GenerateContentResponse = response
-            .json()
-            .await
-            .map_err(|e| AppError::GeminiApi(format!("Failed to parse response: {}", e)))?;
+        Ok(Generated { value, model })
+    }
 
-        // Extract text from response
-        let text = api_response
-            .candidates
-            .first()
-            .and_then(|c| c.content.parts.first())
-            .map(|p| p.text.clone())
-            .ok_or_else(|| AppError::GeminiApi("Empty response from Gemini".to_string()))?;
+    /// Generate content from a prompt plus one image (multimodal)
+    ///
+    /// `mime_type` must be the validated type of `image_bytes` (magic bytes), not the
+    /// client-provided Content-Type.
+    pub async fn generate_json_with_image<T: DeserializeOwned>(
+        &self,
+        prompt: &str,
+        image_bytes: &[u8],
+        mime_type: &str,
+        system_instruction: Option<&str>,
+    ) -> AppResult<T> {
+        let contents = vec![Content {
+            role: "user".to_string(),
+            parts: vec![
+                Part::inline_data(mime_type, BASE64_STANDARD.encode(image_bytes)),
+                Part::text(prompt),
+            ],
+        }];
 
-        // Parse the JSON response
-        let parsed: GeminiResponse = serde_json::from_str(&text)
-            .map_err(|e| AppError::GeminiApi(format!("Failed to parse JSON response: {} - Raw: {}", e, text)))?;
+        let (value, _) = self
+            .generate_parsed(contents, system_instruction, Some(default_safety_settings()))
+            .await?;
 
-        Ok(parsed)
+        Ok(value)
     }
 
     /// Generate with chat history
@@ -114,24 +144,35 @@ impl GeminiClient {
         messages: Vec<ChatMessage>,
         system_instruction: Option<&str>,
     ) -> AppResult<GeminiResponse> {
-        // SECURITY: Use header-based authentication (same as generate())
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
-            self.model
-        );
-
         let contents: Vec<Content> = messages
             .into_iter()
             .map(|m| Content {
                 role: m.role,
-                parts: vec![Part { text: m.content }],
+                parts: vec![Part::text(m.content)],
             })
             .collect();
 
+        let (response, model) = self
+            .generate_parsed::<GeminiResponse>(contents, system_instruction, None)
+            .await?;
+        Ok(GeminiResponse { model, ..response })
+    }
+
+    /// Send a generateContent request and parse the JSON text of the first candidate,
+    /// returning it together with the model that produced it
+    ///
+    /// Retries timeouts / connect errors / 429 / 5xx with jittered backoff, then falls
+    /// back to `fallback_model`. Output that does not parse also triggers the fallback.
+    async fn generate_parsed<T: DeserializeOwned>(
+        &self,
+        contents: Vec<Content>,
+        system_instruction: Option<&str>,
+        safety_settings: Option<Vec<SafetySetting>>,
+    ) -> AppResult<(T, String)> {
         let request = GenerateContentRequest {
             contents,
             system_instruction: system_instruction.map(|s| SystemInstruction {
-                parts: vec![Part { text: s.to_string() }],
+                parts: vec![Part::text(s)],
             }),
             generation_config: Some(GenerationConfig {
                 temperature: Some(0.7),
@@ -140,44 +181,170 @@ impl GeminiClient {
                 max_output_tokens: Some(4096),
                 response_mime_type: Some("application/json".to_string()),
             }),
-            safety_settings: None,
+            safety_settings,
         };
 
+        let models = std::iter::once(&self.model).chain(self.fallback_model.as_ref());
+        let mut last_error = None;
+        for model in models {
+            let result = self
+                .generate_with_retries(model, &request)
+                .await
+                .and_then(|text| parse_json_output::<T>(&text));
+            match result {
+                Ok(value) => return Ok((value, model.clone())),
+                Err(e @ AppError::Unavailable(_)) => return Err(e),
+                Err(e) => {
+                    tracing::warn!(model = %model, "Gemini model failed: {}", e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| AppError::GeminiApi("No model configured".to_string())))
+    }
+
+    async fn generate_with_retries(
+        &self,
+        model: &str,
+        request: &GenerateContentRequest,
+    ) -> AppResult<String> {
+        let mut attempt = 0;
+        loop {
+            match self.send_once(model, request).await {
+                Ok(text) => return Ok(text),
+                Err(failure) if failure.retryable && attempt < self.max_retries => {
+                    let delay = backoff_delay(attempt, jitter_ms(RETRY_BASE_DELAY_MS));
+                    tracing::debug!(
+                        model = %model,
+                        attempt = attempt + 1,
+                        delay_ms = delay.as_millis() as u64,
+                        "Retrying Gemini call: {}",
+                        failure.error
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(failure) => return Err(failure.error),
+            }
+        }
+    }
+
+    /// One HTTP attempt
+    ///
+    /// The concurrency slot is held only for the request itself, so backoff sleeps
+    /// and the fallback model never block other callers. Waiting longer than
+    /// `queue_timeout` for a slot is a 503.
+    async fn send_once(
+        &self,
+        model: &str,
+        request: &GenerateContentRequest,
+    ) -> Result<String, AttemptFailure> {
+        let _permit = tokio::time::timeout(self.queue_timeout, self.permits.acquire())
+            .await
+            .map_err(|_| AttemptFailure {
+                retryable: false,
+                error: AppError::Unavailable("Gemini call queue timed out".to_string()),
+            })?
+            .map_err(|_| AttemptFailure {
+                retryable: false,
+                error: AppError::Internal("Gemini semaphore closed".to_string()),
+            })?;
+
+        // SECURITY: Use header-based authentication instead of URL parameter to prevent:
+        // - API key exposure in HTTP logs
+        // - API key leakage via Referer headers
+        // - API key exposure in browser history
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
+            model
+        );
+
         let response = self
             .client
             .post(&url)
             .header("x-goog-api-key", &self.api_key)
-            .json(&request)
+            .json(request)
             .send()
             .await
-            .map_err(|e| AppError::GeminiApi(format!("Request failed: {}", e)))?;
+            .map_err(|e| AttemptFailure {
+                retryable: e.is_timeout() || e.is_connect(),
+                error: AppError::GeminiApi(format!("Request failed: {}", e)),
+            })?;
 
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
-            return Err(AppError::GeminiApi(format!(
-                "API error: {} - {}",
-                status, body
-            )));
+            return Err(AttemptFailure {
+                retryable: is_retryable_status(status),
+                error: AppError::GeminiApi(format!("API error: {} - {}", status, body)),
+            });
         }
 
-        let api_response: GenerateContentResponse = response
-            .json()
-            .await
-            .map_err(|e| AppError::GeminiApi(format!("Failed to parse response: {}", e)))?;
+        let api_response: GenerateContentResponse =
+            response.json().await.map_err(|e| AttemptFailure {
+                retryable: false,
+                error: AppError::GeminiApi(format!("Failed to parse response: {}", e)),
+            })?;
 
-        let text = api_response
+        // Extract text from response
+        api_response
             .candidates
             .first()
             .and_then(|c| c.content.parts.first())
             .map(|p| p.text.clone())
-            .ok_or_else(|| AppError::GeminiApi("Empty response from Gemini".to_string()))?;
+            .ok_or_else(|| AttemptFailure {
+                retryable: false,
+                error: AppError::GeminiApi("Empty response from Gemini".to_string()),
+            })
+    }
+}
 
-        let parsed: GeminiResponse = serde_json::from_str(&text)
-            .map_err(|e| AppError::GeminiApi(format!("Failed to parse JSON response: {} - Raw: {}", e, text)))?;
+/// A failed attempt and whether it is worth retrying on the same model
+struct AttemptFailure {
+    retryable: bool,
+    error: AppError,
+}
 
-        Ok(parsed)
-    }
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Exponential backoff (base * 2^attempt, capped) plus jitter
+fn backoff_delay(attempt: u32, jitter_ms: u64) -> Duration {
+    let exp = RETRY_BASE_DELAY_MS.saturating_mul(1u64 << attempt.min(16));
+    Duration::from_millis(exp.min(RETRY_MAX_DELAY_MS) + jitter_ms)
+}
+
+/// Random jitter in `0..max_ms` (uuid v4 is already a dependency; no need for `rand`)
+fn jitter_ms(max_ms: u64) -> u64 {
+    (uuid::Uuid::new_v4().as_u128() % u128::from(max_ms.max(1))) as u64
+}
+
+/// Parse the JSON text returned by the model (response_mime_type = application/json)
+fn parse_json_output<T: DeserializeOwned>(text: &str) -> AppResult<T> {
+    serde_json::from_str(text)
+        .map_err(|e| AppError::GeminiApi(format!("Failed to parse JSON response: {} - Raw: {}", e, text)))
+}
+
+fn default_safety_settings() -> Vec<SafetySetting> {
+    vec![
+        SafetySetting {
+            category: "HARM_CATEGORY_HARASSMENT".to_string(),
+            threshold: "BLOCK_MEDIUM_AND_ABOVE".to_string(),
+        },
+        SafetySetting {
+            category: "HARM_CATEGORY_HATE_SPEECH".to_string(),
+            threshold: "BLOCK_MEDIUM_AND_ABOVE".to_string(),
+        },
+        SafetySetting {
+            category: "HARM_CATEGORY_SEXUALLY_EXPLICIT".to_string(),
+            threshold: "BLOCK_MEDIUM_AND_ABOVE".to_string(),
+        },
+        SafetySetting {
+            category: "HARM_CATEGORY_DANGEROUS_CONTENT".to_string(),
+            threshold: "BLOCK_MEDIUM_AND_ABOVE".to_string(),
+        },
+    ]
 }
 
 // =============================================================================
@@ -208,7 +375,36 @@ struct Content {
 
 #[derive(Debug, Serialize)]
 struct Part {
-    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inline_data: Option<InlineData>,
+}
+
+impl Part {
+    fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: Some(text.into()),
+            inline_data: None,
+        }
+    }
+
+    fn inline_data(mime_type: &str, data: String) -> Self {
+        Self {
+            text: None,
+            inline_data: Some(InlineData {
+                mime_type: mime_type.to_string(),
+                data,
+            }),
+        }
+    }
+}
+
+/// Base64-encoded binary payload (images) sent inline with the prompt
+#[derive(Debug, Serialize)]
+struct InlineData {
+    mime_type: String,
+    data: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -262,6 +458,9 @@ pub struct GeminiResponse {
     pub recommendations: Vec<Recommendation>,
     #[serde(default)]
     pub warnings: Vec<String>,
+    /// Model that produced this response (set by the client, not by the model)
+    #[serde(skip)]
+    pub model: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -291,3 +490,33 @@ impl ChatMessage {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_only_rate_limits_and_server_errors() {
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_retryable_status(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(!is_retryable_status(StatusCode::BAD_REQUEST));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn backoff_grows_and_is_capped() {
+        assert_eq!(backoff_delay(0, 0), Duration::from_millis(500));
+        assert_eq!(backoff_delay(1, 0), Duration::from_millis(1_000));
+        assert_eq!(backoff_delay(2, 100), Duration::from_millis(2_100));
+        assert_eq!(backoff_delay(10, 0), Duration::from_millis(RETRY_MAX_DELAY_MS));
+        assert_eq!(backoff_delay(u32::MAX, 0), Duration::from_millis(RETRY_MAX_DELAY_MS));
+    }
+
+    #[test]
+    fn jitter_stays_below_max() {
+        for _ in 0..100 {
+            assert!(jitter_ms(RETRY_BASE_DELAY_MS) < RETRY_BASE_DELAY_MS);
+        }
+    }
+}
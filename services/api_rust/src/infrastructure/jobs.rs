@@ -0,0 +1,96 @@
+//! Bounded background worker pool for long-running requests (AI jobs).
+//!
+//! Jobs are boxed futures pushed onto a bounded channel; a dispatcher task runs
+//! at most `workers` of them concurrently. Submitting never waits: a full queue
+//! is reported back to the caller so the handler can answer 503 right away.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Semaphore};
+
+pub type Job = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+#[derive(Clone)]
+pub struct JobQueue {
+    sender: mpsc::Sender<Job>,
+}
+
+impl JobQueue {
+    /// Start the dispatcher. Must be called from within the Tokio runtime.
+    pub fn start(workers: usize, capacity: usize) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<Job>(capacity.max(1));
+        let permits = Arc::new(Semaphore::new(workers.max(1)));
+
+        tokio::spawn(async move {
+            while let Some(job) = receiver.recv().await {
+                let Ok(permit) = permits.clone().acquire_owned().await else {
+                    break;
+                };
+                tokio::spawn(async move {
+                    job.await;
+                    drop(permit);
+                });
+            }
+        });
+
+        Self { sender }
+    }
+
+    /// Enqueue a job. Returns `false` if the queue is full (or shut down).
+    pub fn try_submit<F>(&self, job: F) -> bool
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.sender.try_send(Box::pin(job)).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn runs_at_most_workers_jobs_at_once() {
+        let queue = JobQueue::start(2, 10);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (done_tx, mut done_rx) = mpsc::channel::<()>(10);
+
+        for _ in 0..6 {
+            let running = running.clone();
+            let peak = peak.clone();
+            let done_tx = done_tx.clone();
+            assert!(queue.try_submit(async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                let _ = done_tx.send(()).await;
+            }));
+        }
+        for _ in 0..6 {
+            done_rx.recv().await.unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn rejects_when_full() {
+        let queue = JobQueue::start(1, 1);
+        let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+        // Occupies the only worker
+        assert!(queue.try_submit(async move {
+            let _ = release_rx.await;
+        }));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        // Held by the dispatcher waiting for a permit, then one in the channel
+        assert!(queue.try_submit(async {}));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(queue.try_submit(async {}));
+        assert!(!queue.try_submit(async {}));
+        let _ = release_tx.send(());
+    }
+}
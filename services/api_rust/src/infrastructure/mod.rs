@@ -1,2 +1,4 @@
+pub mod embedding;
 pub mod gemini;
+pub mod jobs;
 pub mod supabase;
@@ -276,6 +276,37 @@ impl SupabaseClient {
         Ok(())
     }
 
+    /// Execute an UPDATE and return the updated rows
+    /// (an empty result means the filter matched nothing, e.g. a lost status race)
+    pub async fn update_returning<T: Serialize, R: DeserializeOwned>(
+        &self,
+        table: &str,
+        query: &str,
+        data: &T,
+        access_token: &str,
+    ) -> AppResult<Vec<R>> {
+        let url = format!("{}/{}?{}", self.rest_url(), table, query);
+
+        let response = self
+            .client
+            .patch(&url)
+            .header("apikey", &self.anon_key)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .header("Content-Type", "application/json")
+            .header("Prefer", "return=representation")
+            .json(data)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(self.map_postgrest_error("UPDATE failed", status, body));
+        }
+
+        Ok(response.json().await?)
+    }
+
     /// Execute an UPSERT (insert or update on conflict)
     pub async fn upsert<T: Serialize>(
         &self,
@@ -470,6 +501,60 @@ impl SupabaseClient {
         Ok(())
     }
 
+    /// List object paths under a folder, recursively (uses anon key + user JWT for RLS)
+    pub async fn list_objects(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        access_token: &str,
+    ) -> AppResult<Vec<String>> {
+        const PAGE: usize = 1000;
+
+        let url = format!("{}/storage/v1/object/list/{}", self.base_url, bucket);
+        let mut folders = vec![prefix.trim_end_matches('/').to_string()];
+        let mut paths = Vec::new();
+
+        while let Some(folder) = folders.pop() {
+            let mut offset = 0;
+            loop {
+                let response = self
+                    .client
+                    .post(&url)
+                    .header("apikey", &self.anon_key)
+                    .header("Authorization", format!("Bearer {}", access_token))
+                    .json(&serde_json::json!({
+                        "prefix": folder,
+                        "limit": PAGE,
+                        "offset": offset,
+                        "sortBy": { "column": "name", "order": "asc" },
+                    }))
+                    .send()
+                    .await?;
+
+                if !response.status().is_success() {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(AppError::SupabaseError(format!(
+                        "Storage list error: {} - {}",
+                        status, body
+                    )));
+                }
+
+                let entries: Vec<StorageEntry> = response.json().await?;
+                let count = entries.len();
+                let (files, subfolders) = split_storage_entries(&folder, entries);
+                paths.extend(files);
+                folders.extend(subfolders);
+                if count < PAGE {
+                    break;
+                }
+                offset += PAGE;
+            }
+        }
+
+        Ok(paths)
+    }
+
     /// Delete an object from Supabase Storage (uses anon key + user JWT for RLS)
     pub async fn delete_object(
         &self,
@@ -508,3 +593,49 @@ impl SupabaseClient {
 
 
 
+
+/// One entry of a Storage `object/list` response
+#[derive(Debug, serde::Deserialize)]
+struct StorageEntry {
+    name: String,
+    /// None for folders
+    id: Option<String>,
+}
+
+/// Split one listing page of `folder` into object paths and sub-folders to descend into
+fn split_storage_entries(folder: &str, entries: Vec<StorageEntry>) -> (Vec<String>, Vec<String>) {
+    let mut files = Vec::new();
+    let mut folders = Vec::new();
+    for entry in entries {
+        let path = format!("{}/{}", folder, entry.name);
+        if entry.id.is_some() {
+            files.push(path);
+        } else {
+            folders.push(path);
+        }
+    }
+    (files, folders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_storage_entries() {
+        let entries: Vec<StorageEntry> = serde_json::from_value(serde_json::json!([
+            { "name": "avatar.jpg", "id": "6f1c0a52-0000-0000-0000-000000000001" },
+            { "name": "posts", "id": null },
+            { "name": "progress", "id": null },
+            { "name": "cover.png", "id": "6f1c0a52-0000-0000-0000-000000000002" },
+        ]))
+        .unwrap();
+
+        let (files, folders) = split_storage_entries("u1", entries);
+        assert_eq!(files, vec!["u1/avatar.jpg", "u1/cover.png"]);
+        assert_eq!(folders, vec!["u1/posts", "u1/progress"]);
+
+        let (files, folders) = split_storage_entries("u1/posts", Vec::new());
+        assert!(files.is_empty() && folders.is_empty());
+    }
+}
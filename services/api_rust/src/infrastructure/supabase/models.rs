@@ -19,6 +19,10 @@ pub struct UserProfile {
     pub onboarding_completed: bool,
     #[serde(default)]
     pub sns_links: Option<serde_json::Value>, // Basic/Premium feature
+    #[serde(default)]
+    pub ai_persona: Option<String>,
+    #[serde(default)]
+    pub ai_language: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
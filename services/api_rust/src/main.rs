@@ -3,11 +3,17 @@ mod config;
 mod domain;
 mod error;
 mod infrastructure;
+mod media;
+mod redaction;
 mod state;
 
 use crate::config::Config;
-use crate::infrastructure::gemini::GeminiClient;
+use crate::infrastructure::embedding::{self, Embedder};
+use crate::infrastructure::gemini::{GeminiClient, GeminiOptions};
+use crate::infrastructure::jobs::JobQueue;
 use crate::infrastructure::supabase::SupabaseClient;
+use crate::state::prompts::PromptRegistry;
+use crate::state::safety::SafetyPolicy;
 use axum::Router;
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -27,7 +33,15 @@ use tower_governor::governor::GovernorConfigBuilder;
 pub struct AppState {
     pub supabase: SupabaseClient,
     pub gemini: GeminiClient,
+    /// Optional embedder for history retrieval (None = lexical search only)
+    pub embedder: Option<Arc<dyn Embedder>>,
     pub config: Arc<Config>,
+    pub prompts: Arc<PromptRegistry>,
+    pub safety: Arc<SafetyPolicy>,
+    /// Background workers for async AI jobs (POST /v1/ai/jobs) and history index jobs
+    pub ai_jobs: JobQueue,
+    /// Users with a history backfill / rebuild queued or running (at most one per user)
+    pub history_jobs: Arc<std::sync::Mutex<std::collections::HashSet<String>>>,
     pub jwks_cache: Arc<RwLock<Option<api::middleware::CachedJwks>>>,
 }
 
@@ -70,14 +84,56 @@ async fn main() -> anyhow::Result<()> {
     tracing::info!("Supabase client initialized (anon key + RLS)");
 
     // Initialize Gemini client
-    let gemini = GeminiClient::new(&config.gemini_api_key, &config.gemini_model);
-    tracing::info!("Gemini client initialized");
+    let gemini = GeminiClient::new(
+        &config.gemini_api_key,
+        GeminiOptions {
+            model: config.gemini_model.clone(),
+            fallback_model: config.gemini_fallback_model.clone(),
+            max_concurrency: config.gemini_max_concurrency,
+            queue_timeout: std::time::Duration::from_millis(config.gemini_queue_timeout_ms),
+            request_timeout: std::time::Duration::from_secs(config.gemini_timeout_secs),
+            max_retries: config.gemini_max_retries,
+        },
+    );
+    tracing::info!(
+        "Gemini client initialized (model={}, fallback={:?}, max_concurrency={})",
+        config.gemini_model,
+        config.gemini_fallback_model,
+        config.gemini_max_concurrency
+    );
+
+    // Initialize embedder (history retrieval)
+    let embedder = embedding::from_config(
+        &config.embedding_provider,
+        &config.embedding_model,
+        &config.gemini_api_key,
+    )?;
+    tracing::info!("Embedding provider: {}", config.embedding_provider);
+
+    // Load prompt templates (fails fast on broken templates/experiments)
+    let prompts = PromptRegistry::load(std::path::Path::new(&config.prompts_dir))?;
+
+    // Load safety policy rules (fails fast on invalid rules)
+    let safety = SafetyPolicy::load(std::path::Path::new(&config.safety_rules_path))?;
+
+    // Start AI job workers
+    let ai_jobs = JobQueue::start(config.ai_job_workers, config.ai_job_queue_size);
+    tracing::info!(
+        "AI job workers: {} (queue size {})",
+        config.ai_job_workers,
+        config.ai_job_queue_size
+    );
 
     // Create application state
     let state = AppState {
         supabase,
         gemini,
+        embedder,
         config: Arc::new(config),
+        prompts: Arc::new(prompts),
+        safety: Arc::new(safety),
+        ai_jobs,
+        history_jobs: Arc::new(std::sync::Mutex::new(std::collections::HashSet::new())),
         jwks_cache: Arc::new(RwLock::new(None)),
     };
 
@@ -0,0 +1,505 @@
+//! Image upload pipeline shared by avatars, posts and progress photos.
+//!
+//! validate (magic bytes) -> decode (size limits) -> auto-rotate (EXIF orientation)
+//! -> resize into the kind's variants -> re-encode (JPEG / WebP).
+//! Re-encoding from raw pixels drops every metadata block, so EXIF (GPS location,
+//! camera serials, ...) never reaches Storage.
+//!
+//! HEIC cannot be decoded here (no HEIF codec in `image`), so stored uploads (avatars,
+//! posts, progress photos) reject it with a 400 and clients convert it to JPEG / PNG
+//! first; the mobile app does this before upload. Posts used to store HEIC as is.
+//!
+//! Storage layout for an upload with base path `{user_id}/{object_id}`:
+//! - `full` JPEG: `{base}.jpg` (the path stored in the DB row)
+//! - other variants: `{base}_{variant}.{jpg|webp}` (e.g. `{base}_thumb.jpg`)
+
+use futures::future::join_all;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+
+use crate::error::{AppError, AppResult};
+use crate::infrastructure::supabase::SupabaseClient;
+
+/// Largest accepted source dimension (either side)
+const MAX_SOURCE_EDGE: u32 = 12_000;
+/// Decoder allocation cap (decompression bombs)
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
+pub const FULL: &str = "full";
+pub const MEDIUM: &str = "medium";
+pub const THUMB: &str = "thumb";
+
+// =============================================================================
+// Formats
+// =============================================================================
+
+/// Uploaded file type, detected from magic bytes (never from the client's Content-Type)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceFormat {
+    Jpeg,
+    Png,
+    WebP,
+    /// Accepted for analysis-only uploads (Gemini reads it); cannot be decoded here,
+    /// so `process` rejects it
+    Heic,
+}
+
+impl SourceFormat {
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            SourceFormat::Jpeg => "image/jpeg",
+            SourceFormat::Png => "image/png",
+            SourceFormat::WebP => "image/webp",
+            SourceFormat::Heic => "image/heic",
+        }
+    }
+
+    fn image_format(self) -> Option<ImageFormat> {
+        match self {
+            SourceFormat::Jpeg => Some(ImageFormat::Jpeg),
+            SourceFormat::Png => Some(ImageFormat::Png),
+            SourceFormat::WebP => Some(ImageFormat::WebP),
+            SourceFormat::Heic => None,
+        }
+    }
+}
+
+/// Validate image format by checking magic bytes
+pub fn detect_format(bytes: &[u8]) -> AppResult<SourceFormat> {
+    if bytes.len() < 12 {
+        return Err(AppError::BadRequest(
+            "ファイルが小さすぎます。有効な画像ファイルをアップロードしてください。".to_string(),
+        ));
+    }
+
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Ok(SourceFormat::Jpeg);
+    }
+    if bytes.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        return Ok(SourceFormat::Png);
+    }
+    if &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return Ok(SourceFormat::WebP);
+    }
+    if &bytes[4..8] == b"ftyp" && matches!(&bytes[8..12], b"heic" | b"heix" | b"mif1" | b"hevc") {
+        return Ok(SourceFormat::Heic);
+    }
+
+    Err(AppError::BadRequest(
+        "サポートされていない画像形式です。JPEG、PNG、WebP、HEICのいずれかをアップロードしてください。".to_string(),
+    ))
+}
+
+/// Encoded output format of a variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Jpeg,
+    /// Lossless (the only WebP encoder in `image`): keep it to small variants
+    WebP,
+}
+
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::WebP => "webp",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::WebP => "image/webp",
+        }
+    }
+}
+
+// =============================================================================
+// Variants
+// =============================================================================
+
+/// One output size: longest edge is scaled down to `max_edge` (never up)
+#[derive(Debug, Clone, Copy)]
+pub struct VariantSpec {
+    pub name: &'static str,
+    pub max_edge: u32,
+    pub formats: &'static [OutputFormat],
+    /// JPEG quality (1-100)
+    pub jpeg_quality: u8,
+}
+
+const AVATAR_VARIANTS: &[VariantSpec] = &[
+    VariantSpec {
+        name: FULL,
+        max_edge: 512,
+        formats: &[OutputFormat::Jpeg],
+        jpeg_quality: 85,
+    },
+    VariantSpec {
+        name: THUMB,
+        max_edge: 128,
+        formats: &[OutputFormat::Jpeg, OutputFormat::WebP],
+        jpeg_quality: 80,
+    },
+];
+
+const POST_VARIANTS: &[VariantSpec] = &[
+    VariantSpec {
+        name: FULL,
+        max_edge: 2048,
+        formats: &[OutputFormat::Jpeg],
+        jpeg_quality: 85,
+    },
+    VariantSpec {
+        name: MEDIUM,
+        max_edge: 1080,
+        formats: &[OutputFormat::Jpeg],
+        jpeg_quality: 82,
+    },
+    VariantSpec {
+        name: THUMB,
+        max_edge: 400,
+        formats: &[OutputFormat::Jpeg, OutputFormat::WebP],
+        jpeg_quality: 80,
+    },
+];
+
+const PROGRESS_PHOTO_VARIANTS: &[VariantSpec] = &[
+    VariantSpec {
+        name: FULL,
+        max_edge: 2048,
+        formats: &[OutputFormat::Jpeg],
+        jpeg_quality: 88,
+    },
+    VariantSpec {
+        name: MEDIUM,
+        max_edge: 1080,
+        formats: &[OutputFormat::Jpeg],
+        jpeg_quality: 85,
+    },
+    VariantSpec {
+        name: THUMB,
+        max_edge: 300,
+        formats: &[OutputFormat::Jpeg, OutputFormat::WebP],
+        jpeg_quality: 80,
+    },
+];
+
+/// What the upload is for; decides the variant set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Avatar,
+    Post,
+    ProgressPhoto,
+}
+
+impl MediaKind {
+    pub fn variants(self) -> &'static [VariantSpec] {
+        match self {
+            MediaKind::Avatar => AVATAR_VARIANTS,
+            MediaKind::Post => POST_VARIANTS,
+            MediaKind::ProgressPhoto => PROGRESS_PHOTO_VARIANTS,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct EncodedVariant {
+    pub name: &'static str,
+    pub format: OutputFormat,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct ProcessedImage {
+    pub variants: Vec<EncodedVariant>,
+}
+
+/// Storage path of a variant under `base` (`{user_id}/{object_id}`)
+pub fn variant_path(base: &str, name: &str, format: OutputFormat) -> String {
+    if name == FULL && format == OutputFormat::Jpeg {
+        format!("{}.jpg", base)
+    } else {
+        format!("{}_{}.{}", base, name, format.extension())
+    }
+}
+
+/// Base path of a stored object ("user_id/uuid.jpg" -> "user_id/uuid")
+pub fn base_path(stored_path: &str) -> &str {
+    match stored_path.rfind('.') {
+        Some(dot) if !stored_path[dot..].contains('/') => &stored_path[..dot],
+        _ => stored_path,
+    }
+}
+
+/// Every object written for `stored_path` (plus the path itself, for uploads
+/// that predate the pipeline and kept their original extension)
+pub fn all_paths(stored_path: &str, kind: MediaKind) -> Vec<String> {
+    let base = base_path(stored_path);
+    let mut paths = vec![stored_path.to_string()];
+    for spec in kind.variants() {
+        for &format in spec.formats {
+            let path = variant_path(base, spec.name, format);
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    paths
+}
+
+// =============================================================================
+// Pipeline
+// =============================================================================
+
+/// Run the pipeline off the async runtime (decode / resize / encode are CPU-bound)
+pub async fn process(bytes: Vec<u8>, kind: MediaKind) -> AppResult<ProcessedImage> {
+    tokio::task::spawn_blocking(move || process_blocking(&bytes, kind))
+        .await
+        .map_err(|e| AppError::Internal(format!("Image processing task failed: {}", e)))?
+}
+
+fn process_blocking(bytes: &[u8], kind: MediaKind) -> AppResult<ProcessedImage> {
+    let source = detect_format(bytes)?;
+    let format = source.image_format().ok_or_else(|| {
+        AppError::BadRequest(
+            "HEIC画像は保存できません。JPEG、PNG、WebPのいずれかに変換してからアップロードしてください。"
+                .to_string(),
+        )
+    })?;
+    let image = decode_oriented(bytes, format)?;
+
+    let mut variants = Vec::new();
+    for spec in kind.variants() {
+        let resized = fit_within(&image, spec.max_edge);
+        for &format in spec.formats {
+            variants.push(EncodedVariant {
+                name: spec.name,
+                format,
+                bytes: encode(&resized, format, spec.jpeg_quality)?,
+            });
+        }
+    }
+    Ok(ProcessedImage { variants })
+}
+
+/// Decode with dimension / allocation limits and apply the EXIF orientation
+fn decode_oriented(bytes: &[u8], format: ImageFormat) -> AppResult<DynamicImage> {
+    let decode_error =
+        |e: image::ImageError| AppError::BadRequest(format!("画像のデコードに失敗しました: {}", e));
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_EDGE);
+    limits.max_image_height = Some(MAX_SOURCE_EDGE);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+
+    let mut reader = ImageReader::new(Cursor::new(bytes));
+    reader.set_format(format);
+    reader.limits(limits);
+    let mut decoder = reader.into_decoder().map_err(decode_error)?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder).map_err(decode_error)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn fit_within(image: &DynamicImage, max_edge: u32) -> DynamicImage {
+    if image.width() <= max_edge && image.height() <= max_edge {
+        image.clone()
+    } else {
+        image.resize(max_edge, max_edge, FilterType::CatmullRom)
+    }
+}
+
+fn encode(image: &DynamicImage, format: OutputFormat, jpeg_quality: u8) -> AppResult<Vec<u8>> {
+    let encode_error =
+        |e: image::ImageError| AppError::Internal(format!("Image encoding failed: {}", e));
+    let mut out = Vec::new();
+    match format {
+        OutputFormat::Jpeg => {
+            // JPEG has no alpha channel
+            DynamicImage::ImageRgb8(image.to_rgb8())
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut out, jpeg_quality))
+                .map_err(encode_error)?;
+        }
+        OutputFormat::WebP => {
+            let pixels = if image.color().has_alpha() {
+                DynamicImage::ImageRgba8(image.to_rgba8())
+            } else {
+                DynamicImage::ImageRgb8(image.to_rgb8())
+            };
+            pixels
+                .write_with_encoder(WebPEncoder::new_lossless(&mut out))
+                .map_err(encode_error)?;
+        }
+    }
+    Ok(out)
+}
+
+// =============================================================================
+// Storage
+// =============================================================================
+
+/// Upload every variant under `base`; returns the `full` JPEG path (stored in the DB).
+/// Only the `full` upload is required, smaller variants are best-effort.
+pub async fn upload_variants(
+    supabase: &SupabaseClient,
+    bucket: &str,
+    base: &str,
+    processed: ProcessedImage,
+    access_token: &str,
+) -> AppResult<String> {
+    let full_path = variant_path(base, FULL, OutputFormat::Jpeg);
+    let uploads = processed.variants.into_iter().map(|variant| {
+        let path = variant_path(base, variant.name, variant.format);
+        async move {
+            let result = supabase
+                .upload_object(
+                    bucket,
+                    &path,
+                    variant.bytes,
+                    variant.format.mime_type(),
+                    access_token,
+                )
+                .await;
+            (path, result)
+        }
+    });
+
+    for (path, result) in join_all(uploads).await {
+        match result {
+            Ok(_) => {}
+            Err(e) if path == full_path => return Err(e),
+            Err(e) => tracing::warn!("Failed to upload image variant {}: {}", path, e),
+        }
+    }
+    Ok(full_path)
+}
+
+/// Best-effort removal of an upload and all of its variants
+pub async fn delete_all(
+    supabase: &SupabaseClient,
+    bucket: &str,
+    stored_path: &str,
+    kind: MediaKind,
+    access_token: &str,
+) {
+    let paths = all_paths(stored_path, kind);
+    let deletions = paths
+        .iter()
+        .map(|path| supabase.delete_object(bucket, path, access_token));
+    for (path, result) in paths.iter().zip(join_all(deletions).await) {
+        if let Err(e) = result {
+            tracing::debug!("Image delete skipped for {}: {}", path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn jpeg(width: u32, height: u32) -> Vec<u8> {
+        let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, Rgb([200, 30, 30])));
+        encode(&img, OutputFormat::Jpeg, 90).unwrap()
+    }
+
+    /// Insert an APP1 EXIF segment (orientation = 6, "rotate 90 CW") plus a fake GPS marker
+    fn with_exif_orientation(jpeg: &[u8]) -> Vec<u8> {
+        let mut tiff = vec![b'M', b'M', 0x00, 0x2A, 0x00, 0x00, 0x00, 0x08];
+        tiff.extend_from_slice(&[0x00, 0x01]); // 1 entry
+        tiff.extend_from_slice(&[0x01, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x06, 0x00, 0x00]);
+        tiff.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]); // no next IFD
+        tiff.extend_from_slice(b"GPSLatitude35.6812");
+
+        let mut payload = b"Exif\0\0".to_vec();
+        payload.extend_from_slice(&tiff);
+        let len = (payload.len() + 2) as u16;
+
+        let mut out = jpeg[..2].to_vec(); // SOI
+        out.extend_from_slice(&[0xFF, 0xE1]);
+        out.extend_from_slice(&len.to_be_bytes());
+        out.extend_from_slice(&payload);
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(detect_format(&jpeg(4, 4)).unwrap(), SourceFormat::Jpeg);
+        let mut heic = vec![0, 0, 0, 24];
+        heic.extend_from_slice(b"ftypheic0000");
+        assert_eq!(detect_format(&heic).unwrap(), SourceFormat::Heic);
+        assert!(detect_format(b"GIF89a........").is_err());
+        assert!(detect_format(b"tiny").is_err());
+
+        // Detected, but stored uploads need a decodable format
+        for kind in [MediaKind::Avatar, MediaKind::Post, MediaKind::ProgressPhoto] {
+            assert!(matches!(process_blocking(&heic, kind), Err(AppError::BadRequest(_))));
+        }
+    }
+
+    fn dimensions(bytes: &[u8]) -> (u32, u32) {
+        let image = image::load_from_memory(bytes).unwrap();
+        (image.width(), image.height())
+    }
+
+    #[test]
+    fn test_strips_exif_and_applies_orientation() {
+        let source = with_exif_orientation(&jpeg(40, 20));
+        assert!(contains(&source, b"GPSLatitude"));
+
+        let processed = process_blocking(&source, MediaKind::Post).unwrap();
+        for variant in &processed.variants {
+            assert!(!contains(&variant.bytes, b"Exif"), "{}", variant.name);
+            assert!(!contains(&variant.bytes, b"GPSLatitude"), "{}", variant.name);
+        }
+        let full = &processed.variants[0];
+        assert_eq!((full.name, dimensions(&full.bytes)), (FULL, (20, 40)));
+    }
+
+    #[test]
+    fn test_variants_fit_and_never_upscale() {
+        let processed = process_blocking(&jpeg(1600, 800), MediaKind::Post).unwrap();
+        let sizes: Vec<_> = processed
+            .variants
+            .iter()
+            .map(|v| {
+                let (width, height) = dimensions(&v.bytes);
+                (v.name, v.format, width, height)
+            })
+            .collect();
+        assert_eq!(
+            sizes,
+            vec![
+                (FULL, OutputFormat::Jpeg, 1600, 800),
+                (MEDIUM, OutputFormat::Jpeg, 1080, 540),
+                (THUMB, OutputFormat::Jpeg, 400, 200),
+                (THUMB, OutputFormat::WebP, 400, 200),
+            ]
+        );
+        let webp = &processed.variants[3].bytes;
+        assert_eq!(detect_format(webp).unwrap(), SourceFormat::WebP);
+    }
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(variant_path("u/abc", FULL, OutputFormat::Jpeg), "u/abc.jpg");
+        assert_eq!(variant_path("u/abc", THUMB, OutputFormat::WebP), "u/abc_thumb.webp");
+        assert_eq!(base_path("u/abc.png"), "u/abc");
+        assert_eq!(base_path("u/abc"), "u/abc");
+        assert_eq!(
+            all_paths("u/old.png", MediaKind::Avatar),
+            vec!["u/old.png", "u/old.jpg", "u/old_thumb.jpg", "u/old_thumb.webp"]
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Masking of secrets and PII
+//!
+//! - `mask_sensitive`: irreversible masking for logs (`error::mask_sensitive_data`)
+//! - `Redactor`: reversible placeholders for text sent to the LLM. PII is replaced
+//!   with `[EMAIL_1]`, `[PHONE_1]`, ... before the prompt leaves the server and the
+//!   placeholders in the model output are restored before it reaches the user.
+
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+
+/// Kind of detected PII (placeholder label)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PiiKind {
+    Email,
+    Phone,
+    CardNumber,
+    PostalCode,
+    Address,
+}
+
+impl PiiKind {
+    fn label(self) -> &'static str {
+        match self {
+            PiiKind::Email => "EMAIL",
+            PiiKind::Phone => "PHONE",
+            PiiKind::CardNumber => "CARD",
+            PiiKind::PostalCode => "POSTAL",
+            PiiKind::Address => "ADDRESS",
+        }
+    }
+}
+
+struct PiiRule {
+    kind: PiiKind,
+    regex: Regex,
+    /// Extra check on the match (digit counts, Luhn, ...)
+    accept: fn(&str) -> bool,
+}
+
+// Secrets (tokens, keys). Order matters: JWTs before the generic Bearer rule.
+static SECRET_RULES: LazyLock<Vec<(Regex, &'static str)>> = LazyLock::new(|| {
+    vec![
+        (
+            Regex::new(r"eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+").unwrap(),
+            "[MASKED_TOKEN]",
+        ),
+        (Regex::new(r"Bearer\s+[A-Za-z0-9_.-]+").unwrap(), "Bearer [MASKED]"),
+        (Regex::new(r"token=[A-Za-z0-9%_.-]+").unwrap(), "token=[MASKED]"),
+        (
+            Regex::new(r"(apikey|api_key|key)=[\w-]+").unwrap(),
+            "$1=[MASKED]",
+        ),
+    ]
+});
+
+// PII. Order matters: emails and card numbers before phone numbers.
+static PII_RULES: LazyLock<Vec<PiiRule>> = LazyLock::new(|| {
+    vec![
+        PiiRule {
+            kind: PiiKind::Email,
+            regex: Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap(),
+            accept: |_| true,
+        },
+        PiiRule {
+            kind: PiiKind::CardNumber,
+            regex: Regex::new(r"(?-u:\b)(?:\d[ -]?){12,18}\d(?-u:\b)").unwrap(),
+            accept: luhn_valid,
+        },
+        PiiRule {
+            kind: PiiKind::Phone,
+            regex: Regex::new(r"(?:\+81[-\s]?|(?-u:\b)0)\d{1,4}[-\s]?\d{1,4}[-\s]?\d{3,4}(?-u:\b)").unwrap(),
+            accept: |m| {
+                let digits = m.chars().filter(|c| c.is_ascii_digit()).count();
+                // +81 drops the leading 0: +81 90 1234 5678 = 12 digits
+                (10..=12).contains(&digits)
+            },
+        },
+        PiiRule {
+            kind: PiiKind::PostalCode,
+            regex: Regex::new(r"〒\s?\d{3}-?\d{4}|(?-u:\b)\d{3}-\d{4}(?-u:\b)").unwrap(),
+            accept: |_| true,
+        },
+        PiiRule {
+            kind: PiiKind::Address,
+            regex: Regex::new(
+                r"(?:北海道|東京都|大阪府|京都府|\p{Han}{2,3}県)\p{Han}{1,6}[市区町村郡][\p{Han}\p{Hiragana}\p{Katakana}ー]{0,12}\d+(?:[-ー−の丁目番地号]+\d+)*",
+            )
+            .unwrap(),
+            accept: |_| true,
+        },
+    ]
+});
+
+/// Luhn checksum (avoids masking arbitrary long digit runs as card numbers)
+fn luhn_valid(m: &str) -> bool {
+    let digits: Vec<u32> = m.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            if i % 2 == 1 {
+                let d2 = d * 2;
+                if d2 > 9 {
+                    d2 - 9
+                } else {
+                    d2
+                }
+            } else {
+                d
+            }
+        })
+        .sum();
+    // Dockerfile pins rust 1.83 (no u32::is_multiple_of)
+    matches!(sum % 10, 0)
+}
+
+/// Mask tokens / API keys
+pub fn mask_secrets(text: &str) -> String {
+    let mut masked = text.to_string();
+    for (regex, replacement) in SECRET_RULES.iter() {
+        masked = regex.replace_all(&masked, *replacement).to_string();
+    }
+    masked
+}
+
+/// Mask PII with fixed labels (`[MASKED_EMAIL]`, ...). Not reversible.
+pub fn mask_pii(text: &str) -> String {
+    let mut masked = text.to_string();
+    for rule in PII_RULES.iter() {
+        masked = rule
+            .regex
+            .replace_all(&masked, |caps: &Captures| {
+                let m = &caps[0];
+                if (rule.accept)(m) {
+                    format!("[MASKED_{}]", rule.kind.label())
+                } else {
+                    m.to_string()
+                }
+            })
+            .to_string();
+    }
+    masked
+}
+
+/// Mask secrets and PII for logging
+pub fn mask_sensitive(text: &str) -> String {
+    mask_pii(&mask_secrets(text))
+}
+
+/// Reversible PII redaction for one LLM round-trip.
+/// The same value always maps to the same placeholder within a `Redactor`.
+#[derive(Debug, Default)]
+pub struct Redactor {
+    /// (placeholder, original)
+    entries: Vec<(String, String)>,
+}
+
+impl Redactor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace PII in `text` with placeholders
+    pub fn redact(&mut self, text: &str) -> String {
+        let mut out = text.to_string();
+        for rule in PII_RULES.iter() {
+            out = rule
+                .regex
+                .replace_all(&out, |caps: &Captures| {
+                    let m = &caps[0];
+                    if (rule.accept)(m) {
+                        self.placeholder_for(rule.kind, m)
+                    } else {
+                        m.to_string()
+                    }
+                })
+                .to_string();
+        }
+        out
+    }
+
+    /// Redact every string in a JSON value (in place)
+    pub fn redact_json(&mut self, value: &mut serde_json::Value) {
+        self.walk_json(value, &mut |r, s| r.redact(s));
+    }
+
+    /// Put original values back into model output
+    pub fn restore(&self, text: &str) -> String {
+        if self.entries.is_empty() || !text.contains('[') {
+            return text.to_string();
+        }
+        let mut out = text.to_string();
+        for (placeholder, original) in &self.entries {
+            out = out.replace(placeholder, original);
+        }
+        out
+    }
+
+    /// Restore every string in a JSON value (in place)
+    pub fn restore_json(&mut self, value: &mut serde_json::Value) {
+        self.walk_json(value, &mut |r, s| r.restore(s));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of redacted values
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn placeholder_for(&mut self, kind: PiiKind, original: &str) -> String {
+        if let Some((placeholder, _)) = self.entries.iter().find(|(_, o)| o == original) {
+            return placeholder.clone();
+        }
+        let n = self
+            .entries
+            .iter()
+            .filter(|(p, _)| p.starts_with(&format!("[{}_", kind.label())))
+            .count()
+            + 1;
+        let placeholder = format!("[{}_{}]", kind.label(), n);
+        self.entries.push((placeholder.clone(), original.to_string()));
+        placeholder
+    }
+
+    fn walk_json(
+        &mut self,
+        value: &mut serde_json::Value,
+        f: &mut impl FnMut(&mut Self, &str) -> String,
+    ) {
+        match value {
+            serde_json::Value::String(s) => *s = f(self, s),
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    self.walk_json(item, f);
+                }
+            }
+            serde_json::Value::Object(map) => {
+                for (_, v) in map.iter_mut() {
+                    self.walk_json(v, f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_pii() {
+        // (input, expected redacted text)
+        let cases = [
+            ("連絡は taro@example.com まで", "連絡は [EMAIL_1] まで"),
+            ("電話 090-1234-5678 です", "電話 [PHONE_1] です"),
+            ("電話 09012345678 です", "電話 [PHONE_1] です"),
+            ("電話090-1234-5678まで", "電話[PHONE_1]まで"),
+            ("+81 90 1234 5678", "[PHONE_1]"),
+            ("〒150-0001 に住んでる", "[POSTAL_1] に住んでる"),
+            ("東京都渋谷区神宮前1-2-3 のジム", "[ADDRESS_1] のジム"),
+            ("カード 4242 4242 4242 4242", "カード [CARD_1]"),
+            // Not PII
+            ("ベンチ 80kg x 8 x 3", "ベンチ 80kg x 8 x 3"),
+            ("2026-10-18 に 1500kcal", "2026-10-18 に 1500kcal"),
+            ("1234 5678 9012 3456", "1234 5678 9012 3456"),
+        ];
+        for (input, expected) in cases {
+            let mut r = Redactor::new();
+            assert_eq!(r.redact(input), expected, "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_placeholders_are_stable_and_reversible() {
+        let mut r = Redactor::new();
+        let text = "a@example.com と b@example.com、もう一度 a@example.com";
+        let redacted = r.redact(text);
+        assert_eq!(redacted, "[EMAIL_1] と [EMAIL_2]、もう一度 [EMAIL_1]");
+        assert_eq!(r.len(), 2);
+        assert_eq!(r.restore(&redacted), text);
+
+        let mut json = serde_json::json!({"notes": ["電話は 03-1234-5678"], "n": 1});
+        r.redact_json(&mut json);
+        assert_eq!(json["notes"][0], "電話は [PHONE_1]");
+        r.restore_json(&mut json);
+        assert_eq!(json["notes"][0], "電話は 03-1234-5678");
+    }
+
+    #[test]
+    fn test_mask_sensitive() {
+        let msg = "Bearer abc.def key=secret123 user taro@example.com token=xyz";
+        assert_eq!(
+            mask_sensitive(msg),
+            "Bearer [MASKED] key=[MASKED] user [MASKED_EMAIL] token=[MASKED]"
+        );
+    }
+}
@@ -1,12 +1,91 @@
-use chrono::{Datelike, Duration, NaiveDate};
+pub mod persona;
+pub mod prompts;
+pub mod retrieval;
+pub mod safety;
+pub mod trends;
+pub mod weekly_report;
+
+use chrono::{Duration, NaiveDate};
+use moka::future::Cache;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::LazyLock;
 
-use crate::error::AppResult;
+use crate::error::{AppError, AppResult};
 use crate::infrastructure::supabase::{
     BodyMetrics, NutritionDaily, SupabaseClient, UserProfile, Workout,
 };
+pub use persona::get_system_instruction;
+use persona::{Language, Persona};
+use trends::{MetricsEntry, RecoveryState, StrengthState, TrainingContext};
+use weekly_report::{
+    nutrition_adherence, weight_trend, NutritionAdherence, NutritionTargets, ReportWorkout,
+    WeightEntry, WeightTrend,
+};
+
+/// Cached `UserState` snapshots (TTL: 10 minutes, max 10,000 entries)
+/// Key: (user_id, cache epoch, target date, version)
+static USER_STATE_CACHE: LazyLock<Cache<(String, u64, NaiveDate, StateVersion), UserState>> =
+    LazyLock::new(|| {
+        Cache::builder()
+            .max_capacity(10_000)
+            .time_to_live(std::time::Duration::from_secs(10 * 60))
+            .build()
+    });
+
+/// Per-user cache epoch, bumped by every write that changes the state.
+/// Outlives `USER_STATE_CACHE` entries so an expired epoch never revives a stale snapshot.
+static USER_STATE_EPOCHS: LazyLock<Cache<String, u64>> = LazyLock::new(|| {
+    Cache::builder()
+        .max_capacity(100_000)
+        .time_to_live(std::time::Duration::from_secs(60 * 60))
+        .build()
+});
+
+/// Source of epoch values (never reused, unlike a per-user counter that could expire)
+static NEXT_STATE_EPOCH: AtomicU64 = AtomicU64::new(1);
+
+/// Drop cached `UserState` snapshots of a user.
+/// Call after writes to workouts, meals, body metrics or the profile.
+pub async fn invalidate_user_state(user_id: &str) {
+    let epoch = NEXT_STATE_EPOCH.fetch_add(1, Ordering::Relaxed);
+    USER_STATE_EPOCHS.insert(user_id.to_string(), epoch).await;
+}
 
-/// User state for AI context (version 1)
+/// `UserState` layout sent to the model (stored in `ai_sessions.state_version`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum StateVersion {
+    /// Today + 14-day exercise summary + 7-day nutrition average
+    V1,
+    /// v1 + real exercise names, best lifts, weekly volume, weight / sleep trends,
+    /// nutrition target adherence, injuries and equipment
+    #[default]
+    V2,
+}
+
+impl StateVersion {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StateVersion::V1 => "v1",
+            StateVersion::V2 => "v2",
+        }
+    }
+
+    /// Version requested by the client (`None` = default)
+    pub fn from_request(value: Option<&str>) -> AppResult<Self> {
+        match value.map(str::trim) {
+            None | Some("") => Ok(Self::default()),
+            Some("v1") => Ok(StateVersion::V1),
+            Some("v2") => Ok(StateVersion::V2),
+            Some(other) => Err(AppError::BadRequest(format!(
+                "Unknown state_version: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// User state for AI context (v1 fields + optional v2 sections)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserState {
     pub version: String,
@@ -14,6 +93,17 @@ pub struct UserState {
     pub today: TodayState,
     pub last_14d: Last14dState,
     pub nutrition_7d_avg: NutritionAvgState,
+    // --- v2 ---
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<TrainingContext>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strength: Option<StrengthState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight_trend_7d: Option<WeightTrend>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery: Option<RecoveryState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nutrition_adherence_7d: Option<NutritionAdherence>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +115,11 @@ pub struct ProfileState {
     pub birth_year: Option<i32>,
     pub environment: serde_json::Value,
     pub constraints: serde_json::Value,
+    /// Coach tone / answer language (applied to the system instruction, not sent as state)
+    #[serde(skip)]
+    pub persona: Persona,
+    #[serde(skip)]
+    pub language: Language,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -81,19 +176,94 @@ impl<'a> StateGenerator<'a> {
         }
     }
 
-    /// Generate complete user state for AI context
-    pub async fn generate(&self, user_id: &str, target_date: NaiveDate) -> AppResult<UserState> {
-        let profile = self.generate_profile(user_id).await?;
-        let today = self.generate_today(user_id, target_date).await?;
-        let last_14d = self.generate_last_14d(user_id, target_date).await?;
-        let nutrition_7d_avg = self.generate_nutrition_avg(user_id, target_date).await?;
+    /// Cached `generate`: follow-up questions reuse the snapshot until the user
+    /// writes something (`invalidate_user_state`) or the entry expires.
+    pub async fn generate_cached(
+        &self,
+        user_id: &str,
+        target_date: NaiveDate,
+        version: StateVersion,
+    ) -> AppResult<UserState> {
+        // A write during generation bumps the epoch, so the (possibly stale)
+        // result lands under a key that is never read again.
+        let epoch = USER_STATE_EPOCHS.get(user_id).await.unwrap_or(0);
+        let key = (user_id.to_string(), epoch, target_date, version);
+        if let Some(cached) = USER_STATE_CACHE.get(&key).await {
+            tracing::debug!(user_id = %user_id, "UserState cache hit");
+            return Ok(cached);
+        }
+
+        let user_state = self.generate(user_id, target_date, version).await?;
+        USER_STATE_CACHE.insert(key, user_state.clone()).await;
+        Ok(user_state)
+    }
+
+    /// Generate complete user state for AI context.
+    /// Independent parts are fetched concurrently.
+    pub async fn generate(
+        &self,
+        user_id: &str,
+        target_date: NaiveDate,
+        version: StateVersion,
+    ) -> AppResult<UserState> {
+        match version {
+            StateVersion::V1 => self.generate_v1(user_id, target_date).await,
+            StateVersion::V2 => self.generate_v2(user_id, target_date).await,
+        }
+    }
+
+    async fn generate_v1(&self, user_id: &str, target_date: NaiveDate) -> AppResult<UserState> {
+        let (profile, today, last_14d, nutrition_7d_avg) = tokio::try_join!(
+            self.generate_profile(user_id),
+            self.generate_today(user_id, target_date),
+            self.generate_last_14d(user_id, target_date, false),
+            self.generate_nutrition_avg(user_id, target_date),
+        )?;
+
+        Ok(UserState {
+            version: StateVersion::V1.as_str().to_string(),
+            profile,
+            today,
+            last_14d,
+            nutrition_7d_avg,
+            context: None,
+            strength: None,
+            weight_trend_7d: None,
+            recovery: None,
+            nutrition_adherence_7d: None,
+        })
+    }
+
+    async fn generate_v2(&self, user_id: &str, target_date: NaiveDate) -> AppResult<UserState> {
+        let (profile, today, last_14d, nutrition_7d_avg, strength, metrics, adherence) =
+            tokio::try_join!(
+                self.generate_profile(user_id),
+                self.generate_today(user_id, target_date),
+                self.generate_last_14d(user_id, target_date, true),
+                self.generate_nutrition_avg(user_id, target_date),
+                self.generate_strength(user_id, target_date),
+                self.fetch_metrics(user_id, target_date - Duration::days(13), target_date),
+                self.generate_nutrition_adherence(user_id, target_date),
+            )?;
+
+        let weights: Vec<WeightEntry> = metrics.iter().map(MetricsEntry::weight).collect();
+        let context = trends::training_context(&profile.environment, &profile.constraints);
 
         Ok(UserState {
-            version: "v1".to_string(),
+            version: StateVersion::V2.as_str().to_string(),
             profile,
             today,
             last_14d,
             nutrition_7d_avg,
+            context: Some(context),
+            strength: Some(strength),
+            weight_trend_7d: Some(weight_trend(
+                &weights,
+                target_date - Duration::days(6),
+                target_date,
+            )),
+            recovery: Some(trends::recovery_state(&metrics, target_date)),
+            nutrition_adherence_7d: Some(adherence),
         })
     }
 
@@ -113,6 +283,8 @@ impl<'a> StateGenerator<'a> {
                 birth_year: p.birth_year,
                 environment: p.environment.unwrap_or(serde_json::json!({})),
                 constraints: p.constraints.unwrap_or(serde_json::json!([])),
+                persona: Persona::from_profile(p.ai_persona.as_deref()),
+                language: Language::from_profile(p.ai_language.as_deref()),
             }),
             None => Ok(ProfileState {
                 goal: "health".to_string(),
@@ -122,6 +294,8 @@ impl<'a> StateGenerator<'a> {
                 birth_year: None,
                 environment: serde_json::json!({}),
                 constraints: serde_json::json!([]),
+                persona: Persona::default(),
+                language: Language::default(),
             }),
         }
     }
@@ -129,29 +303,19 @@ impl<'a> StateGenerator<'a> {
     async fn generate_today(&self, user_id: &str, date: NaiveDate) -> AppResult<TodayState> {
         let date_str = date.format("%Y-%m-%d").to_string();
 
-        // Get body metrics
-        let metrics_query = format!("user_id=eq.{}&date=eq.{}&select=*", user_id, date_str);
-        let metrics: Vec<BodyMetrics> = self
-            .supabase
-            .select("body_metrics", &metrics_query, self.access_token)
-            .await?;
+        // Body metrics, nutrition and workouts for the day (independent; run concurrently)
+        let query = format!("user_id=eq.{}&date=eq.{}&select=*", user_id, date_str);
+        let (metrics, nutrition, workouts) = tokio::try_join!(
+            self.supabase
+                .select::<BodyMetrics>("body_metrics", &query, self.access_token),
+            self.supabase
+                .select::<NutritionDaily>("nutrition_daily", &query, self.access_token),
+            self.supabase
+                .select::<Workout>("workouts", &query, self.access_token),
+        )?;
         let metrics = metrics.into_iter().next();
-
-        // Get nutrition
-        let nutrition_query = format!("user_id=eq.{}&date=eq.{}&select=*", user_id, date_str);
-        let nutrition: Vec<NutritionDaily> = self
-            .supabase
-            .select("nutrition_daily", &nutrition_query, self.access_token)
-            .await?;
         let nutrition = nutrition.into_iter().next();
 
-        // Get workouts
-        let workouts_query = format!("user_id=eq.{}&date=eq.{}&select=*", user_id, date_str);
-        let workouts: Vec<Workout> = self
-            .supabase
-            .select("workouts", &workouts_query, self.access_token)
-            .await?;
-
         Ok(TodayState {
             date,
             weight_kg: metrics.as_ref().and_then(|m| m.weight_kg),
@@ -165,14 +329,20 @@ impl<'a> StateGenerator<'a> {
         })
     }
 
-    async fn generate_last_14d(&self, user_id: &str, end_date: NaiveDate) -> AppResult<Last14dState> {
+    /// `resolve_names`: use `exercises.name` (v2) instead of `exercise_<uuid>` (v1)
+    async fn generate_last_14d(
+        &self,
+        user_id: &str,
+        end_date: NaiveDate,
+        resolve_names: bool,
+    ) -> AppResult<Last14dState> {
         let start_date = end_date - Duration::days(14);
         let start_str = start_date.format("%Y-%m-%d").to_string();
         let end_str = end_date.format("%Y-%m-%d").to_string();
 
         // Get workouts with exercises and sets in a single JOIN query (fixes N+1)
         let workouts_query = format!(
-            "user_id=eq.{}&date=gte.{}&date=lte.{}&select=id,date,workout_exercises(id,exercise_id,custom_exercise_name,muscle_tag,workout_sets(weight_kg,reps,is_warmup))&order=date.desc",
+            "user_id=eq.{}&date=gte.{}&date=lte.{}&select=id,date,workout_exercises(id,exercise_id,custom_exercise_name,muscle_tag,exercises(name),workout_sets(weight_kg,reps,is_warmup))&order=date.desc",
             user_id, start_str, end_str
         );
         let workouts: Vec<serde_json::Value> = self
@@ -199,8 +369,12 @@ impl<'a> StateGenerator<'a> {
                     let muscle_tag = exercise["muscle_tag"].as_str().unwrap_or_default().to_string();
                     muscle_groups.insert(muscle_tag.clone());
 
+                    let master_name = exercise["exercises"]["name"]
+                        .as_str()
+                        .filter(|_| resolve_names);
                     let name = exercise["custom_exercise_name"]
                         .as_str()
+                        .or(master_name)
                         .map(String::from)
                         .unwrap_or_else(|| {
                             format!(
@@ -321,6 +495,67 @@ impl<'a> StateGenerator<'a> {
             days_logged: nutrition.len() as i32,
         })
     }
+
+    async fn generate_strength(
+        &self,
+        user_id: &str,
+        end_date: NaiveDate,
+    ) -> AppResult<StrengthState> {
+        let start_date = end_date - Duration::days(trends::STRENGTH_LOOKBACK_DAYS);
+        let query = format!(
+            "user_id=eq.{}&date=gte.{}&date=lte.{}&select=date,workout_exercises(exercise_id,custom_exercise_name,muscle_tag,exercises(name),workout_sets(weight_kg,reps,is_warmup))&order=date.asc",
+            user_id, start_date, end_date
+        );
+        let workouts: Vec<ReportWorkout> = self
+            .supabase
+            .select("workouts", &query, self.access_token)
+            .await?;
+        Ok(trends::strength_state(&workouts, end_date))
+    }
+
+    async fn fetch_metrics(
+        &self,
+        user_id: &str,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> AppResult<Vec<MetricsEntry>> {
+        let query = format!(
+            "user_id=eq.{}&date=gte.{}&date=lte.{}&select=date,weight_kg,sleep_hours,steps&order=date.asc",
+            user_id, start_date, end_date
+        );
+        self.supabase
+            .select("body_metrics", &query, self.access_token)
+            .await
+    }
+
+    async fn generate_nutrition_adherence(
+        &self,
+        user_id: &str,
+        end_date: NaiveDate,
+    ) -> AppResult<NutritionAdherence> {
+        let start_date = end_date - Duration::days(6);
+        let query = format!(
+            "user_id=eq.{}&date=gte.{}&date=lte.{}&meals_logged=gt.0&select=*",
+            user_id, start_date, end_date
+        );
+        let nutrition: Vec<NutritionDaily> = self
+            .supabase
+            .select("nutrition_daily", &query, self.access_token)
+            .await?;
+        let targets: Option<NutritionTargets> = self
+            .supabase
+            .select_single(
+                "user_profiles",
+                &format!(
+                    "user_id=eq.{}&select=target_calories,target_protein_g",
+                    user_id
+                ),
+                self.access_token,
+            )
+            .await?;
+        let days: Vec<&NutritionDaily> = nutrition.iter().collect();
+        Ok(nutrition_adherence(&days, &targets.unwrap_or_default()))
+    }
 }
 
 /// Calculate estimated 1RM using Epley formula
@@ -334,213 +569,6 @@ pub fn calculate_e1rm(weight: f64, reps: i32) -> f64 {
     (e1rm * 100.0).round() / 100.0
 }
 
-/// System instruction for Gemini AI
-pub fn get_system_instruction(state: &UserState) -> String {
-    fn fmt_i32(opt: Option<i32>, unit: &str) -> String {
-        opt.map(|v| format!("{}{}", v, unit))
-            .unwrap_or_else(|| "不明".to_string())
-    }
-
-    fn fmt_f64(opt: Option<f64>, unit: &str, digits: usize) -> String {
-        opt.map(|v| format!("{:.*}{}", digits, v, unit))
-            .unwrap_or_else(|| "不明".to_string())
-    }
-
-    fn fmt_str(opt: Option<&str>) -> String {
-        opt.map(|v| v.to_string()).unwrap_or_else(|| "不明".to_string())
-    }
-
-    let age_str = match state.profile.birth_year {
-        Some(by) => {
-            let age = state.today.date.year() - by;
-            if age > 0 && age < 120 {
-                format!("{}歳", age)
-            } else {
-                "不明".to_string()
-            }
-        }
-        None => "不明".to_string(),
-    };
-
-    let height_cm = state.profile.height_cm;
-    let weight_kg = state.today.weight_kg;
-    let weight_example_str = state
-        .today
-        .weight_kg
-        .map(|v| format!("{:.1}kg", v))
-        .unwrap_or_else(|| "不明".to_string());
-    let bmi_str = match (height_cm, weight_kg) {
-        (Some(h_cm), Some(w_kg)) if h_cm > 0 && w_kg > 0.0 => {
-            let h_m = h_cm as f64 / 100.0;
-            let bmi = w_kg / (h_m * h_m);
-            format!("{:.1}", bmi)
-        }
-        _ => "不明".to_string(),
-    };
-
-    format!(
-        r#"
-あなたはトレーニングコーチ「ガチトレAI」。友達みたいに自然に話して。
-
-【ユーザー（プロフィール・身体データ）】
-- 目標: {}
-- レベル: {}
-- 性別: {}
-- 年齢: {}
-- 身長: {}
-- 体重: {}
-- 体脂肪率: {}
-- BMI: {}
-- 睡眠: {}
-- 歩数: {}
-- 今日の摂取カロリー: {}
-- 今日のたんぱく質: {}
-- 食事記録回数: {}
-- 今日のワークアウト数: {}
-- 環境: {}
-- 制約: {}
-
-【会話スタイル - 超重要】
-- 結論から簡潔に答える。1-2文で十分
-- 質問をオウム返ししない（「〇〇についてですね」とか不要）
-- 理由は聞かれたときだけ説明する
-- 前置き・まとめ不要。本題だけ
-- 敬語だけど堅くない。「〜ですね！」「〜しましょう」くらいのノリ
-
-【Q&Aのルール（超重要）】
-- まず「ユーザーが何を求めているか」を特定して、それにだけ答える（Q→Aの直結）。
-- 「何kg？」「何回？」「何分？」「何kcal？」「何％？」「何cm？」「どれくらい？」「どのくらい増やす？」など“数値”を聞かれたら、必ず具体的な数値（単位付き）で答える（kgに限らない）。
-- 単位が質問文に明示されている場合はその単位で答える。単位が曖昧なら、もっとも自然な単位で答えつつ、最後に1つだけ確認質問を添える（例:「回数の話で合ってますか？」）。
-- 体重（例: {}）など文脈に関連する基準値があるなら、比率の目安だけで終わらせず“単位換算した具体例”まで提示する（例:「体重の1.0倍」→「{}」）。
-- 質問が曖昧で種目/条件が特定できない場合は、
-  - ①まず結論として「候補を2-3パターン」具体的な数値（単位付き）で提示（例: ベンチ/スクワット/デッド等）
-  - ②最後に1つだけ確認質問（例:「どの種目ですか？」）をする
-  - ただし「わからないので答えられません」で終わらない
-- 数値のない曖昧回答は禁止（例:「半分くらい」「人による」だけで終わるのはNG）
-
-【ダメな例】
-❌「トレーニングメニューについてのご質問ですね。あなたの目標である筋肥大を考慮すると...理由としては...」
-⭕「今日は胸の日にしましょう！ベンチプレス3セット、ダンベルフライ3セットでいきましょう」
-
-【禁止】
-- 医学的診断・治療の提案
-- 基礎代謝以下のカロリー制限
-- 怪我リスクは必ず警告
-
-【出力フォーマット】
-{{
-  "answer_text": "回答（短く自然に）",
-  "recommendations": [
-    {{"kind": "workout|nutrition|recovery", "payload": {{...}}}}
-  ],
-  "warnings": ["必要な場合のみ"]
-}}
-"#,
-        state.profile.goal,
-        state.profile.training_level,
-        fmt_str(state.profile.sex.as_deref()),
-        age_str,
-        fmt_i32(state.profile.height_cm, "cm"),
-        fmt_f64(state.today.weight_kg, "kg", 1),
-        fmt_f64(state.today.bodyfat_pct, "%", 1),
-        bmi_str,
-        fmt_f64(state.today.sleep_hours, "時間", 1),
-        state.today
-            .steps
-            .map(|v| format!("{}歩", v))
-            .unwrap_or_else(|| "不明".to_string()),
-        state
-            .today
-            .calories
-            .map(|v| format!("{}kcal", v))
-            .unwrap_or_else(|| "不明".to_string()),
-        fmt_f64(state.today.protein_g, "g", 0),
-        state
-            .today
-            .meals_logged
-            .map(|v| format!("{}回", v))
-            .unwrap_or_else(|| "不明".to_string()),
-        format!("{}回", state.today.workout_count),
-        state.profile.environment,
-        state.profile.constraints,
-        weight_example_str.clone(),
-        weight_example_str
-    )
-}
-
-/// Safety guard - check for dangerous advice requests and prompt injection attempts
-pub fn check_safety_flags(message: &str) -> Vec<String> {
-    let mut flags = Vec::new();
-
-    let dangerous_keywords = [
-        ("ステロイド", "steroids"),
-        ("アナボリック", "anabolics"),
-        ("断食", "extreme_fasting"),
-        ("500kcal以下", "extreme_calorie_restriction"),
-        ("怪我を無視", "ignoring_injury"),
-        ("痛みがあるけど", "training_with_pain"),
-        // Additional dangerous substances
-        ("成長ホルモン", "growth_hormone"),
-        ("インスリン", "insulin_abuse"),
-        ("利尿剤", "diuretics"),
-        ("エフェドリン", "ephedrine"),
-        ("クレンブテロール", "clenbuterol"),
-        // Eating disorders
-        ("吐く", "purging"),
-        ("過食嘔吐", "binge_purge"),
-        ("拒食", "anorexia"),
-    ];
-
-    // Prompt injection patterns
-    let injection_patterns = [
-        ("ignore previous", "prompt_injection"),
-        ("ignore all", "prompt_injection"),
-        ("disregard", "prompt_injection"),
-        ("forget your", "prompt_injection"),
-        ("new instructions", "prompt_injection"),
-        ("system prompt", "prompt_injection"),
-        ("you are now", "prompt_injection"),
-        ("act as", "prompt_injection"),
-        ("pretend to be", "prompt_injection"),
-        ("jailbreak", "prompt_injection"),
-        ("無視して", "prompt_injection"),
-        ("命令を変更", "prompt_injection"),
-        ("システムプロンプト", "prompt_injection"),
-        ("別の指示", "prompt_injection"),
-        ("役割を変更", "prompt_injection"),
-    ];
-
-    // SECURITY: Normalize Unicode to NFKD form to detect homoglyphs and lookalikes
-    // Remove zero-width characters and control characters to prevent bypass attacks
-    use unicode_normalization::UnicodeNormalization;
-
-    let normalized: String = message
-        .nfkd()
-        .filter(|c| {
-            !c.is_control() &&
-            *c != '\u{200B}' && // Zero-width space
-            *c != '\u{200C}' && // Zero-width non-joiner
-            *c != '\u{200D}' && // Zero-width joiner
-            *c != '\u{FEFF}'    // Zero-width no-break space
-        })
-        .collect::<String>()
-        .to_lowercase();
-
-    for (jp, flag) in &dangerous_keywords {
-        if normalized.contains(jp) {
-            flags.push(flag.to_string());
-        }
-    }
-
-    for (pattern, flag) in &injection_patterns {
-        if normalized.contains(&pattern.to_lowercase()) {
-            flags.push(flag.to_string());
-        }
-    }
-
-    flags
-}
-
 /// Sanitize user input for AI prompts
 /// Removes or escapes potentially harmful patterns
 pub fn sanitize_user_input(input: &str) -> String {
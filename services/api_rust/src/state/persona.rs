@@ -0,0 +1,363 @@
+//! Coach persona / language layer for the system instruction.
+//!
+//! The user picks a persona (tone) and a language on the profile
+//! (`user_profiles.ai_persona` / `ai_language`). The language selects the
+//! prompt templates (`ask` = Japanese, `ask_en` = English, ...) and the unit /
+//! number formatting of the profile block and plan request; the persona adds
+//! the tone line.
+
+use chrono::Datelike;
+use std::collections::HashMap;
+
+use super::prompts::{PromptRegistry, PromptSelection};
+use super::UserState;
+use crate::error::AppResult;
+
+/// Coaching tone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Persona {
+    /// Demanding, no-excuses coach
+    StrictCoach,
+    /// Casual training partner (the original tone)
+    #[default]
+    FriendlyBuddy,
+    /// Evidence-based, explains the why with numbers
+    Science,
+}
+
+impl Persona {
+    pub const ALLOWED: &'static [&'static str] = &["strict_coach", "friendly_buddy", "science"];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Persona::StrictCoach => "strict_coach",
+            Persona::FriendlyBuddy => "friendly_buddy",
+            Persona::Science => "science",
+        }
+    }
+
+    /// Unknown / missing values fall back to the default persona
+    pub fn from_profile(value: Option<&str>) -> Self {
+        match value {
+            Some("strict_coach") => Persona::StrictCoach,
+            Some("science") => Persona::Science,
+            _ => Persona::FriendlyBuddy,
+        }
+    }
+
+    /// Tone line placed at the top of the system instruction
+    fn tone(self, language: Language) -> &'static str {
+        match (self, language) {
+            (Persona::StrictCoach, Language::Ja) => {
+                "厳しめの鬼コーチとして話して。甘やかさず、言い訳には短く釘を刺す。ただし人格否定や罵倒はしない。「〜しよう」「〜だ」と言い切る口調で。"
+            }
+            (Persona::FriendlyBuddy, Language::Ja) => {
+                "友達みたいに自然に話して。敬語だけど堅くない。「〜ですね！」「〜しましょう」くらいのノリ。"
+            }
+            (Persona::Science, Language::Ja) => {
+                "スポーツ科学に詳しいコーチとして話して。落ち着いた丁寧語で、推奨には根拠（研究で一般的な目安・数値）を一言添える。断定できない点は不確かさを明示する。"
+            }
+            (Persona::StrictCoach, Language::En) => {
+                "Talk like a tough, no-nonsense coach. Be direct and demanding, call out excuses briefly, but never insult or demean the user."
+            }
+            (Persona::FriendlyBuddy, Language::En) => {
+                "Talk like a friendly training buddy: warm, casual and encouraging, but still to the point."
+            }
+            (Persona::Science, Language::En) => {
+                "Talk like an evidence-based sports scientist: calm and precise. Back each recommendation with a short rationale (typical research-based ranges or numbers) and say so when evidence is uncertain."
+            }
+        }
+    }
+}
+
+/// Language of the coach's answers (and of the profile block in the prompt)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Language {
+    #[default]
+    Ja,
+    En,
+}
+
+impl Language {
+    pub const ALLOWED: &'static [&'static str] = &["ja", "en"];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Language::Ja => "ja",
+            Language::En => "en",
+        }
+    }
+
+    /// Unknown / missing values fall back to Japanese
+    pub fn from_profile(value: Option<&str>) -> Self {
+        match value {
+            Some("en") => Language::En,
+            _ => Language::Ja,
+        }
+    }
+
+    /// Prompt template for this language: Japanese uses `name`, English `name_en`
+    pub fn template(self, name: &str) -> String {
+        match self {
+            Language::Ja => name.to_string(),
+            Language::En => format!("{}_en", name),
+        }
+    }
+
+    fn unknown(self) -> &'static str {
+        match self {
+            Language::Ja => "不明",
+            Language::En => "unknown",
+        }
+    }
+
+    /// Placeholder for an empty value in a prompt ("なし" / "none")
+    pub fn none(self) -> &'static str {
+        match self {
+            Language::Ja => "なし",
+            Language::En => "none",
+        }
+    }
+
+    /// Plan request: muscle groups left to the coach
+    pub fn any_muscle_groups(self) -> &'static str {
+        match self {
+            Language::Ja => "おまかせ",
+            Language::En => "coach's choice",
+        }
+    }
+
+    /// Plan request: no equipment restriction given
+    pub fn any_equipment(self) -> &'static str {
+        match self {
+            Language::Ja => "制限なし",
+            Language::En => "no restrictions",
+        }
+    }
+
+    /// Plan request: requested duration, or the default when none was given
+    pub fn plan_duration(self, minutes: Option<i32>) -> String {
+        match (minutes, self) {
+            (Some(m), _) => self.with_unit(&m.to_string(), Unit::Minutes),
+            (None, Language::Ja) => "60分程度".to_string(),
+            (None, Language::En) => "about 60 min".to_string(),
+        }
+    }
+
+    /// Integer with the locale's digit grouping ("8,500" in English, "8500" in Japanese)
+    pub fn int(self, value: i64) -> String {
+        match self {
+            Language::Ja => value.to_string(),
+            Language::En => group_thousands(value),
+        }
+    }
+
+    /// Number followed by the locale's unit label ("80.5kg" / "80.5 kg", "7時間" / "7 h")
+    pub fn with_unit(self, number: &str, unit: Unit) -> String {
+        let (ja, en) = unit.labels();
+        match self {
+            Language::Ja => format!("{}{}", number, ja),
+            Language::En => format!("{}{}", number, en),
+        }
+    }
+}
+
+/// Units used in the profile block of the system instruction
+#[derive(Debug, Clone, Copy)]
+pub enum Unit {
+    Years,
+    Cm,
+    Kg,
+    Percent,
+    Hours,
+    Minutes,
+    Steps,
+    Kcal,
+    Grams,
+    Meals,
+    Workouts,
+}
+
+impl Unit {
+    /// (Japanese, English) labels; English labels carry their own leading space
+    fn labels(self) -> (&'static str, &'static str) {
+        match self {
+            Unit::Years => ("歳", " years"),
+            Unit::Cm => ("cm", " cm"),
+            Unit::Kg => ("kg", " kg"),
+            Unit::Percent => ("%", "%"),
+            Unit::Hours => ("時間", " h"),
+            Unit::Minutes => ("分", " min"),
+            Unit::Steps => ("歩", " steps"),
+            Unit::Kcal => ("kcal", " kcal"),
+            Unit::Grams => ("g", " g"),
+            Unit::Meals => ("回", " meals"),
+            Unit::Workouts => ("回", " workouts"),
+        }
+    }
+}
+
+fn group_thousands(value: i64) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let grouped = digits
+        .as_bytes()
+        .rchunks(3)
+        .rev()
+        .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(",");
+    if value < 0 {
+        format!("-{}", grouped)
+    } else {
+        grouped
+    }
+}
+
+/// System instruction for Gemini AI, built from the user's persona and language
+pub fn get_system_instruction(
+    prompts: &PromptRegistry,
+    selection: &PromptSelection,
+    state: &UserState,
+) -> AppResult<String> {
+    let lang = state.profile.language;
+    let persona = state.profile.persona;
+
+    let fmt_i32 = |opt: Option<i32>, unit: Unit| -> String {
+        opt.map(|v| lang.with_unit(&lang.int(v.into()), unit))
+            .unwrap_or_else(|| lang.unknown().to_string())
+    };
+    let fmt_f64 = |opt: Option<f64>, unit: Unit, digits: usize| -> String {
+        opt.map(|v| lang.with_unit(&format!("{:.*}", digits, v), unit))
+            .unwrap_or_else(|| lang.unknown().to_string())
+    };
+    let fmt_str = |opt: Option<&str>| -> String {
+        opt.map(|v| v.to_string())
+            .unwrap_or_else(|| lang.unknown().to_string())
+    };
+
+    let age_str = match state.profile.birth_year {
+        Some(by) => {
+            let age = state.today.date.year() - by;
+            if age > 0 && age < 120 {
+                lang.with_unit(&age.to_string(), Unit::Years)
+            } else {
+                lang.unknown().to_string()
+            }
+        }
+        None => lang.unknown().to_string(),
+    };
+
+    let height_cm = state.profile.height_cm;
+    let weight_kg = state.today.weight_kg;
+    let bmi_str = match (height_cm, weight_kg) {
+        (Some(h_cm), Some(w_kg)) if h_cm > 0 && w_kg > 0.0 => {
+            let h_m = h_cm as f64 / 100.0;
+            let bmi = w_kg / (h_m * h_m);
+            format!("{:.1}", bmi)
+        }
+        _ => lang.unknown().to_string(),
+    };
+
+    let vars = HashMap::from([
+        ("persona", persona.tone(lang).to_string()),
+        ("goal", state.profile.goal.clone()),
+        ("training_level", state.profile.training_level.clone()),
+        ("sex", fmt_str(state.profile.sex.as_deref())),
+        ("age", age_str),
+        ("height", fmt_i32(state.profile.height_cm, Unit::Cm)),
+        ("weight", fmt_f64(state.today.weight_kg, Unit::Kg, 1)),
+        ("bodyfat", fmt_f64(state.today.bodyfat_pct, Unit::Percent, 1)),
+        ("bmi", bmi_str),
+        ("sleep", fmt_f64(state.today.sleep_hours, Unit::Hours, 1)),
+        ("steps", fmt_i32(state.today.steps, Unit::Steps)),
+        ("calories", fmt_i32(state.today.calories, Unit::Kcal)),
+        ("protein", fmt_f64(state.today.protein_g, Unit::Grams, 0)),
+        ("meals_logged", fmt_i32(state.today.meals_logged, Unit::Meals)),
+        (
+            "workout_count",
+            lang.with_unit(&state.today.workout_count.to_string(), Unit::Workouts),
+        ),
+        ("environment", state.profile.environment.to_string()),
+        ("constraints", state.profile.constraints.to_string()),
+        ("weight_example", fmt_f64(state.today.weight_kg, Unit::Kg, 1)),
+    ]);
+
+    prompts.render(&lang.template("system"), selection, &vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_values_fall_back_to_defaults() {
+        assert_eq!(Persona::from_profile(Some("science")), Persona::Science);
+        assert_eq!(Persona::from_profile(Some("nope")), Persona::FriendlyBuddy);
+        assert_eq!(Persona::from_profile(None), Persona::FriendlyBuddy);
+        assert_eq!(Language::from_profile(Some("en")), Language::En);
+        assert_eq!(Language::from_profile(Some("fr")), Language::Ja);
+        for p in Persona::ALLOWED {
+            assert_eq!(Persona::from_profile(Some(p)).as_str(), *p);
+        }
+        for l in Language::ALLOWED {
+            assert_eq!(Language::from_profile(Some(l)).as_str(), *l);
+        }
+    }
+
+    #[test]
+    fn test_locale_formatting() {
+        assert_eq!(Language::En.int(8500), "8,500");
+        assert_eq!(Language::En.int(1_234_567), "1,234,567");
+        assert_eq!(Language::En.int(-1200), "-1,200");
+        assert_eq!(Language::En.int(999), "999");
+        assert_eq!(Language::Ja.int(8500), "8500");
+        assert_eq!(Language::En.with_unit("80.5", Unit::Kg), "80.5 kg");
+        assert_eq!(Language::Ja.with_unit("80.5", Unit::Kg), "80.5kg");
+        assert_eq!(Language::Ja.with_unit("7.0", Unit::Hours), "7.0時間");
+        assert_eq!(Language::En.with_unit("18.0", Unit::Percent), "18.0%");
+        assert_eq!(Language::En.plan_duration(Some(45)), "45 min");
+        assert_eq!(Language::Ja.plan_duration(Some(45)), "45分");
+        assert_eq!(Language::Ja.plan_duration(None), "60分程度");
+        assert_eq!(Language::En.template("ask"), "ask_en");
+        assert_eq!(Language::Ja.template("ask"), "ask");
+    }
+
+    #[test]
+    fn test_every_persona_renders_in_both_languages() {
+        let reg = PromptRegistry::bundled().unwrap();
+        let selection = reg.select("user");
+        let mut state: UserState = serde_json::from_value(serde_json::json!({
+            "version": "v2",
+            "profile": {
+                "goal": "hypertrophy", "training_level": "beginner", "height_cm": 175,
+                "sex": "male", "birth_year": 1995, "environment": {}, "constraints": []
+            },
+            "today": {
+                "date": "2026-10-18", "weight_kg": 72.5, "bodyfat_pct": null, "sleep_hours": 7.0,
+                "steps": 8500, "calories": 2100, "protein_g": 120.0, "meals_logged": 3,
+                "workout_count": 1
+            },
+            "last_14d": {
+                "workout_count": 0, "workout_days": [], "muscle_groups_trained": [], "top_exercises": []
+            },
+            "nutrition_7d_avg": {
+                "avg_calories": null, "avg_protein_g": null, "avg_fat_g": null, "avg_carbs_g": null,
+                "days_logged": 0
+            }
+        }))
+        .unwrap();
+
+        for persona in [Persona::StrictCoach, Persona::FriendlyBuddy, Persona::Science] {
+            for lang in [Language::Ja, Language::En] {
+                state.profile.persona = persona;
+                state.profile.language = lang;
+                let out = get_system_instruction(&reg, &selection, &state).unwrap();
+                assert!(out.contains(persona.tone(lang)));
+                match lang {
+                    Language::Ja => assert!(out.contains("8500歩") && out.contains("72.5kg")),
+                    Language::En => assert!(out.contains("8,500 steps") && out.contains("72.5 kg")),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,439 @@
+//! Versioned prompt templates + A/B experiments
+//!
+//! Templates live in `PROMPTS_DIR` (default `prompts/`) as `<name>.<version>.txt`.
+//! Variables are written as `{{var_name}}`; everything else (including JSON
+//! braces) is copied verbatim.
+//!
+//! `experiments.json` holds the default version per template and the active
+//! experiments. Users are assigned to a variant by a stable hash of
+//! (experiment key, user_id), so the same user always sees the same variant.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::LazyLock;
+
+use crate::error::{AppError, AppResult};
+
+/// `{{ var }}` placeholder
+static PLACEHOLDER_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([a-z_][a-z0-9_]*)\s*\}\}").unwrap());
+
+/// Copies bundled into the binary, used when `PROMPTS_DIR` does not exist
+const BUNDLED_TEMPLATES: &[(&str, &str)] = &[
+    ("system.v1.txt", include_str!("../../prompts/system.v1.txt")),
+    ("system.v2.txt", include_str!("../../prompts/system.v2.txt")),
+    ("system.v3.txt", include_str!("../../prompts/system.v3.txt")),
+    ("system.v4.txt", include_str!("../../prompts/system.v4.txt")),
+    ("system_en.v1.txt", include_str!("../../prompts/system_en.v1.txt")),
+    ("ask.v1.txt", include_str!("../../prompts/ask.v1.txt")),
+    ("ask_history.v1.txt", include_str!("../../prompts/ask_history.v1.txt")),
+    ("ask_history.v2.txt", include_str!("../../prompts/ask_history.v2.txt")),
+    ("plan_today.v1.txt", include_str!("../../prompts/plan_today.v1.txt")),
+    ("session_summary.v1.txt", include_str!("../../prompts/session_summary.v1.txt")),
+    ("summarize_session.v1.txt", include_str!("../../prompts/summarize_session.v1.txt")),
+    ("history_context.v1.txt", include_str!("../../prompts/history_context.v1.txt")),
+    ("weekly_report.v1.txt", include_str!("../../prompts/weekly_report.v1.txt")),
+    ("ask_en.v1.txt", include_str!("../../prompts/ask_en.v1.txt")),
+    ("ask_history_en.v1.txt", include_str!("../../prompts/ask_history_en.v1.txt")),
+    ("plan_today_en.v1.txt", include_str!("../../prompts/plan_today_en.v1.txt")),
+    ("session_summary_en.v1.txt", include_str!("../../prompts/session_summary_en.v1.txt")),
+    ("summarize_session_en.v1.txt", include_str!("../../prompts/summarize_session_en.v1.txt")),
+    ("history_context_en.v1.txt", include_str!("../../prompts/history_context_en.v1.txt")),
+    ("weekly_report_en.v1.txt", include_str!("../../prompts/weekly_report_en.v1.txt")),
+];
+const BUNDLED_EXPERIMENTS: &str = include_str!("../../prompts/experiments.json");
+
+const EXPERIMENTS_FILE: &str = "experiments.json";
+
+#[derive(Debug, Clone, Deserialize)]
+struct ExperimentsFile {
+    #[serde(default)]
+    defaults: HashMap<String, String>,
+    #[serde(default)]
+    experiments: Vec<Experiment>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Experiment {
+    pub key: String,
+    pub template: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub variants: Vec<ExperimentVariant>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExperimentVariant {
+    pub version: String,
+    pub weight: u32,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Template versions chosen for one request.
+/// Recorded on `ai_sessions` so acceptance can be compared per variant.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PromptSelection {
+    /// template name -> version
+    pub versions: BTreeMap<String, String>,
+    /// experiment key -> assigned version
+    pub experiments: BTreeMap<String, String>,
+}
+
+impl PromptSelection {
+    /// Assigned variant of the first active experiment (ai_sessions.experiment_key/variant)
+    pub fn primary_experiment(&self) -> Option<(&str, &str)> {
+        self.experiments
+            .iter()
+            .next()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PromptRegistry {
+    /// name -> version -> body
+    templates: HashMap<String, BTreeMap<String, String>>,
+    defaults: HashMap<String, String>,
+    experiments: Vec<Experiment>,
+}
+
+impl PromptRegistry {
+    /// Load templates from `dir`, falling back to the bundled copies if it does not exist
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        if !dir.is_dir() {
+            tracing::warn!(
+                "Prompt directory {} not found, using bundled templates",
+                dir.display()
+            );
+            return Self::bundled();
+        }
+
+        let mut files = Vec::new();
+        let mut experiments_json = None;
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if file_name == EXPERIMENTS_FILE {
+                experiments_json = Some(std::fs::read_to_string(&path)?);
+            } else if file_name.ends_with(".txt") {
+                files.push((file_name.to_string(), std::fs::read_to_string(&path)?));
+            }
+        }
+
+        let experiments_json = experiments_json
+            .ok_or_else(|| anyhow::anyhow!("{} is missing in {}", EXPERIMENTS_FILE, dir.display()))?;
+        let registry = Self::from_sources(files, &experiments_json)?;
+        tracing::info!(
+            "Loaded prompt templates from {} ({} templates, {} experiments)",
+            dir.display(),
+            registry.templates.len(),
+            registry.experiments.iter().filter(|e| e.enabled).count()
+        );
+        Ok(registry)
+    }
+
+    /// Registry built from the templates compiled into the binary
+    pub fn bundled() -> anyhow::Result<Self> {
+        Self::from_sources(
+            BUNDLED_TEMPLATES
+                .iter()
+                .map(|(name, body)| (name.to_string(), body.to_string())),
+            BUNDLED_EXPERIMENTS,
+        )
+    }
+
+    fn from_sources(
+        files: impl IntoIterator<Item = (String, String)>,
+        experiments_json: &str,
+    ) -> anyhow::Result<Self> {
+        let mut templates: HashMap<String, BTreeMap<String, String>> = HashMap::new();
+        for (file_name, body) in files {
+            let stem = file_name.trim_end_matches(".txt");
+            let (name, version) = stem.split_once('.').ok_or_else(|| {
+                anyhow::anyhow!("Prompt file {} must be named <name>.<version>.txt", file_name)
+            })?;
+            // Editors append a trailing newline; it is not part of the prompt
+            let body = body.strip_suffix('\n').unwrap_or(&body).to_string();
+            templates
+                .entry(name.to_string())
+                .or_default()
+                .insert(version.to_string(), body);
+        }
+
+        let file: ExperimentsFile = serde_json::from_str(experiments_json)
+            .map_err(|e| anyhow::anyhow!("Invalid {}: {}", EXPERIMENTS_FILE, e))?;
+
+        let registry = Self {
+            templates,
+            defaults: file.defaults,
+            experiments: file.experiments,
+        };
+        registry.validate()?;
+        Ok(registry)
+    }
+
+    /// Fail at startup rather than on the first AI request
+    fn validate(&self) -> anyhow::Result<()> {
+        let has = |name: &str, version: &str| {
+            self.templates
+                .get(name)
+                .is_some_and(|versions| versions.contains_key(version))
+        };
+
+        for (name, version) in &self.defaults {
+            if !has(name, version) {
+                anyhow::bail!("Default prompt {}.{} does not exist", name, version);
+            }
+        }
+        for name in self.templates.keys() {
+            if !self.defaults.contains_key(name) {
+                anyhow::bail!("Prompt template {} has no default version", name);
+            }
+        }
+
+        for exp in self.experiments.iter().filter(|e| e.enabled) {
+            if exp.variants.is_empty() || exp.variants.iter().all(|v| v.weight == 0) {
+                anyhow::bail!("Experiment {} has no weighted variants", exp.key);
+            }
+            for variant in &exp.variants {
+                if !has(&exp.template, &variant.version) {
+                    anyhow::bail!(
+                        "Experiment {} references missing prompt {}.{}",
+                        exp.key,
+                        exp.template,
+                        variant.version
+                    );
+                }
+                // A variant that drops e.g. {{persona}} would render fine but silently lose context
+                if let Some(default) = self.defaults.get(&exp.template) {
+                    let versions = &self.templates[&exp.template];
+                    if placeholders(&versions[&variant.version]) != placeholders(&versions[default]) {
+                        anyhow::bail!(
+                            "Experiment {} variant {}.{} must use the same variables as {}.{}",
+                            exp.key,
+                            exp.template,
+                            variant.version,
+                            exp.template,
+                            default
+                        );
+                    }
+                }
+            }
+            if self
+                .experiments
+                .iter()
+                .filter(|e| e.enabled && e.template == exp.template)
+                .count()
+                > 1
+            {
+                anyhow::bail!(
+                    "Only one enabled experiment per template is allowed ({})",
+                    exp.template
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve which version of every template this user gets
+    pub fn select(&self, user_id: &str) -> PromptSelection {
+        let mut selection = PromptSelection {
+            versions: self
+                .defaults
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            experiments: BTreeMap::new(),
+        };
+
+        for exp in self.experiments.iter().filter(|e| e.enabled) {
+            let version = assign_variant(exp, user_id).to_string();
+            selection
+                .versions
+                .insert(exp.template.clone(), version.clone());
+            selection.experiments.insert(exp.key.clone(), version);
+        }
+
+        selection
+    }
+
+    /// Render template `name` at the version chosen in `selection`
+    pub fn render(
+        &self,
+        name: &str,
+        selection: &PromptSelection,
+        vars: &HashMap<&str, String>,
+    ) -> AppResult<String> {
+        let version = selection
+            .versions
+            .get(name)
+            .or_else(|| self.defaults.get(name))
+            .ok_or_else(|| AppError::Internal(format!("Unknown prompt template: {}", name)))?;
+        let body = self
+            .templates
+            .get(name)
+            .and_then(|versions| versions.get(version))
+            .ok_or_else(|| {
+                AppError::Internal(format!("Unknown prompt template: {}.{}", name, version))
+            })?;
+
+        render_template(body, vars)
+            .map_err(|var| AppError::Internal(format!("Prompt {}.{} needs variable {}", name, version, var)))
+    }
+}
+
+/// Substitute `{{var}}` placeholders. Returns the first missing variable name on error.
+fn render_template(body: &str, vars: &HashMap<&str, String>) -> Result<String, String> {
+    let mut out = String::with_capacity(body.len());
+    let mut last = 0;
+    for caps in PLACEHOLDER_REGEX.captures_iter(body) {
+        let whole = caps.get(0).unwrap();
+        let var = &caps[1];
+        let value = vars.get(var).ok_or_else(|| var.to_string())?;
+        out.push_str(&body[last..whole.start()]);
+        out.push_str(value);
+        last = whole.end();
+    }
+    out.push_str(&body[last..]);
+    Ok(out)
+}
+
+/// Variable names referenced by a template body
+fn placeholders(body: &str) -> BTreeSet<&str> {
+    PLACEHOLDER_REGEX
+        .captures_iter(body)
+        .map(|caps| caps.get(1).unwrap().as_str())
+        .collect()
+}
+
+/// Deterministic weighted assignment (FNV-1a, stable across builds unlike DefaultHasher)
+fn assign_variant<'a>(exp: &'a Experiment, user_id: &str) -> &'a str {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in exp.key.bytes().chain([b':']).chain(user_id.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    let total: u64 = exp.variants.iter().map(|v| v.weight as u64).sum();
+    let mut bucket = hash % total;
+    for variant in &exp.variants {
+        if bucket < variant.weight as u64 {
+            return &variant.version;
+        }
+        bucket -= variant.weight as u64;
+    }
+    // Unreachable: bucket < total
+    &exp.variants[0].version
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry(experiments_json: &str) -> anyhow::Result<PromptRegistry> {
+        PromptRegistry::from_sources(
+            vec![
+                ("greet.v1.txt".to_string(), "Hi {{name}}, {\"a\": 1}\n".to_string()),
+                ("greet.v2.txt".to_string(), "Yo {{ name }}!".to_string()),
+            ],
+            experiments_json,
+        )
+    }
+
+    const AB: &str = r#"{
+        "defaults": {"greet": "v1"},
+        "experiments": [{"key": "tone", "template": "greet",
+            "variants": [{"version": "v1", "weight": 50}, {"version": "v2", "weight": 50}]}]
+    }"#;
+
+    #[test]
+    fn test_bundled_templates_are_valid() {
+        let reg = PromptRegistry::bundled().unwrap();
+        let selection = reg.select("user");
+        for name in [
+            "system",
+            "system_en",
+            "ask",
+            "ask_history",
+            "plan_today",
+            "session_summary",
+            "summarize_session",
+            "history_context",
+        ] {
+            assert!(selection.versions.contains_key(name), "{}", name);
+        }
+
+        // English mode (`<name>_en`) must exist for every template
+        for name in reg.templates.keys().filter(|n| !n.ends_with("_en")) {
+            assert!(reg.templates.contains_key(&format!("{}_en", name)), "{}_en", name);
+        }
+
+        // Disabled experiments must also be valid once switched on
+        PromptRegistry::from_sources(
+            BUNDLED_TEMPLATES
+                .iter()
+                .map(|(name, body)| (name.to_string(), body.to_string())),
+            &BUNDLED_EXPERIMENTS.replace("\"enabled\": false", "\"enabled\": true"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_render_substitutes_and_keeps_json_braces() {
+        let reg = registry(r#"{"defaults": {"greet": "v1"}}"#).unwrap();
+        let vars = HashMap::from([("name", "Taro".to_string())]);
+        let out = reg.render("greet", &reg.select("u"), &vars).unwrap();
+        assert_eq!(out, "Hi Taro, {\"a\": 1}");
+    }
+
+    #[test]
+    fn test_render_missing_variable_is_error() {
+        let reg = registry(r#"{"defaults": {"greet": "v1"}}"#).unwrap();
+        assert!(reg.render("greet", &reg.select("u"), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_assignment_is_stable_and_splits_users() {
+        let reg = registry(AB).unwrap();
+        let a = reg.select("user-a");
+        assert_eq!(a.versions, reg.select("user-a").versions);
+        assert_eq!(a.experiments["tone"], a.versions["greet"]);
+
+        let v2_count = (0..1000)
+            .filter(|i| reg.select(&format!("user-{}", i)).versions["greet"] == "v2")
+            .count();
+        assert!((350..650).contains(&v2_count), "v2_count={}", v2_count);
+    }
+
+    #[test]
+    fn test_disabled_experiment_uses_default() {
+        let reg = registry(&AB.replace("\"key\": \"tone\",", "\"key\": \"tone\", \"enabled\": false,")).unwrap();
+        let selection = reg.select("user-a");
+        assert_eq!(selection.versions["greet"], "v1");
+        assert!(selection.experiments.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_config_rejected() {
+        assert!(registry(r#"{"defaults": {"greet": "v9"}}"#).is_err());
+        assert!(registry(r#"{"defaults": {}}"#).is_err());
+        assert!(registry(&AB.replace("\"v2\", \"weight\"", "\"v3\", \"weight\"")).is_err());
+
+        // Variant dropping a variable the default uses
+        let dropped = PromptRegistry::from_sources(
+            vec![
+                ("greet.v1.txt".to_string(), "Hi {{name}}".to_string()),
+                ("greet.v2.txt".to_string(), "Hi".to_string()),
+            ],
+            AB,
+        );
+        assert!(dropped.is_err());
+    }
+}
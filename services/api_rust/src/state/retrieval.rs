@@ -0,0 +1,971 @@
+//! Retrieval over the user's own history (workouts, meals, body metrics, AI sessions)
+//!
+//! Each source row becomes one short text document in `user_history_docs`.
+//! Search is lexical (ilike candidates ranked by term overlap) and, when an
+//! embedder is configured, also by embedding similarity (pgvector). Both lists
+//! are merged with reciprocal rank fusion.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    domain::services::set_parser::{normalize_exercise_name, EXERCISE_ALIASES},
+    error::AppResult,
+    infrastructure::{
+        embedding::{to_pgvector, Embedder},
+        supabase::SupabaseClient,
+    },
+    redaction::mask_pii,
+};
+
+/// Rows per source indexed inline by `sync` (on the ask path, so kept small)
+const RECENT_BATCH: usize = 10;
+/// Rows per source and page of a backfill / rebuild
+const SYNC_BATCH: usize = 50;
+/// Upper bound of batches for a backfill / rebuild (per source)
+const MAX_REBUILD_BATCHES: usize = 40;
+const LEXICAL_CANDIDATES: usize = 100;
+const MAX_QUERY_TERMS: usize = 8;
+const MAX_DOC_CHARS: usize = 600;
+/// Reciprocal rank fusion constant
+const RRF_K: f64 = 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceType {
+    Workout,
+    Meal,
+    BodyMetric,
+    AiSession,
+}
+
+impl SourceType {
+    const ALL: [SourceType; 4] = [
+        SourceType::Workout,
+        SourceType::Meal,
+        SourceType::BodyMetric,
+        SourceType::AiSession,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SourceType::Workout => "workout",
+            SourceType::Meal => "meal",
+            SourceType::BodyMetric => "body_metric",
+            SourceType::AiSession => "ai_session",
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            SourceType::Workout => "workouts",
+            SourceType::Meal => "meals",
+            SourceType::BodyMetric => "body_metrics",
+            SourceType::AiSession => "ai_sessions",
+        }
+    }
+
+    fn select(self) -> &'static str {
+        match self {
+            SourceType::Workout => "id,date,note,created_at,workout_exercises(custom_exercise_name,muscle_tag,exercise_order,exercises(name),workout_sets(set_index,weight_kg,reps,is_warmup))",
+            SourceType::Meal => "id,date,meal_type,note,created_at,meal_items(name,quantity,unit,calories,protein_g)",
+            SourceType::BodyMetric => "id,date,weight_kg,bodyfat_pct,sleep_hours,steps,note,created_at",
+            SourceType::AiSession => "id,intent,summary,created_at,ai_messages(role,content,created_at)",
+        }
+    }
+
+    fn empty_content(self) -> &'static str {
+        match self {
+            SourceType::Workout => "ワークアウト: （種目の記録なし）",
+            SourceType::Meal => "食事: （品目の記録なし）",
+            SourceType::BodyMetric => "体組成: （記録なし）",
+            SourceType::AiSession => "AI相談: （メッセージなし）",
+        }
+    }
+
+    /// Build the document text for one source row
+    fn build_content(self, row: &Value) -> Option<String> {
+        match self {
+            SourceType::Workout => workout_content(row),
+            SourceType::Meal => meal_content(row),
+            SourceType::BodyMetric => body_metric_content(row),
+            SourceType::AiSession => ai_session_content(row),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct HistoryDocRow {
+    user_id: String,
+    source_type: &'static str,
+    source_id: String,
+    doc_date: String,
+    content: String,
+    embedding: Option<String>,
+    embedding_model: Option<String>,
+    source_created_at: String,
+    indexed_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct StoredDoc {
+    id: String,
+    source_type: String,
+    source_id: String,
+    doc_date: String,
+    content: String,
+}
+
+/// A retrieved record, cited in the answer as `[ref_no]`
+#[derive(Debug, Clone, Serialize)]
+pub struct HistorySnippet {
+    #[serde(rename = "ref")]
+    pub ref_no: usize,
+    pub source_type: String,
+    pub source_id: String,
+    pub date: String,
+    pub content: String,
+}
+
+impl HistorySnippet {
+    /// Prompt line: `[1] 2026-09-30 workout: ...`
+    pub fn prompt_line(&self) -> String {
+        format!(
+            "[{}] {} {}: {}",
+            self.ref_no, self.date, self.source_type, self.content
+        )
+    }
+}
+
+/// History index for one user (RLS via the user's access token)
+pub struct HistoryIndex<'a> {
+    supabase: &'a SupabaseClient,
+    access_token: &'a str,
+    embedder: Option<&'a dyn Embedder>,
+}
+
+impl<'a> HistoryIndex<'a> {
+    pub fn new(
+        supabase: &'a SupabaseClient,
+        access_token: &'a str,
+        embedder: Option<&'a dyn Embedder>,
+    ) -> Self {
+        Self {
+            supabase,
+            access_token,
+            embedder,
+        }
+    }
+
+    /// Index the newest source rows created since the last sync (one batch per source,
+    /// newest first) so recent records are searchable right away. Older rows that do not
+    /// fit are left to `backfill`. Returns the number of indexed docs.
+    pub async fn sync(&self, user_id: &str) -> AppResult<usize> {
+        // Sources are independent; this runs on the ask path
+        let counts = futures::future::try_join_all(
+            SourceType::ALL
+                .into_iter()
+                .map(|source| self.sync_source(user_id, source)),
+        )
+        .await?;
+        Ok(counts.into_iter().sum())
+    }
+
+    /// Index every source row that has no doc yet, newest first. Meant for a background
+    /// job: completes a long history or a large import that `sync` only started on.
+    /// Sources whose doc count already matches the row count are skipped.
+    pub async fn backfill(&self, user_id: &str) -> AppResult<usize> {
+        let mut total = 0;
+        for source in SourceType::ALL {
+            let rows = self
+                .supabase
+                .count(source.table(), &format!("user_id=eq.{}", user_id), self.access_token)
+                .await?;
+            let docs = self
+                .supabase
+                .count(
+                    "user_history_docs",
+                    &format!("user_id=eq.{}&source_type=eq.{}", user_id, source.as_str()),
+                    self.access_token,
+                )
+                .await?;
+            if docs >= rows {
+                continue;
+            }
+
+            let mut cursor: Option<(String, String)> = None;
+            for _ in 0..MAX_REBUILD_BATCHES {
+                let page: Vec<Value> = self
+                    .supabase
+                    .select(
+                        source.table(),
+                        &page_query(user_id, "id,created_at", cursor.as_ref()),
+                        self.access_token,
+                    )
+                    .await?;
+                let ids: Vec<&str> = page.iter().filter_map(|r| r["id"].as_str()).collect();
+                let indexed = self.indexed_ids(user_id, source, &ids).await?;
+                let missing: Vec<&str> = ids
+                    .into_iter()
+                    .filter(|id| !indexed.contains(*id))
+                    .collect();
+                if !missing.is_empty() {
+                    let rows: Vec<Value> = self
+                        .supabase
+                        .select(
+                            source.table(),
+                            &format!(
+                                "user_id=eq.{}&id=in.({})&select={}",
+                                user_id,
+                                missing.join(","),
+                                source.select()
+                            ),
+                            self.access_token,
+                        )
+                        .await?;
+                    total += self.index_rows(user_id, source, &rows).await?;
+                }
+
+                if page.len() < SYNC_BATCH {
+                    break;
+                }
+                cursor = page.last().and_then(row_keyset);
+            }
+        }
+        Ok(total)
+    }
+
+    /// Re-index every source row (also picks up edits to old rows), then prune docs whose
+    /// source row is gone. Docs are upserted in place, so a failed rebuild never leaves
+    /// the index emptier than before. Meant for a background job.
+    pub async fn rebuild(&self, user_id: &str) -> AppResult<usize> {
+        let started = Utc::now().to_rfc3339();
+        let mut total = 0;
+        for source in SourceType::ALL {
+            let mut cursor: Option<(String, String)> = None;
+            let mut complete = false;
+            for _ in 0..MAX_REBUILD_BATCHES {
+                let rows: Vec<Value> = self
+                    .supabase
+                    .select(
+                        source.table(),
+                        &page_query(user_id, source.select(), cursor.as_ref()),
+                        self.access_token,
+                    )
+                    .await?;
+                total += self.index_rows(user_id, source, &rows).await?;
+                if rows.len() < SYNC_BATCH {
+                    complete = true;
+                    break;
+                }
+                cursor = rows.last().and_then(row_keyset);
+            }
+
+            // Every doc still older than `started` belongs to a row that no longer exists.
+            // If the walk stopped early, only the range it covered can be judged.
+            let mut prune = format!(
+                "user_id=eq.{}&source_type=eq.{}&indexed_at=lt.{}",
+                user_id,
+                source.as_str(),
+                urlencoding::encode(&started)
+            );
+            match (&cursor, complete) {
+                (_, true) => {}
+                (Some((created_at, _)), false) => {
+                    prune.push_str(&format!(
+                        "&source_created_at=gt.{}",
+                        urlencoding::encode(created_at)
+                    ));
+                }
+                (None, false) => continue,
+            }
+            self.supabase
+                .delete("user_history_docs", &prune, self.access_token)
+                .await?;
+        }
+        Ok(total)
+    }
+
+    async fn sync_source(&self, user_id: &str, source: SourceType) -> AppResult<usize> {
+        // High-water mark: newest indexed source row of this type, as a (created_at, id) keyset
+        let last: Vec<Value> = self
+            .supabase
+            .select(
+                "user_history_docs",
+                &format!(
+                    "user_id=eq.{}&source_type=eq.{}&select=source_created_at,source_id&order=source_created_at.desc,source_id.desc&limit=1",
+                    user_id,
+                    source.as_str()
+                ),
+                self.access_token,
+            )
+            .await?;
+
+        // Newest first: with more than one batch of new rows, the older ones between the
+        // mark and this batch are picked up by `backfill`
+        let mut query = format!(
+            "user_id=eq.{}&select={}&order=created_at.desc,id.desc&limit={}",
+            user_id,
+            source.select(),
+            RECENT_BATCH
+        );
+        if let Some((since, id)) = last.first().and_then(|r| {
+            Some((r["source_created_at"].as_str()?, r["source_id"].as_str()?))
+        }) {
+            query.push('&');
+            query.push_str(&keyset_filter("gt", since, id));
+        }
+        let rows: Vec<Value> = self
+            .supabase
+            .select(source.table(), &query, self.access_token)
+            .await?;
+        self.index_rows(user_id, source, &rows).await
+    }
+
+    /// Rebuild the docs of already-indexed rows matching `filter` (e.g. `id=eq.<uuid>`)
+    /// after the source rows were edited. Rows not indexed yet are left to `sync`/`backfill`.
+    pub async fn reindex(&self, user_id: &str, source: SourceType, filter: &str) -> AppResult<usize> {
+        let rows: Vec<Value> = self
+            .supabase
+            .select(
+                source.table(),
+                &format!(
+                    "user_id=eq.{}&{}&select={}&limit={}",
+                    user_id,
+                    filter,
+                    source.select(),
+                    SYNC_BATCH
+                ),
+                self.access_token,
+            )
+            .await?;
+        let ids: Vec<&str> = rows.iter().filter_map(|r| r["id"].as_str()).collect();
+        let indexed = self.indexed_ids(user_id, source, &ids).await?;
+        let rows: Vec<Value> = rows
+            .into_iter()
+            .filter(|r| r["id"].as_str().is_some_and(|id| indexed.contains(id)))
+            .collect();
+        self.index_rows(user_id, source, &rows).await
+    }
+
+    /// Which of `ids` already have a doc
+    async fn indexed_ids(
+        &self,
+        user_id: &str,
+        source: SourceType,
+        ids: &[&str],
+    ) -> AppResult<HashSet<String>> {
+        if ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+        let docs: Vec<Value> = self
+            .supabase
+            .select(
+                "user_history_docs",
+                &format!(
+                    "user_id=eq.{}&source_type=eq.{}&source_id=in.({})&select=source_id",
+                    user_id,
+                    source.as_str(),
+                    ids.join(",")
+                ),
+                self.access_token,
+            )
+            .await?;
+        Ok(docs
+            .iter()
+            .filter_map(|d| d["source_id"].as_str().map(String::from))
+            .collect())
+    }
+
+    /// Drop the doc of a deleted source row so it is never retrieved or cited
+    pub async fn remove(&self, user_id: &str, source: SourceType, source_id: &str) -> AppResult<()> {
+        self.supabase
+            .delete(
+                "user_history_docs",
+                &format!(
+                    "user_id=eq.{}&source_type=eq.{}&source_id=eq.{}",
+                    user_id,
+                    source.as_str(),
+                    source_id
+                ),
+                self.access_token,
+            )
+            .await
+    }
+
+    /// Build and upsert the docs for source rows. Returns the number of upserted docs.
+    async fn index_rows(&self, user_id: &str, source: SourceType, rows: &[Value]) -> AppResult<usize> {
+        if rows.is_empty() {
+            return Ok(0);
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let mut docs = Vec::with_capacity(rows.len());
+        for row in rows {
+            let (Some(source_id), Some(created_at)) =
+                (row["id"].as_str(), row["created_at"].as_str())
+            else {
+                continue;
+            };
+            // Rows without text are still stored so they are not reported missing again
+            let content = source
+                .build_content(row)
+                .unwrap_or_else(|| source.empty_content().to_string());
+            let doc_date = row["date"]
+                .as_str()
+                .map(String::from)
+                .or_else(|| {
+                    DateTime::parse_from_rfc3339(created_at)
+                        .ok()
+                        .map(|t| t.date_naive().to_string())
+                })
+                .unwrap_or_default();
+
+            let (embedding, embedding_model) = match self.embedder {
+                // PII is never sent to the embedding provider
+                Some(embedder) => match embedder.embed(&mask_pii(&content)).await {
+                    Ok(values) => (
+                        Some(to_pgvector(&values)),
+                        Some(embedder.model().to_string()),
+                    ),
+                    Err(e) => {
+                        tracing::warn!("History embedding failed ({}): {}", source.as_str(), e);
+                        (None, None)
+                    }
+                },
+                None => (None, None),
+            };
+
+            docs.push(HistoryDocRow {
+                user_id: user_id.to_string(),
+                source_type: source.as_str(),
+                source_id: source_id.to_string(),
+                doc_date,
+                content,
+                embedding,
+                embedding_model,
+                source_created_at: created_at.to_string(),
+                indexed_at: now.clone(),
+            });
+        }
+
+        if !docs.is_empty() {
+            self.supabase
+                .upsert(
+                    "user_history_docs",
+                    &docs,
+                    "source_type,source_id",
+                    self.access_token,
+                )
+                .await?;
+        }
+        Ok(docs.len())
+    }
+
+    /// Top `limit` records relevant to `query`
+    pub async fn search(
+        &self,
+        user_id: &str,
+        query: &str,
+        limit: usize,
+    ) -> AppResult<Vec<HistorySnippet>> {
+        let mut docs: HashMap<String, StoredDoc> = HashMap::new();
+        let mut rankings: Vec<Vec<String>> = Vec::new();
+
+        // Lexical
+        let terms = query_terms(query);
+        if !terms.is_empty() {
+            let or = terms
+                .iter()
+                .map(|t| format!("content.ilike.*{}*", t))
+                .collect::<Vec<_>>()
+                .join(",");
+            let candidates: Vec<StoredDoc> = self
+                .supabase
+                .select(
+                    "user_history_docs",
+                    &format!(
+                        "user_id=eq.{}&or=({})&select=id,source_type,source_id,doc_date,content&order=doc_date.desc&limit={}",
+                        user_id,
+                        urlencoding::encode(&or),
+                        LEXICAL_CANDIDATES
+                    ),
+                    self.access_token,
+                )
+                .await?;
+
+            let ranked = rank_lexical(&terms, &candidates);
+            rankings.push(ranked.iter().map(|&i| candidates[i].id.clone()).collect());
+            for doc in candidates {
+                docs.insert(doc.id.clone(), doc);
+            }
+        }
+
+        // Embedding (optional)
+        if let Some(embedder) = self.embedder {
+            match embedder.embed(&mask_pii(query)).await {
+                Ok(values) => {
+                    let matches: Vec<StoredDoc> = self
+                        .supabase
+                        .rpc(
+                            "match_user_history_docs",
+                            &serde_json::json!({
+                                "query_embedding": to_pgvector(&values),
+                                "match_count": limit * 2,
+                            }),
+                            self.access_token,
+                        )
+                        .await?;
+                    rankings.push(matches.iter().map(|d| d.id.clone()).collect());
+                    for doc in matches {
+                        docs.entry(doc.id.clone()).or_insert(doc);
+                    }
+                }
+                Err(e) => tracing::warn!("Query embedding failed: {}", e),
+            }
+        }
+
+        Ok(fuse_rankings(&rankings)
+            .into_iter()
+            .filter_map(|id| docs.remove(&id))
+            .take(limit)
+            .enumerate()
+            .map(|(i, doc)| HistorySnippet {
+                ref_no: i + 1,
+                source_type: doc.source_type,
+                source_id: doc.source_id,
+                date: doc.doc_date,
+                content: doc.content,
+            })
+            .collect())
+    }
+}
+
+/// PostgREST filter for rows after (`gt`) or before (`lt`) `(created_at, id)` in
+/// `(created_at, id)` order. `created_at` alone is not unique: rows inserted in one
+/// transaction (e.g. an import batch) share `now()`, and a plain `gt` would skip them.
+fn keyset_filter(op: &str, created_at: &str, id: &str) -> String {
+    let or = format!(
+        "created_at.{op}.\"{ts}\",and(created_at.eq.\"{ts}\",id.{op}.{id})",
+        op = op,
+        ts = created_at,
+        id = id
+    );
+    format!("or=({})", urlencoding::encode(&or))
+}
+
+/// One page of a newest-first walk over a source table, continuing below `cursor`
+fn page_query(user_id: &str, select: &str, cursor: Option<&(String, String)>) -> String {
+    let mut query = format!(
+        "user_id=eq.{}&select={}&order=created_at.desc,id.desc&limit={}",
+        user_id, select, SYNC_BATCH
+    );
+    if let Some((created_at, id)) = cursor {
+        query.push('&');
+        query.push_str(&keyset_filter("lt", created_at, id));
+    }
+    query
+}
+
+/// `(created_at, id)` of a source row, the cursor for the next page
+fn row_keyset(row: &Value) -> Option<(String, String)> {
+    Some((
+        row["created_at"].as_str()?.to_string(),
+        row["id"].as_str()?.to_string(),
+    ))
+}
+
+// =============================================================================
+// Document builders
+// =============================================================================
+
+fn fmt_num(v: &Value) -> Option<String> {
+    v.as_f64().map(|n| {
+        if n.fract() == 0.0 {
+            format!("{}", n as i64)
+        } else {
+            format!("{:.1}", n)
+        }
+    })
+}
+
+fn truncate_doc(mut text: String) -> String {
+    if text.chars().count() > MAX_DOC_CHARS {
+        text = text.chars().take(MAX_DOC_CHARS).collect::<String>() + "…";
+    }
+    text
+}
+
+fn workout_content(row: &Value) -> Option<String> {
+    let mut exercises: Vec<&Value> = row["workout_exercises"].as_array()?.iter().collect();
+    exercises.sort_by_key(|e| e["exercise_order"].as_i64().unwrap_or(0));
+
+    let parts: Vec<String> = exercises
+        .iter()
+        .map(|e| {
+            let name = e["exercises"]["name"]
+                .as_str()
+                .or_else(|| e["custom_exercise_name"].as_str())
+                .unwrap_or("種目");
+            let mut sets: Vec<&Value> = e["workout_sets"]
+                .as_array()
+                .map(|s| s.iter().collect())
+                .unwrap_or_default();
+            sets.sort_by_key(|s| s["set_index"].as_i64().unwrap_or(0));
+            let sets: Vec<String> = sets
+                .iter()
+                .filter(|s| !s["is_warmup"].as_bool().unwrap_or(false))
+                .filter_map(|s| {
+                    let reps = s["reps"].as_i64()?;
+                    Some(match fmt_num(&s["weight_kg"]) {
+                        Some(w) if w != "0" => format!("{}kg×{}", w, reps),
+                        _ => format!("{}回", reps),
+                    })
+                })
+                .collect();
+            if sets.is_empty() {
+                name.to_string()
+            } else {
+                format!("{} {}", name, sets.join(", "))
+            }
+        })
+        .collect();
+    if parts.is_empty() {
+        return None;
+    }
+
+    let mut text = format!("ワークアウト: {}", parts.join(" / "));
+    if let Some(note) = row["note"].as_str().filter(|n| !n.is_empty()) {
+        text.push_str(&format!("（メモ: {}）", note));
+    }
+    Some(truncate_doc(text))
+}
+
+fn meal_content(row: &Value) -> Option<String> {
+    let items: Vec<String> = row["meal_items"]
+        .as_array()?
+        .iter()
+        .filter_map(|i| {
+            let name = i["name"].as_str()?;
+            let mut s = name.to_string();
+            if let (Some(q), Some(unit)) = (fmt_num(&i["quantity"]), i["unit"].as_str()) {
+                if unit != "serving" {
+                    s.push_str(&format!(" {}{}", q, unit));
+                }
+            }
+            if let Some(kcal) = i["calories"].as_i64() {
+                s.push_str(&format!(" {}kcal", kcal));
+            }
+            Some(s)
+        })
+        .collect();
+    if items.is_empty() {
+        return None;
+    }
+
+    let meal_type = match row["meal_type"].as_str().unwrap_or_default() {
+        "breakfast" => "朝食",
+        "lunch" => "昼食",
+        "dinner" => "夕食",
+        "snack" => "間食",
+        "pre_workout" => "トレ前",
+        "post_workout" => "トレ後",
+        _ => "食事",
+    };
+    let mut text = format!("{}: {}", meal_type, items.join(", "));
+    if let Some(note) = row["note"].as_str().filter(|n| !n.is_empty()) {
+        text.push_str(&format!("（メモ: {}）", note));
+    }
+    Some(truncate_doc(text))
+}
+
+fn body_metric_content(row: &Value) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(w) = fmt_num(&row["weight_kg"]) {
+        parts.push(format!("体重 {}kg", w));
+    }
+    if let Some(bf) = fmt_num(&row["bodyfat_pct"]) {
+        parts.push(format!("体脂肪率 {}%", bf));
+    }
+    if let Some(sleep) = fmt_num(&row["sleep_hours"]) {
+        parts.push(format!("睡眠 {}時間", sleep));
+    }
+    if let Some(steps) = row["steps"].as_i64() {
+        parts.push(format!("歩数 {}歩", steps));
+    }
+    if let Some(note) = row["note"].as_str().filter(|n| !n.is_empty()) {
+        parts.push(format!("メモ: {}", note));
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    Some(truncate_doc(format!("体組成: {}", parts.join(", "))))
+}
+
+fn ai_session_content(row: &Value) -> Option<String> {
+    let body = match row["summary"].as_str().filter(|s| !s.trim().is_empty()) {
+        Some(summary) => summary.to_string(),
+        None => {
+            let mut messages: Vec<&Value> = row["ai_messages"].as_array()?.iter().collect();
+            messages.sort_by(|a, b| {
+                a["created_at"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .cmp(b["created_at"].as_str().unwrap_or_default())
+            });
+            let text = messages
+                .iter()
+                .take(4)
+                .filter_map(|m| {
+                    let role = if m["role"].as_str()? == "user" {
+                        "Q"
+                    } else {
+                        "A"
+                    };
+                    Some(format!("{}: {}", role, m["content"].as_str()?))
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            if text.is_empty() {
+                return None;
+            }
+            text
+        }
+    };
+    Some(truncate_doc(format!("AI相談: {}", body)))
+}
+
+// =============================================================================
+// Ranking
+// =============================================================================
+
+fn is_term_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '.' || c == 'ー'
+}
+
+/// Search terms from a question: alphanumeric / kana / kanji runs of 2+ chars,
+/// plus canonical exercise names for known aliases ("sq" -> "スクワット").
+/// Only characters that are safe inside a PostgREST `or=(...ilike...)` filter are kept.
+pub fn query_terms(query: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for term in query_term_groups(query).into_iter().flatten() {
+        if !out.contains(&term) {
+            out.push(term);
+        }
+    }
+    out.truncate(MAX_QUERY_TERMS);
+    out
+}
+
+/// `query_terms` grouped per word: a word and its canonical exercise name are
+/// alternatives (`["ベンチプレス", "ベンチ"]`), so "all words must match" filters
+/// should OR within a group and AND across groups.
+pub fn query_term_groups(query: &str) -> Vec<Vec<String>> {
+    use unicode_normalization::UnicodeNormalization;
+
+    let normalized: String = query.nfkc().collect::<String>().to_lowercase();
+
+    // Split on script changes too, so "スクワット120kg" -> ["スクワット", "120kg"]
+    let mut terms: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut last_class = 0u8;
+    let class = |c: char| -> u8 {
+        match c {
+            '\u{3040}'..='\u{309F}' => 1, // hiragana (particles split words)
+            '\u{30A0}'..='\u{30FF}' => 2, // katakana
+            '\u{4E00}'..='\u{9FFF}' => 3, // kanji
+            _ => 4,                       // latin / digits
+        }
+    };
+    for c in normalized.chars() {
+        if !is_term_char(c) {
+            if !current.is_empty() {
+                terms.push(std::mem::take(&mut current));
+            }
+            last_class = 0;
+            continue;
+        }
+        let k = class(c);
+        // Digits followed by a unit stay together ("120kg")
+        if !current.is_empty() && k != last_class {
+            terms.push(std::mem::take(&mut current));
+        }
+        current.push(c);
+        last_class = k;
+    }
+    if !current.is_empty() {
+        terms.push(current);
+    }
+
+    let mut out: Vec<Vec<String>> = Vec::new();
+    for term in terms {
+        // Hiragana runs are mostly particles / inflections
+        if term.chars().all(|c| class(c) == 1) {
+            continue;
+        }
+        let is_number = term.chars().next().is_some_and(|c| c.is_ascii_digit());
+        if term.chars().count() < 2 && !is_number {
+            continue;
+        }
+        let mut group = Vec::new();
+        let key = normalize_exercise_name(&term);
+        if let Some((_, canonical)) = EXERCISE_ALIASES
+            .iter()
+            .find(|(alias, _)| normalize_exercise_name(alias) == key)
+        {
+            if *canonical != term {
+                group.push(canonical.to_string());
+            }
+        }
+        group.push(term);
+        if !out.contains(&group) {
+            out.push(group);
+        }
+    }
+    out.truncate(MAX_QUERY_TERMS);
+    out
+}
+
+/// Order candidate indices by matched terms (longer terms weigh more), newest first on ties
+fn rank_lexical(terms: &[String], docs: &[StoredDoc]) -> Vec<usize> {
+    let mut scored: Vec<(usize, f64)> = docs
+        .iter()
+        .enumerate()
+        .filter_map(|(i, doc)| {
+            let content = doc.content.to_lowercase();
+            let score: f64 = terms
+                .iter()
+                .filter(|t| content.contains(t.as_str()))
+                .map(|t| (t.chars().count() as f64).sqrt())
+                .sum();
+            (score > 0.0).then_some((i, score))
+        })
+        .collect();
+    // Candidates arrive newest first; stable sort keeps that order on ties
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    scored.into_iter().map(|(i, _)| i).collect()
+}
+
+/// Reciprocal rank fusion of several rankings (ids, best first)
+fn fuse_rankings(rankings: &[Vec<String>]) -> Vec<String> {
+    let mut scores: HashMap<&str, f64> = HashMap::new();
+    let mut first_seen: Vec<&str> = Vec::new();
+    for ranking in rankings {
+        for (rank, id) in ranking.iter().enumerate() {
+            let entry = scores.entry(id.as_str()).or_insert_with(|| {
+                first_seen.push(id.as_str());
+                0.0
+            });
+            *entry += 1.0 / (RRF_K + rank as f64 + 1.0);
+        }
+    }
+    let mut ids = first_seen;
+    ids.sort_by(|a, b| {
+        scores[b]
+            .partial_cmp(&scores[a])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    ids.into_iter().map(String::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(id: &str, content: &str) -> StoredDoc {
+        StoredDoc {
+            id: id.to_string(),
+            source_type: "workout".to_string(),
+            source_id: id.to_string(),
+            doc_date: "2026-10-01".to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_query_terms() {
+        let cases: &[(&str, &[&str])] = &[
+            (
+                "最後にスクワット120kgを挙げたのはいつ？",
+                &["最後", "スクワット", "120kg"],
+            ),
+            ("ベンチのベスト", &["ベンチプレス", "ベンチ", "ベスト"]),
+            (
+                "when did I last sq 100kg",
+                &["when", "did", "last", "スクワット", "sq", "100kg"],
+            ),
+            ("a,b),(c*", &[]),
+        ];
+        for (query, expected) in cases {
+            assert_eq!(query_terms(query), *expected, "query {:?}", query);
+        }
+    }
+
+    #[test]
+    fn test_query_term_groups() {
+        assert_eq!(
+            query_term_groups("ベンチのベスト"),
+            vec![vec!["ベンチプレス", "ベンチ"], vec!["ベスト"]]
+        );
+        assert_eq!(query_term_groups("スクワット"), vec![vec!["スクワット"]]);
+    }
+
+    #[test]
+    fn test_keyset_filter() {
+        let filter = keyset_filter("gt", "2026-10-18T09:00:00.123+00:00", "b0c1");
+        assert_eq!(
+            urlencoding::decode(&filter).unwrap(),
+            "or=(created_at.gt.\"2026-10-18T09:00:00.123+00:00\",and(created_at.eq.\"2026-10-18T09:00:00.123+00:00\",id.gt.b0c1))"
+        );
+        // '+' in the offset must not turn into a space
+        assert!(!filter.contains('+'));
+
+        let cursor = ("2026-10-18T09:00:00+00:00".to_string(), "b0c1".to_string());
+        let query = page_query("u1", "id,created_at", Some(&cursor));
+        assert!(query.starts_with("user_id=eq.u1&select=id,created_at&order=created_at.desc,id.desc&limit=50&or="));
+        assert!(urlencoding::decode(&query).unwrap().contains("id.lt.b0c1"));
+    }
+
+    #[test]
+    fn test_rank_lexical() {
+        let docs = vec![
+            doc("a", "ワークアウト: ベンチプレス 80kg×8"),
+            doc("b", "ワークアウト: スクワット 120kg×5"),
+            doc("c", "ワークアウト: スクワット 100kg×5"),
+            doc("d", "朝食: 卵"),
+        ];
+        let terms = vec!["スクワット".to_string(), "120kg".to_string()];
+        assert_eq!(rank_lexical(&terms, &docs), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_fuse_rankings() {
+        let lexical = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let semantic = vec!["c".to_string(), "d".to_string()];
+        let fused = fuse_rankings(&[lexical, semantic]);
+        assert_eq!(fused[0], "c");
+        assert_eq!(fused.len(), 4);
+    }
+
+    #[test]
+    fn test_workout_content() {
+        let row = serde_json::json!({
+            "id": "w1", "date": "2026-10-01", "note": null, "created_at": "2026-10-01T10:00:00Z",
+            "workout_exercises": [
+                {"exercise_order": 2, "custom_exercise_name": null, "muscle_tag": "chest",
+                 "exercises": {"name": "ベンチプレス"},
+                 "workout_sets": [{"set_index": 1, "weight_kg": 80, "reps": 8, "is_warmup": false}]},
+                {"exercise_order": 1, "custom_exercise_name": "懸垂", "muscle_tag": "back",
+                 "exercises": null,
+                 "workout_sets": [
+                    {"set_index": 2, "weight_kg": 0, "reps": 10, "is_warmup": false},
+                    {"set_index": 1, "weight_kg": 0, "reps": 5, "is_warmup": true}]}
+            ]
+        });
+        assert_eq!(
+            workout_content(&row).unwrap(),
+            "ワークアウト: 懸垂 10回 / ベンチプレス 80kg×8"
+        );
+    }
+}
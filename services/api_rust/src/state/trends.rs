@@ -0,0 +1,373 @@
+//! `UserState` v2 sections (trends over recent history)
+
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use super::calculate_e1rm;
+use super::weekly_report::{
+    exercise_key, round1, week_start_of, working_sets, MuscleGroupVolume, ReportWorkout,
+    WeightEntry,
+};
+
+/// History scanned for best lifts
+pub const STRENGTH_LOOKBACK_DAYS: i64 = 180;
+/// Weeks of per-muscle volume (current week included)
+const VOLUME_WEEKS: i64 = 4;
+const MAX_BEST_LIFTS: usize = 8;
+/// Best lifts set this recently are flagged as new PRs
+const RECENT_PR_DAYS: i64 = 14;
+const MAX_CONTEXT_ITEMS: usize = 20;
+
+/// Injuries / equipment from the profile (`constraints` / `environment`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrainingContext {
+    pub injuries: Vec<Injury>,
+    pub equipment: Vec<String>,
+    /// "gym" / "home"
+    pub locations: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Injury {
+    pub part: String,
+    pub severity: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StrengthState {
+    /// Best e1RM per exercise over the last 180 days
+    pub best_lifts: Vec<BestLift>,
+    /// Oldest -> newest (Monday weeks)
+    pub weekly_volume: Vec<WeekVolume>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BestLift {
+    pub exercise: String,
+    pub muscle_tag: String,
+    pub e1rm: f64,
+    pub weight_kg: f64,
+    pub reps: i32,
+    pub date: NaiveDate,
+    /// Set within the last 14 days
+    pub recent_pr: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeekVolume {
+    pub week_start: NaiveDate,
+    pub sets: i32,
+    pub volume_kg: f64,
+    pub muscles: Vec<MuscleGroupVolume>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecoveryState {
+    pub sleep_avg_7d: Option<f64>,
+    pub sleep_days_logged: i32,
+    pub steps_avg_7d: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MetricsEntry {
+    pub date: NaiveDate,
+    pub weight_kg: Option<f64>,
+    pub sleep_hours: Option<f64>,
+    pub steps: Option<i32>,
+}
+
+impl MetricsEntry {
+    pub fn weight(&self) -> WeightEntry {
+        WeightEntry {
+            date: self.date,
+            weight_kg: self.weight_kg,
+        }
+    }
+}
+
+fn json_strings(value: &serde_json::Value) -> impl Iterator<Item = String> + '_ {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Onboarding stores `{"gym": bool, "home": bool, "equipment": [..]}` and
+/// `[{"part": "膝", "severity": "mild"}]`; older profiles may have plain strings.
+pub fn training_context(
+    environment: &serde_json::Value,
+    constraints: &serde_json::Value,
+) -> TrainingContext {
+    let injuries = constraints
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|c| match c {
+            serde_json::Value::String(part) => Some(Injury {
+                part: part.trim().to_string(),
+                severity: None,
+            }),
+            serde_json::Value::Object(map) => Some(Injury {
+                part: map.get("part")?.as_str()?.trim().to_string(),
+                severity: map
+                    .get("severity")
+                    .and_then(|s| s.as_str())
+                    .map(String::from),
+            }),
+            _ => None,
+        })
+        .filter(|i| !i.part.is_empty())
+        .take(MAX_CONTEXT_ITEMS)
+        .collect();
+
+    let mut equipment: Vec<String> = json_strings(&environment["equipment"]).collect();
+    equipment.dedup();
+    equipment.truncate(MAX_CONTEXT_ITEMS);
+
+    let locations = ["gym", "home"]
+        .into_iter()
+        .filter(|k| environment[*k].as_bool() == Some(true))
+        .map(String::from)
+        .collect();
+
+    TrainingContext {
+        injuries,
+        equipment,
+        locations,
+    }
+}
+
+/// Best lifts and weekly volume per muscle up to `end_date`
+pub fn strength_state(workouts: &[ReportWorkout], end_date: NaiveDate) -> StrengthState {
+    let first_week = week_start_of(end_date) - Duration::days(7 * (VOLUME_WEEKS - 1));
+    let recent_from = end_date - Duration::days(RECENT_PR_DAYS);
+
+    let mut best: HashMap<String, BestLift> = HashMap::new();
+    // week_start -> muscle -> (sets, volume)
+    let mut weeks: BTreeMap<NaiveDate, BTreeMap<String, (i32, f64)>> = BTreeMap::new();
+    for i in 0..VOLUME_WEEKS {
+        weeks.insert(first_week + Duration::days(7 * i), BTreeMap::new());
+    }
+
+    for workout in workouts.iter().filter(|w| w.date <= end_date) {
+        let week = week_start_of(workout.date);
+        for exercise in &workout.workout_exercises {
+            let (key, name) = exercise_key(exercise);
+            for (weight, reps) in working_sets(exercise) {
+                if let Some(muscles) = weeks.get_mut(&week) {
+                    let entry = muscles.entry(exercise.muscle_tag.clone()).or_default();
+                    entry.0 += 1;
+                    entry.1 += weight * reps as f64;
+                }
+
+                let e1rm = calculate_e1rm(weight, reps);
+                let current = best.get(&key).map(|b| b.e1rm).unwrap_or(0.0);
+                if e1rm > current {
+                    best.insert(
+                        key.clone(),
+                        BestLift {
+                            exercise: name.clone(),
+                            muscle_tag: exercise.muscle_tag.clone(),
+                            e1rm,
+                            weight_kg: weight,
+                            reps,
+                            date: workout.date,
+                            recent_pr: false,
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    let mut best_lifts: Vec<BestLift> = best
+        .into_values()
+        .map(|b| BestLift {
+            recent_pr: b.date >= recent_from,
+            ..b
+        })
+        .collect();
+    best_lifts.sort_by(|a, b| {
+        b.e1rm
+            .partial_cmp(&a.e1rm)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.exercise.cmp(&b.exercise))
+    });
+    best_lifts.truncate(MAX_BEST_LIFTS);
+
+    let weekly_volume = weeks
+        .into_iter()
+        .map(|(week_start, muscles)| {
+            let mut muscles: Vec<MuscleGroupVolume> = muscles
+                .into_iter()
+                .map(|(muscle_tag, (sets, volume))| MuscleGroupVolume {
+                    muscle_tag,
+                    sets,
+                    volume_kg: round1(volume),
+                })
+                .collect();
+            muscles.sort_by(|a, b| b.sets.cmp(&a.sets).then(a.muscle_tag.cmp(&b.muscle_tag)));
+            WeekVolume {
+                week_start,
+                sets: muscles.iter().map(|m| m.sets).sum(),
+                volume_kg: round1(muscles.iter().map(|m| m.volume_kg).sum()),
+                muscles,
+            }
+        })
+        .collect();
+
+    StrengthState {
+        best_lifts,
+        weekly_volume,
+    }
+}
+
+/// Sleep / steps averages over the 7 days ending at `end_date`
+pub fn recovery_state(metrics: &[MetricsEntry], end_date: NaiveDate) -> RecoveryState {
+    let start = end_date - Duration::days(6);
+    let window: Vec<&MetricsEntry> = metrics
+        .iter()
+        .filter(|m| m.date >= start && m.date <= end_date)
+        .collect();
+    let sleep: Vec<f64> = window.iter().filter_map(|m| m.sleep_hours).collect();
+    let steps: Vec<f64> = window
+        .iter()
+        .filter_map(|m| m.steps)
+        .map(f64::from)
+        .collect();
+    let mean = |values: &[f64]| {
+        (!values.is_empty()).then(|| round1(values.iter().sum::<f64>() / values.len() as f64))
+    };
+
+    RecoveryState {
+        sleep_avg_7d: mean(&sleep),
+        sleep_days_logged: sleep.len() as i32,
+        steps_avg_7d: mean(&steps).map(f64::round),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::weekly_report::{ExerciseName, ReportExercise, ReportSet};
+
+    fn d(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn workout(date: &str, name: &str, muscle: &str, sets: &[(f64, i32)]) -> ReportWorkout {
+        ReportWorkout {
+            date: d(date),
+            workout_exercises: vec![ReportExercise {
+                exercise_id: Some(format!("id-{}", name)),
+                custom_exercise_name: None,
+                muscle_tag: muscle.to_string(),
+                exercises: Some(ExerciseName {
+                    name: name.to_string(),
+                }),
+                workout_sets: sets
+                    .iter()
+                    .map(|&(w, r)| ReportSet {
+                        weight_kg: Some(w),
+                        reps: Some(r),
+                        is_warmup: false,
+                    })
+                    .collect(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_training_context() {
+        let cases = [
+            (
+                serde_json::json!({"gym": true, "home": false, "equipment": ["dumbbell", "bench"]}),
+                serde_json::json!([{"part": "膝", "severity": "mild"}, "腰", 3]),
+                TrainingContext {
+                    injuries: vec![
+                        Injury {
+                            part: "膝".to_string(),
+                            severity: Some("mild".to_string()),
+                        },
+                        Injury {
+                            part: "腰".to_string(),
+                            severity: None,
+                        },
+                    ],
+                    equipment: vec!["dumbbell".to_string(), "bench".to_string()],
+                    locations: vec!["gym".to_string()],
+                },
+            ),
+            (
+                serde_json::json!({}),
+                serde_json::json!([]),
+                TrainingContext::default(),
+            ),
+        ];
+        for (environment, constraints, expected) in cases {
+            assert_eq!(training_context(&environment, &constraints), expected);
+        }
+    }
+
+    #[test]
+    fn test_strength_state() {
+        // 2026-10-18 is a Sunday: weeks 09-21, 09-28, 10-05, 10-12
+        let workouts = vec![
+            workout("2026-06-01", "ベンチプレス", "chest", &[(90.0, 5)]),
+            workout(
+                "2026-10-06",
+                "ベンチプレス",
+                "chest",
+                &[(80.0, 8), (80.0, 8)],
+            ),
+            workout("2026-10-13", "スクワット", "legs", &[(120.0, 5)]),
+            workout("2026-10-20", "スクワット", "legs", &[(200.0, 5)]),
+        ];
+        let state = strength_state(&workouts, d("2026-10-18"));
+
+        assert_eq!(state.best_lifts.len(), 2);
+        assert_eq!(state.best_lifts[0].exercise, "スクワット");
+        assert!(state.best_lifts[0].recent_pr);
+        // June 90 x 5 (e1RM 105.0) still beats October 80 x 8 (101.33)
+        assert_eq!(state.best_lifts[1].weight_kg, 90.0);
+        assert!(!state.best_lifts[1].recent_pr);
+
+        assert_eq!(state.weekly_volume.len(), 4);
+        assert_eq!(state.weekly_volume[0].week_start, d("2026-09-21"));
+        assert_eq!(state.weekly_volume[2].sets, 2);
+        assert_eq!(state.weekly_volume[2].volume_kg, 1280.0);
+        assert_eq!(state.weekly_volume[3].muscles[0].muscle_tag, "legs");
+    }
+
+    #[test]
+    fn test_recovery_state() {
+        let metrics = vec![
+            MetricsEntry {
+                date: d("2026-10-10"),
+                weight_kg: None,
+                sleep_hours: Some(4.0),
+                steps: None,
+            },
+            MetricsEntry {
+                date: d("2026-10-17"),
+                weight_kg: None,
+                sleep_hours: Some(7.0),
+                steps: Some(8000),
+            },
+            MetricsEntry {
+                date: d("2026-10-18"),
+                weight_kg: Some(70.0),
+                sleep_hours: Some(6.5),
+                steps: Some(10001),
+            },
+        ];
+        let state = recovery_state(&metrics, d("2026-10-18"));
+        assert_eq!(state.sleep_avg_7d, Some(6.8));
+        assert_eq!(state.sleep_days_logged, 2);
+        assert_eq!(state.steps_avg_7d, Some(9001.0));
+    }
+}
@@ -0,0 +1,725 @@
+//! Weekly progress digest (Monday-Sunday, JST)
+//!
+//! `ai_weekly_reports` rows are queued every Monday by `public.run_weekly_report()`
+//! (pg_cron). The numbers are computed here with the user's token; the narrative
+//! is written by the LLM when the report is delivered to `ai_inbox_messages`.
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::error::AppResult;
+use crate::infrastructure::supabase::{NutritionDaily, SupabaseClient};
+
+use super::calculate_e1rm;
+use super::persona::Language;
+
+/// History scanned for previous bests when detecting PRs
+const PR_LOOKBACK_DAYS: i64 = 365;
+const MAX_PRS: usize = 5;
+const MAX_MUSCLE_GROUPS: usize = 8;
+/// Calories within ±10% of the target count as on target
+const CALORIE_TOLERANCE: f64 = 0.10;
+/// Protein at 90%+ of the target counts as on target
+const PROTEIN_MIN_RATIO: f64 = 0.90;
+/// Weekly average change (kg) below this is "stable"
+const WEIGHT_STABLE_KG: f64 = 0.2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeeklyStats {
+    pub week_start: NaiveDate,
+    pub week_end: NaiveDate,
+    pub training: TrainingStats,
+    pub prs: Vec<PersonalRecord>,
+    pub nutrition: NutritionAdherence,
+    pub weight: WeightTrend,
+    /// One point per day (7 entries) for charts
+    pub daily: Vec<DailyPoint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrainingStats {
+    pub sessions: i32,
+    pub training_days: i32,
+    pub working_sets: i32,
+    pub total_volume_kg: f64,
+    pub prev_week_volume_kg: f64,
+    pub volume_change_pct: Option<f64>,
+    pub muscle_groups: Vec<MuscleGroupVolume>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MuscleGroupVolume {
+    pub muscle_tag: String,
+    pub sets: i32,
+    pub volume_kg: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersonalRecord {
+    pub exercise: String,
+    pub date: NaiveDate,
+    pub weight_kg: f64,
+    pub reps: i32,
+    pub e1rm: f64,
+    pub previous_e1rm: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NutritionAdherence {
+    pub days_logged: i32,
+    pub avg_calories: Option<f64>,
+    pub avg_protein_g: Option<f64>,
+    pub target_calories: Option<i32>,
+    pub target_protein_g: Option<f64>,
+    /// Logged days with calories within ±10% of the target
+    pub calorie_days_on_target: i32,
+    /// Logged days with protein at 90%+ of the target
+    pub protein_days_on_target: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeightTrend {
+    pub entries: i32,
+    pub start_kg: Option<f64>,
+    pub end_kg: Option<f64>,
+    pub change_kg: Option<f64>,
+    pub avg_kg: Option<f64>,
+    pub prev_week_avg_kg: Option<f64>,
+    pub trend: Option<String>, // "up", "down", "stable"
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyPoint {
+    pub date: NaiveDate,
+    pub volume_kg: f64,
+    pub calories: Option<i32>,
+    pub protein_g: Option<f64>,
+    pub weight_kg: Option<f64>,
+}
+
+// Rows as returned by the nested workouts query
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportWorkout {
+    pub date: NaiveDate,
+    #[serde(default)]
+    pub workout_exercises: Vec<ReportExercise>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportExercise {
+    pub exercise_id: Option<String>,
+    pub custom_exercise_name: Option<String>,
+    pub muscle_tag: String,
+    pub exercises: Option<ExerciseName>,
+    #[serde(default)]
+    pub workout_sets: Vec<ReportSet>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExerciseName {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportSet {
+    pub weight_kg: Option<f64>,
+    pub reps: Option<i32>,
+    #[serde(default)]
+    pub is_warmup: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WeightEntry {
+    pub date: NaiveDate,
+    pub weight_kg: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NutritionTargets {
+    pub target_calories: Option<i32>,
+    pub target_protein_g: Option<f64>,
+}
+
+/// Monday of the week containing `date`
+pub fn week_start_of(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// Monday of the last fully completed week before `today`
+pub fn last_completed_week(today: NaiveDate) -> NaiveDate {
+    week_start_of(today) - Duration::days(7)
+}
+
+/// Today's date in JST (reports follow the app's calendar, not UTC)
+pub fn today_jst() -> NaiveDate {
+    let jst = chrono::FixedOffset::east_opt(9 * 3600).unwrap();
+    chrono::Utc::now().with_timezone(&jst).date_naive()
+}
+
+pub(crate) fn round1(v: f64) -> f64 {
+    (v * 10.0).round() / 10.0
+}
+
+pub(crate) fn exercise_key(exercise: &ReportExercise) -> (String, String) {
+    let name = exercise
+        .exercises
+        .as_ref()
+        .map(|e| e.name.clone())
+        .or_else(|| exercise.custom_exercise_name.clone())
+        .unwrap_or_else(|| "不明な種目".to_string());
+    let key = exercise
+        .exercise_id
+        .clone()
+        .unwrap_or_else(|| format!("custom:{}", name.to_lowercase()));
+    (key, name)
+}
+
+/// Working sets with a usable weight and reps
+pub(crate) fn working_sets(exercise: &ReportExercise) -> impl Iterator<Item = (f64, i32)> + '_ {
+    exercise
+        .workout_sets
+        .iter()
+        .filter_map(|s| match (s.weight_kg, s.reps) {
+            (Some(w), Some(r)) if !s.is_warmup && w > 0.0 && r > 0 => Some((w, r)),
+            _ => None,
+        })
+}
+
+/// Build the digest for the week starting at `week_start`.
+///
+/// `workouts` should cover `PR_LOOKBACK_DAYS` before the week (previous bests and
+/// last week's volume), `weights` the previous week as well.
+pub fn summarize_week(
+    week_start: NaiveDate,
+    workouts: &[ReportWorkout],
+    nutrition: &[NutritionDaily],
+    weights: &[WeightEntry],
+    targets: &NutritionTargets,
+) -> WeeklyStats {
+    let week_end = week_start + Duration::days(6);
+    let prev_start = week_start - Duration::days(7);
+    let in_week = |d: NaiveDate| d >= week_start && d <= week_end;
+
+    // --- Training ---
+    let mut sessions = 0;
+    let mut training_days: Vec<NaiveDate> = Vec::new();
+    let mut working_set_count = 0;
+    let mut total_volume = 0.0;
+    let mut prev_volume = 0.0;
+    let mut daily_volume: HashMap<NaiveDate, f64> = HashMap::new();
+    let mut muscles: BTreeMap<String, (i32, f64)> = BTreeMap::new();
+    // key -> (name, best e1rm this week with set/date)
+    let mut week_best: HashMap<String, (String, NaiveDate, f64, i32, f64)> = HashMap::new();
+    let mut previous_best: HashMap<String, f64> = HashMap::new();
+
+    for workout in workouts {
+        let current = in_week(workout.date);
+        if current {
+            sessions += 1;
+            if !training_days.contains(&workout.date) {
+                training_days.push(workout.date);
+            }
+        } else if workout.date > week_end {
+            continue;
+        }
+
+        for exercise in &workout.workout_exercises {
+            let (key, name) = exercise_key(exercise);
+            for (weight, reps) in working_sets(exercise) {
+                let volume = weight * reps as f64;
+                let e1rm = calculate_e1rm(weight, reps);
+                if current {
+                    working_set_count += 1;
+                    total_volume += volume;
+                    *daily_volume.entry(workout.date).or_default() += volume;
+                    let entry = muscles.entry(exercise.muscle_tag.clone()).or_default();
+                    entry.0 += 1;
+                    entry.1 += volume;
+
+                    let best = week_best
+                        .entry(key.clone())
+                        .or_insert_with(|| (name.clone(), workout.date, weight, reps, 0.0));
+                    if e1rm > best.4 {
+                        *best = (name.clone(), workout.date, weight, reps, e1rm);
+                    }
+                } else {
+                    if workout.date >= prev_start {
+                        prev_volume += volume;
+                    }
+                    let prev = previous_best.entry(key.clone()).or_default();
+                    if e1rm > *prev {
+                        *prev = e1rm;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut muscle_groups: Vec<MuscleGroupVolume> = muscles
+        .into_iter()
+        .map(|(muscle_tag, (sets, volume))| MuscleGroupVolume {
+            muscle_tag,
+            sets,
+            volume_kg: round1(volume),
+        })
+        .collect();
+    muscle_groups.sort_by(|a, b| b.sets.cmp(&a.sets).then(a.muscle_tag.cmp(&b.muscle_tag)));
+    muscle_groups.truncate(MAX_MUSCLE_GROUPS);
+
+    // PR = this week's best beats every logged set in the lookback window.
+    // First-time exercises have nothing to beat and are not counted.
+    let mut prs: Vec<PersonalRecord> = week_best
+        .into_iter()
+        .filter_map(|(key, (exercise, date, weight_kg, reps, e1rm))| {
+            let previous = *previous_best.get(&key)?;
+            (e1rm > previous).then_some(PersonalRecord {
+                exercise,
+                date,
+                weight_kg,
+                reps,
+                e1rm,
+                previous_e1rm: previous,
+            })
+        })
+        .collect();
+    prs.sort_by(|a, b| {
+        let gain_a = a.e1rm / a.previous_e1rm;
+        let gain_b = b.e1rm / b.previous_e1rm;
+        gain_b
+            .partial_cmp(&gain_a)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.exercise.cmp(&b.exercise))
+    });
+    prs.truncate(MAX_PRS);
+
+    let volume_change_pct =
+        (prev_volume > 0.0).then(|| round1((total_volume - prev_volume) / prev_volume * 100.0));
+
+    // --- Nutrition ---
+    let week_nutrition: Vec<&NutritionDaily> = nutrition
+        .iter()
+        .filter(|n| {
+            NaiveDate::parse_from_str(&n.date, "%Y-%m-%d").is_ok_and(in_week) && n.meals_logged > 0
+        })
+        .collect();
+
+    // --- Daily series ---
+    let mut week_weights: Vec<(NaiveDate, f64)> = weights
+        .iter()
+        .filter_map(|w| Some((w.date, w.weight_kg?)))
+        .filter(|(d, _)| in_week(*d))
+        .collect();
+    week_weights.sort_by_key(|(d, _)| *d);
+    let daily = (0..7)
+        .map(|i| {
+            let date = week_start + Duration::days(i);
+            let n = week_nutrition
+                .iter()
+                .find(|n| NaiveDate::parse_from_str(&n.date, "%Y-%m-%d").ok() == Some(date));
+            DailyPoint {
+                date,
+                volume_kg: round1(daily_volume.get(&date).copied().unwrap_or(0.0)),
+                calories: n.map(|n| n.calories),
+                protein_g: n.map(|n| round1(n.protein_g)),
+                weight_kg: week_weights
+                    .iter()
+                    .rev()
+                    .find(|(d, _)| *d == date)
+                    .map(|(_, w)| *w),
+            }
+        })
+        .collect();
+
+    WeeklyStats {
+        week_start,
+        week_end,
+        training: TrainingStats {
+            sessions,
+            training_days: training_days.len() as i32,
+            working_sets: working_set_count,
+            total_volume_kg: round1(total_volume),
+            prev_week_volume_kg: round1(prev_volume),
+            volume_change_pct,
+            muscle_groups,
+        },
+        prs,
+        nutrition: nutrition_adherence(&week_nutrition, targets),
+        weight: weight_trend(weights, week_start, week_end),
+        daily,
+    }
+}
+
+/// Averages and days on target over logged days (`meals_logged > 0`)
+pub fn nutrition_adherence(
+    days: &[&NutritionDaily],
+    targets: &NutritionTargets,
+) -> NutritionAdherence {
+    let days_logged = days.len() as i32;
+    let avg = |f: fn(&NutritionDaily) -> f64| {
+        (days_logged > 0)
+            .then(|| round1(days.iter().map(|n| f(n)).sum::<f64>() / days_logged as f64))
+    };
+    let calorie_days_on_target = match targets.target_calories.filter(|t| *t > 0) {
+        Some(target) => days
+            .iter()
+            .filter(|n| ((n.calories - target) as f64).abs() <= target as f64 * CALORIE_TOLERANCE)
+            .count() as i32,
+        None => 0,
+    };
+    let protein_days_on_target = match targets.target_protein_g.filter(|t| *t > 0.0) {
+        Some(target) => days
+            .iter()
+            .filter(|n| n.protein_g >= target * PROTEIN_MIN_RATIO)
+            .count() as i32,
+        None => 0,
+    };
+
+    NutritionAdherence {
+        days_logged,
+        avg_calories: avg(|n| n.calories as f64).map(f64::round),
+        avg_protein_g: avg(|n| n.protein_g),
+        target_calories: targets.target_calories,
+        target_protein_g: targets.target_protein_g,
+        calorie_days_on_target,
+        protein_days_on_target,
+    }
+}
+
+/// Weight over `start..=end` compared with the 7 days before `start`
+pub fn weight_trend(weights: &[WeightEntry], start: NaiveDate, end: NaiveDate) -> WeightTrend {
+    let prev_start = start - Duration::days(7);
+    let mut current: Vec<(NaiveDate, f64)> = weights
+        .iter()
+        .filter_map(|w| Some((w.date, w.weight_kg?)))
+        .filter(|(d, _)| *d >= start && *d <= end)
+        .collect();
+    current.sort_by_key(|(d, _)| *d);
+    let prev: Vec<f64> = weights
+        .iter()
+        .filter(|w| w.date >= prev_start && w.date < start)
+        .filter_map(|w| w.weight_kg)
+        .collect();
+    let mean = |values: &[f64]| {
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    };
+    let values: Vec<f64> = current.iter().map(|(_, w)| *w).collect();
+    let avg_kg = mean(&values);
+    let prev_week_avg_kg = mean(&prev);
+    let start_kg = current.first().map(|(_, w)| *w);
+    let end_kg = current.last().map(|(_, w)| *w);
+    let trend = match (avg_kg, prev_week_avg_kg) {
+        (Some(now), Some(prev)) if now - prev > WEIGHT_STABLE_KG => Some("up"),
+        (Some(now), Some(prev)) if prev - now > WEIGHT_STABLE_KG => Some("down"),
+        (Some(_), Some(_)) => Some("stable"),
+        _ => None,
+    };
+
+    WeightTrend {
+        entries: current.len() as i32,
+        start_kg,
+        end_kg,
+        change_kg: start_kg.zip(end_kg).map(|(s, e)| round1(e - s)),
+        avg_kg: avg_kg.map(round1),
+        prev_week_avg_kg: prev_week_avg_kg.map(round1),
+        trend: trend.map(String::from),
+    }
+}
+
+impl WeeklyStats {
+    /// Nothing logged this week (the narrative would be empty)
+    pub fn is_empty(&self) -> bool {
+        self.training.sessions == 0 && self.nutrition.days_logged == 0 && self.weight.entries == 0
+    }
+
+    /// Plain digest used when the LLM narrative is unavailable
+    pub fn fallback_narrative(&self, lang: Language) -> String {
+        let en = lang == Language::En;
+        let (start, end) = (self.week_start.format("%-m/%-d"), self.week_end.format("%-m/%-d"));
+        let mut lines = vec![if en {
+            format!("Your review for {}-{}.", start, end)
+        } else {
+            format!("{}〜{} の振り返りです。", start, end)
+        }];
+        let t = &self.training;
+        if t.sessions > 0 {
+            let mut line = if en {
+                format!(
+                    "Training: {} days, {} sets, total volume {} kg",
+                    t.training_days, t.working_sets, t.total_volume_kg
+                )
+            } else {
+                format!(
+                    "トレーニング {}日・{}セット、総ボリューム {}kg",
+                    t.training_days, t.working_sets, t.total_volume_kg
+                )
+            };
+            if let Some(pct) = t.volume_change_pct {
+                line.push_str(&if en {
+                    format!(" ({:+}% vs last week)", pct)
+                } else {
+                    format!("（先週比 {:+}%）", pct)
+                });
+            }
+            lines.push(line);
+        } else if en {
+            lines.push("No workouts were logged this week.".to_string());
+        } else {
+            lines.push("今週はトレーニングの記録がありませんでした。".to_string());
+        }
+        for pr in &self.prs {
+            lines.push(if en {
+                format!(
+                    "Personal best: {} {} kg × {} (est. 1RM {} kg)",
+                    pr.exercise, pr.weight_kg, pr.reps, pr.e1rm
+                )
+            } else {
+                format!(
+                    "自己ベスト: {} {}kg × {}回（推定1RM {}kg）",
+                    pr.exercise, pr.weight_kg, pr.reps, pr.e1rm
+                )
+            });
+        }
+        let n = &self.nutrition;
+        if n.days_logged > 0 {
+            let (kcal, protein) = (n.avg_calories.unwrap_or(0.0), n.avg_protein_g.unwrap_or(0.0));
+            lines.push(if en {
+                format!(
+                    "Meals logged on {}/7 days, avg {} kcal and {} g protein",
+                    n.days_logged, kcal, protein
+                )
+            } else {
+                format!(
+                    "食事記録 {}/7日、平均 {}kcal・たんぱく質 {}g",
+                    n.days_logged, kcal, protein
+                )
+            });
+        }
+        if let (Some(avg), Some(change)) = (self.weight.avg_kg, self.weight.change_kg) {
+            lines.push(if en {
+                format!("Weight: avg {} kg ({:+} kg over the week)", avg, change)
+            } else {
+                format!("体重 平均 {}kg（週内 {:+}kg）", avg, change)
+            });
+        }
+        lines.join("\n")
+    }
+}
+
+/// Fetches the rows for one week with the user's token (RLS applies)
+pub struct WeeklyReportBuilder<'a> {
+    supabase: &'a SupabaseClient,
+    access_token: &'a str,
+}
+
+impl<'a> WeeklyReportBuilder<'a> {
+    pub fn new(supabase: &'a SupabaseClient, access_token: &'a str) -> Self {
+        Self {
+            supabase,
+            access_token,
+        }
+    }
+
+    pub async fn build(&self, user_id: &str, week_start: NaiveDate) -> AppResult<WeeklyStats> {
+        let week_end = week_start + Duration::days(6);
+        let lookback = week_start - Duration::days(PR_LOOKBACK_DAYS);
+        let prev_start = week_start - Duration::days(7);
+
+        let workouts_query = format!(
+            "user_id=eq.{}&date=gte.{}&date=lte.{}&select=date,workout_exercises(exercise_id,custom_exercise_name,muscle_tag,exercises(name),workout_sets(weight_kg,reps,is_warmup))&order=date.asc",
+            user_id, lookback, week_end
+        );
+        let workouts: Vec<ReportWorkout> = self
+            .supabase
+            .select("workouts", &workouts_query, self.access_token)
+            .await?;
+
+        let nutrition_query = format!(
+            "user_id=eq.{}&date=gte.{}&date=lte.{}&select=*",
+            user_id, week_start, week_end
+        );
+        let nutrition: Vec<NutritionDaily> = self
+            .supabase
+            .select("nutrition_daily", &nutrition_query, self.access_token)
+            .await?;
+
+        let weights_query = format!(
+            "user_id=eq.{}&date=gte.{}&date=lte.{}&weight_kg=not.is.null&select=date,weight_kg&order=date.asc",
+            user_id, prev_start, week_end
+        );
+        let weights: Vec<WeightEntry> = self
+            .supabase
+            .select("body_metrics", &weights_query, self.access_token)
+            .await?;
+
+        let targets: Option<NutritionTargets> = self
+            .supabase
+            .select_single(
+                "user_profiles",
+                &format!(
+                    "user_id=eq.{}&select=target_calories,target_protein_g",
+                    user_id
+                ),
+                self.access_token,
+            )
+            .await?;
+
+        Ok(summarize_week(
+            week_start,
+            &workouts,
+            &nutrition,
+            &weights,
+            &targets.unwrap_or_default(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn workout(
+        date: &str,
+        exercise_id: &str,
+        muscle: &str,
+        sets: &[(f64, i32, bool)],
+    ) -> ReportWorkout {
+        ReportWorkout {
+            date: d(date),
+            workout_exercises: vec![ReportExercise {
+                exercise_id: Some(exercise_id.to_string()),
+                custom_exercise_name: None,
+                muscle_tag: muscle.to_string(),
+                exercises: Some(ExerciseName {
+                    name: exercise_id.to_string(),
+                }),
+                workout_sets: sets
+                    .iter()
+                    .map(|&(w, r, warmup)| ReportSet {
+                        weight_kg: Some(w),
+                        reps: Some(r),
+                        is_warmup: warmup,
+                    })
+                    .collect(),
+            }],
+        }
+    }
+
+    fn nutrition(date: &str, calories: i32, protein_g: f64) -> NutritionDaily {
+        NutritionDaily {
+            id: String::new(),
+            user_id: String::new(),
+            date: date.to_string(),
+            calories,
+            protein_g,
+            fat_g: 0.0,
+            carbs_g: 0.0,
+            fiber_g: None,
+            meals_logged: 3,
+        }
+    }
+
+    #[test]
+    fn test_week_boundaries() {
+        // 2026-10-18 is a Sunday
+        assert_eq!(week_start_of(d("2026-10-18")), d("2026-10-12"));
+        assert_eq!(week_start_of(d("2026-10-12")), d("2026-10-12"));
+        assert_eq!(last_completed_week(d("2026-10-19")), d("2026-10-12"));
+        assert_eq!(last_completed_week(d("2026-10-18")), d("2026-10-05"));
+    }
+
+    #[test]
+    fn test_summarize_week() {
+        let week = d("2026-10-12");
+        let workouts = vec![
+            // Older best (not last week): e1rm 100 * (1 + 5/30) = 116.67
+            workout("2026-08-01", "bench", "chest", &[(100.0, 5, false)]),
+            // Last week: 1000kg volume
+            workout("2026-10-06", "squat", "legs", &[(100.0, 10, false)]),
+            // This week: bench PR (102.5 x 5 = 119.58), warmup ignored
+            workout(
+                "2026-10-13",
+                "bench",
+                "chest",
+                &[(60.0, 10, true), (102.5, 5, false), (90.0, 8, false)],
+            ),
+            // First-time exercise is not a PR
+            workout("2026-10-15", "deadlift", "back", &[(140.0, 5, false)]),
+            // Next week is ignored
+            workout("2026-10-19", "bench", "chest", &[(120.0, 5, false)]),
+        ];
+        let nutrition = vec![
+            nutrition("2026-10-12", 2300, 150.0),
+            nutrition("2026-10-13", 3000, 100.0),
+            nutrition("2026-10-11", 2400, 150.0),
+        ];
+        let weights = vec![
+            WeightEntry {
+                date: d("2026-10-07"),
+                weight_kg: Some(71.0),
+            },
+            WeightEntry {
+                date: d("2026-10-12"),
+                weight_kg: Some(70.4),
+            },
+            WeightEntry {
+                date: d("2026-10-18"),
+                weight_kg: Some(70.0),
+            },
+        ];
+        let targets = NutritionTargets {
+            target_calories: Some(2400),
+            target_protein_g: Some(150.0),
+        };
+
+        let stats = summarize_week(week, &workouts, &nutrition, &weights, &targets);
+
+        assert_eq!(stats.week_end, d("2026-10-18"));
+        assert_eq!(stats.training.sessions, 2);
+        assert_eq!(stats.training.working_sets, 3);
+        // 512.5 + 720 + 700
+        assert_eq!(stats.training.total_volume_kg, 1932.5);
+        assert_eq!(stats.training.prev_week_volume_kg, 1000.0);
+        assert_eq!(stats.training.volume_change_pct, Some(93.3));
+        assert_eq!(stats.training.muscle_groups[0].muscle_tag, "chest");
+        assert_eq!(stats.training.muscle_groups[0].sets, 2);
+
+        assert_eq!(stats.prs.len(), 1);
+        assert_eq!(stats.prs[0].exercise, "bench");
+        assert_eq!(stats.prs[0].weight_kg, 102.5);
+        assert_eq!(stats.prs[0].previous_e1rm, calculate_e1rm(100.0, 5));
+
+        assert_eq!(stats.nutrition.days_logged, 2);
+        assert_eq!(stats.nutrition.avg_calories, Some(2650.0));
+        assert_eq!(stats.nutrition.calorie_days_on_target, 1);
+        assert_eq!(stats.nutrition.protein_days_on_target, 1);
+
+        assert_eq!(stats.weight.entries, 2);
+        assert_eq!(stats.weight.change_kg, Some(-0.4));
+        assert_eq!(stats.weight.prev_week_avg_kg, Some(71.0));
+        assert_eq!(stats.weight.trend.as_deref(), Some("down"));
+
+        assert_eq!(stats.daily.len(), 7);
+        assert_eq!(stats.daily[1].volume_kg, 1232.5);
+        assert_eq!(stats.daily[6].weight_kg, Some(70.0));
+        assert!(!stats.is_empty());
+    }
+
+    #[test]
+    fn test_empty_week() {
+        let stats = summarize_week(d("2026-10-12"), &[], &[], &[], &NutritionTargets::default());
+        assert!(stats.is_empty());
+        assert_eq!(stats.training.volume_change_pct, None);
+        assert_eq!(stats.weight.trend, None);
+        assert!(stats
+            .fallback_narrative(Language::Ja)
+            .contains("記録がありませんでした"));
+        assert!(stats
+            .fallback_narrative(Language::En)
+            .contains("No workouts were logged"));
+    }
+}
@@ -0,0 +1,235 @@
+-- アカウント削除 (DELETE /v1/users/me)
+-- - public.delete_my_account() が呼び出しユーザーの行を全テーブルから1トランザクションで削除し、
+--   最後に auth.users の行を削除する（auth.sessions / refresh_tokens も連鎖削除され、全端末のセッションが失効する）
+-- - user-photos のオブジェクトは RPC の前に API 側で削除し（リトライあり。本人の JWT が
+--   有効なうちにストレージ RLS で削除する）、その件数を RPC に渡してレシートに記録する
+--   （RPC が失敗した場合は行と auth.users が残り、再実行で削除を完了できる）
+-- - 削除レシートは監査用に残す（個人データは含めず、テーブル別の削除件数のみ）
+
+create table if not exists public.account_deletion_receipts (
+  id uuid primary key,
+  -- auth.users は削除済みのため外部キーは張らない
+  user_id uuid not null,
+  requested_at timestamptz not null,
+  completed_at timestamptz not null default now(),
+  deleted_rows jsonb not null default '{}'::jsonb,
+  storage_objects_deleted integer not null default 0,
+  storage_objects_failed integer not null default 0,
+  sessions_revoked boolean not null default false
+);
+
+comment on table public.account_deletion_receipts is 'アカウント削除の監査レシート（サービスロールのみ参照）';
+comment on column public.account_deletion_receipts.deleted_rows is 'テーブル名 -> 削除行数';
+comment on column public.account_deletion_receipts.storage_objects_deleted is 'user-photos から削除したオブジェクト数';
+comment on column public.account_deletion_receipts.storage_objects_failed is '削除に失敗したオブジェクト数（手動対応が必要）';
+comment on column public.account_deletion_receipts.sessions_revoked is 'Supabase Auth の全セッションが失効したか（auth.users 削除による連鎖削除）';
+
+create index if not exists idx_account_deletion_receipts_user_id
+  on public.account_deletion_receipts (user_id);
+
+-- ポリシーなし: authenticated からは読み書き不可（delete_my_account 経由でのみ作成）
+alter table public.account_deletion_receipts enable row level security;
+
+drop function if exists public.record_account_deletion_storage(uuid, integer, integer);
+drop function if exists public.delete_my_account(uuid, timestamptz);
+
+create or replace function public.delete_my_account(
+  p_receipt_id uuid,
+  p_requested_at timestamptz,
+  p_storage_objects_deleted integer default 0,
+  p_storage_objects_failed integer default 0
+)
+returns jsonb
+language plpgsql
+security definer
+set search_path = public, pg_temp
+as $$
+declare
+  v_user_id uuid := auth.uid();
+  v_rows jsonb := '{}'::jsonb;
+  v_n integer;
+  v_completed_at timestamptz;
+begin
+  -- SECURITY: 自分自身のアカウントのみ削除可能
+  if v_user_id is null then
+    raise exception 'Unauthorized: authentication required';
+  end if;
+
+  -- 掲示板: 自分の投稿・コメントに付いた他ユーザーの反応も含めて削除
+  delete from public.comment_likes
+  where user_id = v_user_id
+     or comment_id in (
+       select c.id from public.post_comments c
+       where c.user_id = v_user_id
+          or c.post_id in (select p.id from public.posts p where p.user_id = v_user_id)
+     );
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('comment_likes', v_n);
+
+  update public.post_comments
+  set reply_to_user_id = null
+  where reply_to_user_id = v_user_id
+    and user_id <> v_user_id;
+
+  delete from public.post_comments
+  where user_id = v_user_id
+     or post_id in (select p.id from public.posts p where p.user_id = v_user_id);
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('post_comments', v_n);
+
+  delete from public.post_likes
+  where user_id = v_user_id
+     or post_id in (select p.id from public.posts p where p.user_id = v_user_id);
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('post_likes', v_n);
+
+  delete from public.posts where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('posts', v_n);
+
+  delete from public.user_blocks
+  where blocker_user_id = v_user_id or blocked_user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('user_blocks', v_n);
+
+  -- トレーニング
+  delete from public.workout_cardio
+  where workout_exercise_id in (
+    select we.id from public.workout_exercises we
+    join public.workouts w on w.id = we.workout_id
+    where w.user_id = v_user_id
+  );
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('workout_cardio', v_n);
+
+  delete from public.workout_sets
+  where workout_exercise_id in (
+    select we.id from public.workout_exercises we
+    join public.workouts w on w.id = we.workout_id
+    where w.user_id = v_user_id
+  );
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('workout_sets', v_n);
+
+  delete from public.workout_exercises
+  where workout_id in (select w.id from public.workouts w where w.user_id = v_user_id);
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('workout_exercises', v_n);
+
+  delete from public.workouts where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('workouts', v_n);
+
+  delete from public.exercises where created_by = v_user_id and is_system = false;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('exercises', v_n);
+
+  -- 体組成
+  delete from public.body_metrics where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('body_metrics', v_n);
+
+  delete from public.body_measurements where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('body_measurements', v_n);
+
+  delete from public.progress_photos where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('progress_photos', v_n);
+
+  -- 食事
+  delete from public.meal_items
+  where meal_id in (select m.id from public.meals m where m.user_id = v_user_id);
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('meal_items', v_n);
+
+  delete from public.meals where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('meals', v_n);
+
+  delete from public.nutrition_daily where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('nutrition_daily', v_n);
+
+  -- AI
+  delete from public.ai_safety_events where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('ai_safety_events', v_n);
+
+  delete from public.ai_recommendations
+  where session_id in (select s.id from public.ai_sessions s where s.user_id = v_user_id);
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('ai_recommendations', v_n);
+
+  delete from public.ai_messages
+  where session_id in (select s.id from public.ai_sessions s where s.user_id = v_user_id);
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('ai_messages', v_n);
+
+  delete from public.ai_sessions where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('ai_sessions', v_n);
+
+  delete from public.ai_jobs where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('ai_jobs', v_n);
+
+  delete from public.ai_weekly_reports where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('ai_weekly_reports', v_n);
+
+  delete from public.ai_inbox_messages where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('ai_inbox_messages', v_n);
+
+  delete from public.user_history_docs where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('user_history_docs', v_n);
+
+  -- アカウント
+  delete from public.user_photos where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('user_photos', v_n);
+
+  delete from public.user_push_tokens where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('user_push_tokens', v_n);
+
+  delete from public.user_subscriptions where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('user_subscriptions', v_n);
+
+  delete from public.support_contacts where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('support_contacts', v_n);
+
+  delete from public.user_profiles where user_id = v_user_id;
+  get diagnostics v_n = row_count;
+  v_rows := v_rows || jsonb_build_object('user_profiles', v_n);
+
+  -- auth.users の削除で auth.sessions / refresh_tokens も削除される（同一トランザクション）
+  insert into public.account_deletion_receipts (
+    id, user_id, requested_at, deleted_rows,
+    storage_objects_deleted, storage_objects_failed, sessions_revoked
+  )
+  values (
+    p_receipt_id, v_user_id, p_requested_at, v_rows,
+    coalesce(p_storage_objects_deleted, 0), coalesce(p_storage_objects_failed, 0), true
+  )
+  returning completed_at into v_completed_at;
+
+  delete from auth.users where id = v_user_id;
+
+  return jsonb_build_object(
+    'receipt_id', p_receipt_id,
+    'completed_at', v_completed_at,
+    'deleted_rows', v_rows
+  );
+end;
+$$;
+
+comment on function public.delete_my_account(uuid, timestamptz, integer, integer) is
+  '呼び出しユーザーの全データと auth.users を削除し、事前に行ったストレージ削除の件数とともに削除レシートを返す。Authorization: 本人のみ。';
+
+revoke all on function public.delete_my_account(uuid, timestamptz, integer, integer) from public, anon;
+grant execute on function public.delete_my_account(uuid, timestamptz, integer, integer) to authenticated;
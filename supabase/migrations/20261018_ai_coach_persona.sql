@@ -0,0 +1,27 @@
+-- =============================================================================
+-- AI coach persona / language (user_profiles)
+-- - ai_persona selects the coach's tone, ai_language the answer language (the
+--   prompt templates and canned safety answers) and
+--   the unit / number formatting of the system instruction
+--   (services/api_rust/src/state/persona.rs).
+-- - Updated via PATCH /v1/users/profile.
+-- =============================================================================
+
+alter table public.user_profiles
+  add column if not exists ai_persona text not null default 'friendly_buddy',
+  add column if not exists ai_language text not null default 'ja';
+
+alter table public.user_profiles
+  drop constraint if exists user_profiles_ai_persona_check;
+alter table public.user_profiles
+  add constraint user_profiles_ai_persona_check
+    check (ai_persona in ('strict_coach', 'friendly_buddy', 'science'));
+
+alter table public.user_profiles
+  drop constraint if exists user_profiles_ai_language_check;
+alter table public.user_profiles
+  add constraint user_profiles_ai_language_check
+    check (ai_language in ('ja', 'en'));
+
+comment on column public.user_profiles.ai_persona is 'AIコーチの口調（strict_coach: 鬼コーチ / friendly_buddy: 友達 / science: 科学派）';
+comment on column public.user_profiles.ai_language is 'AIの回答言語（ja / en）';
@@ -0,0 +1,59 @@
+-- =============================================================================
+-- Async AI jobs (ai_jobs)
+-- - POST /v1/ai/jobs stores the request here and returns the job id at once;
+--   an in-process worker pool runs ask / plan_today and writes result or error.
+-- - (user_id, request_id) is unique: a retried submit returns the same job
+--   instead of calling the model (and billing) again.
+-- - Clients poll GET /v1/ai/jobs/:id or wait for the 'ai_job' push.
+-- =============================================================================
+
+create table if not exists public.ai_jobs (
+  id uuid primary key default gen_random_uuid(),
+  user_id uuid not null references auth.users(id) on delete cascade,
+  request_id uuid not null,
+  kind text not null check (kind in ('ask', 'plan_today')),
+  status text not null default 'queued' check (status in ('queued', 'running', 'succeeded', 'failed')),
+  request jsonb not null,
+  result jsonb,
+  error jsonb,
+  created_at timestamptz not null default now(),
+  started_at timestamptz,
+  finished_at timestamptz,
+
+  constraint ai_jobs_request_unique
+    unique (user_id, request_id)
+);
+
+comment on table public.ai_jobs is 'AI非同期ジョブ（質問・今日のメニュー生成）';
+comment on column public.ai_jobs.request_id is 'クライアント発行のID（再送時に同じジョブを返すための冪等キー）';
+comment on column public.ai_jobs.status is 'queued: 実行待ち / running: 実行中 / succeeded: 完了 / failed: 失敗';
+comment on column public.ai_jobs.request is '送信されたリクエスト内容';
+comment on column public.ai_jobs.result is '成功時のレスポンス（AskResponse / PlanTodayResponse）';
+comment on column public.ai_jobs.error is '失敗時のエラー内容（error / message）';
+
+create index if not exists idx_ai_jobs_user_created
+  on public.ai_jobs (user_id, created_at desc);
+
+alter table public.ai_jobs enable row level security;
+
+drop policy if exists "ai_jobs_select_own" on public.ai_jobs;
+drop policy if exists "ai_jobs_insert_own" on public.ai_jobs;
+drop policy if exists "ai_jobs_update_own" on public.ai_jobs;
+drop policy if exists "ai_jobs_delete_own" on public.ai_jobs;
+
+create policy "ai_jobs_select_own" on public.ai_jobs
+  for select
+  using ((select auth.uid()) = user_id);
+
+create policy "ai_jobs_insert_own" on public.ai_jobs
+  for insert
+  with check ((select auth.uid()) = user_id);
+
+create policy "ai_jobs_update_own" on public.ai_jobs
+  for update
+  using ((select auth.uid()) = user_id)
+  with check ((select auth.uid()) = user_id);
+
+create policy "ai_jobs_delete_own" on public.ai_jobs
+  for delete
+  using ((select auth.uid()) = user_id);
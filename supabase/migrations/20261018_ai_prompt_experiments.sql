@@ -0,0 +1,20 @@
+-- =============================================================================
+-- Prompt template versions / A/B experiments on ai_sessions
+-- - Records which prompt template versions (services/api_rust/prompts) were used
+--   so recommendation acceptance (ai_recommendations.is_applied) can be
+--   compared per experiment variant.
+-- =============================================================================
+
+alter table public.ai_sessions
+  add column if not exists prompt_versions jsonb not null default '{}'::jsonb,
+  add column if not exists experiment_key text,
+  add column if not exists experiment_variant text;
+
+comment on column public.ai_sessions.prompt_versions is '使用したプロンプトテンプレートのバージョン（テンプレート名 -> バージョン）';
+comment on column public.ai_sessions.experiment_key is '割り当てられたA/Bテストのキー';
+comment on column public.ai_sessions.experiment_variant is '割り当てられたA/Bテストのバリアント（テンプレートのバージョン）';
+
+-- バリアント別の提案採用率の集計用
+create index if not exists idx_ai_sessions_experiment
+  on public.ai_sessions (experiment_key, experiment_variant)
+  where experiment_key is not null;
@@ -0,0 +1,44 @@
+-- =============================================================================
+-- Safety policy decisions (ai_safety_events)
+-- - Written by the API when a safety rule matches (block / safe_answer / warn /
+--   escalate). Rows with needs_review = true are escalations for human review.
+-- - Users can only insert their own rows; reading is done with the service role.
+-- =============================================================================
+
+create table if not exists public.ai_safety_events (
+  id uuid primary key default gen_random_uuid(),
+  user_id uuid not null references auth.users(id) on delete cascade,
+  session_id uuid references public.ai_sessions(id) on delete set null,
+  intent text not null,
+  flags jsonb not null default '[]'::jsonb,
+  action text not null check (action in ('allow', 'block', 'safe_answer', 'warn', 'escalate')),
+  severity text check (severity is null or severity in ('low', 'medium', 'high', 'critical')),
+  needs_review boolean not null default false,
+  reviewed_at timestamptz,
+  created_at timestamptz not null default now()
+);
+
+comment on table public.ai_safety_events is 'AI安全ポリシーの判定ログ（エスカレーションは needs_review = true）';
+
+create index if not exists idx_ai_safety_events_user_id on public.ai_safety_events(user_id);
+create index if not exists idx_ai_safety_events_session_id on public.ai_safety_events(session_id);
+create index if not exists idx_ai_safety_events_needs_review
+  on public.ai_safety_events(created_at)
+  where needs_review and reviewed_at is null;
+
+alter table public.ai_safety_events enable row level security;
+
+drop policy if exists "ai_safety_events_insert_own" on public.ai_safety_events;
+create policy "ai_safety_events_insert_own" on public.ai_safety_events
+  for insert
+  with check (
+    (select auth.uid()) = user_id
+    and (
+      session_id is null
+      or exists (
+        select 1 from public.ai_sessions s
+        where s.id = ai_safety_events.session_id
+          and s.user_id = (select auth.uid())
+      )
+    )
+  );
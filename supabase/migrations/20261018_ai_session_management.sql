@@ -0,0 +1,53 @@
+-- =============================================================================
+-- AI session management
+-- - ai_sessions.title / pinned: renamed and pinned from the app
+--   (PATCH /v1/ai/sessions/:id). GET /v1/ai/history lists pinned sessions first.
+-- - DELETE /v1/ai/sessions/:id removes the session; messages and
+--   recommendations go with it (on delete cascade).
+-- - GET /v1/ai/sessions/search: ilike over ai_messages.content, backed by a
+--   trigram index (works for Japanese, unlike the default text search parsers).
+-- =============================================================================
+
+create extension if not exists pg_trgm with schema extensions;
+
+alter table public.ai_sessions
+  add column if not exists title text,
+  add column if not exists pinned boolean not null default false;
+
+alter table public.ai_sessions
+  drop constraint if exists ai_sessions_title_length_check;
+alter table public.ai_sessions
+  add constraint ai_sessions_title_length_check
+    check (title is null or char_length(title) between 1 and 100);
+
+comment on column public.ai_sessions.title is 'ユーザーが付けたセッション名（null = 未設定）';
+comment on column public.ai_sessions.pinned is 'ピン留め（履歴一覧で先頭に表示）';
+
+create index if not exists idx_ai_sessions_user_pinned_created
+  on public.ai_sessions (user_id, pinned desc, created_at desc);
+
+-- セッション削除時にメッセージ・提案も削除する
+alter table public.ai_messages
+  drop constraint if exists ai_messages_session_id_fkey;
+alter table public.ai_messages
+  add constraint ai_messages_session_id_fkey
+    foreign key (session_id) references public.ai_sessions(id) on delete cascade;
+
+alter table public.ai_recommendations
+  drop constraint if exists ai_recommendations_session_id_fkey;
+alter table public.ai_recommendations
+  add constraint ai_recommendations_session_id_fkey
+    foreign key (session_id) references public.ai_sessions(id) on delete cascade;
+
+-- cascade delete looks up recommendations by session_id
+create index if not exists idx_ai_recommendations_session_id
+  on public.ai_recommendations (session_id);
+
+drop policy if exists "ai_sessions_delete_own" on public.ai_sessions;
+create policy "ai_sessions_delete_own" on public.ai_sessions
+  for delete
+  using ((select auth.uid()) = user_id);
+
+-- メッセージ本文の部分一致検索用
+create index if not exists idx_ai_messages_content_trgm
+  on public.ai_messages using gin (content extensions.gin_trgm_ops);
@@ -0,0 +1,24 @@
+-- =============================================================================
+-- Rolling session summaries (long-conversation memory)
+-- - POST /v1/ai/ask folds older turns of a long session into ai_sessions.summary.
+--   Messages created after summary_through are still sent verbatim.
+-- =============================================================================
+
+alter table public.ai_sessions
+  add column if not exists summary text,
+  add column if not exists summary_through timestamptz,
+  add column if not exists summary_updated_at timestamptz;
+
+comment on column public.ai_sessions.summary is 'これまでの会話の要約（古いターンをAIが要約したもの）';
+comment on column public.ai_sessions.summary_through is '要約に含まれる最後のメッセージの created_at';
+comment on column public.ai_sessions.summary_updated_at is '要約の最終更新日時';
+
+-- 要約の更新は API (ユーザーJWT + RLS) から行う
+drop policy if exists "ai_sessions_update_own" on public.ai_sessions;
+create policy "ai_sessions_update_own" on public.ai_sessions
+  for update
+  using ((select auth.uid()) = user_id)
+  with check ((select auth.uid()) = user_id);
+
+create index if not exists idx_ai_messages_session_created_at
+  on public.ai_messages (session_id, created_at);
@@ -0,0 +1,108 @@
+-- =============================================================================
+-- Weekly progress digest (ai_weekly_reports)
+-- - pg_cron queues one 'pending' row per active user every Monday (JST).
+-- - The next GET /v1/ai/inbox claims the row ('generating') and a background
+--   job computes the numbers with the user's token, has the LLM write the
+--   narrative, stores both here and posts the narrative to ai_inbox_messages
+--   (kind = 'weekly_report', date = week_start).
+-- - stats (jsonb) is returned by GET /v1/ai/reports/weekly for charts.
+-- =============================================================================
+
+create table if not exists public.ai_weekly_reports (
+  id uuid primary key default gen_random_uuid(),
+  user_id uuid not null references auth.users(id) on delete cascade,
+  week_start date not null,
+  status text not null default 'pending' check (status in ('pending', 'generating', 'ready', 'skipped')),
+  stats jsonb,
+  narrative text,
+  model text,
+  prompt_version text,
+  generated_at timestamptz,
+  claimed_at timestamptz,
+  created_at timestamptz not null default now(),
+
+  constraint ai_weekly_reports_week_start_monday_check
+    check (extract(isodow from week_start) = 1),
+
+  constraint ai_weekly_reports_unique
+    unique (user_id, week_start)
+);
+
+comment on table public.ai_weekly_reports is '週次振り返りレポート（集計値とAIの講評）';
+comment on column public.ai_weekly_reports.week_start is '対象週の月曜日（JST）';
+comment on column public.ai_weekly_reports.status is 'pending: 生成待ち / generating: 生成中 / ready: 配信済み / skipped: 記録なしのため配信しない';
+comment on column public.ai_weekly_reports.claimed_at is '生成ジョブが行を確保した時刻（古いものは再確保される）';
+comment on column public.ai_weekly_reports.stats is 'グラフ表示用の集計値（トレーニング量・自己ベスト・栄養達成度・体重推移・日別データ）';
+comment on column public.ai_weekly_reports.model is '講評を生成したモデル（null = 集計値からの定型文）';
+
+create index if not exists idx_ai_weekly_reports_user_status
+  on public.ai_weekly_reports (user_id, status, week_start desc);
+
+alter table public.ai_weekly_reports enable row level security;
+
+drop policy if exists "ai_weekly_reports_select_own" on public.ai_weekly_reports;
+drop policy if exists "ai_weekly_reports_update_own" on public.ai_weekly_reports;
+
+create policy "ai_weekly_reports_select_own" on public.ai_weekly_reports
+  for select
+  using ((select auth.uid()) = user_id);
+
+create policy "ai_weekly_reports_update_own" on public.ai_weekly_reports
+  for update
+  using ((select auth.uid()) = user_id)
+  with check ((select auth.uid()) = user_id);
+
+-- The API posts the digest to the inbox with the user's token
+drop policy if exists "ai_inbox_insert_own_weekly_report" on public.ai_inbox_messages;
+create policy "ai_inbox_insert_own_weekly_report" on public.ai_inbox_messages
+  for insert
+  with check ((select auth.uid()) = user_id and kind = 'weekly_report');
+
+-- -----------------------------------------------------------------------------
+-- Queue last week's report for users who logged anything that week
+-- -----------------------------------------------------------------------------
+create or replace function public.run_weekly_report()
+returns void
+language plpgsql
+as $$
+declare
+  v_week_start date;
+  v_week_end date;
+begin
+  v_week_start := date_trunc('week', (now() at time zone 'Asia/Tokyo'))::date - 7;
+  v_week_end := v_week_start + 6;
+
+  insert into public.ai_weekly_reports (user_id, week_start)
+  select up.user_id, v_week_start
+  from public.user_profiles up
+  where
+    up.onboarding_completed = true
+    and (
+      exists (
+        select 1 from public.workouts w
+        where w.user_id = up.user_id and w.date between v_week_start and v_week_end
+      )
+      or exists (
+        select 1 from public.nutrition_daily n
+        where n.user_id = up.user_id and n.date between v_week_start and v_week_end
+          and n.meals_logged > 0
+      )
+      or exists (
+        select 1 from public.body_metrics b
+        where b.user_id = up.user_id and b.date between v_week_start and v_week_end
+          and b.weight_kg is not null
+      )
+    )
+  on conflict (user_id, week_start)
+  do nothing;
+end;
+$$;
+
+-- Schedule (DB timezone is typically UTC on Supabase)
+-- Monday 07:00 JST = Sunday 22:00 UTC
+select cron.unschedule(jobid) from cron.job where jobname = 'weekly-report';
+select cron.schedule(
+  'weekly-report',
+  '0 22 * * 0',
+  $$ select public.run_weekly_report(); $$
+);
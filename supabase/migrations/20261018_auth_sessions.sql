@@ -0,0 +1,42 @@
+-- アカウントのセッション一覧 (GET /v1/account/sessions)
+-- - auth.sessions は PostgREST から直接参照できないため、security definer 関数で本人分のみ返す
+-- - 失効済み（有効な refresh token が残っていない）・期限切れのセッションは除外する
+-- - ログアウト（全端末 / 他端末）は Supabase Auth の /logout?scope=... を API から呼ぶ
+
+create or replace function public.list_my_sessions()
+returns table (
+  id uuid,
+  created_at timestamptz,
+  last_active_at timestamptz,
+  user_agent text,
+  ip text,
+  aal text
+)
+language sql
+stable
+security definer
+set search_path = public, auth, pg_temp
+as $$
+  select
+    s.id,
+    s.created_at,
+    coalesce(s.refreshed_at::timestamptz, s.updated_at, s.created_at) as last_active_at,
+    s.user_agent,
+    host(s.ip) as ip,
+    s.aal::text as aal
+  from auth.sessions s
+  where s.user_id = auth.uid()
+    and (s.not_after is null or s.not_after > now())
+    and exists (
+      select 1 from auth.refresh_tokens rt
+      where rt.session_id = s.id
+        and rt.revoked = false
+    )
+  order by last_active_at desc;
+$$;
+
+comment on function public.list_my_sessions() is
+  '呼び出しユーザーの有効なセッション（端末）一覧を返す。Authorization: 本人のみ。';
+
+revoke all on function public.list_my_sessions() from public, anon;
+grant execute on function public.list_my_sessions() to authenticated;
@@ -0,0 +1,56 @@
+-- =============================================================================
+-- Body circumference measurements (body_measurements)
+-- - One row per (user, date, site, side); values are stored in cm
+--   (POST /v1/log/measurements converts from inches).
+-- - Limbs (arm / thigh) are logged per side; neck / chest / waist / hips use 'center'.
+-- - GET /v1/measurements/trends returns per-site series and a body-fat trend
+--   (logged body_metrics.bodyfat_pct and a U.S. Navy estimate from
+--   waist / neck / hips + user_profiles.height_cm, computed in the API).
+-- =============================================================================
+
+create table if not exists public.body_measurements (
+  id uuid primary key default gen_random_uuid(),
+  user_id uuid not null references auth.users(id) on delete cascade,
+  date date not null,
+  site text not null check (site in ('neck', 'chest', 'waist', 'hips', 'arm', 'thigh')),
+  side text not null default 'center' check (side in ('center', 'left', 'right')),
+  value_cm numeric not null check (value_cm >= 10 and value_cm <= 300),
+  created_at timestamptz not null default now(),
+
+  constraint body_measurements_user_date_site_side_unique
+    unique (user_id, date, site, side),
+  constraint body_measurements_side_check
+    check ((site in ('arm', 'thigh')) = (side <> 'center'))
+);
+
+comment on table public.body_measurements is '体の周囲径（ウエスト・胸囲・腕・脚・ヒップ・首）';
+comment on column public.body_measurements.site is 'neck: 首 / chest: 胸囲 / waist: ウエスト / hips: ヒップ / arm: 上腕 / thigh: 太もも';
+comment on column public.body_measurements.side is 'left / right（腕・太もも）、それ以外は center';
+comment on column public.body_measurements.value_cm is '周囲径（cm。インチ入力はAPIで換算）';
+
+create index if not exists idx_body_measurements_user_date
+  on public.body_measurements (user_id, date desc);
+
+alter table public.body_measurements enable row level security;
+
+drop policy if exists "body_measurements_select_own" on public.body_measurements;
+drop policy if exists "body_measurements_insert_own" on public.body_measurements;
+drop policy if exists "body_measurements_update_own" on public.body_measurements;
+drop policy if exists "body_measurements_delete_own" on public.body_measurements;
+
+create policy "body_measurements_select_own" on public.body_measurements
+  for select
+  using ((select auth.uid()) = user_id);
+
+create policy "body_measurements_insert_own" on public.body_measurements
+  for insert
+  with check ((select auth.uid()) = user_id);
+
+create policy "body_measurements_update_own" on public.body_measurements
+  for update
+  using ((select auth.uid()) = user_id)
+  with check ((select auth.uid()) = user_id);
+
+create policy "body_measurements_delete_own" on public.body_measurements
+  for delete
+  using ((select auth.uid()) = user_id);
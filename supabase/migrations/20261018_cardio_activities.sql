@@ -0,0 +1,121 @@
+-- =============================================================================
+-- Cardio / endurance activities
+-- - exercises.modality / workout_exercises.modality: 'strength' (weight × reps sets)
+--   or 'cardio' (distance / duration). Volume and e1RM statistics only read
+--   strength exercises.
+-- - workout_cardio: one row per cardio workout exercise (no workout_sets rows).
+--   Pace / speed are derived in the API from distance and duration.
+-- =============================================================================
+
+alter table public.exercises
+  add column if not exists modality text not null default 'strength';
+alter table public.exercises
+  drop constraint if exists exercises_modality_check;
+alter table public.exercises
+  add constraint exercises_modality_check check (modality in ('strength', 'cardio'));
+
+comment on column public.exercises.modality is 'strength: 重量×回数のセット / cardio: 距離・時間で記録する有酸素運動';
+
+alter table public.workout_exercises
+  add column if not exists modality text not null default 'strength';
+alter table public.workout_exercises
+  drop constraint if exists workout_exercises_modality_check;
+alter table public.workout_exercises
+  add constraint workout_exercises_modality_check check (modality in ('strength', 'cardio'));
+
+comment on column public.workout_exercises.modality is 'strength: workout_sets に記録 / cardio: workout_cardio に記録';
+
+create table if not exists public.workout_cardio (
+  id uuid primary key default gen_random_uuid(),
+  workout_exercise_id uuid not null references public.workout_exercises(id) on delete cascade,
+  duration_sec integer not null check (duration_sec between 1 and 86400),
+  distance_m numeric check (distance_m is null or distance_m between 0 and 1000000),
+  elevation_gain_m numeric check (elevation_gain_m is null or elevation_gain_m between 0 and 20000),
+  avg_heart_rate integer check (avg_heart_rate is null or avg_heart_rate between 30 and 250),
+  max_heart_rate integer check (max_heart_rate is null or max_heart_rate between 30 and 250),
+  calories_kcal integer check (calories_kcal is null or calories_kcal between 0 and 20000),
+  created_at timestamptz not null default now(),
+
+  constraint workout_cardio_workout_exercise_unique
+    unique (workout_exercise_id),
+  constraint workout_cardio_heart_rate_check
+    check (avg_heart_rate is null or max_heart_rate is null or avg_heart_rate <= max_heart_rate)
+);
+
+comment on table public.workout_cardio is '有酸素運動の記録（ラン・バイク・ローイングなど。workout_exercises と1対1）';
+comment on column public.workout_cardio.duration_sec is '運動時間（秒）';
+comment on column public.workout_cardio.distance_m is '距離（m）';
+comment on column public.workout_cardio.elevation_gain_m is '獲得標高（m）';
+comment on column public.workout_cardio.avg_heart_rate is '平均心拍数（bpm）';
+comment on column public.workout_cardio.max_heart_rate is '最大心拍数（bpm）';
+comment on column public.workout_cardio.calories_kcal is '消費カロリー（kcal）';
+
+alter table public.workout_cardio enable row level security;
+
+drop policy if exists "workout_cardio_select_own" on public.workout_cardio;
+drop policy if exists "workout_cardio_insert_own" on public.workout_cardio;
+drop policy if exists "workout_cardio_update_own" on public.workout_cardio;
+drop policy if exists "workout_cardio_delete_own" on public.workout_cardio;
+
+create policy "workout_cardio_select_own" on public.workout_cardio
+  for select
+  using (exists (
+    select 1
+    from public.workout_exercises we
+    join public.workouts w on w.id = we.workout_id
+    where we.id = workout_cardio.workout_exercise_id
+      and w.user_id = (select auth.uid())
+  ));
+
+create policy "workout_cardio_insert_own" on public.workout_cardio
+  for insert
+  with check (exists (
+    select 1
+    from public.workout_exercises we
+    join public.workouts w on w.id = we.workout_id
+    where we.id = workout_cardio.workout_exercise_id
+      and w.user_id = (select auth.uid())
+  ));
+
+create policy "workout_cardio_update_own" on public.workout_cardio
+  for update
+  using (exists (
+    select 1
+    from public.workout_exercises we
+    join public.workouts w on w.id = we.workout_id
+    where we.id = workout_cardio.workout_exercise_id
+      and w.user_id = (select auth.uid())
+  ))
+  with check (exists (
+    select 1
+    from public.workout_exercises we
+    join public.workouts w on w.id = we.workout_id
+    where we.id = workout_cardio.workout_exercise_id
+      and w.user_id = (select auth.uid())
+  ));
+
+create policy "workout_cardio_delete_own" on public.workout_cardio
+  for delete
+  using (exists (
+    select 1
+    from public.workout_exercises we
+    join public.workouts w on w.id = we.workout_id
+    where we.id = workout_cardio.workout_exercise_id
+      and w.user_id = (select auth.uid())
+  ));
+
+-- システム種目（有酸素）
+insert into public.exercises (name, name_en, primary_muscle, equipment, is_system, modality)
+select v.name, v.name_en, 'cardio', v.equipment, true, 'cardio'
+from (values
+  ('ランニング', 'Running', null),
+  ('ウォーキング', 'Walking', null),
+  ('サイクリング', 'Cycling', null),
+  ('エアロバイク', 'Stationary Bike', 'machine'),
+  ('ローイング', 'Rowing', 'machine'),
+  ('水泳', 'Swimming', null),
+  ('エリプティカル', 'Elliptical', 'machine')
+) as v(name, name_en, equipment)
+where not exists (
+  select 1 from public.exercises e where e.is_system and e.name = v.name
+);
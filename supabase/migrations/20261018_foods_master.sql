@@ -0,0 +1,34 @@
+-- =============================================================================
+-- Food master (foods)
+-- - Used by POST /v1/ai/meal/parse to resolve AI-parsed meal items to known
+--   nutrition values (per 100g) instead of relying on model estimates only.
+-- =============================================================================
+
+create table if not exists public.foods (
+  id uuid primary key default gen_random_uuid(),
+  name text not null unique,
+  aliases text[] not null default array[]::text[],
+  -- Typical serving (e.g. "個" = 50g for eggs). Optional.
+  serving_unit text,
+  serving_grams numeric check (serving_grams is null or serving_grams > 0),
+  calories_per_100g numeric not null check (calories_per_100g >= 0),
+  protein_g_per_100g numeric not null default 0 check (protein_g_per_100g >= 0),
+  fat_g_per_100g numeric not null default 0 check (fat_g_per_100g >= 0),
+  carbs_g_per_100g numeric not null default 0 check (carbs_g_per_100g >= 0),
+  fiber_g_per_100g numeric not null default 0 check (fiber_g_per_100g >= 0),
+  created_at timestamptz not null default now()
+);
+
+comment on table public.foods is '食品マスタ（100gあたりの栄養素）。自然文の食事記録の名寄せに使用';
+
+alter table public.foods enable row level security;
+
+-- Read-only master data for signed-in users (writes are done via migrations/seed)
+drop policy if exists "foods_select_authenticated" on public.foods;
+create policy "foods_select_authenticated"
+  on public.foods
+  for select
+  to authenticated
+  using (true);
+
+create index if not exists idx_foods_aliases on public.foods using gin (aliases);
@@ -0,0 +1,83 @@
+-- =============================================================================
+-- Workout history import (POST /v1/import/workouts)
+-- - public.import_workouts() inserts one batch of workouts with all of their
+--   workout_exercises / workout_sets / workout_cardio rows in a single transaction.
+--   A failed batch leaves nothing behind, so a re-upload (which skips workouts
+--   by start_time) re-imports it completely.
+-- - security invoker: the existing RLS insert policies apply. user_id /
+--   created_by are always taken from auth.uid(), not from the payload.
+-- =============================================================================
+
+create or replace function public.import_workouts(
+  p_exercises jsonb,
+  p_workouts jsonb,
+  p_workout_exercises jsonb,
+  p_sets jsonb,
+  p_cardio jsonb
+)
+returns jsonb
+language plpgsql
+security invoker
+set search_path = public, pg_temp
+as $$
+declare
+  v_user_id uuid := auth.uid();
+  v_exercises integer;
+  v_workouts integer;
+  v_workout_exercises integer;
+  v_sets integer;
+  v_cardio integer;
+begin
+  if v_user_id is null then
+    raise exception 'Unauthorized: authentication required';
+  end if;
+
+  insert into public.exercises (
+    id, name, primary_muscle, secondary_muscles, equipment, is_system, created_by, modality
+  )
+  select x.id, x.name, x.primary_muscle, coalesce(x.secondary_muscles, '{}'), x.equipment,
+         false, v_user_id, x.modality
+  from jsonb_populate_recordset(null::public.exercises, coalesce(p_exercises, '[]'::jsonb)) x;
+  get diagnostics v_exercises = row_count;
+
+  insert into public.workouts (id, user_id, date, start_time, end_time, note)
+  select x.id, v_user_id, x.date, x.start_time, x.end_time, x.note
+  from jsonb_populate_recordset(null::public.workouts, coalesce(p_workouts, '[]'::jsonb)) x;
+  get diagnostics v_workouts = row_count;
+
+  insert into public.workout_exercises (
+    id, workout_id, exercise_id, custom_exercise_name, muscle_tag, modality, exercise_order
+  )
+  select x.id, x.workout_id, x.exercise_id, x.custom_exercise_name, x.muscle_tag, x.modality,
+         x.exercise_order
+  from jsonb_populate_recordset(null::public.workout_exercises, coalesce(p_workout_exercises, '[]'::jsonb)) x;
+  get diagnostics v_workout_exercises = row_count;
+
+  insert into public.workout_sets (
+    id, workout_exercise_id, set_index, weight_kg, reps, rpe, is_warmup, is_dropset
+  )
+  select x.id, x.workout_exercise_id, x.set_index, x.weight_kg, x.reps, x.rpe,
+         coalesce(x.is_warmup, false), coalesce(x.is_dropset, false)
+  from jsonb_populate_recordset(null::public.workout_sets, coalesce(p_sets, '[]'::jsonb)) x;
+  get diagnostics v_sets = row_count;
+
+  insert into public.workout_cardio (workout_exercise_id, duration_sec, distance_m)
+  select x.workout_exercise_id, x.duration_sec, x.distance_m
+  from jsonb_populate_recordset(null::public.workout_cardio, coalesce(p_cardio, '[]'::jsonb)) x;
+  get diagnostics v_cardio = row_count;
+
+  return jsonb_build_object(
+    'exercises', v_exercises,
+    'workouts', v_workouts,
+    'workout_exercises', v_workout_exercises,
+    'sets', v_sets,
+    'cardio', v_cardio
+  );
+end;
+$$;
+
+comment on function public.import_workouts(jsonb, jsonb, jsonb, jsonb, jsonb) is
+  'ワークアウト履歴インポートの1バッチを1トランザクションで登録する（RLS 適用、user_id は auth.uid()）';
+
+revoke all on function public.import_workouts(jsonb, jsonb, jsonb, jsonb, jsonb) from public, anon;
+grant execute on function public.import_workouts(jsonb, jsonb, jsonb, jsonb, jsonb) to authenticated;
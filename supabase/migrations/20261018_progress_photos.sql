@@ -0,0 +1,55 @@
+-- =============================================================================
+-- Progress photos (progress_photos)
+-- - Private physique photos: one per (user, date, pose), pose = front / side / back.
+--   Re-uploading the same date + pose replaces the photo.
+-- - Images live in the private 'user-photos' bucket under
+--   '<user_id>/progress/<uuid>.jpg' (+ '_medium' / '_thumb' variants), covered by
+--   the per-owner storage policies from 20251218_user_photos_storage.sql.
+-- - That day's body_metrics are joined by (user_id, date) at read time, so
+--   metrics logged after the photo still show up.
+-- =============================================================================
+
+create table if not exists public.progress_photos (
+  id uuid primary key default gen_random_uuid(),
+  user_id uuid not null references auth.users(id) on delete cascade,
+  date date not null,
+  pose text not null check (pose in ('front', 'side', 'back')),
+  object_path text not null,
+  created_at timestamptz not null default now(),
+
+  constraint progress_photos_user_date_pose_unique
+    unique (user_id, date, pose)
+);
+
+comment on table public.progress_photos is '進捗写真（体型記録・本人のみ閲覧可）';
+comment on column public.progress_photos.date is '撮影日（同日の body_metrics と紐づく）';
+comment on column public.progress_photos.pose is 'front: 正面 / side: 横 / back: 背面';
+comment on column public.progress_photos.object_path is 'user-photos バケット内のパス（full画像。サイズ違いは _medium / _thumb）';
+
+-- タイムライン（日付降順）・比較（日付指定）用
+create index if not exists idx_progress_photos_user_date
+  on public.progress_photos (user_id, date desc);
+
+alter table public.progress_photos enable row level security;
+
+drop policy if exists "progress_photos_select_own" on public.progress_photos;
+drop policy if exists "progress_photos_insert_own" on public.progress_photos;
+drop policy if exists "progress_photos_update_own" on public.progress_photos;
+drop policy if exists "progress_photos_delete_own" on public.progress_photos;
+
+create policy "progress_photos_select_own" on public.progress_photos
+  for select
+  using ((select auth.uid()) = user_id);
+
+create policy "progress_photos_insert_own" on public.progress_photos
+  for insert
+  with check ((select auth.uid()) = user_id);
+
+create policy "progress_photos_update_own" on public.progress_photos
+  for update
+  using ((select auth.uid()) = user_id)
+  with check ((select auth.uid()) = user_id);
+
+create policy "progress_photos_delete_own" on public.progress_photos
+  for delete
+  using ((select auth.uid()) = user_id);
@@ -0,0 +1,90 @@
+-- =============================================================================
+-- User history retrieval index (user_history_docs)
+-- - One text document per workout / meal / body_metrics row / AI session,
+--   built by the API (services/api_rust/src/state/retrieval.rs).
+-- - Lexical search: content ilike on the API side.
+-- - Embedding search (optional): pgvector cosine distance via
+--   match_user_history_docs(). The embedding column is null when no embedder
+--   is configured (EMBEDDING_PROVIDER=none).
+-- =============================================================================
+
+create extension if not exists vector with schema extensions;
+
+create table if not exists public.user_history_docs (
+  id uuid primary key default gen_random_uuid(),
+  user_id uuid not null references auth.users(id) on delete cascade,
+  source_type text not null check (source_type in ('workout', 'meal', 'body_metric', 'ai_session')),
+  source_id uuid not null,
+  doc_date date not null,
+  content text not null,
+  embedding extensions.vector(768),
+  embedding_model text,
+  -- created_at of the source row (incremental sync high-water mark)
+  source_created_at timestamptz not null,
+  indexed_at timestamptz not null default now(),
+  unique (source_type, source_id)
+);
+
+comment on table public.user_history_docs is 'AI回答用の履歴検索インデックス（ワークアウト・食事・体組成・AIセッション）';
+
+create index if not exists idx_user_history_docs_user_type_created
+  on public.user_history_docs(user_id, source_type, source_created_at desc);
+create index if not exists idx_user_history_docs_embedding
+  on public.user_history_docs using hnsw (embedding extensions.vector_cosine_ops);
+
+alter table public.user_history_docs enable row level security;
+
+drop policy if exists "user_history_docs_select_own" on public.user_history_docs;
+create policy "user_history_docs_select_own" on public.user_history_docs
+  for select
+  using ((select auth.uid()) = user_id);
+
+drop policy if exists "user_history_docs_insert_own" on public.user_history_docs;
+create policy "user_history_docs_insert_own" on public.user_history_docs
+  for insert
+  with check ((select auth.uid()) = user_id);
+
+drop policy if exists "user_history_docs_update_own" on public.user_history_docs;
+create policy "user_history_docs_update_own" on public.user_history_docs
+  for update
+  using ((select auth.uid()) = user_id)
+  with check ((select auth.uid()) = user_id);
+
+drop policy if exists "user_history_docs_delete_own" on public.user_history_docs;
+create policy "user_history_docs_delete_own" on public.user_history_docs
+  for delete
+  using ((select auth.uid()) = user_id);
+
+-- Embedding search. security invoker: RLS restricts results to the caller's rows.
+create or replace function public.match_user_history_docs(
+  query_embedding text,
+  match_count integer default 5
+)
+returns table (
+  id uuid,
+  source_type text,
+  source_id uuid,
+  doc_date date,
+  content text,
+  similarity double precision
+)
+language sql
+stable
+security invoker
+set search_path = public, extensions, pg_temp
+as $$
+  select
+    d.id,
+    d.source_type,
+    d.source_id,
+    d.doc_date,
+    d.content,
+    1 - (d.embedding <=> query_embedding::extensions.vector) as similarity
+  from public.user_history_docs d
+  where d.user_id = auth.uid()
+    and d.embedding is not null
+  order by d.embedding <=> query_embedding::extensions.vector
+  limit least(greatest(match_count, 1), 20);
+$$;
+
+grant execute on function public.match_user_history_docs(text, integer) to authenticated;
@@ -222,3 +222,27 @@ WHERE is_system = TRUE
 GROUP BY primary_muscle
 ORDER BY primary_muscle;
 
+
+-- =============================================================================
+-- Seed Data: 食品マスタ (foods) - 100gあたり（日本食品標準成分表ベースの概算）
+-- =============================================================================
+
+INSERT INTO public.foods (name, aliases, serving_unit, serving_grams, calories_per_100g, protein_g_per_100g, fat_g_per_100g, carbs_g_per_100g, fiber_g_per_100g) VALUES
+('白米', ARRAY['ご飯', 'ごはん', 'ライス', '白ご飯', '米'], '杯', 150, 156, 2.5, 0.3, 37.1, 1.5),
+('玄米', ARRAY['玄米ご飯', '玄米ごはん'], '杯', 150, 152, 2.8, 1.0, 35.6, 1.4),
+('オートミール', ARRAY['オーツ', 'oatmeal'], 'serving', 30, 350, 13.7, 5.7, 69.1, 9.4),
+('食パン', ARRAY['パン', 'トースト'], '枚', 60, 248, 8.9, 4.1, 46.4, 4.2),
+('鶏むね肉', ARRAY['鶏むね', '鶏胸肉', '胸肉', 'むね肉', 'チキンブレスト'], 'g', 1, 105, 23.3, 1.9, 0.1, 0),
+('鶏もも肉', ARRAY['鶏もも', 'もも肉'], 'g', 1, 113, 19.0, 5.0, 0, 0),
+('ささみ', ARRAY['鶏ささみ', 'ササミ'], '本', 50, 98, 23.9, 0.8, 0.1, 0),
+('サラダチキン', ARRAY[]::TEXT[], '個', 110, 105, 24.0, 1.5, 0.5, 0),
+('鮭', ARRAY['焼き鮭', 'サーモン', 'さけ'], '切れ', 80, 160, 29.1, 5.1, 0.1, 0),
+('卵', ARRAY['たまご', '玉子', '全卵', 'ゆで卵', '生卵'], '個', 50, 142, 12.2, 10.2, 0.4, 0),
+('納豆', ARRAY['なっとう'], 'パック', 45, 190, 16.5, 10.0, 12.1, 6.7),
+('木綿豆腐', ARRAY['豆腐', 'とうふ'], '丁', 300, 73, 7.0, 4.9, 1.5, 1.1),
+('味噌汁', ARRAY['みそ汁', 'お味噌汁'], '杯', 200, 25, 1.6, 0.7, 3.0, 0.5),
+('ブロッコリー', ARRAY['ブロッコリ'], 'g', 1, 30, 3.9, 0.4, 5.2, 4.3),
+('バナナ', ARRAY[]::TEXT[], '本', 100, 93, 1.1, 0.2, 22.5, 1.1),
+('牛乳', ARRAY['ミルク'], '杯', 200, 61, 3.3, 3.8, 4.8, 0),
+('ホエイプロテイン', ARRAY['プロテイン', 'whey'], 'scoop', 30, 400, 75.0, 6.0, 10.0, 0)
+ON CONFLICT (name) DO NOTHING;